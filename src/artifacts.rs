@@ -0,0 +1,134 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::Result;
+use tokio::fs as async_fs;
+
+use crate::infer::InferredContext;
+use crate::run_log;
+use crate::tags::TagFormat;
+
+/// Run directories this process has prepared, which pruning must leave alone: `diff-rc`
+/// compares two of them.
+static IN_USE: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Root of the per-tag run directories: `--artifact-dir` or `artifacts.dir`, relative to
+/// the repo root (the git dir of a bare repository), and `target/asfship` otherwise.
+pub fn root(ctx: &InferredContext) -> PathBuf {
+    // A bare repository's snapshot is temporary; keep artifacts next to its objects, where
+    // commands that run without a context look for them too.
+    let base = match &ctx.snapshot {
+        Some(snapshot) => snapshot.git_dir(),
+        None => ctx.repo_root.as_path(),
+    };
+    root_in(base, ctx.config.artifacts.dir.as_deref())
+}
+
+/// [`root`] for commands that run without an inferred context.
+pub fn root_in(repo_root: &Path, dir: Option<&Path>) -> PathBuf {
+    match dir {
+        Some(dir) => repo_root.join(dir),
+        None => repo_root.join("target").join("asfship"),
+    }
+}
+
+/// Directory every command uses for `tag`'s files, so `sync`, `release`, and `diff-rc`
+/// find what `prerelease` packaged instead of downloading it again.
+pub fn run_dir(ctx: &InferredContext, tag: &str) -> PathBuf {
+    root(ctx).join(dir_name(tag))
+}
+
+pub(crate) fn dir_name(tag: &str) -> String {
+    tag.replace('/', "_")
+}
+
+/// Create `tag`'s run directory, start the run log there, and apply `artifacts.keep` to
+/// the others.
+pub async fn prepare(ctx: &InferredContext, tag: &str) -> Result<PathBuf> {
+    let dir = run_dir(ctx, tag);
+    async_fs::create_dir_all(&dir).await?;
+    run_log::attach(&dir);
+    let in_use = {
+        let mut in_use = IN_USE.lock().unwrap();
+        in_use.insert(dir_name(tag));
+        in_use.clone()
+    };
+    if let Some(keep) = ctx.config.artifacts.keep
+        && let Err(err) = prune(&root(ctx), &ctx.config.tags(), &in_use, keep).await
+    {
+        tracing::warn!("artifacts: pruning old run directories failed: {:#}", err);
+    }
+    Ok(dir)
+}
+
+/// Remove all but the `keep` most recently modified tag directories under `root`, never
+/// one in `in_use`.
+///
+/// Only directories named after an rc or stable tag (as encoded by [`dir_name`]) are
+/// considered, so caches and other files sharing the root are left alone.
+async fn prune(
+    root: &Path,
+    tags: &TagFormat,
+    in_use: &BTreeSet<String>,
+    keep: usize,
+) -> Result<()> {
+    let mut runs: Vec<(SystemTime, PathBuf)> = Vec::new();
+    let mut entries = async_fs::read_dir(root).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if in_use.contains(&name) || !is_run_dir(tags, &name) || !entry.file_type().await?.is_dir()
+        {
+            continue;
+        }
+        runs.push((entry.metadata().await?.modified()?, entry.path()));
+    }
+    runs.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    // The directories in use count toward `keep`.
+    for (_, path) in runs.into_iter().skip(keep.saturating_sub(in_use.len())) {
+        tracing::info!("artifacts: removing {}", path.display());
+        async_fs::remove_dir_all(&path).await?;
+    }
+    Ok(())
+}
+
+/// Whether `name` is the [`dir_name`] of an rc or stable tag; a `/` in the tag prefix
+/// is stored as `_`.
+fn is_run_dir(tags: &TagFormat, name: &str) -> bool {
+    [name.to_string(), name.replace('_', "/")]
+        .iter()
+        .any(|tag| tags.parse_rc(tag).is_some() || tags.parse_stable(tag).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn prune_matches_encoded_tags_and_spares_dirs_in_use() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let tags = TagFormat::new("bar/v");
+        for name in [
+            "bar_v0.1.0-rc.1",
+            "bar_v0.1.0-rc.2",
+            "bar_v0.1.0",
+            "plan-cache",
+        ] {
+            std::fs::create_dir_all(tmp.path().join(name)).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        // diff-rc prepares both rcs it compares, oldest first.
+        let in_use: BTreeSet<String> = [dir_name("bar/v0.1.0-rc.1"), dir_name("bar/v0.1.0-rc.2")]
+            .into_iter()
+            .collect();
+        prune(tmp.path(), &tags, &in_use, 1).await.unwrap();
+
+        let mut left: Vec<String> = std::fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["bar_v0.1.0-rc.1", "bar_v0.1.0-rc.2", "plan-cache"]);
+    }
+}
@@ -0,0 +1,350 @@
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::AsfConfig;
+use crate::endpoints;
+use crate::infer::InferredContext;
+use crate::net;
+
+/// Membership of an Apache id in a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsfRole {
+    /// PMC member; their votes are binding.
+    Pmc,
+    Committer,
+}
+
+/// PMC roster, committers, and mailing list domain published by Whimsy.
+#[derive(Debug, Clone)]
+pub struct ProjectRoster {
+    pub pmc: BTreeSet<String>,
+    pub committers: BTreeSet<String>,
+    /// Mailing list domain prefix, e.g. `opendal` for `dev@opendal.apache.org`.
+    pub mail_list: String,
+}
+
+impl ProjectRoster {
+    pub fn role_of(&self, apache_id: &str) -> Option<AsfRole> {
+        let id = apache_id.trim().to_ascii_lowercase();
+        if self.pmc.contains(&id) {
+            Some(AsfRole::Pmc)
+        } else if self.committers.contains(&id) {
+            Some(AsfRole::Committer)
+        } else {
+            None
+        }
+    }
+
+    pub fn dev_list(&self) -> String {
+        format!("dev@{}.apache.org", self.mail_list)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LdapProjects {
+    projects: HashMap<String, LdapProject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LdapProject {
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    owners: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitteeInfo {
+    committees: HashMap<String, Committee>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Committee {
+    mail_list: Option<String>,
+}
+
+/// ASF project id: `[asf].project`, else the repository name without `incubator-`.
+pub fn project_name(ctx: &InferredContext) -> String {
+    match &ctx.config.asf.project {
+        Some(project) => project.clone(),
+        None => ctx
+            .repo_name
+            .trim_start_matches("incubator-")
+            .to_ascii_lowercase(),
+    }
+}
+
+/// Whether the project is an Incubator podling, hosted as `incubator-<project>`.
+pub fn is_incubating(ctx: &InferredContext) -> bool {
+    ctx.repo_name.starts_with("incubator-")
+}
+
+/// Archive formats ASF source releases may use, as file extensions without the dot.
+pub const ARCHIVE_EXTENSIONS: [&str; 3] = ["tar.gz", "tar.zst", "zip"];
+
+/// Whether `name` is a source archive in one of [`ARCHIVE_EXTENSIONS`].
+pub fn is_archive(name: &str) -> bool {
+    ARCHIVE_EXTENSIONS
+        .iter()
+        .any(|ext| name.ends_with(&format!(".{}", ext)))
+}
+
+/// How ASF policy names the project's source archives:
+/// `apache-<project>[-<crate>]-<version>[-incubating][-rcN]-src`. Packaging and `verify`
+/// both go through it, so what one writes the other accepts.
+#[derive(Debug, Clone)]
+pub struct ArchiveNaming {
+    project: String,
+    incubating: bool,
+}
+
+impl ArchiveNaming {
+    pub fn new(project: &str, incubating: bool) -> Self {
+        Self {
+            project: project.to_string(),
+            incubating,
+        }
+    }
+
+    pub fn of(ctx: &InferredContext) -> Self {
+        Self::new(&project_name(ctx), is_incubating(ctx))
+    }
+
+    /// Archive name without its extension. `component` is `None` for the main crate,
+    /// whose archive is named after the project alone.
+    pub fn base_name(
+        &self,
+        component: Option<&str>,
+        version: &semver::Version,
+        rc: Option<u32>,
+    ) -> String {
+        let mut name = format!("apache-{}", self.project);
+        if let Some(component) = component {
+            name.push_str(&format!("-{}", component));
+        }
+        name.push_str(&format!("-{}", version));
+        if self.incubating {
+            name.push_str("-incubating");
+        }
+        if let Some(rc) = rc {
+            name.push_str(&format!("-rc{}", rc));
+        }
+        name.push_str("-src");
+        name
+    }
+
+    /// Matches archive names with one of `extensions`, capturing `version` and `rc`.
+    pub fn pattern(&self, extensions: &[&str]) -> Regex {
+        Regex::new(&format!(
+            r"^apache-{}(?:-[a-z0-9_-]+?)?-(?P<version>\d+\.\d+\.\d+){}(?:-rc(?P<rc>\d+))?-src\.(?:{})$",
+            regex::escape(&self.project),
+            if self.incubating { "-incubating" } else { "" },
+            extensions
+                .iter()
+                .map(|e| regex::escape(e))
+                .collect::<Vec<_>>()
+                .join("|")
+        ))
+        .expect("valid archive name pattern")
+    }
+
+    /// The rc `rc`'s archive name with placeholders, for messages.
+    pub fn template(&self, rc: u32, extensions: &[&str]) -> String {
+        format!(
+            "apache-{}-<version>{}-rc{}-src.<{}>",
+            self.project,
+            if self.incubating { "-incubating" } else { "" },
+            rc,
+            extensions.join("|")
+        )
+    }
+}
+
+pub async fn fetch_roster(project: &str) -> Result<ProjectRoster> {
+    let client = net::client()?;
+    let whimsy = endpoints::whimsy();
+    let projects = client
+        .get(format!("{}/public/public_ldap_projects.json", whimsy))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await
+        .context("failed to fetch ASF project roster from whimsy")?;
+    let committees = client
+        .get(format!("{}/public/committee-info.json", whimsy))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await
+        .context("failed to fetch ASF committee info from whimsy")?;
+    parse_roster(project, &projects, &committees)
+}
+
+fn parse_roster(
+    project: &str,
+    projects_json: &str,
+    committees_json: &str,
+) -> Result<ProjectRoster> {
+    let mut projects: LdapProjects =
+        serde_json::from_str(projects_json).context("failed to parse whimsy project roster")?;
+    let Some(entry) = projects.projects.remove(project) else {
+        bail!(
+            "ASF project {:?} not found on whimsy; set [asf].project in .asfship.toml",
+            project
+        );
+    };
+    // Podlings are absent from committee-info; their lists follow the project id.
+    let mail_list = serde_json::from_str::<CommitteeInfo>(committees_json)
+        .ok()
+        .and_then(|mut info| info.committees.remove(project))
+        .and_then(|c| c.mail_list)
+        .unwrap_or_else(|| project.to_string());
+    Ok(ProjectRoster {
+        pmc: entry.owners.into_iter().collect(),
+        committers: entry.members.into_iter().collect(),
+        mail_list,
+    })
+}
+
+/// Who is running the release, from `[asf]`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ReleaseManager {
+    pub name: String,
+    pub apache_id: String,
+    pub email: String,
+    /// Uppercase hex without spaces.
+    pub gpg_fingerprint: Option<String>,
+}
+
+/// The configured release manager, or `None` when `asf.release_manager` is unset.
+pub fn release_manager(asf: &AsfConfig) -> Result<Option<ReleaseManager>> {
+    let Some(apache_id) = asf.release_manager.as_deref().map(str::trim) else {
+        return Ok(None);
+    };
+    let gpg_fingerprint = match &asf.gpg_fingerprint {
+        Some(raw) => {
+            let fpr: String = raw
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_ascii_uppercase();
+            if !matches!(fpr.len(), 40 | 64) || !fpr.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!(
+                    "asf.gpg_fingerprint {:?} is not a full key fingerprint",
+                    raw
+                );
+            }
+            Some(fpr)
+        }
+        None => None,
+    };
+    Ok(Some(ReleaseManager {
+        name: asf
+            .release_manager_name
+            .clone()
+            .unwrap_or_else(|| apache_id.to_string()),
+        apache_id: apache_id.to_string(),
+        email: format!("{}@apache.org", apache_id),
+        gpg_fingerprint,
+    }))
+}
+
+/// Fetch the project's roster from whimsy and check the configured release manager
+/// against it.
+///
+/// Only the commands that need the roster call this (`vote`, `result`, `retract`), so
+/// dry runs of everything else and `plan` never reach out to whimsy.
+pub async fn load_roster(ctx: &InferredContext) -> Result<ProjectRoster> {
+    let project = project_name(ctx);
+    let roster = fetch_roster(&project).await?;
+    if let Some(rm) = &ctx.config.asf.release_manager {
+        match roster.role_of(rm) {
+            Some(role) => {
+                tracing::info!("asf: release manager {} is {:?} of {}", rm, role, project)
+            }
+            None => bail!(
+                "release manager {:?} is neither a committer nor a PMC member of {}",
+                rm,
+                project
+            ),
+        }
+    }
+    Ok(roster)
+}
+
+/// `ctx` with `[asf].dev_list` filled from the roster, for templates that name the list.
+///
+/// The roster is only fetched when the `[asf]` table names a project or release manager
+/// and no list is configured, so repositories without it never reach out to whimsy.
+pub async fn with_dev_list(ctx: &InferredContext) -> Result<Cow<'_, InferredContext>> {
+    let asf = &ctx.config.asf;
+    if asf.dev_list.is_some() || (asf.project.is_none() && asf.release_manager.is_none()) {
+        return Ok(Cow::Borrowed(ctx));
+    }
+    let roster = load_roster(ctx).await?;
+    Ok(dev_list_from(ctx, &roster))
+}
+
+/// `ctx` with `[asf].dev_list` filled from an already loaded `roster` unless configured.
+pub fn dev_list_from<'a>(
+    ctx: &'a InferredContext,
+    roster: &ProjectRoster,
+) -> Cow<'a, InferredContext> {
+    if ctx.config.asf.dev_list.is_some() {
+        return Cow::Borrowed(ctx);
+    }
+    let mut ctx = ctx.clone();
+    ctx.config.asf.dev_list = Some(roster.dev_list());
+    Cow::Owned(ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECTS: &str = r#"{"lastTimestamp":"20240501","projects":{
+        "opendal":{"createTimestamp":"20230101","members":["alice","bob","carol"],"owners":["alice","bob"]}
+    }}"#;
+    const COMMITTEES: &str =
+        r#"{"committees":{"opendal":{"display_name":"OpenDAL","mail_list":"opendal"}}}"#;
+
+    #[test]
+    fn parse_roster_resolves_roles_and_lists() {
+        let roster = parse_roster("opendal", PROJECTS, COMMITTEES).unwrap();
+        assert_eq!(roster.role_of("alice"), Some(AsfRole::Pmc));
+        assert_eq!(roster.role_of("Carol"), Some(AsfRole::Committer));
+        assert_eq!(roster.role_of("mallory"), None);
+        assert_eq!(roster.dev_list(), "dev@opendal.apache.org");
+
+        assert!(parse_roster("missing", PROJECTS, COMMITTEES).is_err());
+    }
+
+    #[test]
+    fn release_manager_normalises_fingerprint() {
+        let asf = AsfConfig {
+            release_manager: Some("alice".into()),
+            release_manager_name: Some("Alice Example".into()),
+            gpg_fingerprint: Some("0123 4567 89ab cdef 0123  4567 89AB CDEF 0123 4567".into()),
+            ..Default::default()
+        };
+        let rm = release_manager(&asf).unwrap().unwrap();
+        assert_eq!(rm.email, "alice@apache.org");
+        assert_eq!(
+            rm.gpg_fingerprint.as_deref(),
+            Some("0123456789ABCDEF0123456789ABCDEF01234567")
+        );
+
+        let short = AsfConfig {
+            gpg_fingerprint: Some("89ABCDEF".into()),
+            ..asf
+        };
+        assert!(release_manager(&short).is_err());
+        assert!(release_manager(&AsfConfig::default()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tokio::fs as async_fs;
+
+/// Content-addressed store of downloaded release assets keyed by sha512 digest.
+///
+/// Lives under `cache/sha512/` in the artifacts root so `sync`, `vote`, and `release`
+/// share it.
+#[derive(Debug, Clone)]
+pub struct AssetCache {
+    root: PathBuf,
+}
+
+impl AssetCache {
+    pub fn new(artifact_root: &Path) -> Self {
+        Self {
+            root: artifact_root.join("cache").join("sha512"),
+        }
+    }
+
+    fn object_path(&self, digest: &str) -> PathBuf {
+        self.root.join(digest.to_ascii_lowercase())
+    }
+
+    /// Place the cached object for `digest` at `dest`, returning false on a cache miss.
+    pub async fn materialize(&self, digest: &str, dest: &Path) -> Result<bool> {
+        let object = self.object_path(digest);
+        if !async_fs::try_exists(&object).await? {
+            return Ok(false);
+        }
+        if async_fs::try_exists(dest).await? {
+            async_fs::remove_file(dest).await?;
+        }
+        if async_fs::hard_link(&object, dest).await.is_err() {
+            async_fs::copy(&object, dest)
+                .await
+                .with_context(|| format!("failed to copy cached asset to {}", dest.display()))?;
+        }
+        Ok(true)
+    }
+
+    /// Store `path` under its already verified `digest`.
+    pub async fn insert(&self, digest: &str, path: &Path) -> Result<()> {
+        async_fs::create_dir_all(&self.root).await?;
+        let object = self.object_path(digest);
+        if async_fs::try_exists(&object).await? {
+            return Ok(());
+        }
+        // Copy to a temporary name first so interrupted runs never leave a truncated object.
+        let partial = object.with_extension("partial");
+        async_fs::copy(path, &partial).await?;
+        async_fs::rename(&partial, &object).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum;
+
+    #[tokio::test]
+    async fn insert_then_materialize_round_trips() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cache = AssetCache::new(dir.path());
+        let src = dir.path().join("a.tar.gz");
+        std::fs::write(&src, b"payload").unwrap();
+        let digest = checksum::sha512_file(&src).await.unwrap();
+
+        let dest = dir.path().join("out.tar.gz");
+        assert!(!cache.materialize(&digest, &dest).await.unwrap());
+
+        cache.insert(&digest, &src).await.unwrap();
+        assert!(cache.materialize(&digest, &dest).await.unwrap());
+        assert_eq!(std::fs::read(&dest).unwrap(), b"payload");
+    }
+}
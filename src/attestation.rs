@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::fs as async_fs;
+use tokio::process::Command;
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::cancel;
+use crate::github;
+use crate::infer::InferredContext;
+use crate::provenance;
+
+const PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+const PREDICATE_FILE: &str = "provenance-predicate.json";
+
+#[derive(Debug, Deserialize)]
+struct Repo {
+    private: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedAttestation {
+    id: u64,
+}
+
+/// Whether the job may mint a GitHub Actions OIDC token (`permissions: id-token: write`).
+fn oidc_available(env: impl Fn(&str) -> Option<String>) -> bool {
+    [
+        "ACTIONS_ID_TOKEN_REQUEST_URL",
+        "ACTIONS_ID_TOKEN_REQUEST_TOKEN",
+    ]
+    .iter()
+    .all(|key| env(key).is_some_and(|v| !v.is_empty()))
+}
+
+/// Register a GitHub artifact attestation for each archive, so `gh attestation verify` works.
+///
+/// `cosign attest-blob` signs the SLSA predicate from `provenance.intoto.json` with a
+/// Sigstore certificate issued for the workflow's OIDC identity; the resulting bundle is
+/// uploaded through the repository attestations API. Outside GitHub Actions this is a no-op.
+pub async fn attest(
+    ctx: &InferredContext,
+    run_dir: &Path,
+    archives: &[PathBuf],
+    audit: &AuditLog,
+) -> Result<()> {
+    if !oidc_available(|key| std::env::var(key).ok()) {
+        tracing::warn!(
+            "attestation: no GitHub Actions OIDC token (the job needs `permissions: id-token: write`); skipping"
+        );
+        return Ok(());
+    }
+    let gh = github::client()?;
+    let repo: Repo = gh
+        .get(
+            format!("/repos/{}/{}", ctx.repo_owner, ctx.repo_name),
+            None::<&()>,
+        )
+        .await
+        .context("failed to look up repository visibility")?;
+    if repo.private {
+        tracing::warn!(
+            "attestation: cosign records signatures in the public Sigstore log, which would expose a private repository; skipping"
+        );
+        return Ok(());
+    }
+
+    let predicate = write_predicate(run_dir).await?;
+    for archive in archives {
+        let name = archive
+            .file_name()
+            .and_then(|n| n.to_str())
+            .with_context(|| format!("invalid artifact path {}", archive.display()))?;
+        cancel::checkpoint(&format!("attesting {}", name))?;
+        let bundle_path = PathBuf::from(format!("{}.sigstore.json", archive.display()));
+        cosign_attest(archive, &predicate, &bundle_path).await?;
+        let bundle: Value = serde_json::from_slice(&async_fs::read(&bundle_path).await?)
+            .with_context(|| format!("invalid Sigstore bundle {}", bundle_path.display()))?;
+        let created: CreatedAttestation = gh
+            .post(
+                format!("/repos/{}/{}/attestations", ctx.repo_owner, ctx.repo_name),
+                Some(&json!({ "bundle": bundle })),
+            )
+            .await
+            .with_context(|| format!("failed to register the attestation for {}", name))?;
+        audit
+            .record(
+                AuditAction::AttestationCreated,
+                &format!("{}#{}", name, created.id),
+            )
+            .await;
+    }
+    println!(
+        "attestation: registered {} attestations; verify with `gh attestation verify <file> --repo {}/{}`",
+        archives.len(),
+        ctx.repo_owner,
+        ctx.repo_name
+    );
+    Ok(())
+}
+
+/// cosign takes the predicate alone and builds the statement around the blob itself.
+async fn write_predicate(run_dir: &Path) -> Result<PathBuf> {
+    let statement_path = run_dir.join(provenance::FILE_NAME);
+    let statement: Value = serde_json::from_slice(
+        &async_fs::read(&statement_path)
+            .await
+            .with_context(|| format!("failed to read {}", statement_path.display()))?,
+    )?;
+    let path = run_dir.join(PREDICATE_FILE);
+    async_fs::write(&path, serde_json::to_vec_pretty(&statement["predicate"])?).await?;
+    Ok(path)
+}
+
+async fn cosign_attest(archive: &Path, predicate: &Path, bundle: &Path) -> Result<()> {
+    tracing::info!("cosign: attesting {}", archive.display());
+    let output = cancel::output(
+        Command::new("cosign")
+            .args([
+                "attest-blob",
+                "--yes",
+                "--new-bundle-format",
+                "--type",
+                PREDICATE_TYPE,
+                "--predicate",
+            ])
+            .arg(predicate)
+            .arg("--bundle")
+            .arg(bundle)
+            .arg(archive),
+    )
+    .await
+    .context("failed to run cosign; prerelease.attest needs cosign on PATH")?;
+    if !output.status.success() {
+        bail!(
+            "cosign attest-blob {} failed: {}",
+            archive.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::oidc_available;
+
+    #[test]
+    fn oidc_requires_both_request_variables() {
+        let env = |url: Option<&str>, token: Option<&str>| {
+            let (url, token) = (url.map(String::from), token.map(String::from));
+            move |key: &str| match key {
+                "ACTIONS_ID_TOKEN_REQUEST_URL" => url.clone(),
+                "ACTIONS_ID_TOKEN_REQUEST_TOKEN" => token.clone(),
+                _ => None,
+            }
+        };
+        assert!(oidc_available(env(Some("https://token"), Some("t"))));
+        assert!(!oidc_available(env(Some("https://token"), None)));
+        assert!(!oidc_available(env(Some("https://token"), Some(""))));
+    }
+}
@@ -0,0 +1,223 @@
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+use crate::artifacts;
+use crate::discussion;
+use crate::github;
+use crate::infer::InferredContext;
+use crate::redact;
+use crate::schema;
+use crate::start;
+
+/// Identifies this asfship process in its audit entries, so a run can list exactly what
+/// it did even when other runs append to the same log.
+static RUN_ID: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "{}-{}",
+        Utc::now().format("%Y%m%dT%H%M%SZ"),
+        std::process::id()
+    )
+});
+
+/// Mutating operations recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    CommitCreated,
+    TagCreated,
+    BranchPushed,
+    TagPushed,
+    ReleaseCreated,
+    AssetUploaded,
+    DiscussionCreated,
+    DiscussionCommented,
+    SvnDirectoryCreated,
+    SvnCommitted,
+    SvnRemoved,
+    CratePublished,
+    CrateYanked,
+    ReleaseEdited,
+    AssetDeleted,
+    AttestationCreated,
+    PullRequestCreated,
+    PullRequestCommented,
+    PullRequestLabeled,
+    MilestoneCreated,
+    MilestoneClosed,
+    IssueMilestoneChanged,
+}
+
+#[derive(Debug, Serialize)]
+struct AuditEntry<'a> {
+    schema_version: u32,
+    timestamp: String,
+    run: &'a str,
+    actor: &'a str,
+    action: AuditAction,
+    target: &'a str,
+}
+
+/// Append-only JSON Lines log stored at `audit.jsonl` in the artifacts root
+/// (`target/asfship` unless `artifacts.dir` or `--artifact-dir` moves it).
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    actor: String,
+}
+
+impl AuditLog {
+    pub async fn open(ctx: &InferredContext) -> Self {
+        let root = ctx.repo_root.clone();
+        let actor = tokio::task::spawn_blocking(move || resolve_actor(&root))
+            .await
+            .unwrap_or_else(|_| String::from("asfship"));
+        Self {
+            path: audit_path(&artifacts::root(ctx)),
+            actor,
+        }
+    }
+
+    /// Record an operation; failures are logged but never abort the caller.
+    pub async fn record(&self, action: AuditAction, target: &str) {
+        if let Err(err) = self.append(action, target).await {
+            tracing::warn!(error=%err, path=%self.path.display(), "audit: failed to append entry");
+        }
+    }
+
+    async fn append(&self, action: AuditAction, target: &str) -> Result<()> {
+        let entry = AuditEntry {
+            schema_version: schema::SCHEMA_VERSION,
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            run: &RUN_ID,
+            actor: &self.actor,
+            action,
+            target: &redact::redact(target),
+        };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("failed to open {}", self.path.display()))?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordedEntry {
+    /// Absent in entries written before runs were tagged.
+    #[serde(default)]
+    run: Option<String>,
+    action: String,
+    target: String,
+}
+
+/// Operations this run recorded, formatted as `action target`.
+pub async fn completed_in_run(ctx: &InferredContext) -> Vec<String> {
+    let content = tokio::fs::read_to_string(audit_path(&artifacts::root(ctx)))
+        .await
+        .unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<RecordedEntry>(line).ok())
+        .filter(|entry| entry.run.as_deref() == Some(RUN_ID.as_str()))
+        .map(|entry| format!("{} {}", entry.action, entry.target))
+        .collect()
+}
+
+/// With `discussion.audit_comment`, comment the operations this run recorded on the
+/// kickoff discussion, when there is one. Failures only warn; the log file stays the record.
+pub async fn comment_on_kickoff(ctx: &InferredContext) {
+    if !ctx.config.discussion.audit_comment {
+        return;
+    }
+    let done = completed_in_run(ctx).await;
+    if done.is_empty() {
+        return;
+    }
+    if let Err(err) = post_kickoff_comment(ctx, &done).await {
+        tracing::warn!(
+            "audit: failed to comment on the kickoff discussion: {:#}",
+            err
+        );
+    }
+}
+
+async fn post_kickoff_comment(ctx: &InferredContext, done: &[String]) -> Result<()> {
+    let gh = github::client()?;
+    let title = start::kickoff_title(&ctx.repo_name);
+    let Some(kickoff) =
+        discussion::find_by_title(&gh, &ctx.repo_owner, &ctx.repo_name, &title).await?
+    else {
+        tracing::debug!("audit: no kickoff discussion {:?}; not commenting", title);
+        return Ok(());
+    };
+    let mut body = String::from("## asfship audit log\n\n");
+    for step in done {
+        body.push_str(&format!("- `{}`\n", step));
+    }
+    discussion::post_comment(&gh, &ctx.repo_owner, &ctx.repo_name, kickoff.number, &body).await?;
+    AuditLog::open(ctx)
+        .await
+        .record(AuditAction::DiscussionCommented, &kickoff.html_url)
+        .await;
+    Ok(())
+}
+
+/// The audit log in `artifact_root`.
+pub fn audit_path(artifact_root: &Path) -> PathBuf {
+    artifact_root.join("audit.jsonl")
+}
+
+fn resolve_actor(repo_root: &Path) -> String {
+    Repository::discover(repo_root)
+        .and_then(|repo| repo.signature())
+        .ok()
+        .and_then(|sig| {
+            let name = sig.name()?.to_string();
+            Some(match sig.email() {
+                Some(email) if !email.is_empty() => format!("{} <{}>", name, email),
+                _ => name,
+            })
+        })
+        .unwrap_or_else(|| String::from("asfship"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn record_appends_json_lines() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log = AuditLog {
+            path: audit_path(dir.path()),
+            actor: "Jane <jane@example.com>".into(),
+        };
+        log.record(AuditAction::TagCreated, "v0.1.0-rc.1").await;
+        log.record(AuditAction::TagPushed, "v0.1.0-rc.1").await;
+
+        let content = std::fs::read_to_string(audit_path(dir.path())).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["action"], "tag_created");
+        assert_eq!(first["actor"], "Jane <jane@example.com>");
+        assert_eq!(first["target"], "v0.1.0-rc.1");
+        assert_eq!(first["run"], RUN_ID.as_str());
+    }
+}
@@ -0,0 +1,208 @@
+use std::path::Path;
+
+use anyhow::{Result, anyhow, bail};
+use git2::{Commit, FileMode, ObjectType, Oid, Repository, Signature, Tree};
+use tokio::process::Command;
+
+use crate::cancel;
+use crate::partial_clone;
+
+/// Branch that generated files are committed to, resolved against origin.
+pub struct BranchTarget {
+    pub branch: String,
+    /// The branch is checked out, so its working tree copies must follow the commit.
+    pub on_current: bool,
+    /// Tip to commit on top of; `None` starts a new orphan branch.
+    pub base: Option<Oid>,
+}
+
+impl BranchTarget {
+    /// Resolve `branch` (or the current branch), fetching it from origin first when it
+    /// is not checked out so the commit lands on top of what is published.
+    pub async fn resolve(repo_root: &Path, branch: Option<&str>, key: &str) -> Result<Self> {
+        let repo = Repository::discover(repo_root)?;
+        let current = if repo.head_detached().unwrap_or(true) {
+            None
+        } else {
+            repo.head()?.shorthand().map(str::to_string)
+        };
+        let branch = match (branch, &current) {
+            (Some(branch), _) => branch.to_string(),
+            (None, Some(current)) => current.clone(),
+            (None, None) => bail!("HEAD is detached; set {} to choose a branch", key),
+        };
+        let on_current = current.as_deref() == Some(branch.as_str());
+        if on_current {
+            return Ok(Self {
+                base: Some(repo.head()?.peel_to_commit()?.id()),
+                branch,
+                on_current,
+            });
+        }
+        fetch_branch(repo_root, &branch).await;
+        let base = [
+            format!("refs/remotes/origin/{}", branch),
+            format!("refs/heads/{}", branch),
+        ]
+        .iter()
+        .find_map(|r| repo.revparse_single(r).ok())
+        .map(|o| o.peel_to_commit().map(|c| c.id()))
+        .transpose()?;
+        Ok(Self {
+            branch,
+            on_current,
+            base,
+        })
+    }
+
+    /// Contents of `path` at the branch tip, if present.
+    pub fn read(&self, repo: &Repository, path: &str) -> Result<Option<Vec<u8>>> {
+        let Some(base) = self.base else {
+            return Ok(None);
+        };
+        let tree = repo.find_commit(base)?.tree()?;
+        match tree.get_path(Path::new(path)) {
+            Ok(entry) => Ok(Some(
+                partial_clone::find_blob(repo, entry.id(), Path::new(path))?
+                    .content()
+                    .to_vec(),
+            )),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Commit `files` on top of the tip and point `refs/heads/<into>` at it.
+    ///
+    /// The working tree is only touched when `into` is the checked-out branch. Returns
+    /// `None` when the files already hold these contents.
+    pub fn commit(
+        &self,
+        repo: &Repository,
+        into: &str,
+        files: &[(String, Vec<u8>)],
+        sig: &Signature<'_>,
+        message: &str,
+    ) -> Result<Option<Oid>> {
+        let base: Option<Commit> = self.base.map(|id| repo.find_commit(id)).transpose()?;
+        let base_tree = base.as_ref().map(|c| c.tree()).transpose()?;
+        let mut tree = base_tree.as_ref().map(|t| t.id());
+        for (path, content) in files {
+            let blob = repo.blob(content)?;
+            let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+            let root = tree.map(|id| repo.find_tree(id)).transpose()?;
+            tree = Some(upsert_blob(repo, root.as_ref(), &parts, blob)?);
+        }
+        let Some(tree) = tree else {
+            return Ok(None);
+        };
+        if base_tree.as_ref().is_some_and(|t| t.id() == tree) {
+            return Ok(None);
+        }
+        let tree = repo.find_tree(tree)?;
+        let parents: Vec<&Commit> = base.iter().collect();
+        let oid = repo.commit(None, sig, sig, message, &tree, &parents)?;
+        repo.reference(&format!("refs/heads/{}", into), oid, true, message)?;
+        if self.on_current && into == self.branch {
+            let mut checkout = git2::build::CheckoutBuilder::new();
+            checkout.force();
+            for (path, _) in files {
+                checkout.path(path);
+            }
+            repo.checkout_head(Some(&mut checkout))?;
+        }
+        Ok(Some(oid))
+    }
+}
+
+/// Write `blob` at `path` below `tree`, creating intermediate trees as needed.
+fn upsert_blob(repo: &Repository, tree: Option<&Tree>, path: &[&str], blob: Oid) -> Result<Oid> {
+    let (name, rest) = path
+        .split_first()
+        .ok_or_else(|| anyhow!("empty file path"))?;
+    let mut builder = repo.treebuilder(tree)?;
+    if rest.is_empty() {
+        builder.insert(name, blob, FileMode::Blob.into())?;
+    } else {
+        let child = tree
+            .and_then(|t| t.get_name(name))
+            .filter(|e| e.kind() == Some(ObjectType::Tree))
+            .map(|e| repo.find_tree(e.id()))
+            .transpose()?;
+        let id = upsert_blob(repo, child.as_ref(), rest, blob)?;
+        builder.insert(name, id, FileMode::Tree.into())?;
+    }
+    Ok(builder.write()?)
+}
+
+/// A branch that does not exist on origin yet is created from scratch.
+async fn fetch_branch(repo_root: &Path, branch: &str) {
+    let result = Command::new("git")
+        .arg("-C")
+        .arg(repo_root)
+        .args(["fetch", "origin"])
+        .arg(format!("+refs/heads/{0}:refs/remotes/origin/{0}", branch))
+        .output()
+        .await;
+    if !result.is_ok_and(|o| o.status.success()) {
+        tracing::warn!(
+            "git: could not fetch {} from origin; starting it locally",
+            branch
+        );
+    }
+}
+
+pub async fn push_branch(repo_root: &Path, branch: &str) -> Result<()> {
+    let status = cancel::status(
+        Command::new("git")
+            .arg("-C")
+            .arg(repo_root)
+            .arg("push")
+            .arg("origin")
+            .arg(format!("refs/heads/{0}:refs/heads/{0}", branch)),
+    )
+    .await?;
+    if !status.success() {
+        bail!("git push {} failed with status: {}", branch, status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commit_writes_nested_files_on_an_orphan_branch() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let sig = Signature::now("a", "a@example.com").unwrap();
+        let target = BranchTarget {
+            branch: "gh-pages".into(),
+            on_current: false,
+            base: None,
+        };
+        let files = vec![
+            ("static/releases.json".to_string(), b"[]".to_vec()),
+            ("index.md".to_string(), b"hi".to_vec()),
+        ];
+        let oid = target
+            .commit(&repo, "gh-pages", &files, &sig, "add")
+            .unwrap()
+            .unwrap();
+
+        let next = BranchTarget {
+            base: Some(oid),
+            ..target
+        };
+        assert_eq!(
+            next.read(&repo, "static/releases.json").unwrap().as_deref(),
+            Some(&b"[]"[..])
+        );
+        assert!(
+            next.commit(&repo, "gh-pages", &files, &sig, "again")
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(repo.refname_to_id("refs/heads/gh-pages").unwrap(), oid);
+    }
+}
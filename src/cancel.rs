@@ -0,0 +1,177 @@
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::process::{ExitStatus, Output, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::Result;
+use tokio::process::{Child, Command};
+use tokio_util::sync::CancellationToken;
+
+static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+/// Process groups of the shielded children still running.
+static CHILDREN: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// Returned when a run stops at a checkpoint after Ctrl-C.
+#[derive(Debug)]
+pub struct Interrupted {
+    pub step: String,
+}
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "interrupted before {}", self.step)
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+fn token() -> &'static CancellationToken {
+    TOKEN.get_or_init(CancellationToken::new)
+}
+
+/// Turn the first Ctrl-C into a cancellation request; a second one interrupts the
+/// running children and exits at once.
+pub fn install() {
+    let token = token().clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!(
+            "Interrupt received; stopping after the current step (Ctrl-C again to abort now)"
+        );
+        token.cancel();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("Aborted.");
+            interrupt_children();
+            std::process::exit(130);
+        }
+    });
+}
+
+/// Fail with [`Interrupted`] if Ctrl-C was pressed; call before each side effect.
+pub fn checkpoint(step: &str) -> Result<()> {
+    check(token(), step)
+}
+
+fn check(token: &CancellationToken, step: &str) -> Result<()> {
+    if token.is_cancelled() {
+        return Err(Interrupted {
+            step: step.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Run `fut`, abandoning it if Ctrl-C arrives first.
+///
+/// Only for work that is safe to drop midway, such as downloads or polling.
+pub async fn abortable<T>(step: &str, fut: impl Future<Output = Result<T>>) -> Result<T> {
+    race(token(), step, fut).await
+}
+
+async fn race<T>(
+    token: &CancellationToken,
+    step: &str,
+    fut: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::select! {
+        res = fut => res,
+        _ = token.cancelled() => Err(Interrupted { step: step.to_string() }.into()),
+    }
+}
+
+/// Run `cmd` to completion outside the terminal's process group and return its exit
+/// status.
+///
+/// The terminal delivers Ctrl-C to its whole foreground process group; keeping a
+/// step's child out of it lets the step finish before the run stops at the next
+/// checkpoint. The child cannot prompt on the terminal, so credentials must come
+/// from an agent or helper.
+pub async fn status(cmd: &mut Command) -> io::Result<ExitStatus> {
+    let mut child = spawn(cmd)?;
+    let _running = Running::register(&child);
+    child.wait().await
+}
+
+/// Like [`status`], capturing stdout and stderr.
+pub async fn output(cmd: &mut Command) -> io::Result<Output> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let child = spawn(cmd)?;
+    let _running = Running::register(&child);
+    child.wait_with_output().await
+}
+
+fn spawn(cmd: &mut Command) -> io::Result<Child> {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd.spawn()
+}
+
+/// A shielded child's entry in [`CHILDREN`], removed once it has been waited for.
+struct Running(Option<u32>);
+
+impl Running {
+    fn register(child: &Child) -> Self {
+        let pid = child.id();
+        if let Some(pid) = pid {
+            CHILDREN.lock().unwrap_or_else(|e| e.into_inner()).push(pid);
+        }
+        Self(pid)
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            let mut children = CHILDREN.lock().unwrap_or_else(|e| e.into_inner());
+            children.retain(|&p| p != pid);
+        }
+    }
+}
+
+/// Send SIGINT to every shielded child's process group, as the terminal would have.
+fn interrupt_children() {
+    #[cfg(unix)]
+    for &pid in CHILDREN.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        // SAFETY: kill(2) takes no pointers; a stale group id only yields ESRCH.
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), libc::SIGINT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn checkpoint_fails_after_cancel() {
+        let token = CancellationToken::new();
+        assert!(check(&token, "tag").is_ok());
+        token.cancel();
+        let err = check(&token, "tag").unwrap_err();
+        assert!(err.downcast_ref::<Interrupted>().is_some());
+        let err = race(&token, "poll", std::future::pending::<Result<()>>())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "interrupted before poll");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn shielded_children_lead_their_own_process_group() {
+        let out = output(Command::new("sh").args(["-c", "echo $$ $(ps -o pgid= -p $$)"]))
+            .await
+            .unwrap();
+        let stdout = String::from_utf8(out.stdout).unwrap();
+        let ids: Vec<&str> = stdout.split_whitespace().collect();
+        assert_eq!(ids.len(), 2, "{}", stdout);
+        assert_eq!(ids[0], ids[1]);
+        assert!(CHILDREN.lock().unwrap().is_empty());
+    }
+}
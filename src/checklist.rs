@@ -0,0 +1,350 @@
+use std::path::Path;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+
+use crate::artifacts;
+use crate::asf;
+use crate::config::VoteConfig;
+use crate::result_cmd::Quota;
+use crate::timeline;
+use crate::vote::MIN_VOTE_HOURS;
+
+/// Where one step of the ASF release policy stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mark {
+    Done,
+    Open,
+    Failed,
+    /// Not something asfship can check; the release manager ticks it off.
+    Manual,
+}
+
+impl Mark {
+    fn symbol(self) -> &'static str {
+        match self {
+            Mark::Done => "[x]",
+            Mark::Open => "[ ]",
+            Mark::Failed => "[!]",
+            Mark::Manual => "[?]",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Item {
+    pub text: String,
+    pub mark: Mark,
+    pub note: Option<String>,
+}
+
+impl Item {
+    fn new(text: impl Into<String>, mark: Mark, note: Option<String>) -> Self {
+        Self {
+            text: text.into(),
+            mark,
+            note,
+        }
+    }
+}
+
+/// What the timeline knows about the release the checklist is for.
+#[derive(Debug, Default)]
+pub struct Progress {
+    /// Most recent rc cut for the version.
+    pub rc_tag: Option<String>,
+    pub vote_opened: Option<DateTime<Utc>>,
+    pub vote_closed: Option<DateTime<Utc>>,
+    /// How `result` recorded the vote on `rc_tag`.
+    pub vote_passed: Option<bool>,
+    /// `release` promoted the artifacts to `dist/release`.
+    pub promoted: bool,
+    pub released: bool,
+}
+
+/// The ASF release policy steps in the order a release meets them, each checked against
+/// the repository, the rc's run directory under `artifact_root`, and `progress`. The vote
+/// lines state the period and quota `vote` configures.
+pub fn evaluate(
+    repo_root: &Path,
+    artifact_root: &Path,
+    vote: &VoteConfig,
+    progress: &Progress,
+    now: DateTime<Utc>,
+) -> Vec<Item> {
+    vec![
+        legal_files(repo_root),
+        signatures(artifact_root, progress),
+        vote_period(vote, progress, now),
+        binding_votes(vote, progress),
+        dist_release(progress),
+        if progress.released {
+            Item::new(
+                "Release announced",
+                Mark::Done,
+                Some("also mail announce@apache.org from an apache.org address".into()),
+            )
+        } else {
+            Item::new("Release announced", Mark::Open, None)
+        },
+    ]
+}
+
+/// Checklist lines for terminal output, one per item.
+pub fn render_text(items: &[Item]) -> Vec<String> {
+    let mut lines = vec!["checklist (ASF release policy):".to_string()];
+    for item in items {
+        let mut line = format!("  {} {}", item.mark.symbol(), item.text);
+        if let Some(note) = &item.note {
+            line.push_str(&format!(" ({})", note));
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn dist_release(progress: &Progress) -> Item {
+    let text = "dist/release updated with the voted artifacts";
+    if progress.promoted {
+        return Item::new(
+            text,
+            Mark::Done,
+            Some("promoted by `asfship release`".into()),
+        );
+    }
+    Item::new(
+        text,
+        Mark::Manual,
+        Some(
+            "set release.promote_dist, or copy the rc artifacts from dist/dev to dist/release without their -rcN marker once the vote passes".into(),
+        ),
+    )
+}
+
+fn legal_files(repo_root: &Path) -> Item {
+    let missing: Vec<&str> = ["LICENSE", "NOTICE"]
+        .into_iter()
+        .filter(|name| {
+            !["", ".txt", ".md"]
+                .iter()
+                .any(|ext| repo_root.join(format!("{}{}", name, ext)).is_file())
+        })
+        .collect();
+    let text = "LICENSE and NOTICE at the repository root";
+    if missing.is_empty() {
+        Item::new(text, Mark::Done, None)
+    } else {
+        Item::new(
+            text,
+            Mark::Failed,
+            Some(format!("missing {}", missing.join(", "))),
+        )
+    }
+}
+
+fn signatures(artifact_root: &Path, progress: &Progress) -> Item {
+    let text = "Source archives signed (.asc) and checksummed (.sha512)";
+    let Some(tag) = &progress.rc_tag else {
+        return Item::new(text, Mark::Open, None);
+    };
+    let dir = artifact_root.join(artifacts::dir_name(tag));
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Item::new(
+            text,
+            Mark::Manual,
+            Some(format!("no local files for {}; run `asfship verify`", tag)),
+        );
+    };
+    let names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    let archives: Vec<&String> = names.iter().filter(|n| asf::is_archive(n)).collect();
+    if archives.is_empty() {
+        return Item::new(text, Mark::Open, Some(format!("no archives for {}", tag)));
+    }
+    let unsigned: Vec<&str> = archives
+        .iter()
+        .filter(|a| {
+            ["asc", "sha512"]
+                .iter()
+                .any(|ext| !names.contains(&format!("{}.{}", a, ext)))
+        })
+        .map(|a| a.as_str())
+        .collect();
+    if unsigned.is_empty() {
+        Item::new(text, Mark::Done, Some(tag.clone()))
+    } else {
+        Item::new(
+            text,
+            Mark::Open,
+            Some(format!("not yet for {}", unsigned.join(", "))),
+        )
+    }
+}
+
+fn vote_period(vote: &VoteConfig, progress: &Progress, now: DateTime<Utc>) -> Item {
+    let hours = vote.duration_hours.unwrap_or(MIN_VOTE_HOURS);
+    let text = format!("Vote open for at least {} hours", hours);
+    let minimum = TimeDelta::hours(hours.into());
+    match (progress.vote_opened, progress.vote_closed) {
+        (None, _) => Item::new(text, Mark::Open, None),
+        (Some(opened), None) => {
+            let open = now - opened;
+            let note = if open >= minimum {
+                format!("open for {}; may close now", timeline::human_duration(open))
+            } else {
+                format!(
+                    "open for {}; closes no earlier than {}",
+                    timeline::human_duration(open),
+                    (opened + minimum).format("%Y-%m-%d %H:%M UTC")
+                )
+            };
+            Item::new(text, Mark::Open, Some(note))
+        }
+        (Some(opened), Some(closed)) => {
+            let held = timeline::human_duration(closed - opened);
+            if closed - opened >= minimum {
+                Item::new(text, Mark::Done, Some(held))
+            } else {
+                Item::new(text, Mark::Failed, Some(format!("closed after {}", held)))
+            }
+        }
+    }
+}
+
+fn binding_votes(vote: &VoteConfig, progress: &Progress) -> Item {
+    let text = format!("Vote passed with {}", Quota::configured(vote));
+    match progress.vote_passed {
+        None => Item::new(text, Mark::Open, None),
+        Some(true) => Item::new(text, Mark::Done, None),
+        Some(false) => Item::new(text, Mark::Failed, Some("the vote failed".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn items_follow_the_release_progress() {
+        let dir = TempDir::new().unwrap();
+        let opened = DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let marks = |items: &[Item]| items.iter().map(|i| i.mark).collect::<Vec<_>>();
+
+        let kickoff = evaluate(
+            dir.path(),
+            dir.path(),
+            &VoteConfig::default(),
+            &Progress::default(),
+            opened,
+        );
+        assert_eq!(
+            marks(&kickoff),
+            [
+                Mark::Failed,
+                Mark::Open,
+                Mark::Open,
+                Mark::Open,
+                Mark::Manual,
+                Mark::Open
+            ]
+        );
+        assert_eq!(kickoff[0].note.as_deref(), Some("missing LICENSE, NOTICE"));
+
+        std::fs::write(dir.path().join("LICENSE"), "").unwrap();
+        std::fs::write(dir.path().join("NOTICE.txt"), "").unwrap();
+        let run_dir = dir.path().join("v0.2.0-rc.1");
+        std::fs::create_dir_all(&run_dir).unwrap();
+        for name in ["a-src.tar.gz", "a-src.tar.gz.sha512"] {
+            std::fs::write(run_dir.join(name), "").unwrap();
+        }
+        let mut progress = Progress {
+            rc_tag: Some("v0.2.0-rc.1".into()),
+            vote_opened: Some(opened),
+            ..Default::default()
+        };
+        let voting = evaluate(
+            dir.path(),
+            dir.path(),
+            &VoteConfig::default(),
+            &progress,
+            opened + TimeDelta::hours(5),
+        );
+        assert_eq!(
+            render_text(&voting)[1..4],
+            [
+                "  [x] LICENSE and NOTICE at the repository root",
+                "  [ ] Source archives signed (.asc) and checksummed (.sha512) (not yet for a-src.tar.gz)",
+                "  [ ] Vote open for at least 72 hours (open for 5h 0m; closes no earlier than 2024-05-04 10:00 UTC)",
+            ]
+        );
+
+        std::fs::write(run_dir.join("a-src.tar.gz.asc"), "").unwrap();
+        progress.vote_closed = Some(opened + TimeDelta::hours(72));
+        progress.vote_passed = Some(true);
+        progress.released = true;
+        let released = evaluate(
+            dir.path(),
+            dir.path(),
+            &VoteConfig::default(),
+            &progress,
+            opened,
+        );
+        assert_eq!(
+            marks(&released),
+            [
+                Mark::Done,
+                Mark::Done,
+                Mark::Done,
+                Mark::Done,
+                Mark::Manual,
+                Mark::Done
+            ]
+        );
+
+        progress.promoted = true;
+        let promoted = evaluate(
+            dir.path(),
+            dir.path(),
+            &VoteConfig::default(),
+            &progress,
+            opened,
+        );
+        assert_eq!(
+            render_text(&promoted)[5],
+            "  [x] dist/release updated with the voted artifacts (promoted by `asfship release`)"
+        );
+    }
+
+    #[test]
+    fn vote_lines_follow_the_vote_config() {
+        let dir = TempDir::new().unwrap();
+        let opened = DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let vote = VoteConfig {
+            duration_hours: Some(120),
+            required_binding: Some(5),
+            allow_negative: Some(false),
+            ..Default::default()
+        };
+        let progress = Progress {
+            vote_opened: Some(opened),
+            vote_closed: Some(opened + TimeDelta::hours(96)),
+            ..Default::default()
+        };
+        let items = evaluate(dir.path(), dir.path(), &vote, &progress, opened);
+        assert_eq!(
+            render_text(&items)[3..5],
+            [
+                "  [!] Vote open for at least 120 hours (closed after 4d 0h)",
+                "  [ ] Vote passed with at least 5 binding +1 votes and no binding -1 votes",
+            ]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Deserialize;
+use sha2::{Digest, Sha512};
+use tokio::io::AsyncReadExt;
+
+/// Layout of generated `.sha512` files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumFormat {
+    /// `<digest>  <filename>` as produced by `shasum -a 512` and `sha512sum`.
+    #[default]
+    Gnu,
+    /// `SHA512 (<filename>) = <digest>` as produced by `shasum --tag` and BSD `sha512`.
+    Bsd,
+    /// The bare hex digest.
+    Bare,
+}
+
+impl ChecksumFormat {
+    pub fn render(self, digest: &str, file_name: &str) -> String {
+        match self {
+            ChecksumFormat::Gnu => format!("{}  {}\n", digest, file_name),
+            ChecksumFormat::Bsd => format!("SHA512 ({}) = {}\n", file_name, digest),
+            ChecksumFormat::Bare => format!("{}\n", digest),
+        }
+    }
+}
+
+/// Compute the hex-encoded sha512 digest of a file.
+pub async fn sha512_file(path: &Path) -> Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha512::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Extract the sha512 digest for `file_name` from checksum file content in any supported format.
+///
+/// Lines naming a different file are skipped; a bare digest is accepted for any file.
+pub fn parse_sha512(content: &str, file_name: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        if let Some(rest) = line.strip_prefix("SHA512 (") {
+            let (name, digest) = rest.split_once(") = ")?;
+            return matching_digest(name, digest, file_name);
+        }
+        match line.split_once(char::is_whitespace) {
+            Some((digest, name)) => {
+                let name = name.trim_start().trim_start_matches('*');
+                matching_digest(name, digest, file_name)
+            }
+            None => normalize_digest(line),
+        }
+    })
+}
+
+fn matching_digest(name: &str, digest: &str, file_name: &str) -> Option<String> {
+    if name == file_name {
+        normalize_digest(digest)
+    } else {
+        None
+    }
+}
+
+fn normalize_digest(digest: &str) -> Option<String> {
+    let digest = digest.trim();
+    (digest.len() == 128 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| digest.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAME: &str = "apache-foo-0.1.0-src.tar.gz";
+
+    fn digest() -> String {
+        "ab".repeat(64)
+    }
+
+    #[test]
+    fn parse_accepts_all_formats() {
+        for format in [
+            ChecksumFormat::Gnu,
+            ChecksumFormat::Bsd,
+            ChecksumFormat::Bare,
+        ] {
+            let rendered = format.render(&digest(), NAME);
+            assert_eq!(parse_sha512(&rendered, NAME), Some(digest()), "{format:?}");
+        }
+        let binary_mode = format!("{} *{}\n", digest(), NAME);
+        assert_eq!(parse_sha512(&binary_mode, NAME), Some(digest()));
+    }
+
+    #[test]
+    fn parse_skips_other_files() {
+        let content = format!("{}  other.zip\n", "cd".repeat(64));
+        assert_eq!(parse_sha512(&content, NAME), None);
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt::Write as _;
+
+use anyhow::Result;
+
+use crate::infer::InferredContext;
+use crate::versioning::{EntryFormat, HIGHLIGHTS, Plan};
+
+/// Whether this run releases a component with its own `[crates.<name>] tag_prefix`
+/// rather than the workspace as a whole.
+pub fn is_component(ctx: &InferredContext) -> bool {
+    ctx.config
+        .crates
+        .get(&ctx.main_crate)
+        .is_some_and(|c| c.tag_prefix.is_some())
+}
+
+/// Name and body of the GitHub Release for `tag`.
+///
+/// Workspace releases are named after the tag and get no body. A component release is
+/// named `<crate> vX.Y.Z`, since a bare version does not say what it ships, and its
+/// body lists the highlights and then the changelog entries of the planned crates,
+/// rendered as in CHANGELOG.md. `release` appends the stable release's links to either.
+pub fn github_release(ctx: &InferredContext, plan: &Plan, tag: &str) -> Result<(String, String)> {
+    if !is_component(ctx) {
+        return Ok((tag.to_string(), String::new()));
+    }
+    let format = EntryFormat::new(&ctx.config.changelog)?;
+    let version = ctx
+        .config
+        .tags()
+        .strip_prefix(tag)
+        .unwrap_or(tag)
+        .to_string();
+    let name = format!("{} v{}", ctx.main_crate, version);
+    let mut body = String::new();
+    let highlights = highlights(&format, plan)?;
+    if !highlights.is_empty() {
+        writeln!(&mut body, "### {}\n", HIGHLIGHTS).unwrap();
+        for line in &highlights {
+            writeln!(&mut body, "- {}", line).unwrap();
+        }
+        body.push('\n');
+    }
+    for (crate_name, crate_plan) in plan.iter() {
+        writeln!(
+            &mut body,
+            "### {} {}\n",
+            crate_name,
+            crate_plan.new_version()
+        )
+        .unwrap();
+        for change in crate_plan.changes().iter().filter(|c| c.in_changelog()) {
+            writeln!(&mut body, "- {}", format.render(change)?).unwrap();
+        }
+        body.push('\n');
+    }
+    Ok((name, body.trim_end().to_string()))
+}
+
+/// The highlighted changes of `plan` as they read under Highlights, without duplicates.
+pub fn highlights(format: &EntryFormat, plan: &Plan) -> Result<Vec<String>> {
+    let mut highlights: Vec<String> = Vec::new();
+    for (_, crate_plan) in plan.iter() {
+        for change in crate_plan
+            .changes()
+            .iter()
+            .filter(|c| c.in_changelog() && c.is_highlight())
+        {
+            let line = format.render_highlight(change)?;
+            if !highlights.contains(&line) {
+                highlights.push(line);
+            }
+        }
+    }
+    Ok(highlights)
+}
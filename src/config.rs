@@ -1,11 +1,751 @@
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use serde::Deserialize;
 
-#[derive(Debug, Default, Deserialize)]
+use crate::checksum::ChecksumFormat;
+use crate::notify::Milestone;
+use crate::promote;
+use crate::redact;
+use crate::tags::TagFormat;
+use crate::versioning::{CommitKind, EntryFormat};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct MinimalConfig {
     pub main_crate: Option<String>,
+    /// Release only these crates, so components of a large workspace can ship on their
+    /// own cadence; `--only` overrides it per invocation.
+    #[serde(default)]
+    pub only: Vec<String>,
+    /// Text before `X.Y.Z` in release tags, e.g. `release-`; defaults to `v`.
+    pub tag_prefix: Option<String>,
+    #[serde(default)]
+    pub checksum_format: ChecksumFormat,
+    /// Plan version bumps and changelogs for `publish = false` crates.
+    #[serde(default)]
+    pub bump_unpublished: bool,
+    /// Crates credited with changes to files at the root of a virtual workspace.
+    #[serde(default)]
+    pub root_files: RootFiles,
+    #[serde(default)]
+    pub crates: BTreeMap<String, CrateConfig>,
+    #[serde(default)]
+    pub vote: VoteConfig,
+    #[serde(default)]
+    pub discussion: DiscussionConfig,
+    #[serde(default)]
+    pub asf: AsfConfig,
+    #[serde(default)]
+    pub release: ReleaseConfig,
+    #[serde(default)]
+    pub changelog: ChangelogConfig,
+    #[serde(default)]
+    pub bootstrap: BootstrapConfig,
+    #[serde(default)]
+    pub prerelease: PrereleaseConfig,
+    #[serde(default)]
+    pub package: PackageConfig,
+    #[serde(default)]
+    pub artifacts: ArtifactsConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub notifications: Vec<NotificationConfig>,
+    #[serde(default)]
+    pub github: GithubConfig,
+    #[serde(default)]
+    pub git: GitConfig,
+}
+
+impl MinimalConfig {
+    pub fn tags(&self) -> TagFormat {
+        self.tag_prefix
+            .as_deref()
+            .map(TagFormat::new)
+            .unwrap_or_default()
+    }
+}
+
+/// Attribution of files directly in the workspace root (`Cargo.toml`, `Cargo.lock`,
+/// `rust-toolchain.toml`, ...) when no crate lives there.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RootFiles {
+    /// No crate; such commits reach no changelog.
+    #[default]
+    None,
+    /// The main crate only.
+    Main,
+    /// Every workspace crate.
+    All,
+}
+
+/// Identity of release commits and tags under `[git]`; see `GitIdentity::resolve`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GitConfig {
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+}
+
+/// How asfship authenticates to GitHub, under `[github]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GithubConfig {
+    #[serde(default)]
+    pub auth: GithubAuth,
+    /// GitHub App id, for `auth = "app"`.
+    pub app_id: Option<u64>,
+    /// Installation on the release repository; looked up from the repository when unset.
+    pub installation_id: Option<u64>,
+    /// PEM private key of the App, relative to the repo root.
+    pub private_key: Option<PathBuf>,
+    /// Environment variable holding the PEM private key instead of a file.
+    pub private_key_env: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GithubAuth {
+    /// Personal access token in `ASFSHIP_GITHUB_TOKEN`.
+    #[default]
+    Token,
+    /// The workflow's `GITHUB_TOKEN` in GitHub Actions.
+    Actions,
+    /// Installation tokens minted for a GitHub App.
+    App,
+}
+
+/// Proxy, TLS trust, timeouts, and retries under `[network]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(from = "RawNetworkConfig")]
+pub struct NetworkConfig {
+    /// Proxy for all HTTP(S) traffic; overrides `HTTPS_PROXY`. `NO_PROXY` still applies.
+    pub proxy: Option<String>,
+    /// PEM bundle trusted in addition to the system roots, relative to the repo root.
+    pub ca_bundle: Option<PathBuf>,
+    pub tuning: NetworkTuning,
+}
+
+/// `[network]` as written, since serde cannot reject unknown keys next to `flatten`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawNetworkConfig {
+    proxy: Option<String>,
+    ca_bundle: Option<PathBuf>,
+    connect_timeout_secs: Option<u64>,
+    timeout_secs: Option<u64>,
+    retries: Option<usize>,
+}
+
+impl From<RawNetworkConfig> for NetworkConfig {
+    fn from(raw: RawNetworkConfig) -> Self {
+        Self {
+            proxy: raw.proxy,
+            ca_bundle: raw.ca_bundle,
+            tuning: NetworkTuning {
+                connect_timeout_secs: raw.connect_timeout_secs,
+                timeout_secs: raw.timeout_secs,
+                retries: raw.retries,
+            },
+        }
+    }
+}
+
+/// Timeouts and retries for every HTTP request, settable in `[network]` and on the
+/// command line.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkTuning {
+    /// Seconds to wait for a connection; defaults to 30.
+    pub connect_timeout_secs: Option<u64>,
+    /// Seconds a request may go without receiving data; defaults to 120.
+    pub timeout_secs: Option<u64>,
+    /// Retries after a connection error, 5xx, or 429 from GitHub; defaults to 3.
+    pub retries: Option<usize>,
+}
+
+impl NetworkTuning {
+    /// Replace the settings `other` sets, as command-line flags do.
+    pub fn override_with(&mut self, other: &NetworkTuning) {
+        if other.connect_timeout_secs.is_some() {
+            self.connect_timeout_secs = other.connect_timeout_secs;
+        }
+        if other.timeout_secs.is_some() {
+            self.timeout_secs = other.timeout_secs;
+        }
+        if other.retries.is_some() {
+            self.retries = other.retries;
+        }
+    }
+}
+
+/// Per-tag run directories shared by every command, under `[artifacts]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArtifactsConfig {
+    /// Root of the run directories, relative to the repo root; `--artifact-dir` overrides it.
+    pub dir: Option<PathBuf>,
+    /// Number of tag directories kept; older ones are removed when a new one is created.
+    pub keep: Option<usize>,
+}
+
+/// Source archive contents under `[package]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageConfig {
+    /// Repo-relative paths (files, directories or submodules) left out of source archives.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub compression: CompressionConfig,
+}
+
+/// Archive formats and compression levels under `[package.compression]`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompressionConfig {
+    /// 0 (store) to 9 (smallest).
+    pub gzip_level: u32,
+    /// 1 to 22; `tar.zst` archives are compressed with the `zstd` CLI.
+    pub zstd_level: i32,
+    pub formats: Vec<ArchiveFormat>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            gzip_level: 6,
+            zstd_level: 19,
+            formats: vec![ArchiveFormat::TarGz, ArchiveFormat::Zip],
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.formats.is_empty() {
+            bail!("package.compression.formats must list at least one archive format");
+        }
+        for (i, format) in self.formats.iter().enumerate() {
+            if self.formats[..i].contains(format) {
+                bail!(
+                    "package.compression.formats lists {} twice",
+                    format.extension()
+                );
+            }
+        }
+        if self.gzip_level > 9 {
+            bail!(
+                "package.compression.gzip_level must be 0-9, got {}",
+                self.gzip_level
+            );
+        }
+        if !(1..=22).contains(&self.zstd_level) {
+            bail!(
+                "package.compression.zstd_level must be 1-22, got {}",
+                self.zstd_level
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ArchiveFormat {
+    #[serde(rename = "tar.gz")]
+    TarGz,
+    #[serde(rename = "tar.zst")]
+    TarZst,
+    #[serde(rename = "zip")]
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::TarZst => "tar.zst",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+}
+
+/// Prerelease checks under `[prerelease]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PrereleaseConfig {
+    /// Compare each changed crate's public API with the base tag via `cargo semver-checks`.
+    #[serde(default)]
+    pub api_check: bool,
+    /// Build each packaged tarball with the toolchain named by its `rust-version`.
+    #[serde(default)]
+    pub msrv_check: bool,
+    /// Run `cargo test` inside each unpacked tarball instead of the git tree.
+    #[serde(default)]
+    pub verify_tarball: bool,
+    /// Attach a gpg-signed SLSA provenance statement to the rc release.
+    #[serde(default)]
+    pub provenance: bool,
+    /// Upload a gpg signature of the run manifest, which `verify` then checks.
+    #[serde(default)]
+    pub sign_manifest: bool,
+    /// Register GitHub artifact attestations for the uploaded archives (Actions only).
+    #[serde(default)]
+    pub attest: bool,
+    /// Apply the release edits in a temporary worktree and fast-forward the checkout
+    /// only once packaging and verification succeeded.
+    #[serde(default)]
+    pub isolated: bool,
+}
+
+/// Commit classification and changelog layout under `[changelog]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChangelogConfig {
+    /// Checked in order against commit subjects before the built-in conventional types.
+    #[serde(default)]
+    pub rules: Vec<ClassifyRule>,
+    /// Changelog sections in output order; empty keeps the built-in layout.
+    #[serde(default)]
+    pub sections: Vec<ChangelogSection>,
+    #[serde(default)]
+    pub labels: ChangelogLabelsConfig,
+    /// Tera snippet rendering each entry after its `- ` marker; see `EntryFormat`.
+    pub entry: Option<String>,
+    /// Per-kind snippets that replace `entry` for that kind.
+    #[serde(default)]
+    pub entries: BTreeMap<CommitKind, String>,
+}
+
+/// Pull request labels that override commit classification, under `[changelog.labels]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChangelogLabelsConfig {
+    /// Fetch the labels of each planned commit's pull request from GitHub.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Marks the change breaking; defaults to `breaking-change`.
+    pub breaking: Option<String>,
+    /// Keeps the change out of changelogs; defaults to `skip-changelog`.
+    pub skip: Option<String>,
+    /// Lists the change first in its section; defaults to `highlight`.
+    pub highlight: Option<String>,
+    /// Other labels mapped to the kind they classify a change as, e.g. `bug = "fix"`.
+    #[serde(default)]
+    pub kinds: BTreeMap<String, CommitKind>,
+}
+
+impl ChangelogLabelsConfig {
+    pub fn breaking(&self) -> &str {
+        self.breaking.as_deref().unwrap_or("breaking-change")
+    }
+
+    pub fn skip(&self) -> &str {
+        self.skip.as_deref().unwrap_or("skip-changelog")
+    }
+
+    pub fn highlight(&self) -> &str {
+        self.highlight.as_deref().unwrap_or("highlight")
+    }
+}
+
+/// First-release settings under `[bootstrap]`, used only while no stable tag exists.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BootstrapConfig {
+    /// Revision the first release's history starts at; older commits are ignored.
+    pub since: Option<String>,
+    /// Write a single "Initial release." changelog entry instead of listing every commit.
+    #[serde(default)]
+    pub initial_changelog: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClassifyRule {
+    /// Case-insensitive regex matched against the commit subject.
+    pub pattern: String,
+    pub kind: CommitKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChangelogSection {
+    pub title: String,
+    pub kinds: Vec<CommitKind>,
+}
+
+/// Release announcement settings under `[release]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseConfig {
+    /// Wait up to this long for files to appear on downloads.apache.org before
+    /// posting the release Discussion; unset skips the check.
+    pub downloads_timeout_minutes: Option<u64>,
+    /// Create the stable GitHub Release as a draft and stop for review; `release
+    /// --finalize` publishes it and continues.
+    #[serde(default)]
+    pub draft: bool,
+    /// Regexes matched against rc asset names; matching assets, with their checksums and
+    /// signatures, stay off the stable release.
+    #[serde(default)]
+    pub exclude_assets: Vec<String>,
+    /// Commit the voted rc artifacts to dist/release under their final names; needs PMC
+    /// write access to the project's dist area.
+    #[serde(default)]
+    pub promote_dist: bool,
+    #[serde(default)]
+    pub gate: ReleaseGateConfig,
+    #[serde(default)]
+    pub feed: ReleaseFeedConfig,
+    #[serde(default)]
+    pub website: ReleaseWebsiteConfig,
+    #[serde(default)]
+    pub pull_requests: ReleasePullRequestsConfig,
+    #[serde(default)]
+    pub milestones: ReleaseMilestonesConfig,
+    #[serde(default)]
+    pub translations: Vec<TranslationConfig>,
+}
+
+/// One `[[release.translations]]` hook turning the release announcement into `language`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TranslationConfig {
+    /// Language tag such as `zh-CN`, passed to the hook and used in comment and file names.
+    pub language: String,
+    /// Shell command reading the announcement on stdin and writing the translation to stdout.
+    pub command: Option<String>,
+    /// HTTP endpoint receiving a JSON POST and answering with the translation.
+    pub url: Option<String>,
+    /// Environment variable holding the endpoint URL, for URLs that embed a key.
+    pub url_env: Option<String>,
+    #[serde(default)]
+    pub output: TranslationOutput,
+}
+
+impl TranslationConfig {
+    pub fn resolve_url(&self) -> Result<Option<String>> {
+        if let Some(url) = &self.url {
+            return Ok(Some(url.clone()));
+        }
+        let Some(var) = self.url_env.as_deref() else {
+            return Ok(None);
+        };
+        let url = std::env::var(var)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .with_context(|| format!("translation URL variable {} is not set", var))?;
+        redact::register(&url);
+        Ok(Some(url))
+    }
+}
+
+/// Where a translated announcement goes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranslationOutput {
+    /// A comment on the release Discussion.
+    #[default]
+    Comment,
+    /// `ANNOUNCEMENT.<language>.md` in the stable tag's run directory.
+    File,
+}
+
+/// Notes left on the pull requests a release shipped, under `[release.pull_requests]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReleasePullRequestsConfig {
+    /// Comment "Released in <tag>" on each pull request.
+    #[serde(default)]
+    pub comment: bool,
+    /// Label added to each pull request, such as `released`.
+    pub label: Option<String>,
+}
+
+impl ReleasePullRequestsConfig {
+    pub fn enabled(&self) -> bool {
+        self.comment || self.label.is_some()
+    }
+}
+
+/// GitHub milestones named after versions, under `[release.milestones]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseMilestonesConfig {
+    /// Close the released version's milestone and roll its open issues over to the next one.
+    #[serde(default)]
+    pub close: bool,
+    /// Milestone title with a `{version}` placeholder; defaults to `{version}`.
+    pub title: Option<String>,
+    /// Which version the next milestone is named after.
+    #[serde(default)]
+    pub next: NextMilestone,
+}
+
+impl ReleaseMilestonesConfig {
+    pub fn title(&self, version: &semver::Version) -> String {
+        self.title
+            .as_deref()
+            .unwrap_or("{version}")
+            .replace("{version}", &version.to_string())
+    }
+}
+
+/// Bump from the released version to the next milestone's version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NextMilestone {
+    #[default]
+    Minor,
+    Patch,
+}
+
+/// Machine-readable release history under `[release.feed]`, for project websites.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseFeedConfig {
+    /// JSON file (relative to the branch root) that `release` prepends each release to.
+    pub json: Option<String>,
+    /// Atom feed rendered from the JSON history after each release.
+    pub atom: Option<String>,
+    /// Branch that holds the feed files, such as `gh-pages`; defaults to the current branch.
+    pub branch: Option<String>,
+}
+
+/// Website files `release` updates with the new version, under `[release.website]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseWebsiteConfig {
+    /// Branch holding the site sources; defaults to the current branch.
+    pub branch: Option<String>,
+    /// Open a pull request against `branch` instead of pushing to it.
+    #[serde(default)]
+    pub pull_request: bool,
+    #[serde(default)]
+    pub files: Vec<WebsiteFile>,
+}
+
+/// One file under `[[release.website.files]]`: rendered from `template` or edited by `replace`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebsiteFile {
+    /// Path relative to the root of the website branch.
+    pub path: String,
+    /// Tera template in this repository that renders the whole file.
+    pub template: Option<String>,
+    #[serde(default)]
+    pub replace: Vec<WebsiteReplacement>,
+}
+
+/// Regex replacement applied to an existing website file; `with` is a Tera template.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebsiteReplacement {
+    pub pattern: String,
+    pub with: String,
+}
+
+/// Approvals `release` waits for, under `[release.gate]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseGateConfig {
+    /// GitHub environment that must hold a successful deployment of the rc commit.
+    pub environment: Option<String>,
+    /// Tracking issue that must carry `label`.
+    pub issue: Option<u64>,
+    pub label: Option<String>,
+}
+
+/// ASF project metadata under `[asf]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AsfConfig {
+    /// Project id on projects.apache.org; defaults to the repository name.
+    pub project: Option<String>,
+    /// Apache id of the release manager, checked against the roster by `vote` and `result`.
+    pub release_manager: Option<String>,
+    /// Display name for release commits, tags, and templates; defaults to the Apache id.
+    pub release_manager_name: Option<String>,
+    /// Fingerprint of the key the release manager signs artifacts with.
+    pub gpg_fingerprint: Option<String>,
+    /// Dev mailing list; filled from the roster when `[asf]` is present.
+    pub dev_list: Option<String>,
+}
+
+/// GitHub Discussions settings under `[discussion]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiscussionConfig {
+    /// Category for the kickoff, vote, and release threads when the template's
+    /// front-matter names none; defaults to "Releases", else the first category.
+    pub category: Option<String>,
+    /// Also comment the operations of each run, from the audit log, on the kickoff thread.
+    #[serde(default)]
+    pub audit_comment: bool,
+}
+
+/// Vote scheduling and quota under `[vote]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VoteConfig {
+    /// Voting period; ASF policy requires at least 72 hours.
+    pub duration_hours: Option<u32>,
+    /// IANA timezone (e.g. `Asia/Shanghai`) shown next to the UTC close time.
+    pub timezone: Option<String>,
+    /// Where artifact links in the vote body point.
+    #[serde(default)]
+    pub artifact_links: ArtifactLinks,
+    /// Binding +1 votes a release needs; ASF policy requires at least 3.
+    pub required_binding: Option<u32>,
+    /// Whether a release can pass despite binding -1 votes, as long as more binding
+    /// votes approve; defaults to `true`, the ASF majority rule.
+    pub allow_negative: Option<bool>,
+}
+
+/// Staging location linked for each artifact in the vote body.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactLinks {
+    /// GitHub prerelease download URLs.
+    #[default]
+    Github,
+    /// dist.apache.org dev URLs, the canonical ASF staging location.
+    Svn,
+    /// SVN URLs with the GitHub URL alongside.
+    Both,
+}
+
+/// Per-crate overrides under `[crates.<name>]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CrateConfig {
+    /// Planned versions must be at least this version.
+    pub min_version: Option<semver::Version>,
+    /// Planned versions must stay strictly below this version.
+    pub max_version: Option<semver::Version>,
+    /// Tag prefix of releases whose main crate is this one, e.g. `bar-v`, making it a
+    /// component with its own tag series and GitHub Release names.
+    pub tag_prefix: Option<String>,
+}
+
+/// One `[[notifications]]` endpoint.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationConfig {
+    pub kind: NotificationKind,
+    /// Webhook URL, or the homeserver base URL for Matrix.
+    pub url: Option<String>,
+    /// Environment variable holding the URL, so secret webhooks stay out of the repo.
+    pub url_env: Option<String>,
+    /// Matrix room id or alias.
+    pub room: Option<String>,
+    /// Environment variable holding the Matrix access token.
+    #[serde(default = "default_matrix_token_env")]
+    pub token_env: String,
+    /// Milestones to send; empty means all.
+    #[serde(default)]
+    pub events: Vec<Milestone>,
+}
+
+fn default_matrix_token_env() -> String {
+    String::from("ASFSHIP_MATRIX_TOKEN")
+}
+
+impl NotificationConfig {
+    pub fn resolve_url(&self) -> Result<String> {
+        if let Some(url) = &self.url {
+            return Ok(url.clone());
+        }
+        let var = self
+            .url_env
+            .as_deref()
+            .context("notification endpoint needs `url` or `url_env`")?;
+        let url = std::env::var(var)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .with_context(|| format!("notification URL variable {} is not set", var))?;
+        // Webhook URLs kept in the environment embed their credential.
+        redact::register(&url);
+        Ok(url)
+    }
+
+    pub fn resolve_token(&self) -> Result<String> {
+        std::env::var(&self.token_env)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .with_context(|| format!("Matrix access token variable {} is not set", self.token_env))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationKind {
+    Slack,
+    Matrix,
+    /// Generic JSON POST with `event`, `repo`, `tag`, `message`, and `url`.
+    Json,
+}
+
+/// What the config names that does not exist: crates outside the workspace and
+/// missing files. Each entry is one line for the user; empty when everything resolves.
+pub fn check_references(cfg: &MinimalConfig, repo_root: &Path, crates: &[&str]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut check_crate = |key: String, name: &str| {
+        if !crates.contains(&name) {
+            problems.push(format!(
+                "{} names {:?}, which is not a workspace crate (crates: {})",
+                key,
+                name,
+                crates.join(", ")
+            ));
+        }
+    };
+    if let Some(name) = &cfg.main_crate {
+        check_crate("main_crate".to_string(), name);
+    }
+    for name in &cfg.only {
+        check_crate("only".to_string(), name);
+    }
+    for name in cfg.crates.keys() {
+        check_crate(format!("[crates.{}]", name), name);
+    }
+    let files = cfg
+        .release
+        .website
+        .files
+        .iter()
+        .filter_map(|f| {
+            f.template
+                .as_deref()
+                .map(|t| ("release.website template", Path::new(t)))
+        })
+        .chain(
+            cfg.github
+                .private_key
+                .as_deref()
+                .map(|p| ("github.private_key", p)),
+        )
+        .chain(
+            cfg.network
+                .ca_bundle
+                .as_deref()
+                .map(|p| ("network.ca_bundle", p)),
+        );
+    for (key, path) in files {
+        if !repo_root.join(path).is_file() {
+            problems.push(format!("{} {} does not exist", key, path.display()));
+        }
+    }
+    if let Err(err) = EntryFormat::new(&cfg.changelog) {
+        problems.push(format!("{:#}", err));
+    }
+    if let Err(err) = promote::exclude_patterns(&cfg.release.exclude_assets) {
+        problems.push(format!("{:#}", err));
+    }
+    problems
 }
 
 pub async fn load_minimal_config(repo_root: &Path) -> Result<MinimalConfig> {
@@ -18,5 +758,137 @@ pub async fn load_minimal_config(repo_root: &Path) -> Result<MinimalConfig> {
         .with_context(|| format!("failed to read {}", path.display()))?;
     let cfg: MinimalConfig =
         toml::from_str(&content).with_context(|| format!("failed to parse {}", path.display()))?;
+    cfg.package.compression.validate()?;
+    let tuning = &cfg.network.tuning;
+    if tuning.connect_timeout_secs == Some(0) || tuning.timeout_secs == Some(0) {
+        bail!("network timeouts must be at least one second");
+    }
+    if cfg.artifacts.keep == Some(0) {
+        bail!("artifacts.keep must be at least 1");
+    }
+    let prefixes = cfg
+        .tag_prefix
+        .iter()
+        .chain(cfg.crates.values().filter_map(|c| c.tag_prefix.as_ref()));
+    for prefix in prefixes {
+        if prefix
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "~^:?*[\\".contains(c))
+        {
+            bail!("tag_prefix {:?} is not valid in a git tag name", prefix);
+        }
+    }
+    for endpoint in &cfg.notifications {
+        if endpoint.url.is_none() && endpoint.url_env.is_none() {
+            bail!("each [[notifications]] entry needs `url` or `url_env`");
+        }
+        if endpoint.kind == NotificationKind::Matrix && endpoint.room.is_none() {
+            bail!("matrix notifications need `room`");
+        }
+    }
+    if cfg.prerelease.attest && !cfg.prerelease.provenance {
+        bail!("prerelease.attest requires prerelease.provenance, whose predicate it attests");
+    }
+    if let Some(title) = &cfg.release.milestones.title
+        && !title.contains("{version}")
+    {
+        bail!("release.milestones.title must contain `{{version}}`");
+    }
+    if cfg.release.gate.label.is_some() && cfg.release.gate.issue.is_none() {
+        bail!("release.gate.label requires release.gate.issue (the tracking issue number)");
+    }
+    for file in &cfg.release.website.files {
+        if file.template.is_some() != file.replace.is_empty() {
+            bail!(
+                "release.website file {} needs exactly one of `template` or `replace`",
+                file.path
+            );
+        }
+        for r in &file.replace {
+            regex::Regex::new(&r.pattern)
+                .with_context(|| format!("invalid release.website pattern for {}", file.path))?;
+        }
+    }
+    if cfg.github.auth == GithubAuth::App {
+        if cfg.github.app_id.is_none() {
+            bail!("github.auth = \"app\" requires github.app_id");
+        }
+        if cfg.github.private_key.is_some() == cfg.github.private_key_env.is_some() {
+            bail!(
+                "github.auth = \"app\" needs exactly one of github.private_key or github.private_key_env"
+            );
+        }
+    }
+    for hook in &cfg.release.translations {
+        let sources = [
+            hook.command.is_some(),
+            hook.url.is_some(),
+            hook.url_env.is_some(),
+        ];
+        if sources.iter().filter(|set| **set).count() != 1 {
+            bail!(
+                "release.translations for {} needs exactly one of `command`, `url`, or `url_env`",
+                hook.language
+            );
+        }
+        if hook.language.is_empty()
+            || !hook
+                .language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!(
+                "release.translations language {:?} must be a tag such as zh-CN",
+                hook.language
+            );
+        }
+    }
+    if cfg.release.feed.atom.is_some() && cfg.release.feed.json.is_none() {
+        bail!("release.feed.atom requires release.feed.json, the history it is rendered from");
+    }
     Ok(cfg)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_keys_and_dangling_references_are_reported() {
+        let err = toml::from_str::<MinimalConfig>("main_create = \"foo\"\n").unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `main_create`"),
+            "{}",
+            err
+        );
+        let err = toml::from_str::<MinimalConfig>("[network]\nretry = 2\n").unwrap_err();
+        assert!(err.to_string().contains("unknown field `retry`"), "{}", err);
+        let cfg: MinimalConfig = toml::from_str("[network]\nretries = 2\n").unwrap();
+        assert_eq!(cfg.network.tuning.retries, Some(2));
+
+        let cfg: MinimalConfig = toml::from_str(
+            "main_crate = \"foo\"\nonly = [\"bar\"]\n[crates.baz]\nmax_version = \"1.0.0\"\n\
+             [network]\nca_bundle = \"certs/missing.pem\"\n",
+        )
+        .unwrap();
+        let problems = check_references(&cfg, Path::new("."), &["foo", "foo-core"]);
+        assert_eq!(
+            problems,
+            [
+                "only names \"bar\", which is not a workspace crate (crates: foo, foo-core)",
+                "[crates.baz] names \"baz\", which is not a workspace crate (crates: foo, foo-core)",
+                "network.ca_bundle certs/missing.pem does not exist",
+            ]
+        );
+
+        let cfg: MinimalConfig =
+            toml::from_str("[changelog.entries]\nfeat = \"{{ description\"\n").unwrap();
+        let problems = check_references(&cfg, Path::new("."), &["foo"]);
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("invalid changelog.entries.feat template"),
+            "{}",
+            problems[0]
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use toml_edit::value;
+
+use crate::config::{self, GithubConfig, MinimalConfig, NetworkConfig};
+use crate::discussion;
+use crate::github;
+use crate::gpg;
+use crate::infer::{self, InferOptions};
+use crate::net;
+use crate::templates::Templates;
+use crate::vote::MIN_VOTE_HOURS;
+
+const FILE_NAME: &str = ".asfship.toml";
+
+/// What `config init` asked for, ready to render.
+#[derive(Debug, PartialEq, Eq)]
+struct Answers {
+    main_crate: String,
+    category: String,
+    project: String,
+    /// Whether `project` is just the default derived from the repository name.
+    project_is_default: bool,
+    vote_hours: u32,
+    gpg_fingerprint: Option<String>,
+}
+
+/// Interview the release manager and write a commented `.asfship.toml`, checking the
+/// main crate against the workspace and the category against the repository's
+/// Discussions as each answer comes in.
+pub async fn run_init(opts: &InferOptions, force: bool) -> Result<()> {
+    let root = infer::repo_root(opts.git_dir.as_deref()).await?;
+    let path = root.join(FILE_NAME);
+    if path.exists() && !force {
+        bail!(
+            "{} already exists; pass --force to replace it",
+            path.display()
+        );
+    }
+    let (owner, name, _) = infer::infer_remote(&root, opts.repo.as_deref()).await?;
+    let meta = infer::load_metadata(&root).await?;
+    let crates = infer::collect_crates(&meta)?;
+    let names: Vec<&str> = crates.iter().map(|c| c.name.as_str()).collect();
+    let inferred = infer::infer_main_crate(&crates, &meta, &name, &MinimalConfig::default()).ok();
+
+    println!("Writing {} for {}/{}.", path.display(), owner, name);
+    println!("Press Enter to accept the value in brackets.\n");
+
+    println!("Workspace crates: {}", names.join(", "));
+    let main_crate = ask_until(
+        "Main crate (names the release)",
+        inferred.as_deref(),
+        |answer| {
+            if names.contains(&answer) {
+                Ok(answer.to_string())
+            } else {
+                bail!("{} is not a workspace crate", answer)
+            }
+        },
+    )
+    .await?;
+
+    let categories = discussion_categories(&root, &owner, &name).await;
+    let default_category = match &categories {
+        Some(list) => discussion::choose_category(list, None).ok().map(|c| c.name),
+        None => Some("Releases".to_string()),
+    };
+    if let Some(list) = &categories {
+        let known: Vec<&str> = list.iter().map(|c| c.name.as_str()).collect();
+        println!("Discussion categories: {}", known.join(", "));
+    }
+    let category = ask_until(
+        "Discussion category for release threads",
+        default_category.as_deref(),
+        |answer| match &categories {
+            Some(list) => Ok(discussion::choose_category(list, Some(answer))?.name),
+            None => Ok(answer.to_string()),
+        },
+    )
+    .await?;
+
+    let default_project = name.trim_start_matches("incubator-").to_ascii_lowercase();
+    let project = ask_until(
+        "ASF project id (apache-<project>-... archive names)",
+        Some(&default_project),
+        |answer| {
+            if answer
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            {
+                Ok(answer.to_string())
+            } else {
+                bail!("project ids use lowercase letters, digits, and `-`")
+            }
+        },
+    )
+    .await?;
+
+    let hours = MIN_VOTE_HOURS.to_string();
+    let vote_hours = ask_until("Vote duration in hours", Some(&hours), |answer| {
+        let hours: u32 = answer
+            .parse()
+            .map_err(|_| anyhow!("{:?} is not a number of hours", answer))?;
+        if hours < MIN_VOTE_HOURS {
+            bail!("ASF policy requires at least {} hours", MIN_VOTE_HOURS);
+        }
+        Ok(hours)
+    })
+    .await?;
+
+    let keys = match gpg::secret_fingerprints().await {
+        Ok(keys) => keys,
+        Err(err) => {
+            println!(
+                "Cannot list gpg keys ({:#}); the fingerprint is not checked.",
+                err
+            );
+            Vec::new()
+        }
+    };
+    if !keys.is_empty() {
+        println!("Secret keys: {}", keys.join(", "));
+    }
+    let gpg_fingerprint = ask_until(
+        "GPG key fingerprint (empty: gpg's default key)",
+        Some(keys.first().map(String::as_str).unwrap_or("")),
+        |answer| check_fingerprint(answer, &keys),
+    )
+    .await?;
+
+    let answers = Answers {
+        main_crate,
+        category,
+        project_is_default: project == default_project,
+        project,
+        vote_hours,
+        gpg_fingerprint,
+    };
+    tokio::fs::write(&path, render(&answers))
+        .await
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    println!("\nconfig: wrote {}", path.display());
+    Ok(())
+}
+
+/// Parse `.asfship.toml` strictly and check what it refers to and the repository's
+/// template overrides, printing every problem found; fails when there is any.
+pub async fn run_validate(opts: &InferOptions) -> Result<()> {
+    let root = infer::repo_root(opts.git_dir.as_deref()).await?;
+    let path = root.join(FILE_NAME);
+    let config = config::load_minimal_config(&root).await?;
+    let meta = infer::load_metadata(&root).await?;
+    let crates = infer::collect_crates(&meta)?;
+    let names: Vec<&str> = crates.iter().map(|c| c.name.as_str()).collect();
+    let mut problems = config::check_references(&config, &root, &names);
+    if let Err(err) = Templates::load(&root) {
+        problems.push(format!("{:#}", err));
+    }
+    if !problems.is_empty() {
+        for problem in &problems {
+            println!("- {}", problem);
+        }
+        bail!("configuration has {} problem(s)", problems.len());
+    }
+    if path.exists() {
+        println!("config: {} is valid", path.display());
+    } else {
+        println!("config: no {}; every setting uses its default", FILE_NAME);
+    }
+    Ok(())
+}
+
+/// The repository's discussion categories, or `None` when they cannot be read and any
+/// name has to be accepted.
+async fn discussion_categories(
+    root: &Path,
+    owner: &str,
+    name: &str,
+) -> Option<Vec<discussion::DiscussionCategory>> {
+    let ready = async {
+        net::init(root, &NetworkConfig::default())?;
+        github::init(root, &GithubConfig::default(), owner, name).await
+    };
+    if let Err(err) = ready.await {
+        println!(
+            "Cannot reach GitHub ({:#}); the discussion category is not checked.",
+            err
+        );
+        return None;
+    }
+    if !github::has_token() {
+        println!("No GitHub token; the discussion category is not checked.");
+        return None;
+    }
+    let listed = async { discussion::list_categories(&github::client()?, owner, name).await };
+    match listed.await {
+        Ok(list) if !list.is_empty() => Some(list),
+        Ok(_) => {
+            println!("{}/{} has no discussion categories yet.", owner, name);
+            None
+        }
+        Err(err) => {
+            println!(
+                "Cannot list discussion categories ({:#}); the category is not checked.",
+                err
+            );
+            None
+        }
+    }
+}
+
+fn check_fingerprint(answer: &str, keys: &[String]) -> Result<Option<String>> {
+    let fingerprint: String = answer.chars().filter(|c| !c.is_whitespace()).collect();
+    if fingerprint.is_empty() {
+        return Ok(None);
+    }
+    let fingerprint = fingerprint.to_ascii_uppercase();
+    if fingerprint.len() != 40 || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("a fingerprint is 40 hexadecimal digits");
+    }
+    if !keys.is_empty() && !keys.contains(&fingerprint) {
+        bail!("no secret key {} in your gpg keyring", fingerprint);
+    }
+    Ok(Some(fingerprint))
+}
+
+/// Ask `question` until `check` accepts the answer; an empty answer takes `default`.
+async fn ask_until<T>(
+    question: &str,
+    default: Option<&str>,
+    check: impl Fn(&str) -> Result<T>,
+) -> Result<T> {
+    loop {
+        let answer = ask(question, default).await?;
+        match check(&answer) {
+            Ok(value) => return Ok(value),
+            Err(err) => println!("  {:#}", err),
+        }
+    }
+}
+
+async fn ask(question: &str, default: Option<&str>) -> Result<String> {
+    let prompt = match default {
+        Some(default) if !default.is_empty() => format!("{} [{}]: ", question, default),
+        _ => format!("{}: ", question),
+    };
+    let default = default.unwrap_or_default().to_string();
+    tokio::task::spawn_blocking(move || -> Result<String> {
+        print!("{}", prompt);
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            bail!("config init: input ended before all questions were answered");
+        }
+        let line = line.trim();
+        Ok(if line.is_empty() {
+            default
+        } else {
+            line.to_string()
+        })
+    })
+    .await
+    .map_err(|e| anyhow!("prompt task join error: {}", e))?
+}
+
+fn render(answers: &Answers) -> String {
+    let mut out = String::from(
+        "# asfship configuration, written by `asfship config init`.\n\
+         # docs/advanced-configuration.md describes every option.\n\n",
+    );
+    writeln!(out, "# Crate whose version names the release and its tags.").unwrap();
+    writeln!(out, "main_crate = {}\n", value(&answers.main_crate)).unwrap();
+
+    writeln!(out, "[discussion]").unwrap();
+    writeln!(
+        out,
+        "# GitHub Discussions category of the kickoff, vote, and release threads."
+    )
+    .unwrap();
+    writeln!(out, "category = {}\n", value(&answers.category)).unwrap();
+
+    writeln!(out, "[asf]").unwrap();
+    writeln!(
+        out,
+        "# Project id on projects.apache.org, used in archive names. Setting it also checks\n\
+         # the release manager against the project roster in vote and result."
+    )
+    .unwrap();
+    let comment = if answers.project_is_default { "# " } else { "" };
+    writeln!(out, "{}project = {}", comment, value(&answers.project)).unwrap();
+    writeln!(out, "# Key that signs the rc tags and artifacts.").unwrap();
+    match &answers.gpg_fingerprint {
+        Some(fpr) => writeln!(out, "gpg_fingerprint = {}\n", value(fpr)).unwrap(),
+        None => writeln!(out, "# gpg_fingerprint = \"<40 hex digits>\"\n").unwrap(),
+    }
+
+    writeln!(out, "[vote]").unwrap();
+    writeln!(
+        out,
+        "# Voting period; ASF policy requires at least {} hours.",
+        MIN_VOTE_HOURS
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "duration_hours = {}",
+        value(i64::from(answers.vote_hours))
+    )
+    .unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendered_config_parses_back() {
+        let answers = Answers {
+            main_crate: "opendal".into(),
+            category: "Announcements".into(),
+            project: "opendal".into(),
+            project_is_default: true,
+            vote_hours: 96,
+            gpg_fingerprint: Some("0123456789ABCDEF0123456789ABCDEF01234567".into()),
+        };
+        let text = render(&answers);
+        assert!(text.contains("\n# project = \"opendal\"\n"), "{}", text);
+        let cfg: MinimalConfig = toml::from_str(&text).unwrap();
+        assert_eq!(cfg.main_crate.as_deref(), Some("opendal"));
+        assert_eq!(cfg.discussion.category.as_deref(), Some("Announcements"));
+        assert_eq!(cfg.asf.project, None);
+        assert_eq!(cfg.vote.duration_hours, Some(96));
+        assert_eq!(
+            cfg.asf.gpg_fingerprint.as_deref(),
+            Some("0123456789ABCDEF0123456789ABCDEF01234567")
+        );
+
+        let keys = vec!["0123456789ABCDEF0123456789ABCDEF01234567".to_string()];
+        assert_eq!(
+            check_fingerprint("0123 4567 89ab cdef 0123 4567 89ab cdef 0123 4567", &keys).unwrap(),
+            Some(keys[0].clone())
+        );
+        assert!(check_fingerprint("FEDCBA9876543210FEDCBA9876543210FEDCBA98", &keys).is_err());
+        assert_eq!(check_fingerprint("", &keys).unwrap(), None);
+    }
+
+    #[test]
+    fn rendered_strings_stay_valid_toml() {
+        // Debug formatting would write `\u{301}`, which TOML rejects.
+        let answers = Answers {
+            main_crate: "opendal".into(),
+            category: "Cafe\u{301} \"releases\"".into(),
+            project: "opendal".into(),
+            project_is_default: false,
+            vote_hours: 72,
+            gpg_fingerprint: None,
+        };
+        let cfg: MinimalConfig = toml::from_str(&render(&answers)).unwrap();
+        assert_eq!(
+            cfg.discussion.category.as_deref(),
+            Some("Cafe\u{301} \"releases\"")
+        );
+        assert_eq!(cfg.asf.project.as_deref(), Some("opendal"));
+    }
+}
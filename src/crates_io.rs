@@ -0,0 +1,296 @@
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use git2::Repository;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::time::{Instant, sleep};
+
+use crate::docs_rs;
+use crate::endpoints;
+use crate::infer::InferredContext;
+use crate::net;
+use crate::released::{ReleasedCrate, released_crates};
+
+// crates.io rejects API requests without an identifying user agent.
+const INDEX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const USER_AGENT: &str = concat!("asfship/", env!("CARGO_PKG_VERSION"));
+
+/// Outcome of checking one published crate version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishStatus {
+    /// API, index, and downloaded `.crate` agree.
+    Verified {
+        checksum: String,
+    },
+    /// crates.io does not know this version.
+    Missing,
+    /// Published, but the sparse index has not caught up yet.
+    IndexPending,
+    /// The version has been yanked.
+    Yanked,
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+    },
+}
+
+impl PublishStatus {
+    /// Whether this outcome should fail the check.
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            PublishStatus::Missing | PublishStatus::Yanked | PublishStatus::ChecksumMismatch { .. }
+        )
+    }
+}
+
+impl fmt::Display for PublishStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishStatus::Verified { checksum } => write!(f, "ok (sha256={})", checksum),
+            PublishStatus::Missing => write!(f, "missing on crates.io"),
+            PublishStatus::IndexPending => {
+                write!(
+                    f,
+                    "published, index not updated yet (retry in a few minutes)"
+                )
+            }
+            PublishStatus::Yanked => write!(f, "yanked"),
+            PublishStatus::ChecksumMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "checksum mismatch (expected {}, got {})",
+                    expected, actual
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionResponse {
+    version: ApiVersion,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiVersion {
+    num: String,
+    checksum: String,
+    yanked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexEntry {
+    vers: String,
+    cksum: String,
+}
+
+/// Verify every crate shipped by `version` (default: the last stable tag) on crates.io,
+/// and that docs.rs did not fail to build it.
+pub async fn run_check_published(ctx: &InferredContext, version: Option<&str>) -> Result<()> {
+    let version = release_version(ctx, version)?;
+    let repo = Repository::discover(&ctx.repo_root)?;
+    let crates = released_crates(&repo, ctx, &version)?;
+    if crates.is_empty() {
+        bail!("no publishable crate was released at v{}", version);
+    }
+
+    let client = client()?;
+    let mut failures = 0;
+    println!("check-published: v{}", version);
+    for c in &crates {
+        let status = check_crate(&client, c).await?;
+        if status == PublishStatus::IndexPending {
+            tracing::warn!("crates.io: {} {} not in the index yet", c.name, c.version);
+        }
+        println!("- {} {}: {}", c.name, c.version, status);
+        // docs.rs only builds what crates.io has.
+        let docs = match status {
+            PublishStatus::Missing => None,
+            _ => Some(docs_rs::check_crate(&client, c).await?),
+        };
+        if let Some(docs) = &docs {
+            println!("  docs.rs: {}", docs);
+        }
+        if status.is_failure() || docs.as_ref().is_some_and(|d| d.is_failure()) {
+            failures += 1;
+        }
+    }
+    if failures > 0 {
+        bail!(
+            "{} of {} crates failed verification",
+            failures,
+            crates.len()
+        );
+    }
+    Ok(())
+}
+
+/// Released version named on the command line, or the last stable tag.
+pub fn release_version(ctx: &InferredContext, version: Option<&str>) -> Result<semver::Version> {
+    let tags = ctx.config.tags();
+    let version = match version {
+        Some(v) => v,
+        None => ctx
+            .last_stable_tag
+            .as_deref()
+            .context("no stable tag found; pass the released version explicitly")?,
+    };
+    tags.parse_version_arg(version)
+        .with_context(|| format!("invalid release version {:?}", version))
+}
+
+/// HTTP client identifying asfship, as crates.io requires.
+pub fn client() -> Result<reqwest::Client> {
+    Ok(net::client_builder()?.user_agent(USER_AGENT).build()?)
+}
+
+pub async fn check_crate(client: &reqwest::Client, c: &ReleasedCrate) -> Result<PublishStatus> {
+    let resp = client
+        .get(format!(
+            "{}/{}/{}",
+            endpoints::crates_io_api(),
+            c.name,
+            c.version
+        ))
+        .send()
+        .await?;
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Ok(PublishStatus::Missing);
+    }
+    let body = resp.error_for_status()?.text().await?;
+    let api: VersionResponse = serde_json::from_str(&body)
+        .with_context(|| format!("failed to parse crates.io response for {}", c.name))?;
+    if api.version.num != c.version {
+        return Ok(PublishStatus::Missing);
+    }
+    if api.version.yanked {
+        return Ok(PublishStatus::Yanked);
+    }
+
+    let Some(index_cksum) = index_entry(client, c).await? else {
+        return Ok(PublishStatus::IndexPending);
+    };
+    if index_cksum != api.version.checksum {
+        return Ok(PublishStatus::ChecksumMismatch {
+            expected: api.version.checksum,
+            actual: index_cksum,
+        });
+    }
+
+    let bytes = client
+        .get(format!(
+            "{}/{}/{}-{}.crate",
+            endpoints::crates_io_download(),
+            c.name,
+            c.name,
+            c.version
+        ))
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let actual = hex::encode(Sha256::digest(&bytes));
+    if actual != api.version.checksum {
+        return Ok(PublishStatus::ChecksumMismatch {
+            expected: api.version.checksum,
+            actual,
+        });
+    }
+    Ok(PublishStatus::Verified { checksum: actual })
+}
+
+/// Checksum the sparse index records for `c`, once the version is listed there.
+async fn index_entry(client: &reqwest::Client, c: &ReleasedCrate) -> Result<Option<String>> {
+    let index = client
+        .get(format!(
+            "{}/{}",
+            endpoints::crates_io_index(),
+            index_path(&c.name)
+        ))
+        .send()
+        .await?;
+    if index.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(index_checksum(
+        &index.error_for_status()?.text().await?,
+        &c.version,
+    ))
+}
+
+/// Poll the sparse index until it lists `c`, so dependents can resolve it.
+pub async fn wait_for_index(
+    client: &reqwest::Client,
+    c: &ReleasedCrate,
+    timeout: Duration,
+) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if index_entry(client, c).await?.is_some() {
+            return Ok(());
+        }
+        if Instant::now() + INDEX_POLL_INTERVAL > deadline {
+            bail!(
+                "{} {} not in the crates.io index after {} minutes",
+                c.name,
+                c.version,
+                timeout.as_secs() / 60
+            );
+        }
+        tracing::debug!(
+            "crates.io: waiting for {} {} in the index",
+            c.name,
+            c.version
+        );
+        sleep(INDEX_POLL_INTERVAL).await;
+    }
+}
+
+/// Path of a crate's file in the sparse index, per the registry index layout.
+fn index_path(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+fn index_checksum(content: &str, version: &str) -> Option<String> {
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<IndexEntry>(line).ok())
+        .find(|entry| entry.vers == version)
+        .map(|entry| entry.cksum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_path_follows_registry_layout() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("ab"), "2/ab");
+        assert_eq!(index_path("abc"), "3/a/abc");
+        assert_eq!(index_path("OpenDAL"), "op/en/opendal");
+    }
+
+    #[test]
+    fn index_checksum_matches_version() {
+        let content = concat!(
+            r#"{"name":"foo","vers":"0.1.0","deps":[],"cksum":"aaa","features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"foo","vers":"0.2.0","deps":[],"cksum":"bbb","features":{},"yanked":false}"#,
+            "\n"
+        );
+        assert_eq!(index_checksum(content, "0.2.0").as_deref(), Some("bbb"));
+        assert_eq!(index_checksum(content, "0.3.0"), None);
+    }
+}
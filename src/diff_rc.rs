@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use regex::Regex;
+use sha2::{Digest, Sha512};
+
+use crate::artifacts;
+use crate::asf;
+use crate::asset_cache::AssetCache;
+use crate::github;
+use crate::infer::InferredContext;
+use crate::rc_release::{
+    RcReleaseInfo, download_assets, fetch_latest_rc_release, fetch_rc_release_by_tag,
+};
+use crate::tags::TagFormat;
+
+pub async fn run_diff_rc(
+    ctx: &InferredContext,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<()> {
+    if !github::has_token() {
+        bail!("missing ASFSHIP_GITHUB_TOKEN for diff-rc command");
+    }
+
+    let new = match to {
+        Some(tag) => fetch_rc_release_by_tag(ctx, tag).await?,
+        None => fetch_latest_rc_release(ctx).await?,
+    };
+    let old_tag = match from {
+        Some(tag) => tag.to_string(),
+        None => previous_rc_tag(&new, &ctx.config.tags())?,
+    };
+    let old = fetch_rc_release_by_tag(ctx, &old_tag).await?;
+
+    let cache = AssetCache::new(&artifacts::root(ctx));
+    let old_dir = artifacts::prepare(ctx, &old.tag).await?;
+    let new_dir = artifacts::prepare(ctx, &new.tag).await?;
+    download_assets(&old, &old_dir, &cache).await?;
+    download_assets(&new, &new_dir, &cache).await?;
+
+    let report = tokio::task::spawn_blocking(move || diff_releases(&old, &old_dir, &new, &new_dir))
+        .await
+        .map_err(|e| anyhow::anyhow!("diff-rc task join error: {}", e))??;
+    print!("{}", report);
+    Ok(())
+}
+
+fn previous_rc_tag(release: &RcReleaseInfo, tags: &TagFormat) -> Result<String> {
+    if release.rc_number <= 1 {
+        bail!(
+            "{} is the first rc for {}; pass an explicit tag to compare against",
+            release.tag,
+            release.version
+        );
+    }
+    Ok(tags.rc(&release.version, release.rc_number - 1))
+}
+
+static RC_MARKER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"-rc\d+").unwrap());
+
+/// Replace the `-rcN` marker so matching artifacts line up across candidates.
+fn normalize_asset_name(name: &str) -> String {
+    RC_MARKER.replace(name, "-rcN").into_owned()
+}
+
+fn diff_releases(
+    old: &RcReleaseInfo,
+    old_dir: &Path,
+    new: &RcReleaseInfo,
+    new_dir: &Path,
+) -> Result<String> {
+    let old_assets: BTreeMap<String, (String, u64)> = old
+        .assets
+        .iter()
+        .map(|a| (normalize_asset_name(&a.name), (a.name.clone(), a.size)))
+        .collect();
+    let new_assets: BTreeMap<String, (String, u64)> = new
+        .assets
+        .iter()
+        .map(|a| (normalize_asset_name(&a.name), (a.name.clone(), a.size)))
+        .collect();
+
+    let mut out = String::new();
+    writeln!(&mut out, "diff-rc: {} -> {}", old.tag, new.tag).unwrap();
+    writeln!(&mut out, "assets:").unwrap();
+    for (key, (_, old_size)) in &old_assets {
+        match new_assets.get(key) {
+            Some((_, new_size)) if new_size == old_size => {
+                writeln!(&mut out, "  = {} ({} bytes)", key, new_size).unwrap()
+            }
+            Some((_, new_size)) => {
+                writeln!(&mut out, "  ~ {} ({} -> {} bytes)", key, old_size, new_size).unwrap()
+            }
+            None => writeln!(&mut out, "  - {}", key).unwrap(),
+        }
+    }
+    for key in new_assets.keys().filter(|k| !old_assets.contains_key(*k)) {
+        writeln!(&mut out, "  + {}", key).unwrap();
+    }
+
+    for (key, (new_name, _)) in &new_assets {
+        if !asf::is_archive(key) {
+            continue;
+        }
+        let Some((old_name, _)) = old_assets.get(key) else {
+            continue;
+        };
+        let entries = read_archive_entries(&old_dir.join(old_name))
+            .and_then(|old| Ok((old, read_archive_entries(&new_dir.join(new_name))?)));
+        let (old_entries, new_entries) = match entries {
+            Ok(entries) => entries,
+            Err(err) => {
+                writeln!(&mut out, "contents of {}: not compared ({:#})", key, err).unwrap();
+                continue;
+            }
+        };
+        let diff = diff_entries(&old_entries, &new_entries);
+        if diff.is_empty() {
+            writeln!(&mut out, "contents of {}: unchanged", key).unwrap();
+            continue;
+        }
+        writeln!(&mut out, "contents of {}:", key).unwrap();
+        for path in &diff.added {
+            writeln!(&mut out, "  A {}", path).unwrap();
+        }
+        for path in &diff.removed {
+            writeln!(&mut out, "  D {}", path).unwrap();
+        }
+        for path in &diff.modified {
+            writeln!(&mut out, "  M {}", path).unwrap();
+        }
+    }
+    Ok(out)
+}
+
+/// Map each file in a `.tar.gz`, `.tar.zst`, or `.zip` to the sha512 of its contents.
+fn read_archive_entries(path: &Path) -> Result<BTreeMap<String, String>> {
+    let name = path.to_string_lossy();
+    if name.ends_with(".zip") {
+        read_zip_entries(path)
+    } else if name.ends_with(".tar.zst") {
+        read_zstd_entries(path)
+    } else {
+        let file =
+            fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        read_tar_entries(GzDecoder::new(file))
+    }
+    .with_context(|| format!("failed to read {}", path.display()))
+}
+
+fn read_tar_entries(reader: impl Read) -> Result<BTreeMap<String, String>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        entries.insert(name, digest(&mut entry)?);
+    }
+    Ok(entries)
+}
+
+/// `.tar.zst` goes through the `zstd` command, as packaging does.
+fn read_zstd_entries(path: &Path) -> Result<BTreeMap<String, String>> {
+    let mut child = std::process::Command::new("zstd")
+        .args(["-d", "-q", "-c"])
+        .arg(path)
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .context("failed to run zstd")?;
+    let stdout = child.stdout.take().context("zstd stdout unavailable")?;
+    let entries = read_tar_entries(stdout);
+    let status = child.wait()?;
+    let entries = entries?;
+    if !status.success() {
+        bail!("zstd exited with {}", status);
+    }
+    Ok(entries)
+}
+
+fn read_zip_entries(path: &Path) -> Result<BTreeMap<String, String>> {
+    let file =
+        fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut entries = BTreeMap::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        entries.insert(name, digest(&mut entry)?);
+    }
+    Ok(entries)
+}
+
+fn digest(reader: &mut impl Read) -> Result<String> {
+    let mut hasher = Sha512::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct EntryDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    modified: Vec<String>,
+}
+
+impl EntryDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+fn diff_entries(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> EntryDiff {
+    let mut diff = EntryDiff::default();
+    for (path, digest) in new {
+        match old.get(path) {
+            None => diff.added.push(path.clone()),
+            Some(old_digest) if old_digest != digest => diff.modified.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    diff.removed = old
+        .keys()
+        .filter(|p| !new.contains_key(*p))
+        .cloned()
+        .collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_asset_name_strips_rc_number() {
+        assert_eq!(
+            normalize_asset_name("apache-foo-0.1.1-rc2-src.tar.gz.sha512"),
+            "apache-foo-0.1.1-rcN-src.tar.gz.sha512"
+        );
+    }
+
+    #[test]
+    fn diff_entries_classifies_changes() {
+        let old: BTreeMap<String, String> = [("a", "1"), ("b", "2"), ("c", "3")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let new: BTreeMap<String, String> = [("a", "1"), ("b", "9"), ("d", "4")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(
+            diff_entries(&old, &new),
+            EntryDiff {
+                added: vec!["d".into()],
+                removed: vec!["c".into()],
+                modified: vec!["b".into()],
+            }
+        );
+    }
+
+    fn release(tag: &str, rc_number: u32, names: &[&str]) -> RcReleaseInfo {
+        RcReleaseInfo {
+            tag: tag.into(),
+            version: semver::Version::new(0, 1, 1),
+            rc_number,
+            assets: names
+                .iter()
+                .map(|name| crate::rc_release::RcAsset {
+                    name: name.to_string(),
+                    download_url: String::new(),
+                    size: 1,
+                    sha512: None,
+                })
+                .collect(),
+            vote: None,
+        }
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &str)]) {
+        use std::io::Write;
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn diff_releases_compares_every_archive_format() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (old_dir, new_dir) = (dir.path().join("old"), dir.path().join("new"));
+        fs::create_dir_all(&old_dir).unwrap();
+        fs::create_dir_all(&new_dir).unwrap();
+        write_zip(
+            &old_dir.join("foo-rc1-src.zip"),
+            &[("a.rs", "a"), ("b.rs", "b")],
+        );
+        write_zip(
+            &new_dir.join("foo-rc2-src.zip"),
+            &[("a.rs", "a"), ("b.rs", "b2")],
+        );
+        fs::write(old_dir.join("foo-rc1-src.tar.zst"), "not zstd").unwrap();
+        fs::write(new_dir.join("foo-rc2-src.tar.zst"), "not zstd").unwrap();
+
+        let old = release(
+            "v0.1.1-rc.1",
+            1,
+            &["foo-rc1-src.zip", "foo-rc1-src.tar.zst"],
+        );
+        let new = release(
+            "v0.1.1-rc.2",
+            2,
+            &["foo-rc2-src.zip", "foo-rc2-src.tar.zst"],
+        );
+        let report = diff_releases(&old, &old_dir, &new, &new_dir).unwrap();
+        assert!(
+            report.contains("contents of foo-rcN-src.zip:\n  M b.rs\n"),
+            "{}",
+            report
+        );
+        assert!(
+            report.contains("contents of foo-rcN-src.tar.zst: not compared ("),
+            "{}",
+            report
+        );
+    }
+}
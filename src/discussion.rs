@@ -1,16 +1,116 @@
+use std::ops::Range;
+
 use anyhow::{Context, Result, bail};
 use octocrab::Octocrab;
 use serde::{Deserialize, Serialize};
 
+use crate::infer::InferredContext;
+use crate::templates::Templates;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct DiscussionCategory {
     pub id: u64,
     pub name: String,
 }
 
+/// GitHub rejects discussion and comment bodies longer than this many characters.
+pub const BODY_LIMIT: usize = 65_536;
+
 #[derive(Debug, Deserialize)]
 pub struct DiscussionResponse {
     pub html_url: String,
+    #[serde(default)]
+    pub number: Option<u64>,
+    #[serde(default)]
+    pub node_id: Option<String>,
+}
+
+/// A rendered discussion as it would be posted.
+#[derive(Debug)]
+pub struct Draft {
+    pub title: String,
+    pub front: FrontMatter,
+    pub body: String,
+    /// Follow-up comments, e.g. an artifact table too long for the body.
+    pub comments: Vec<String>,
+}
+
+/// Posting options declared at the top of a template between `+++` lines.
+///
+/// ```text
+/// +++
+/// category = "Votes"
+/// labels = ["release", "vote"]
+/// pin = true
+/// lock = false
+/// +++
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrontMatter {
+    /// Discussion category; defaults to "Releases", else the first category.
+    pub category: Option<String>,
+    /// Existing repository labels added to the discussion.
+    pub labels: Vec<String>,
+    pub pin: bool,
+    pub lock: bool,
+}
+
+impl FrontMatter {
+    /// ` key=value` pairs for dry-run output; empty when nothing is set.
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        if let Some(category) = &self.category {
+            out.push_str(&format!(" category={}", category));
+        }
+        if !self.labels.is_empty() {
+            out.push_str(&format!(" labels={}", self.labels.join(",")));
+        }
+        if self.pin {
+            out.push_str(" pin=true");
+        }
+        if self.lock {
+            out.push_str(" lock=true");
+        }
+        out
+    }
+}
+
+/// Split a template into its front-matter and the Tera body that follows it.
+pub fn split_front_matter<'a>(kind: &str, template: &'a str) -> Result<(FrontMatter, &'a str)> {
+    let Some(rest) = template
+        .strip_prefix("+++\n")
+        .or_else(|| template.strip_prefix("+++\r\n"))
+    else {
+        return Ok((FrontMatter::default(), template));
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "+++" {
+            let front = toml::from_str(&rest[..offset])
+                .with_context(|| format!("invalid front-matter in {} template", kind))?;
+            return Ok((front, &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    bail!(
+        "front-matter in {} template is missing its closing `+++`",
+        kind
+    )
+}
+
+/// Front-matter of the `kind` template posted to `ctx`'s repository, with
+/// `discussion.category` filling in a category the front-matter leaves unset.
+pub fn template_front_matter(
+    ctx: &InferredContext,
+    templates: &Templates,
+    kind: &str,
+) -> FrontMatter {
+    let mut front = templates.front_matter(kind);
+    if front.category.is_none() {
+        front.category = ctx.config.discussion.category.clone();
+    }
+    front
 }
 
 #[derive(Debug, Serialize)]
@@ -20,30 +120,261 @@ pub struct CreateDiscussionPayload<'a> {
     pub category_id: u64,
 }
 
-pub async fn fetch_default_category(
+#[derive(Debug, Deserialize)]
+pub struct DiscussionSummary {
+    pub number: u64,
+    pub title: String,
+    pub html_url: String,
+}
+
+/// Find the most recent discussion with exactly `title`, scanning the newest 100 entries.
+pub async fn find_by_title(
     gh: &Octocrab,
     owner: &str,
     repo: &str,
-) -> Result<DiscussionCategory> {
-    let categories: Vec<DiscussionCategory> = gh
+    title: &str,
+) -> Result<Option<DiscussionSummary>> {
+    let discussions: Vec<DiscussionSummary> = gh
         .get(
-            format!("repos/{}/{}/discussions/categories", owner, repo),
-            None::<&()>,
+            format!("/repos/{}/{}/discussions", owner, repo),
+            Some(&[("per_page", "100")]),
+        )
+        .await
+        .with_context(|| format!("failed to list discussions for {}/{}", owner, repo))?;
+    Ok(discussions.into_iter().find(|d| d.title == title))
+}
+
+#[derive(Debug, Serialize)]
+struct CreateCommentPayload<'a> {
+    body: &'a str,
+}
+
+pub async fn post_comment(
+    gh: &Octocrab,
+    owner: &str,
+    repo: &str,
+    number: u64,
+    body: &str,
+) -> Result<()> {
+    let _: serde_json::Value = gh
+        .post(
+            format!("/repos/{}/{}/discussions/{}/comments", owner, repo, number),
+            Some(&CreateCommentPayload { body }),
+        )
+        .await
+        .with_context(|| format!("failed to comment on discussion #{}", number))?;
+    Ok(())
+}
+
+/// Split `count` items into comment bodies titled `"{title} (i/n)"` that each fit within
+/// `limit`. `render` produces the text of a run of items, so every page is complete on its
+/// own (a table repeats its header); an item too long for a page by itself is cut across
+/// consecutive pages.
+pub fn paginate(
+    title: &str,
+    count: usize,
+    limit: usize,
+    render: impl Fn(Range<usize>) -> Result<String>,
+) -> Result<Vec<String>> {
+    // Reserve room for the "(i/n)" heading suffix.
+    let budget = limit.saturating_sub(title.len() + 32).max(1);
+    let mut pages: Vec<String> = Vec::new();
+    let mut start = 0;
+    while start < count {
+        let mut end = start + 1;
+        let mut page = render(start..end)?;
+        while end < count {
+            let longer = render(start..end + 1)?;
+            if longer.len() > budget {
+                break;
+            }
+            page = longer;
+            end += 1;
+        }
+        if page.len() > budget {
+            pages.extend(split_at_budget(&page, budget));
+        } else {
+            pages.push(page);
+        }
+        start = end;
+    }
+    let total = pages.len();
+    Ok(pages
+        .into_iter()
+        .enumerate()
+        .map(|(i, page)| format!("## {} ({}/{})\n\n{}", title, i + 1, total, page))
+        .collect())
+}
+
+/// Cut `text` into pieces of at most `budget` bytes, on char boundaries.
+fn split_at_budget(text: &str, budget: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut cut = budget.min(rest.len());
+        while !rest.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        if cut == 0 {
+            cut = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        let (piece, tail) = rest.split_at(cut);
+        pieces.push(piece.to_string());
+        rest = tail;
+    }
+    pieces
+}
+
+/// Create a discussion in the front-matter's category, then apply its labels and lock.
+pub async fn create(
+    gh: &Octocrab,
+    owner: &str,
+    repo: &str,
+    title: &str,
+    body: &str,
+    front: &FrontMatter,
+) -> Result<(DiscussionCategory, DiscussionResponse)> {
+    let category = fetch_category(gh, owner, repo, front.category.as_deref()).await?;
+    let payload = CreateDiscussionPayload {
+        title,
+        body,
+        category_id: category.id,
+    };
+    let created: DiscussionResponse = gh
+        .post(
+            format!("/repos/{}/{}/discussions", owner, repo),
+            Some(&payload),
+        )
+        .await
+        .with_context(|| format!("failed to create discussion in {}/{}", owner, repo))?;
+    apply_front_matter(gh, owner, repo, &created, front).await?;
+    Ok((category, created))
+}
+
+#[derive(Debug, Deserialize)]
+struct LabelNode {
+    node_id: String,
+}
+
+async fn apply_front_matter(
+    gh: &Octocrab,
+    owner: &str,
+    repo: &str,
+    created: &DiscussionResponse,
+    front: &FrontMatter,
+) -> Result<()> {
+    if front.pin {
+        // GitHub exposes no API for pinning discussions.
+        println!(
+            "discussion: pin {} from the discussion sidebar (GitHub has no API for it)",
+            created.html_url
+        );
+    }
+    if front.labels.is_empty() && !front.lock {
+        return Ok(());
+    }
+    let node_id = created
+        .node_id
+        .as_deref()
+        .context("discussion response is missing its node id; cannot apply labels or lock")?;
+    if !front.labels.is_empty() {
+        let mut label_ids = Vec::new();
+        for name in &front.labels {
+            let label: LabelNode = gh
+                .get(
+                    format!(
+                        "/repos/{}/{}/labels/{}",
+                        owner,
+                        repo,
+                        urlencoding::encode(name)
+                    ),
+                    None::<&()>,
+                )
+                .await
+                .with_context(|| {
+                    format!("label {:?} does not exist in {}/{}", name, owner, repo)
+                })?;
+            label_ids.push(label.node_id);
+        }
+        graphql(
+            gh,
+            "mutation($id: ID!, $labels: [ID!]!) { addLabelsToLabelable(input: {labelableId: $id, labelIds: $labels}) { clientMutationId } }",
+            serde_json::json!({ "id": node_id, "labels": label_ids }),
+        )
+        .await
+        .with_context(|| format!("failed to label {}", created.html_url))?;
+    }
+    if front.lock {
+        graphql(
+            gh,
+            "mutation($id: ID!) { lockLockable(input: {lockableId: $id}) { clientMutationId } }",
+            serde_json::json!({ "id": node_id }),
         )
         .await
-        .with_context(|| {
-            format!(
-                "failed to load discussion categories for {}/{}",
-                owner, repo
-            )
-        })?;
-    choose_category(&categories)
+        .with_context(|| format!("failed to lock {}", created.html_url))?;
+    }
+    Ok(())
 }
 
-fn choose_category(categories: &[DiscussionCategory]) -> Result<DiscussionCategory> {
+async fn graphql(gh: &Octocrab, query: &str, variables: serde_json::Value) -> Result<()> {
+    let response: serde_json::Value = gh
+        .graphql(&serde_json::json!({ "query": query, "variables": variables }))
+        .await?;
+    if let Some(errors) = response.get("errors") {
+        bail!("GitHub GraphQL error: {}", errors);
+    }
+    Ok(())
+}
+
+pub async fn fetch_category(
+    gh: &Octocrab,
+    owner: &str,
+    repo: &str,
+    name: Option<&str>,
+) -> Result<DiscussionCategory> {
+    choose_category(&list_categories(gh, owner, repo).await?, name)
+}
+
+pub async fn list_categories(
+    gh: &Octocrab,
+    owner: &str,
+    repo: &str,
+) -> Result<Vec<DiscussionCategory>> {
+    gh.get(
+        format!("/repos/{}/{}/discussions/categories", owner, repo),
+        None::<&()>,
+    )
+    .await
+    .with_context(|| {
+        format!(
+            "failed to load discussion categories for {}/{}",
+            owner, repo
+        )
+    })
+}
+
+/// The category called `name` (case-insensitively), or the default one.
+pub fn choose_category(
+    categories: &[DiscussionCategory],
+    name: Option<&str>,
+) -> Result<DiscussionCategory> {
     if categories.is_empty() {
         bail!("repository has no discussion categories; enable GitHub Discussions first");
     }
+    if let Some(name) = name {
+        return categories
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .cloned()
+            .with_context(|| {
+                let known: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
+                format!(
+                    "discussion category {:?} not found (available: {})",
+                    name,
+                    known.join(", ")
+                )
+            });
+    }
     let choice = categories
         .iter()
         .find(|c| c.name.eq_ignore_ascii_case("Releases"))
@@ -51,3 +382,54 @@ fn choose_category(categories: &[DiscussionCategory]) -> Result<DiscussionCatego
         .expect("non-empty categories");
     Ok(choice.clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paginate_respects_limit() {
+        let mut lines: Vec<String> = (0..50).map(|i| format!("- artifact-{:02}", i)).collect();
+        lines.push(format!("- {}", "é".repeat(300)));
+        let render = |range: Range<usize>| Ok(format!("header\n{}\n", lines[range].join("\n")));
+        let pages = paginate("Artifacts", lines.len(), 200, render).unwrap();
+        assert!(pages.len() > 1);
+        assert!(pages.iter().all(|p| p.len() <= 200));
+        assert!(pages[0].starts_with(&format!("## Artifacts (1/{})", pages.len())));
+        assert!(pages[0].contains("header\n- artifact-00\n"));
+        let joined = pages.join("");
+        assert!(lines[..50].iter().all(|l| joined.contains(l.as_str())));
+        assert_eq!(joined.matches('é').count(), 300);
+    }
+
+    #[test]
+    fn front_matter_is_split_from_body() {
+        let template = "+++\ncategory = \"Votes\"\nlabels = [\"vote\"]\npin = true\n+++\n# [VOTE] {{ repo }}\n";
+        let (front, body) = split_front_matter("vote", template).unwrap();
+        assert_eq!(front.category.as_deref(), Some("Votes"));
+        assert_eq!(front.labels, vec!["vote".to_string()]);
+        assert!(front.pin && !front.lock);
+        assert_eq!(body, "# [VOTE] {{ repo }}\n");
+
+        let (front, body) = split_front_matter("start", "# Kickoff\n").unwrap();
+        assert_eq!(front, FrontMatter::default());
+        assert_eq!(body, "# Kickoff\n");
+
+        assert!(split_front_matter("vote", "+++\npin = true\n# body\n").is_err());
+        assert!(split_front_matter("vote", "+++\npinned = true\n+++\n").is_err());
+
+        let categories = vec![
+            DiscussionCategory {
+                id: 1,
+                name: "General".into(),
+            },
+            DiscussionCategory {
+                id: 2,
+                name: "Releases".into(),
+            },
+        ];
+        assert_eq!(choose_category(&categories, None).unwrap().id, 2);
+        assert_eq!(choose_category(&categories, Some("general")).unwrap().id, 1);
+        assert!(choose_category(&categories, Some("Votes")).is_err());
+    }
+}
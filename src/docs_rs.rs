@@ -0,0 +1,155 @@
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tokio::time::{Instant, sleep};
+
+use crate::endpoints;
+use crate::released::ReleasedCrate;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// State of the docs.rs build of one published crate version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocsStatus {
+    Built,
+    /// The latest build failed; `log` links its build log.
+    Failed {
+        log: String,
+    },
+    /// docs.rs has not finished a build yet: the crate is queued or building.
+    Pending,
+}
+
+impl DocsStatus {
+    pub fn is_failure(&self) -> bool {
+        matches!(self, DocsStatus::Failed { .. })
+    }
+}
+
+impl fmt::Display for DocsStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocsStatus::Built => write!(f, "built"),
+            DocsStatus::Failed { log } => write!(f, "build failed (log: {})", log),
+            DocsStatus::Pending => write!(f, "not built yet"),
+        }
+    }
+}
+
+/// One entry of `/crate/<name>/<version>/builds.json`.
+#[derive(Debug, Deserialize)]
+struct Build {
+    id: u64,
+    build_status: BuildState,
+}
+
+/// docs.rs reports `success`, `failure`, or `in_progress`; older responses use a boolean.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BuildState {
+    Flag(bool),
+    Named(String),
+}
+
+/// Page of a crate version's builds on docs.rs, the parent of each build log.
+fn builds_url(c: &ReleasedCrate) -> String {
+    format!(
+        "{}/crate/{}/{}/builds",
+        endpoints::docs_rs(),
+        c.name,
+        c.version
+    )
+}
+
+pub async fn check_crate(client: &reqwest::Client, c: &ReleasedCrate) -> Result<DocsStatus> {
+    let builds = builds_url(c);
+    let resp = client.get(format!("{}.json", builds)).send().await?;
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Ok(DocsStatus::Pending);
+    }
+    let body = resp.error_for_status()?.text().await?;
+    status_from_builds(&body, &builds)
+        .with_context(|| format!("failed to parse docs.rs builds of {} {}", c.name, c.version))
+}
+
+/// Status of the most recent build listed in `body`, with logs linked under `builds`.
+fn status_from_builds(body: &str, builds: &str) -> Result<DocsStatus> {
+    let list: Vec<Build> = serde_json::from_str(body)?;
+    let Some(latest) = list.into_iter().max_by_key(|b| b.id) else {
+        return Ok(DocsStatus::Pending);
+    };
+    Ok(match latest.build_status {
+        BuildState::Flag(true) => DocsStatus::Built,
+        BuildState::Named(s) if s == "success" => DocsStatus::Built,
+        BuildState::Flag(false) => DocsStatus::Failed {
+            log: format!("{}/{}", builds, latest.id),
+        },
+        BuildState::Named(s) if s == "failure" => DocsStatus::Failed {
+            log: format!("{}/{}", builds, latest.id),
+        },
+        BuildState::Named(_) => DocsStatus::Pending,
+    })
+}
+
+/// Poll docs.rs until every crate in `crates` has a finished build, or `timeout` passes.
+/// Returns the last status of each crate, in input order; crates still queued at the
+/// deadline are `Pending`.
+pub async fn wait_for_builds<'a>(
+    client: &reqwest::Client,
+    crates: &'a [ReleasedCrate],
+    timeout: Duration,
+) -> Result<Vec<(&'a ReleasedCrate, DocsStatus)>> {
+    let deadline = Instant::now() + timeout;
+    let mut statuses: Vec<_> = crates.iter().map(|c| (c, DocsStatus::Pending)).collect();
+    loop {
+        for (c, status) in statuses.iter_mut() {
+            if *status == DocsStatus::Pending {
+                *status = check_crate(client, c).await?;
+            }
+        }
+        let pending = statuses
+            .iter()
+            .filter(|(_, s)| *s == DocsStatus::Pending)
+            .count();
+        if pending == 0 || Instant::now() + POLL_INTERVAL > deadline {
+            return Ok(statuses);
+        }
+        tracing::debug!("docs.rs: waiting for {} builds", pending);
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_build_decides_and_failures_link_their_log() {
+        let builds = "https://docs.rs/crate/foo/0.2.0/builds";
+        assert_eq!(
+            status_from_builds("[]", builds).unwrap(),
+            DocsStatus::Pending
+        );
+        assert_eq!(
+            status_from_builds(
+                r#"[{"id": 7, "build_status": "failure"}, {"id": 9, "build_status": "success"}]"#,
+                builds
+            )
+            .unwrap(),
+            DocsStatus::Built
+        );
+        assert_eq!(
+            status_from_builds(r#"[{"id": 12, "build_status": "in_progress"}]"#, builds).unwrap(),
+            DocsStatus::Pending
+        );
+        let failed = status_from_builds(r#"[{"id": 12, "build_status": false}]"#, builds).unwrap();
+        assert_eq!(
+            failed.to_string(),
+            "build failed (log: https://docs.rs/crate/foo/0.2.0/builds/12)"
+        );
+        assert!(failed.is_failure());
+    }
+}
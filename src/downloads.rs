@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use regex::Regex;
+use serde::Serialize;
+use tokio::time::{Instant, sleep};
+
+use crate::cancel;
+use crate::endpoints;
+use crate::net;
+
+const DOWNLOADS_BASE: &str = "https://downloads.apache.org";
+const CLOSER_BASE: &str = "https://www.apache.org/dyn/closer.lua";
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Public download location of a released artifact.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct DownloadLink {
+    pub name: String,
+    /// Link for announcements: closer.lua for archives, downloads.apache.org for
+    /// checksums and signatures (ASF policy forbids serving those from mirrors).
+    pub url: String,
+    /// Canonical file on downloads.apache.org, used for availability checks.
+    pub canonical_url: String,
+}
+
+/// Name of an rc artifact once promoted, i.e. without the `-rcN` marker.
+pub fn final_asset_name(name: &str) -> String {
+    let re = Regex::new(r"-rc\d+").unwrap();
+    re.replace(name, "").into_owned()
+}
+
+/// The project's `KEYS` file, which voters import before checking signatures.
+pub fn keys_url(project: &str) -> String {
+    format!("{}/{}/KEYS", DOWNLOADS_BASE, project)
+}
+
+/// Directory on downloads.apache.org holding every file of a release.
+pub fn downloads_dir_url(project: &str, version: &str) -> String {
+    format!("{}/{}/{}/", DOWNLOADS_BASE, project, version)
+}
+
+/// Page of a published crate version on crates.io.
+pub fn crates_io_url(name: &str, version: &str) -> String {
+    format!("{}/crates/{}/{}", endpoints::crates_io_web(), name, version)
+}
+
+/// Documentation of a published crate version, which docs.rs builds after publishing.
+pub fn docs_rs_url(name: &str, version: &str) -> String {
+    format!("{}/{}/{}", endpoints::docs_rs(), name, version)
+}
+
+pub fn download_links(project: &str, version: &str, asset_names: &[String]) -> Vec<DownloadLink> {
+    let mut links: Vec<DownloadLink> = asset_names
+        .iter()
+        .map(|name| {
+            let name = final_asset_name(name);
+            let path = format!("{}/{}/{}", project, version, name);
+            let canonical_url = format!("{}/{}", DOWNLOADS_BASE, path);
+            let url = if name.ends_with(".sha512") || name.ends_with(".asc") {
+                canonical_url.clone()
+            } else {
+                format!("{}/{}", CLOSER_BASE, path)
+            };
+            DownloadLink {
+                name,
+                url,
+                canonical_url,
+            }
+        })
+        .collect();
+    links.sort_by(|a, b| a.name.cmp(&b.name));
+    links
+}
+
+/// Links to the rc artifacts in `dir`, the dist/dev directory the vote was held on,
+/// under the names they were voted with.
+pub fn rc_links(dir: &str, asset_names: &[String]) -> Vec<DownloadLink> {
+    let mut links: Vec<DownloadLink> = asset_names
+        .iter()
+        .map(|name| {
+            let url = format!("{}/{}", dir.trim_end_matches('/'), name);
+            DownloadLink {
+                name: name.clone(),
+                url: url.clone(),
+                canonical_url: url,
+            }
+        })
+        .collect();
+    links.sort_by(|a, b| a.name.cmp(&b.name));
+    links
+}
+
+/// Poll downloads.apache.org until every link answers, or fail after `timeout`.
+pub async fn wait_until_available(links: &[DownloadLink], timeout: Duration) -> Result<()> {
+    let client = net::client()?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        let mut missing = Vec::new();
+        for link in links {
+            let ok = client
+                .head(&link.canonical_url)
+                .send()
+                .await
+                .map(|resp| resp.status().is_success())
+                .unwrap_or(false);
+            if !ok {
+                missing.push(link.canonical_url.as_str());
+            }
+        }
+        if missing.is_empty() {
+            tracing::info!("downloads: all {} files available", links.len());
+            return Ok(());
+        }
+        if Instant::now() + POLL_INTERVAL > deadline {
+            bail!(
+                "release files not available on downloads.apache.org after {} minutes:\n  - {}",
+                timeout.as_secs() / 60,
+                missing.join("\n  - ")
+            );
+        }
+        tracing::info!(
+            "downloads: waiting for {} of {} files to appear",
+            missing.len(),
+            links.len()
+        );
+        cancel::abortable("waiting for downloads.apache.org", async {
+            sleep(POLL_INTERVAL).await;
+            Ok(())
+        })
+        .await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn download_links_use_closer_for_archives_only() {
+        let names = vec![
+            "apache-foo-0.1.1-rc2-src.tar.gz".to_string(),
+            "apache-foo-0.1.1-rc2-src.tar.gz.sha512".to_string(),
+        ];
+        let links = download_links("foo", "0.1.1", &names);
+        assert_eq!(links[0].name, "apache-foo-0.1.1-src.tar.gz");
+        assert_eq!(
+            links[0].url,
+            "https://www.apache.org/dyn/closer.lua/foo/0.1.1/apache-foo-0.1.1-src.tar.gz"
+        );
+        assert_eq!(
+            links[1].url,
+            "https://downloads.apache.org/foo/0.1.1/apache-foo-0.1.1-src.tar.gz.sha512"
+        );
+        assert_eq!(links[1].url, links[1].canonical_url);
+    }
+}
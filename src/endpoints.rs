@@ -0,0 +1,75 @@
+const GITHUB_API: &str = "https://api.github.com";
+const DIST: &str = "https://dist.apache.org/repos/dist";
+const CRATES_IO_API: &str = "https://crates.io/api/v1/crates";
+const CRATES_IO_INDEX: &str = "https://index.crates.io";
+const CRATES_IO_DOWNLOAD: &str = "https://static.crates.io/crates";
+const CRATES_IO_WEB: &str = "https://crates.io";
+const DOCS_RS: &str = "https://docs.rs";
+const WHIMSY: &str = "https://whimsy.apache.org";
+
+// Hidden overrides so the integration tests can run a whole release against local mocks.
+const GITHUB_API_VAR: &str = "ASFSHIP_GITHUB_API_URL";
+const DIST_VAR: &str = "ASFSHIP_DIST_URL";
+const CRATES_IO_VAR: &str = "ASFSHIP_CRATES_IO_URL";
+const DOCS_RS_VAR: &str = "ASFSHIP_DOCS_RS_URL";
+const WHIMSY_VAR: &str = "ASFSHIP_WHIMSY_URL";
+
+fn overridden(var: &str) -> Option<String> {
+    std::env::var(var)
+        .ok()
+        .map(|v| v.trim_end_matches('/').to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// GitHub REST API root, also used for GraphQL (`{root}/graphql`).
+pub fn github_api() -> String {
+    overridden(GITHUB_API_VAR).unwrap_or_else(|| GITHUB_API.to_string())
+}
+
+/// SVN area where release candidates are staged for voting.
+pub fn dist_dev() -> String {
+    format!("{}/dev", dist())
+}
+
+/// SVN area of released artifacts.
+pub fn dist_release() -> String {
+    format!("{}/release", dist())
+}
+
+fn dist() -> String {
+    overridden(DIST_VAR).unwrap_or_else(|| DIST.to_string())
+}
+
+/// crates.io API for crate versions; a mock serves all three under one root.
+pub fn crates_io_api() -> String {
+    overridden(CRATES_IO_VAR)
+        .map(|base| format!("{}/api/v1/crates", base))
+        .unwrap_or_else(|| CRATES_IO_API.to_string())
+}
+
+pub fn crates_io_index() -> String {
+    overridden(CRATES_IO_VAR)
+        .map(|base| format!("{}/index", base))
+        .unwrap_or_else(|| CRATES_IO_INDEX.to_string())
+}
+
+pub fn crates_io_download() -> String {
+    overridden(CRATES_IO_VAR)
+        .map(|base| format!("{}/crates", base))
+        .unwrap_or_else(|| CRATES_IO_DOWNLOAD.to_string())
+}
+
+/// crates.io website, for links to published crate pages.
+pub fn crates_io_web() -> String {
+    overridden(CRATES_IO_VAR).unwrap_or_else(|| CRATES_IO_WEB.to_string())
+}
+
+/// docs.rs, for the build status and documentation of published crates.
+pub fn docs_rs() -> String {
+    overridden(DOCS_RS_VAR).unwrap_or_else(|| DOCS_RS.to_string())
+}
+
+/// Whimsy, which publishes the ASF project rosters under `public/`.
+pub fn whimsy() -> String {
+    overridden(WHIMSY_VAR).unwrap_or_else(|| WHIMSY.to_string())
+}
@@ -0,0 +1,292 @@
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, Utc};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::branch_commit::{BranchTarget, push_branch};
+use crate::config::ReleaseFeedConfig;
+use crate::downloads::DownloadLink;
+use crate::git_identity::GitIdentity;
+use crate::infer::InferredContext;
+use crate::schema;
+use crate::versioning::{CommitKind, Plan};
+
+/// History stored in `release.feed.json`, newest release first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Feed {
+    #[serde(default = "schema::current")]
+    schema_version: u32,
+    project: String,
+    releases: Vec<FeedEntry>,
+}
+
+/// One release as websites consume it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedEntry {
+    pub version: String,
+    pub tag: String,
+    pub date: NaiveDate,
+    /// GitHub release page.
+    pub url: String,
+    /// Release announcement discussion.
+    pub announcement: Option<String>,
+    pub downloads: Vec<FeedLink>,
+    pub crates: Vec<FeedCrate>,
+    /// Subjects of the breaking changes and features in this release.
+    pub highlights: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedLink {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedCrate {
+    pub name: String,
+    pub version: String,
+}
+
+/// Build the feed entry for a release from its plan and download links.
+pub fn entry(
+    ctx: &InferredContext,
+    version: &str,
+    tag: &str,
+    plan: &Plan,
+    links: &[DownloadLink],
+    announcement: Option<&str>,
+) -> FeedEntry {
+    let mut highlights: Vec<String> = Vec::new();
+    for (_, cp) in plan.iter() {
+        for change in cp.changes().iter().filter(|c| c.in_changelog()) {
+            let text = change.highlight_note().unwrap_or(change.subject());
+            if (change.is_highlight()
+                || matches!(change.kind(), CommitKind::Breaking | CommitKind::Feat))
+                && !highlights.iter().any(|h| h == text)
+            {
+                highlights.push(text.to_string());
+            }
+        }
+    }
+    FeedEntry {
+        version: version.to_string(),
+        tag: tag.to_string(),
+        date: Utc::now().date_naive(),
+        url: format!(
+            "https://github.com/{}/{}/releases/tag/{}",
+            ctx.repo_owner, ctx.repo_name, tag
+        ),
+        announcement: announcement.map(str::to_string),
+        downloads: links
+            .iter()
+            .map(|l| FeedLink {
+                name: l.name.clone(),
+                url: l.url.clone(),
+            })
+            .collect(),
+        crates: plan
+            .iter()
+            .map(|(name, cp)| FeedCrate {
+                name: name.clone(),
+                version: cp.new_version().to_string(),
+            })
+            .collect(),
+        highlights,
+    }
+}
+
+/// Print what `release` would add to the feed.
+pub fn describe(cfg: &ReleaseFeedConfig, entry: &FeedEntry) {
+    let Some(json) = &cfg.json else {
+        return;
+    };
+    println!(
+        "  feed: {} {} ({} highlights) -> {}{} on {}",
+        entry.version,
+        entry.date,
+        entry.highlights.len(),
+        json,
+        cfg.atom
+            .as_deref()
+            .map(|a| format!(" and {}", a))
+            .unwrap_or_default(),
+        cfg.branch.as_deref().unwrap_or("the current branch")
+    );
+}
+
+/// Record `entry` in the configured feed files, commit them, and push the branch.
+///
+/// The commit is built from the branch tip without a checkout, so a separate
+/// `gh-pages` branch never needs to be switched to.
+pub async fn publish(
+    ctx: &InferredContext,
+    entry: &FeedEntry,
+    identity: &GitIdentity,
+    audit: &AuditLog,
+) -> Result<()> {
+    let cfg = &ctx.config.release.feed;
+    let Some(json_path) = cfg.json.as_deref() else {
+        return Ok(());
+    };
+    let target =
+        BranchTarget::resolve(&ctx.repo_root, cfg.branch.as_deref(), "release.feed.branch").await?;
+    let repo = Repository::discover(&ctx.repo_root)?;
+    let mut feed = match target.read(&repo, json_path)? {
+        Some(content) => serde_json::from_slice(&content)
+            .with_context(|| format!("{} on {} is not a release feed", json_path, target.branch))?,
+        None => Feed {
+            schema_version: schema::SCHEMA_VERSION,
+            project: ctx.repo_name.clone(),
+            releases: Vec::new(),
+        },
+    };
+    schema::ensure_supported(json_path, feed.schema_version)?;
+    feed.schema_version = schema::SCHEMA_VERSION;
+    feed.add(entry.clone());
+
+    let mut files = vec![(json_path.to_string(), serde_json::to_vec_pretty(&feed)?)];
+    if let Some(atom) = &cfg.atom {
+        files.push((atom.clone(), render_atom(ctx, &feed).into_bytes()));
+    }
+    let sig = identity.signature()?;
+    let message = format!(
+        "docs: add {} {} to the release feed",
+        ctx.repo_name, entry.version
+    );
+    let Some(oid) = target.commit(&repo, &target.branch, &files, &sig, &message)? else {
+        println!("feed: {} already lists {}", json_path, entry.version);
+        return Ok(());
+    };
+    audit
+        .record(AuditAction::CommitCreated, &oid.to_string())
+        .await;
+    push_branch(&ctx.repo_root, &target.branch).await?;
+    audit
+        .record(AuditAction::BranchPushed, &target.branch)
+        .await;
+    println!(
+        "feed: added {} to {} on {}",
+        entry.version, json_path, target.branch
+    );
+    Ok(())
+}
+
+impl Feed {
+    /// Put `entry` first, replacing an earlier record of the same version.
+    fn add(&mut self, entry: FeedEntry) {
+        self.releases.retain(|r| r.version != entry.version);
+        self.releases.insert(0, entry);
+    }
+}
+
+fn render_atom(ctx: &InferredContext, feed: &Feed) -> String {
+    let releases_url = format!(
+        "https://github.com/{}/{}/releases",
+        ctx.repo_owner, ctx.repo_name
+    );
+    let timestamp = |date: NaiveDate| format!("{}T00:00:00Z", date);
+    let updated = feed
+        .releases
+        .first()
+        .map(|r| timestamp(r.date))
+        .unwrap_or_else(|| timestamp(Utc::now().date_naive()));
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str(&format!(
+        "  <title>{} releases</title>\n",
+        xml(&feed.project)
+    ));
+    out.push_str(&format!("  <id>{}</id>\n", xml(&releases_url)));
+    out.push_str(&format!("  <link href=\"{}\"/>\n", xml(&releases_url)));
+    out.push_str(&format!("  <updated>{}</updated>\n", updated));
+    for release in &feed.releases {
+        let link = release.announcement.as_deref().unwrap_or(&release.url);
+        let mut summary: Vec<String> = release
+            .crates
+            .iter()
+            .map(|c| format!("{} {}", c.name, c.version))
+            .collect();
+        summary.extend(release.highlights.iter().map(|h| format!("* {}", h)));
+        out.push_str("  <entry>\n");
+        out.push_str(&format!(
+            "    <title>{} {}</title>\n",
+            xml(&feed.project),
+            xml(&release.version)
+        ));
+        out.push_str(&format!("    <id>{}</id>\n", xml(&release.url)));
+        out.push_str(&format!("    <link href=\"{}\"/>\n", xml(link)));
+        out.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            timestamp(release.date)
+        ));
+        out.push_str(&format!(
+            "    <content type=\"text\">{}</content>\n",
+            xml(&summary.join("\n"))
+        ));
+        out.push_str("  </entry>\n");
+    }
+    out.push_str("</feed>\n");
+    out
+}
+
+fn xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn release(version: &str, highlights: &[&str]) -> FeedEntry {
+        FeedEntry {
+            version: version.into(),
+            tag: format!("v{}", version),
+            date: NaiveDate::from_ymd_opt(2026, 1, 2).unwrap(),
+            url: format!("https://github.com/apache/foo/releases/tag/v{}", version),
+            announcement: None,
+            downloads: Vec::new(),
+            crates: vec![FeedCrate {
+                name: "foo".into(),
+                version: version.into(),
+            }],
+            highlights: highlights.iter().map(|h| h.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn feed_lists_newest_release_first_and_renders_atom() {
+        let mut feed = Feed {
+            schema_version: schema::SCHEMA_VERSION,
+            project: "foo".into(),
+            releases: vec![release("0.1.0", &[])],
+        };
+        feed.add(release("0.2.0", &["feat: add <bar> & baz"]));
+        feed.add(release("0.2.0", &["feat: add <bar> & baz"]));
+        let versions: Vec<&str> = feed.releases.iter().map(|r| r.version.as_str()).collect();
+        assert_eq!(versions, ["0.2.0", "0.1.0"]);
+
+        let ctx = InferredContext {
+            repo_root: PathBuf::from("."),
+            repo_owner: "apache".into(),
+            repo_name: "foo".into(),
+            crates: Vec::new(),
+            main_crate: "foo".into(),
+            last_stable_tag: None,
+            since: None,
+            until: None,
+            config: Default::default(),
+            snapshot: None,
+        };
+        let atom = render_atom(&ctx, &feed);
+        assert!(atom.contains("<title>foo 0.2.0</title>"));
+        assert!(atom.contains("<updated>2026-01-02T00:00:00Z</updated>"));
+        assert!(atom.contains("* feat: add &lt;bar&gt; &amp; baz"));
+        assert_eq!(atom.matches("<entry>").count(), 2);
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use git2::{Repository, Signature};
+
+use crate::asf;
+use crate::config::MinimalConfig;
+
+const NAME_VAR: &str = "ASFSHIP_GIT_AUTHOR_NAME";
+const EMAIL_VAR: &str = "ASFSHIP_GIT_AUTHOR_EMAIL";
+
+/// Author of the version bump commit and tagger of rc and stable tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitIdentity {
+    pub name: String,
+    pub email: String,
+}
+
+impl GitIdentity {
+    /// Resolve each field from, in order: `ASFSHIP_GIT_AUTHOR_NAME`/`_EMAIL`,
+    /// `[git] author_name`/`author_email`, the `[asf]` release manager, and the
+    /// `user.name`/`user.email` git config of the checkout.
+    pub fn resolve(repo_root: &Path, config: &MinimalConfig) -> Result<Self> {
+        let repo = Repository::discover(repo_root)?;
+        let git_config = repo.config().ok();
+        let from_git = |key: &str| git_config.as_ref().and_then(|c| c.get_string(key).ok());
+        let rm = asf::release_manager(&config.asf)?;
+        let name = pick([
+            env(NAME_VAR),
+            config.git.author_name.clone(),
+            rm.as_ref().map(|rm| rm.name.clone()),
+            from_git("user.name"),
+        ]);
+        let email = pick([
+            env(EMAIL_VAR),
+            config.git.author_email.clone(),
+            rm.map(|rm| rm.email),
+            from_git("user.email"),
+        ]);
+        match (name, email) {
+            (Some(name), Some(email)) => Ok(Self { name, email }),
+            (name, _) => bail!(
+                "no git {} for release commits and tags; set [git] author_name and author_email in .asfship.toml, {} and {}, or git config user.name and user.email",
+                if name.is_none() {
+                    "author name"
+                } else {
+                    "author email"
+                },
+                NAME_VAR,
+                EMAIL_VAR
+            ),
+        }
+    }
+
+    pub fn signature(&self) -> Result<Signature<'static>> {
+        Signature::now(&self.name, &self.email).context("failed to build git signature")
+    }
+}
+
+fn env(var: &str) -> Option<String> {
+    std::env::var(var).ok()
+}
+
+fn pick<const N: usize>(candidates: [Option<String>; N]) -> Option<String> {
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AsfConfig, GitConfig};
+    use tempfile::TempDir;
+
+    #[test]
+    fn git_table_wins_over_release_manager_and_checkout() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut git_config = repo.config().unwrap();
+        git_config.set_str("user.name", "Checkout").unwrap();
+        git_config
+            .set_str("user.email", "checkout@example.com")
+            .unwrap();
+        let mut config = MinimalConfig {
+            asf: AsfConfig {
+                release_manager: Some("alice".into()),
+                release_manager_name: Some("Alice Example".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            GitIdentity::resolve(dir.path(), &config).unwrap(),
+            GitIdentity {
+                name: "Alice Example".into(),
+                email: "alice@apache.org".into()
+            }
+        );
+
+        config.git = GitConfig {
+            author_name: Some("Release Bot".into()),
+            author_email: Some(" ".into()),
+        };
+        assert_eq!(
+            GitIdentity::resolve(dir.path(), &config).unwrap(),
+            GitIdentity {
+                name: "Release Bot".into(),
+                email: "alice@apache.org".into()
+            }
+        );
+
+        config.asf = AsfConfig::default();
+        assert_eq!(
+            GitIdentity::resolve(dir.path(), &config).unwrap().email,
+            "checkout@example.com"
+        );
+    }
+
+    #[test]
+    fn blank_values_are_skipped() {
+        assert_eq!(
+            pick([None, Some("  ".into()), Some("b".into())]),
+            Some("b".into())
+        );
+        assert_eq!(pick::<2>([None, None]), None);
+    }
+}
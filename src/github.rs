@@ -1,28 +1,280 @@
-use anyhow::{Context, Result};
-use octocrab::Octocrab;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 
-/// Return true if ASFSHIP_GITHUB_TOKEN is present and non-empty.
+use anyhow::{Context, Result, bail};
+use octocrab::service::middleware::auth_header::AuthHeaderLayer;
+use octocrab::service::middleware::base_uri::BaseUriLayer;
+use octocrab::service::middleware::extra_headers::ExtraHeadersLayer;
+use octocrab::service::middleware::retry::RetryConfig;
+use octocrab::{AuthState, Octocrab, OctocrabBuilder};
+
+use crate::config::{GithubAuth, GithubConfig};
+use crate::endpoints;
+use crate::github_app::{AppAuth, AppTokenLayer};
+use crate::net;
+use crate::redact;
+
+/// Where GitHub credentials come from, selected by `[github] auth`.
+enum Auth {
+    /// `ASFSHIP_GITHUB_TOKEN`, falling back to the gh CLI login.
+    Personal,
+    /// A token in this environment variable.
+    Env(&'static str),
+    App(AppAuth),
+}
+
+const TOKEN_VAR: &str = "ASFSHIP_GITHUB_TOKEN";
+
+static AUTH: OnceLock<Auth> = OnceLock::new();
+static GH_TOKEN: OnceLock<Option<String>> = OnceLock::new();
+
+fn auth() -> &'static Auth {
+    AUTH.get_or_init(|| Auth::Personal)
+}
+
+fn env_token(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|v| !v.is_empty())
+}
+
+/// The gh CLI login `init` found, if any.
+fn gh_token() -> Option<&'static str> {
+    GH_TOKEN.get().and_then(Option::as_deref)
+}
+
+/// Ask gh for the token it stores for github.com (in its keyring or hosts file).
+async fn query_gh_token() -> Option<String> {
+    let output = tokio::process::Command::new("gh")
+        .args(["auth", "token", "--hostname", "github.com"])
+        .output()
+        .await
+        .ok()?;
+    let token = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if !output.status.success() || token.is_empty() {
+        return None;
+    }
+    redact::register(&token);
+    tracing::debug!("github: using the gh CLI login");
+    Some(token)
+}
+
+/// Select the credential source from `[github]`, asking gh for its login once when
+/// `ASFSHIP_GITHUB_TOKEN` is unset; call once after `net::init`.
+pub async fn init(repo_root: &Path, config: &GithubConfig, owner: &str, repo: &str) -> Result<()> {
+    let auth = match config.auth {
+        GithubAuth::Token => Auth::Personal,
+        GithubAuth::Actions => Auth::Env("GITHUB_TOKEN"),
+        GithubAuth::App => {
+            let app_id = config.app_id.context("github.app_id is not set")?;
+            let key = match (&config.private_key, &config.private_key_env) {
+                (Some(path), _) => {
+                    let path = repo_root.join(path);
+                    std::fs::read(&path).with_context(|| {
+                        format!("failed to read GitHub App key {}", path.display())
+                    })?
+                }
+                (None, Some(var)) => std::env::var(var)
+                    .ok()
+                    .filter(|v| !v.is_empty())
+                    .with_context(|| format!("GitHub App key variable {} is not set", var))?
+                    .into_bytes(),
+                (None, None) => bail!("github.private_key or github.private_key_env is not set"),
+            };
+            Auth::App(AppAuth::new(
+                app_id,
+                config.installation_id,
+                &key,
+                owner,
+                repo,
+            )?)
+        }
+    };
+    if AUTH.set(auth).is_err() {
+        tracing::debug!("github: auth already initialised");
+    }
+    if matches!(AUTH.get(), Some(Auth::Personal))
+        && env_token(TOKEN_VAR).is_none()
+        && GH_TOKEN.get().is_none()
+    {
+        let _ = GH_TOKEN.set(query_gh_token().await);
+    }
+    Ok(())
+}
+
+/// Human-readable name of the credential in use, for error messages.
+pub fn token_source() -> String {
+    match auth() {
+        Auth::Personal if env_token(TOKEN_VAR).is_none() && gh_token().is_some() => {
+            "the gh CLI login".to_string()
+        }
+        Auth::Personal => TOKEN_VAR.to_string(),
+        Auth::Env(var) => var.to_string(),
+        Auth::App(app) => format!("GitHub App {} installation token", app.app_id()),
+    }
+}
+
+/// Return true if a GitHub credential is available.
 pub fn has_token() -> bool {
-    std::env::var("ASFSHIP_GITHUB_TOKEN")
-        .map(|v| !v.is_empty())
-        .unwrap_or(false)
+    match auth() {
+        Auth::Personal => env_token(TOKEN_VAR).is_some() || gh_token().is_some(),
+        Auth::Env(var) => env_token(var).is_some(),
+        Auth::App(_) => true,
+    }
+}
+
+/// Fetch the GitHub token, minting a fresh installation token for Apps when needed.
+///
+/// Installation tokens expire, so callers making requests over a long stretch should
+/// ask again before each one rather than hold on to the result.
+pub async fn token() -> Result<String> {
+    match auth() {
+        Auth::App(app) => app.token().await,
+        _ => personal_token(),
+    }
 }
 
-/// Fetch the GitHub token from the environment.
-pub fn token() -> Result<String> {
-    match std::env::var("ASFSHIP_GITHUB_TOKEN") {
-        Ok(token) if !token.is_empty() => Ok(token),
-        _ => Err(anyhow::anyhow!(
-            "missing ASFSHIP_GITHUB_TOKEN for GitHub API"
-        )),
+/// The token of a personal or environment credential, which does not expire mid-run.
+fn personal_token() -> Result<String> {
+    match auth() {
+        Auth::Personal => env_token(TOKEN_VAR)
+            .or_else(|| gh_token().map(str::to_string))
+            .with_context(|| {
+                format!(
+                    "missing {} for GitHub API (and `gh auth token` found no login)",
+                    TOKEN_VAR
+                )
+            }),
+        Auth::Env(var) => env_token(var).with_context(|| format!("missing {} for GitHub API", var)),
+        Auth::App(app) => bail!("GitHub App {} has no personal token", app.app_id()),
     }
 }
 
 /// Build an authenticated Octocrab client using the token.
+///
+/// App clients authorize each request with a current installation token instead.
 pub fn client() -> Result<Octocrab> {
-    let token = token()?;
-    Octocrab::builder()
+    if let Auth::App(app) = auth() {
+        return app_client(app);
+    }
+    let token = personal_token()?;
+    if net::needs_custom_transport() {
+        return proxied_client(&token);
+    }
+    let mut builder = Octocrab::builder();
+    builder.add_retry_config(retry_config());
+    builder
+        .set_connect_timeout(Some(net::connect_timeout()))
+        .set_read_timeout(Some(net::read_timeout()))
+        .set_write_timeout(Some(net::read_timeout()))
+        .base_uri(endpoints::github_api())
+        .context("invalid GitHub API URL")?
         .personal_token(token)
         .build()
         .context("failed to build GitHub client")
 }
+
+/// Retries for every GitHub client: after connection errors, 5xx, and 429, up to
+/// `[network] retries` times.
+fn retry_config() -> RetryConfig {
+    RetryConfig::Simple(net::retries())
+}
+
+/// Octocrab on top of the shared reqwest client, so proxy and CA settings apply.
+///
+/// Mirrors the layers octocrab's default builder installs around its own connector.
+fn proxied_client(token: &str) -> Result<Octocrab> {
+    let base_uri: http::Uri = endpoints::github_api()
+        .parse()
+        .context("invalid GitHub API URL")?;
+    let auth = format!("Bearer {}", token)
+        .parse()
+        .context("invalid GitHub token")?;
+    let headers = vec![(
+        http::header::USER_AGENT,
+        http::HeaderValue::from_static("asfship"),
+    )];
+    let client = OctocrabBuilder::new_empty()
+        .with_service(net::ReqwestService::new(retry_config())?)
+        .with_layer(&ExtraHeadersLayer::new(Arc::new(headers)))
+        .with_layer(&BaseUriLayer::new(base_uri.clone()))
+        .with_layer(&AuthHeaderLayer::new(Some(auth), base_uri))
+        .with_auth(AuthState::None)
+        .build()
+        .map_err(|e| anyhow::anyhow!("failed to build GitHub client: {}", e))?;
+    Ok(client)
+}
+
+/// Octocrab on the shared reqwest client that asks `app` for a token on every request.
+fn app_client(app: &'static AppAuth) -> Result<Octocrab> {
+    let base_uri: http::Uri = endpoints::github_api()
+        .parse()
+        .context("invalid GitHub API URL")?;
+    let headers = vec![(
+        http::header::USER_AGENT,
+        http::HeaderValue::from_static("asfship"),
+    )];
+    let client = OctocrabBuilder::new_empty()
+        .with_service(net::ReqwestService::new(retry_config())?)
+        .with_layer(&AppTokenLayer(app))
+        .with_layer(&ExtraHeadersLayer::new(Arc::new(headers)))
+        .with_layer(&BaseUriLayer::new(base_uri))
+        .with_auth(AuthState::None)
+        .build()
+        .map_err(|e| anyhow::anyhow!("failed to build GitHub client: {}", e))?;
+    Ok(client)
+}
+
+/// The release for `tag`, drafts included.
+///
+/// `releases/tags/{tag}` does not return drafts, so a miss there falls back to the
+/// newest 100 releases, which do list them for users with push access.
+pub async fn release_by_tag(
+    gh: &Octocrab,
+    owner: &str,
+    repo: &str,
+    tag: &str,
+) -> Result<Option<octocrab::models::repos::Release>> {
+    let repos = gh.repos(owner, repo);
+    let releases = repos.releases();
+    match releases.get_by_tag(tag).await {
+        Ok(release) => return Ok(Some(release)),
+        Err(octocrab::Error::GitHub { source, .. })
+            if source.status_code == reqwest::StatusCode::NOT_FOUND => {}
+        Err(err) => return Err(err.into()),
+    }
+    let page = releases
+        .list()
+        .per_page(100)
+        .send()
+        .await
+        .with_context(|| format!("failed to list releases of {}/{}", owner, repo))?;
+    Ok(page.items.into_iter().find(|r| r.tag_name == tag))
+}
+
+/// Delete a release asset; octocrab has no API for this, so call REST directly.
+///
+/// A missing asset counts as deleted.
+pub async fn delete_release_asset(
+    client: &reqwest::Client,
+    token: &str,
+    owner: &str,
+    repo: &str,
+    asset_id: u64,
+) -> Result<()> {
+    let url = format!(
+        "{}/repos/{}/{}/releases/assets/{}",
+        endpoints::github_api(),
+        owner,
+        repo,
+        asset_id
+    );
+    let resp = client
+        .delete(&url)
+        .bearer_auth(token)
+        .header(reqwest::header::USER_AGENT, "asfship")
+        .send()
+        .await?;
+    if !resp.status().is_success() && resp.status() != reqwest::StatusCode::NOT_FOUND {
+        anyhow::bail!("delete asset {} failed: {}", asset_id, resp.status());
+    }
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tower::{BoxError, Layer, Service};
+
+use crate::endpoints;
+use crate::net;
+use crate::redact;
+
+/// Installation tokens live for an hour; renew them a little before they run out so
+/// a request started with one never reaches GitHub expired.
+const REFRESH_MARGIN: Duration = Duration::minutes(5);
+
+/// Authentication as a GitHub App installation on the release repository.
+pub struct AppAuth {
+    app_id: u64,
+    key: EncodingKey,
+    owner: String,
+    repo: String,
+    state: Mutex<AppState>,
+}
+
+#[derive(Default)]
+struct AppState {
+    installation_id: Option<u64>,
+    token: Option<InstallationToken>,
+}
+
+#[derive(Clone, Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct Installation {
+    id: u64,
+}
+
+#[derive(Serialize)]
+struct Claims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+impl AppAuth {
+    pub fn new(
+        app_id: u64,
+        installation_id: Option<u64>,
+        private_key: &[u8],
+        owner: &str,
+        repo: &str,
+    ) -> Result<Self> {
+        let key = EncodingKey::from_rsa_pem(private_key)
+            .context("GitHub App private key is not an RSA PEM key")?;
+        Ok(Self {
+            app_id,
+            key,
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            state: Mutex::new(AppState {
+                installation_id,
+                token: None,
+            }),
+        })
+    }
+
+    pub fn app_id(&self) -> u64 {
+        self.app_id
+    }
+
+    /// A valid installation token, minting a new one when the cached one is about to expire.
+    pub async fn token(&self) -> Result<String> {
+        let mut state = self.state.lock().await;
+        if let Some(cached) = &state.token
+            && cached.expires_at - Utc::now() > REFRESH_MARGIN
+        {
+            return Ok(cached.token.clone());
+        }
+        let fresh = self.mint(&mut state).await?;
+        state.token = Some(fresh.clone());
+        Ok(fresh.token)
+    }
+
+    async fn mint(&self, state: &mut AppState) -> Result<InstallationToken> {
+        let jwt = self.jwt()?;
+        let client = net::client()?;
+        let installation_id = match state.installation_id {
+            Some(id) => id,
+            None => {
+                let url = format!(
+                    "{}/repos/{}/{}/installation",
+                    endpoints::github_api(),
+                    self.owner,
+                    self.repo
+                );
+                let installation: Installation = send(client.get(&url).bearer_auth(&jwt))
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "GitHub App {} is not installed on {}/{}",
+                            self.app_id, self.owner, self.repo
+                        )
+                    })?;
+                state.installation_id = Some(installation.id);
+                installation.id
+            }
+        };
+        let url = format!(
+            "{}/app/installations/{}/access_tokens",
+            endpoints::github_api(),
+            installation_id
+        );
+        let token: InstallationToken = send(client.post(&url).bearer_auth(&jwt))
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to create an installation token for GitHub App {}",
+                    self.app_id
+                )
+            })?;
+        redact::register(&token.token);
+        tracing::debug!(
+            "github: minted installation token valid until {}",
+            token.expires_at
+        );
+        Ok(token)
+    }
+
+    /// App JWT; backdated a minute to absorb clock drift, and GitHub caps it at ten minutes.
+    fn jwt(&self) -> Result<String> {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            iat: now - 60,
+            exp: now + 9 * 60,
+            iss: self.app_id.to_string(),
+        };
+        jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.key)
+            .context("failed to sign the GitHub App JWT")
+    }
+}
+
+/// Tower layer that authorizes each octocrab request with the current installation
+/// token, so a long run keeps working past the hour a single token lasts.
+pub struct AppTokenLayer(pub &'static AppAuth);
+
+impl<S> Layer<S> for AppTokenLayer {
+    type Service = AppTokenService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AppTokenService { app: self.0, inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct AppTokenService<S> {
+    app: &'static AppAuth,
+    inner: S,
+}
+
+impl<S> Service<http::Request<String>> for AppTokenService<S>
+where
+    S: Service<http::Request<String>> + Clone + Send + 'static,
+    S::Response: Send,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<String>) -> Self::Future {
+        // Use the service that was polled ready; leave a fresh clone for the next call.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let app = self.app;
+        Box::pin(async move {
+            let token = app.token().await?;
+            let value = format!("Bearer {}", token).parse()?;
+            req.headers_mut().insert(http::header::AUTHORIZATION, value);
+            inner.call(req).await.map_err(Into::into)
+        })
+    }
+}
+
+async fn send<T: serde::de::DeserializeOwned>(req: reqwest::RequestBuilder) -> Result<T> {
+    let resp = req
+        .header(reqwest::header::USER_AGENT, "asfship")
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await?;
+    let status = resp.status();
+    let body = resp.bytes().await?;
+    if !status.is_success() {
+        bail!(
+            "GitHub returned {}: {}",
+            status,
+            String::from_utf8_lossy(&body)
+        );
+    }
+    Ok(serde_json::from_slice(&body)?)
+}
@@ -0,0 +1,270 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use tokio::fs as async_fs;
+use tokio::process::Command;
+
+/// Write an armored detached signature of `path` to `<path>.asc`, using `fingerprint`
+/// or gpg's default key.
+pub async fn sign(path: &Path, fingerprint: Option<&str>) -> Result<PathBuf> {
+    let signature = PathBuf::from(format!("{}.asc", path.display()));
+    let mut cmd = Command::new("gpg");
+    cmd.args(["--batch", "--yes", "--armor", "--detach-sign"]);
+    if let Some(fpr) = fingerprint {
+        cmd.args(["--local-user", fpr]);
+    }
+    let output = cmd
+        .arg("--output")
+        .arg(&signature)
+        .arg(path)
+        .output()
+        .await
+        .context("failed to run gpg; signing release files needs gpg on PATH")?;
+    if !output.status.success() {
+        bail!(
+            "gpg could not sign {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(signature)
+}
+
+/// Check the detached `signature` of `path` against `keys` alone.
+///
+/// `keys` (armored public keys, such as the project's `KEYS` file) are imported into a
+/// fresh keyring at `home`, so keys that merely happen to be in the user's keyring do not
+/// count. With `signer`, the signature must also come from that primary key.
+///
+/// Returns gpg's `Good signature from ...` line, or its complaint as the error.
+pub async fn verify(
+    signature: &Path,
+    path: &Path,
+    home: &Path,
+    keys: &[u8],
+    signer: Option<&str>,
+) -> Result<String> {
+    if async_fs::try_exists(home).await? {
+        async_fs::remove_dir_all(home).await?;
+    }
+    async_fs::create_dir_all(home).await?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        async_fs::set_permissions(home, std::fs::Permissions::from_mode(0o700)).await?;
+    }
+    let keys_path = home.join("KEYS");
+    async_fs::write(&keys_path, keys).await?;
+    let output = Command::new("gpg")
+        .arg("--homedir")
+        .arg(home)
+        .args(["--batch", "--import"])
+        .arg(&keys_path)
+        .output()
+        .await
+        .context("failed to run gpg; checking signatures needs gpg on PATH")?;
+    if !output.status.success() {
+        bail!(
+            "gpg could not import the trusted keys: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let output = Command::new("gpg")
+        .arg("--homedir")
+        .arg(home)
+        .args(["--batch", "--status-fd", "1", "--verify"])
+        .arg(signature)
+        .arg(path)
+        .output()
+        .await
+        .context("failed to run gpg; checking signatures needs gpg on PATH")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let summary = |prefix: &str| {
+        stderr
+            .lines()
+            .map(|l| l.trim_start_matches("gpg:").trim())
+            .find(|l| l.starts_with(prefix))
+            .map(str::to_string)
+    };
+    if !output.status.success() {
+        bail!(
+            "{}",
+            summary("BAD signature")
+                .or_else(|| summary("Can't check signature"))
+                .unwrap_or_else(|| stderr.trim().to_string())
+        );
+    }
+    let signed_by = valid_signer(&String::from_utf8_lossy(&output.stdout))
+        .context("gpg reported no valid signature")?;
+    if let Some(expected) = signer
+        && !signed_by.eq_ignore_ascii_case(expected)
+    {
+        bail!("signed by key {}, not {}", signed_by, expected);
+    }
+    Ok(summary("Good signature").unwrap_or_else(|| "good signature".to_string()))
+}
+
+/// Export the armored public key `fingerprint` from the local keyring.
+pub async fn export_public_key(fingerprint: &str) -> Result<Vec<u8>> {
+    let output = Command::new("gpg")
+        .args(["--batch", "--armor", "--export", fingerprint])
+        .output()
+        .await
+        .context("failed to run gpg")?;
+    if !output.status.success() || output.stdout.is_empty() {
+        bail!("gpg has no public key {} to export", fingerprint);
+    }
+    Ok(output.stdout)
+}
+
+/// Primary key fingerprint of the `VALIDSIG` status line, the last of its fields.
+fn valid_signer(status: &str) -> Option<String> {
+    status
+        .lines()
+        .find_map(|l| l.strip_prefix("[GNUPG:] VALIDSIG "))
+        .and_then(|rest| rest.split_whitespace().last())
+        .map(str::to_string)
+}
+
+/// Fingerprints of the secret keys in the local keyring, in gpg's listing order.
+pub async fn secret_fingerprints() -> Result<Vec<String>> {
+    let output = Command::new("gpg")
+        .args(["--batch", "--list-secret-keys", "--with-colons"])
+        .output()
+        .await
+        .context("failed to run gpg")?;
+    if !output.status.success() {
+        bail!(
+            "gpg could not list secret keys: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(primary_fingerprints(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// The `fpr` record following each `sec` record; subkey fingerprints are skipped.
+fn primary_fingerprints(listing: &str) -> Vec<String> {
+    let mut fingerprints = Vec::new();
+    let mut in_primary = false;
+    for line in listing.lines() {
+        let mut fields = line.split(':');
+        match fields.next() {
+            Some("sec") => in_primary = true,
+            Some("fpr") if in_primary => {
+                if let Some(fpr) = fields.nth(8) {
+                    fingerprints.push(fpr.to_string());
+                }
+                in_primary = false;
+            }
+            Some("ssb") => in_primary = false,
+            _ => {}
+        }
+    }
+    fingerprints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_primary_key_fingerprints_only() {
+        let listing = "\
+sec:u:4096:1:8F3B2C1D0E9A7B6C:1700000000:::u:::scESC:::+:::23::0:
+fpr:::::::::0123456789ABCDEF0123456789ABCDEF01234567:
+grp:::::::::AAAA:
+uid:u::::1700000000::HASH::Alice <alice@apache.org>::::::::::0:
+ssb:u:4096:1:1111222233334444:1700000000::::::e:::+:::23:
+fpr:::::::::FEDCBA9876543210FEDCBA9876543210FEDCBA98:
+";
+        assert_eq!(
+            primary_fingerprints(listing),
+            ["0123456789ABCDEF0123456789ABCDEF01234567"]
+        );
+    }
+
+    #[test]
+    fn valid_signer_is_the_primary_key() {
+        let status = "\
+[GNUPG:] NEWSIG
+[GNUPG:] GOODSIG 1111222233334444 Alice <alice@apache.org>
+[GNUPG:] VALIDSIG FEDCBA9876543210FEDCBA9876543210FEDCBA98 2024-05-01 1714557600 0 4 0 22 10 00 0123456789ABCDEF0123456789ABCDEF01234567
+";
+        assert_eq!(
+            valid_signer(status).as_deref(),
+            Some("0123456789ABCDEF0123456789ABCDEF01234567")
+        );
+        assert_eq!(valid_signer("[GNUPG:] BADSIG 1111 Alice\n"), None);
+    }
+
+    #[tokio::test]
+    async fn verify_trusts_only_the_given_keys() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let signer_home = dir.path().join("signer");
+        std::fs::create_dir_all(&signer_home).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&signer_home, std::fs::Permissions::from_mode(0o700)).unwrap();
+        }
+        let gpg = |args: &[&str]| {
+            std::process::Command::new("gpg")
+                .arg("--homedir")
+                .arg(&signer_home)
+                .args(["--batch", "--passphrase", ""])
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        for uid in ["Alice <alice@apache.org>", "Mallory <mallory@example.com>"] {
+            assert!(
+                gpg(&["--quick-gen-key", uid, "ed25519", "sign", "never"])
+                    .status
+                    .success()
+            );
+        }
+        let listing = gpg(&["--list-secret-keys", "--with-colons"]);
+        let fprs = primary_fingerprints(&String::from_utf8_lossy(&listing.stdout));
+        let (alice, mallory) = (&fprs[0], &fprs[1]);
+        let file = dir.path().join("manifest.json");
+        std::fs::write(&file, "{}").unwrap();
+        let signed = std::process::Command::new("gpg")
+            .arg("--homedir")
+            .arg(&signer_home)
+            .args([
+                "--batch",
+                "--local-user",
+                mallory,
+                "--detach-sign",
+                "--armor",
+            ])
+            .arg(&file)
+            .status()
+            .unwrap();
+        assert!(signed.success());
+        let signature = dir.path().join("manifest.json.asc");
+        let alice_key = gpg(&["--armor", "--export", alice]).stdout;
+        let both_keys = gpg(&["--armor", "--export"]).stdout;
+        let _ = std::process::Command::new("gpgconf")
+            .arg("--homedir")
+            .arg(&signer_home)
+            .args(["--kill", "gpg-agent"])
+            .status();
+
+        let home = dir.path().join("keyring");
+        let err = verify(&signature, &file, &home, &alice_key, None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("No public key"), "{}", err);
+        verify(&signature, &file, &home, &both_keys, None)
+            .await
+            .unwrap();
+        let err = verify(&signature, &file, &home, &both_keys, Some(alice))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not"), "{}", err);
+    }
+}
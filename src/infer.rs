@@ -1,12 +1,17 @@
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
 use cargo_metadata::{CargoOpt, Metadata, MetadataCommand, Package, PackageId};
 use git2::{Repository, StatusOptions};
 use regex::Regex;
+use tokio::process::Command;
 
-use crate::config::load_minimal_config;
+use crate::artifacts;
+use crate::config::{MinimalConfig, NetworkTuning, check_references, load_minimal_config};
+use crate::snapshot::Snapshot;
+use crate::tags::TagFormat;
 
 #[derive(Debug, Clone)]
 pub struct CrateInfo {
@@ -15,6 +20,8 @@ pub struct CrateInfo {
     pub manifest_path: PathBuf,
     pub package_root: PathBuf,
     pub internal_dep_count: usize,
+    /// False when the manifest sets `publish = false`.
+    pub publish: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -25,27 +32,116 @@ pub struct InferredContext {
     pub crates: Vec<CrateInfo>,
     pub main_crate: String,
     pub last_stable_tag: Option<String>,
+    /// `--since`: revision the plan starts after instead of `last_stable_tag`.
+    pub since: Option<String>,
+    /// `--until`: revision the plan ends at instead of HEAD; only `plan` accepts it.
+    pub until: Option<String>,
+    pub config: MinimalConfig,
+    /// Export of HEAD that `repo_root` points at when running against a bare repository.
+    pub snapshot: Option<Arc<Snapshot>>,
 }
 
-pub async fn repo_root() -> Result<PathBuf> {
+impl InferredContext {
+    /// Revision the plan starts after: `--since`, or else the last stable tag.
+    pub fn plan_base(&self) -> Option<&str> {
+        self.since.as_deref().or(self.last_stable_tag.as_deref())
+    }
+}
+
+/// Where to find the repository and which GitHub repository it belongs to.
+#[derive(Debug, Clone, Default)]
+pub struct InferOptions {
+    pub no_base: bool,
+    /// `<owner>/<name>` overriding the one parsed from the git remote.
+    pub repo: Option<String>,
+    /// Repository to open instead of discovering one from the current directory.
+    pub git_dir: Option<PathBuf>,
+    /// Skip the clean working tree check, for read-only commands.
+    pub allow_dirty: bool,
+    /// Leave a shallow clone as it is instead of fetching its history, for `plan` and
+    /// dry runs.
+    pub read_only: bool,
+    /// Run against a bare repository by reading files from an export of HEAD, for
+    /// read-only commands.
+    pub allow_bare: bool,
+    /// Report unmet `[release.gate]` approvals up front, for `release`.
+    pub release_gate: bool,
+    /// Command-line overrides of the `[network]` timeouts and retries.
+    pub network: NetworkTuning,
+    /// `--only`: release just these crates instead of the configured `only`.
+    pub only: Vec<String>,
+    /// `--since`: revision the plan starts after, instead of the last stable tag.
+    pub since: Option<String>,
+    /// `--until`: revision the plan ends at, instead of HEAD.
+    pub until: Option<String>,
+}
+
+/// Where a repository keeps its files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoLocation {
+    Worktree(PathBuf),
+    /// The git directory of a bare repository, such as a server-side mirror.
+    Bare(PathBuf),
+}
+
+impl RepoLocation {
+    pub fn path(&self) -> &Path {
+        match self {
+            RepoLocation::Worktree(path) | RepoLocation::Bare(path) => path,
+        }
+    }
+}
+
+/// Find the repository at `git_dir`, or from `GIT_DIR` and the current directory like git.
+pub async fn locate(git_dir: Option<&Path>) -> Result<RepoLocation> {
     tracing::trace!("infer: discovering repo root");
-    tokio::task::spawn_blocking(|| {
-        let repo = Repository::discover(".")?;
-        let raw = repo.workdir().unwrap_or(repo.path()).to_path_buf();
-        Ok::<_, anyhow::Error>(normalize_path(&raw))
+    let start = git_dir.map(Path::to_path_buf);
+    tokio::task::spawn_blocking(move || {
+        let repo = match &start {
+            Some(start) => Repository::open(start)
+                .with_context(|| format!("{} is not a git repository", start.display()))?,
+            None => Repository::open_from_env()?,
+        };
+        Ok::<_, anyhow::Error>(match repo.workdir() {
+            Some(workdir) => RepoLocation::Worktree(normalize_path(workdir)),
+            None => RepoLocation::Bare(normalize_path(repo.path())),
+        })
     })
     .await
     .map_err(|e| anyhow::anyhow!("repo_root task join error: {}", e))?
 }
 
-pub async fn ensure_clean_repo(root: &Path) -> Result<()> {
+pub async fn repo_root(git_dir: Option<&Path>) -> Result<PathBuf> {
+    match locate(git_dir).await? {
+        RepoLocation::Worktree(root) => Ok(root),
+        RepoLocation::Bare(git_dir) => Err(bare_error(&git_dir)),
+    }
+}
+
+fn bare_error(git_dir: &Path) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} is a bare repository; asfship needs a checkout (use `git worktree add` to create one), except for `plan` and `status`",
+        git_dir.display()
+    )
+}
+
+/// Fail on any change to the working tree, except untracked files below `artifacts`
+/// (relative to `root`).
+pub async fn ensure_clean_repo(root: &Path, artifacts: &Path) -> Result<()> {
     let root = root.to_path_buf();
+    let artifacts = artifacts.to_path_buf();
     tokio::task::spawn_blocking(move || {
         let repo = Repository::discover(root)?;
         let mut opts = StatusOptions::new();
         opts.include_untracked(true).recurse_untracked_dirs(true);
         let statuses = repo.statuses(Some(&mut opts))?;
         let dirty = statuses.iter().any(|s| {
+            if s.status() == git2::Status::WT_NEW
+                && s.path()
+                    .is_some_and(|p| Path::new(p).starts_with(&artifacts))
+            {
+                return false;
+            }
             s.status().intersects(
                 git2::Status::INDEX_NEW
                     | git2::Status::INDEX_MODIFIED
@@ -65,11 +161,123 @@ pub async fn ensure_clean_repo(root: &Path) -> Result<()> {
     Ok(())
 }
 
-pub async fn infer_remote(root: &Path) -> Result<(String, String, String)> {
+/// Refuse to tag HEAD unless it is a branch tracking origin whose tip, after a fetch,
+/// is exactly HEAD, so a pushed tag never points at a commit origin lacks.
+///
+/// Returns the branch's ref on origin (e.g. `refs/heads/main`), which may be named
+/// differently from the local branch.
+pub async fn ensure_head_pushed(root: &Path) -> Result<String> {
+    let root = root.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let repo = Repository::discover(&root)?;
+        if repo.head_detached()? {
+            let head = repo.head()?.peel_to_commit()?.id();
+            bail!(
+                "HEAD is detached at {}; check out the release branch (`git switch <branch>`) before tagging",
+                head
+            );
+        }
+        let head = repo.head()?;
+        let refname = head.name().context("HEAD is not a valid UTF-8 reference")?;
+        let branch = head.shorthand().unwrap_or(refname).to_string();
+        let remote = repo
+            .branch_upstream_remote(refname)
+            .ok()
+            .and_then(|r| r.as_str().map(str::to_string));
+        if remote.as_deref() != Some("origin") {
+            bail!(
+                "branch {} {}; asfship pushes to origin, so run `git push -u origin {}` first",
+                branch,
+                match remote {
+                    Some(remote) => format!("tracks {} instead of origin", remote),
+                    None => "does not track a remote branch".to_string(),
+                },
+                branch
+            );
+        }
+        let merge_ref = repo
+            .config()?
+            .get_string(&format!("branch.{}.merge", branch))
+            .with_context(|| format!("branch {} has no upstream ref on origin", branch))?;
+        let upstream_ref = repo.branch_upstream_name(refname)?;
+        let upstream_ref = upstream_ref
+            .as_str()
+            .context("upstream is not a valid UTF-8 reference")?
+            .to_string();
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(&root)
+            .args(["fetch", "--quiet", "--no-tags", "origin"])
+            .output()
+            .context("failed to run git fetch")?;
+        if !output.status.success() {
+            bail!(
+                "failed to fetch origin to compare {} with it: {}",
+                branch,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let upstream_name = upstream_ref
+            .strip_prefix("refs/remotes/")
+            .unwrap_or(&upstream_ref)
+            .to_string();
+        let local = head.peel_to_commit()?.id();
+        let Ok(remote_tip) = repo.refname_to_id(&upstream_ref) else {
+            bail!(
+                "{} does not exist; run `git push -u origin {}` first",
+                upstream_name,
+                branch
+            );
+        };
+        if local == remote_tip {
+            return Ok(merge_ref);
+        }
+        match repo.graph_ahead_behind(local, remote_tip)? {
+            (ahead, 0) => bail!(
+                "{} has {} commit(s) not on {}; push them (`git push origin {}`) or reset to {} before tagging",
+                branch,
+                ahead,
+                upstream_name,
+                branch,
+                upstream_name
+            ),
+            (0, behind) => bail!(
+                "{} is {} commit(s) behind {}; run `git pull --ff-only` before tagging",
+                branch,
+                behind,
+                upstream_name
+            ),
+            (ahead, behind) => bail!(
+                "{} and {} have diverged ({} local and {} remote commit(s)), e.g. after a rebase; reconcile them (`git pull --rebase`, or `git reset --hard {}` to drop the local ones) before tagging",
+                branch,
+                upstream_name,
+                ahead,
+                behind,
+                upstream_name
+            ),
+        }
+    })
+    .await
+    .map_err(|e| anyhow::anyhow!("ensure_head_pushed task join error: {}", e))?
+}
+
+pub async fn infer_remote(
+    root: &Path,
+    override_repo: Option<&str>,
+) -> Result<(String, String, String)> {
     let root = root.to_path_buf();
+    let override_repo = override_repo.map(parse_repo_slug).transpose()?;
     tokio::task::spawn_blocking(move || {
         // returns (owner, name, url)
         let repo = Repository::discover(root)?;
+        if let Some((owner, name)) = override_repo {
+            let url = repo
+                .find_remote("origin")
+                .ok()
+                .and_then(|r| r.url().map(str::to_string))
+                .unwrap_or_else(|| format!("https://github.com/{}/{}.git", owner, name));
+            return Ok((owner, name, url));
+        }
         let remotes = repo.remotes()?;
         let mut chosen: Option<String> = None;
         if let Some(name) = remotes.iter().flatten().find(|r| *r == "origin") {
@@ -104,9 +312,22 @@ pub async fn infer_remote(root: &Path) -> Result<(String, String, String)> {
     .map_err(|e| anyhow::anyhow!("infer_remote task join error: {}", e))?
 }
 
-pub async fn load_metadata() -> Result<Metadata> {
-    tokio::task::spawn_blocking(|| {
+/// Split `--repo <owner>/<name>`.
+pub fn parse_repo_slug(slug: &str) -> Result<(String, String)> {
+    let slug = slug.trim_end_matches(".git");
+    match slug.split_once('/') {
+        Some((owner, name)) if !owner.is_empty() && !name.is_empty() && !name.contains('/') => {
+            Ok((owner.to_string(), name.to_string()))
+        }
+        _ => bail!("invalid --repo {:?}; expected <owner>/<name>", slug),
+    }
+}
+
+pub async fn load_metadata(root: &Path) -> Result<Metadata> {
+    let root = root.to_path_buf();
+    tokio::task::spawn_blocking(move || {
         let mut cmd = MetadataCommand::new();
+        cmd.current_dir(root);
         cmd.features(CargoOpt::AllFeatures);
         let meta = cmd.exec()?;
         Ok::<_, anyhow::Error>(meta)
@@ -153,6 +374,10 @@ pub fn collect_crates(meta: &Metadata) -> Result<Vec<CrateInfo>> {
             manifest_path,
             package_root,
             internal_dep_count: count,
+            publish: pkg
+                .publish
+                .as_ref()
+                .is_none_or(|registries| !registries.is_empty()),
         });
     }
 
@@ -189,48 +414,112 @@ fn root_package(meta: &Metadata) -> Option<&Package> {
 
 // async variant defined below
 
-pub async fn find_last_stable_tag(root: &Path) -> Result<Option<String>> {
+/// Unshallow CI checkouts so the base tag and the full range since it are visible.
+/// `read_only` runs only warn, so they never touch the repository.
+pub async fn ensure_full_history(root: &Path, remote_url: &str, read_only: bool) -> Result<()> {
+    let repo_path = root.to_path_buf();
+    let shallow = tokio::task::spawn_blocking(move || {
+        Ok::<_, anyhow::Error>(Repository::discover(repo_path)?.is_shallow())
+    })
+    .await
+    .map_err(|e| anyhow::anyhow!("shallow check task join error: {}", e))??;
+    if !shallow {
+        return Ok(());
+    }
+    if read_only {
+        tracing::warn!("infer: shallow clone detected; not fetching history in a read-only run");
+        eprintln!(
+            "warning: shallow clone; not fetching history in a read-only run, so the base tag and the commits since it may be missing (fetch with `git fetch --unshallow --tags`)"
+        );
+        return Ok(());
+    }
+
+    tracing::warn!("infer: shallow clone detected; fetching full history and tags");
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["fetch", "--unshallow", "--tags", remote_url])
+        .status()
+        .await?;
+    if !status.success() {
+        bail!(
+            "repository is a shallow clone and `git fetch --unshallow --tags` failed ({}); \
+             fetch the full history (e.g. actions/checkout with `fetch-depth: 0`) or rerun with \
+             --no-base to plan from the available history without a base tag",
+            status
+        );
+    }
+    Ok(())
+}
+
+pub async fn find_last_stable_tag(root: &Path, tags: &TagFormat) -> Result<Option<String>> {
     let root = root.to_path_buf();
+    let tags = tags.clone();
     tokio::task::spawn_blocking(move || {
-        // Find the latest annotated tag that matches vX.Y.Z
-        // Fallback: none
+        // The highest stable tag (e.g. vX.Y.Z) by semver; none when nothing matches.
         let repo = Repository::discover(root)?;
-        let tag_re = Regex::new(r"^v\d+\.\d+\.\d+$").unwrap();
-        let mut tags = Vec::new();
+        let mut best: Option<(semver::Version, String)> = None;
         for r in repo.references_glob("refs/tags/*")?.flatten() {
             if let Some(name) = r.shorthand()
-                && tag_re.is_match(name)
+                && let Some(version) = tags.parse_stable(name)
+                && best.as_ref().is_none_or(|(v, _)| version > *v)
             {
-                tags.push(name.to_string());
+                best = Some((version, name.to_string()));
             }
         }
-        // Sort by tag name semver descending
-        tags.sort_by(|a, b| semver_cmp(b, a));
-        Ok::<_, anyhow::Error>(tags.first().cloned())
+        Ok::<_, anyhow::Error>(best.map(|(_, name)| name))
     })
     .await
     .map_err(|e| anyhow::anyhow!("find_last_stable_tag task join error: {}", e))?
 }
 
-fn semver_cmp(a: &str, b: &str) -> std::cmp::Ordering {
-    let pa = a.trim_start_matches('v');
-    let pb = b.trim_start_matches('v');
-    let va = semver::Version::parse(pa);
-    let vb = semver::Version::parse(pb);
-    match (va, vb) {
-        (Ok(va), Ok(vb)) => va.cmp(&vb),
-        _ => a.cmp(b),
+pub async fn build_context(opts: &InferOptions) -> Result<InferredContext> {
+    let no_base = opts.no_base;
+    let (root, snapshot) = match locate(opts.git_dir.as_deref()).await? {
+        RepoLocation::Worktree(root) => (root, None),
+        RepoLocation::Bare(git_dir) if opts.allow_bare => {
+            let snapshot = tokio::task::spawn_blocking(move || Snapshot::export(&git_dir))
+                .await
+                .map_err(|e| anyhow::anyhow!("snapshot task join error: {}", e))??;
+            (snapshot.dir().to_path_buf(), Some(Arc::new(snapshot)))
+        }
+        RepoLocation::Bare(git_dir) => return Err(bare_error(&git_dir)),
+    };
+    let mut config = load_minimal_config(&root).await?;
+    if !opts.allow_dirty && snapshot.is_none() {
+        // asfship's own output, such as the log of an earlier run, does not count.
+        let artifacts = artifacts::root_in(Path::new(""), config.artifacts.dir.as_deref());
+        ensure_clean_repo(&root, &artifacts).await?;
     }
-}
-
-pub async fn build_context() -> Result<InferredContext> {
-    let root = repo_root().await?;
-    ensure_clean_repo(&root).await?;
-    let (owner, name, _remote_url) = infer_remote(&root).await?;
-    let meta = load_metadata().await?;
+    let (owner, name, remote_url) = infer_remote(&root, opts.repo.as_deref()).await?;
+    if !no_base {
+        ensure_full_history(&root, &remote_url, opts.read_only).await?;
+    }
+    let meta = load_metadata(&root).await?;
     let crates = collect_crates(&meta)?;
-    let main_crate = infer_main_crate(&crates, &meta, &name, &root).await?;
-    let last = find_last_stable_tag(&root).await?;
+    let names: Vec<&str> = crates.iter().map(|c| c.name.as_str()).collect();
+    let problems = check_references(&config, &root, &names);
+    if !problems.is_empty() {
+        bail!("invalid .asfship.toml:\n  {}", problems.join("\n  "));
+    }
+    if !opts.only.is_empty() {
+        config.only = opts.only.clone();
+    }
+    let main_crate = infer_main_crate(&crates, &meta, &name, &config)?;
+    // A component's own tag series replaces the workspace one for this invocation.
+    if let Some(prefix) = config
+        .crates
+        .get(&main_crate)
+        .and_then(|c| c.tag_prefix.clone())
+    {
+        config.tag_prefix = Some(prefix);
+    }
+    let last = if no_base {
+        tracing::warn!("infer: --no-base set; planning from all reachable history");
+        None
+    } else {
+        find_last_stable_tag(&root, &config.tags()).await?
+    };
     tracing::info!(
         "infer: ok owner={} repo={} crates={} main={} base_tag={:?}",
         owner,
@@ -246,19 +535,50 @@ pub async fn build_context() -> Result<InferredContext> {
         crates,
         main_crate,
         last_stable_tag: last,
+        since: opts.since.clone(),
+        until: opts.until.clone(),
+        config,
+        snapshot,
     })
 }
 
-pub async fn infer_main_crate(
+/// The crate whose version names the release. With `only`, it is the workspace's main
+/// crate when that is selected and the first selected crate otherwise.
+pub fn infer_main_crate(
+    crates: &[CrateInfo],
+    meta: &Metadata,
+    repo_name: &str,
+    cfg: &MinimalConfig,
+) -> Result<String> {
+    for name in &cfg.only {
+        if !crates.iter().any(|c| &c.name == name) {
+            bail!("only lists {} which is not a workspace crate", name);
+        }
+    }
+    let main = workspace_main_crate(crates, meta, repo_name, cfg)?;
+    match cfg.only.as_slice() {
+        [] => Ok(main),
+        _ if cfg.only.contains(&main) => Ok(main),
+        // A single component names the release unambiguously.
+        [component] => Ok(component.clone()),
+        others => bail!(
+            "only lists {} but not the main crate {}; list {} too, or release one component at a time",
+            others.join(", "),
+            main,
+            main
+        ),
+    }
+}
+
+fn workspace_main_crate(
     crates: &[CrateInfo],
     meta: &Metadata,
     repo_name: &str,
-    repo_root: &Path,
+    cfg: &MinimalConfig,
 ) -> Result<String> {
-    let cfg = load_minimal_config(repo_root).await.unwrap_or_default();
-    if let Some(name) = cfg.main_crate {
-        if crates.iter().any(|c| c.name == name) {
-            return Ok(name);
+    if let Some(name) = &cfg.main_crate {
+        if crates.iter().any(|c| &c.name == name) {
+            return Ok(name.clone());
         } else {
             bail!("main_crate specified but not found in workspace: {}", name);
         }
@@ -1,29 +1,85 @@
+mod artifacts;
+mod asf;
+mod asset_cache;
+mod attestation;
+mod audit;
+mod branch_commit;
+mod cancel;
+mod checklist;
+mod checksum;
+mod component;
 mod config;
+mod config_cmd;
+mod crates_io;
+mod diff_rc;
 mod discussion;
+mod docs_rs;
+mod downloads;
+mod endpoints;
+mod feed;
+mod git_identity;
 mod github;
+mod github_app;
+mod gpg;
 mod infer;
+mod manifest;
+mod milestones;
+mod net;
+mod notify;
+mod open_cmd;
+mod partial_clone;
+mod plan_cmd;
+mod pr_labels;
 mod preflight;
+mod preview;
+mod promote;
+mod provenance;
+mod publish;
 mod rc_release;
+mod redact;
 mod release_cmd;
+mod release_gate;
+mod released;
+mod result_cmd;
+mod retract;
+mod run_log;
+mod schema;
+mod shipped_prs;
+mod snapshot;
 mod start;
 mod sync;
+mod tag_cmd;
+mod tags;
+mod template_lint;
+mod templates;
+mod timeline;
+mod token_scope;
+mod translate;
+mod verify_cmd;
 mod versioning;
 mod vote;
+mod website;
 
 use std::path::PathBuf;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use clap::{Parser, Subcommand};
+use tracing::Instrument;
+use tracing_subscriber::prelude::*;
 use tracing_subscriber::{EnvFilter, fmt};
 
 #[derive(Parser, Debug)]
 #[command(name = "asfship", version, about = "ASF release helper", long_about = None)]
 struct Cli {
+    /// Run as if asfship was started in <PATH> (like `git -C`)
+    #[arg(global = true, short = 'C', value_name = "PATH")]
+    chdir: Option<PathBuf>,
+
     /// Perform a dry run without mutating repo or network state
     #[arg(global = true, long = "dry-run", default_value_t = false)]
     dry_run: bool,
 
-    /// Override artifact output directory (defaults to target/asfship/<tag>)
+    /// Root of the per-tag artifact directories (defaults to artifacts.dir or target/asfship)
     #[arg(global = true, long = "artifact-dir")]
     artifact_dir: Option<PathBuf>,
 
@@ -31,6 +87,52 @@ struct Cli {
     #[arg(global = true, long = "local-assets", default_value_t = false)]
     local_assets: bool,
 
+    /// Ignore the last stable tag and plan from all reachable history (allows shallow clones)
+    #[arg(global = true, long = "no-base", default_value_t = false)]
+    no_base: bool,
+
+    /// GitHub repository as <owner>/<name>, instead of the one parsed from the git remote
+    #[arg(global = true, long = "repo", value_name = "OWNER/NAME")]
+    repo: Option<String>,
+
+    /// Repository to operate on instead of the one containing the current directory
+    #[arg(global = true, long = "git-dir", value_name = "PATH")]
+    git_dir: Option<PathBuf>,
+
+    /// Seconds a GitHub or download request may go without receiving data (default 120)
+    #[arg(global = true, long = "github-timeout", value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    github_timeout: Option<u64>,
+
+    /// Seconds to wait for a network connection (default 30)
+    #[arg(global = true, long = "connect-timeout", value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    connect_timeout: Option<u64>,
+
+    /// Retries after connection errors, 5xx, or 429 responses (default 3)
+    #[arg(global = true, long = "network-retries", value_name = "N")]
+    network_retries: Option<usize>,
+
+    /// Plan from this revision instead of the last stable tag (e.g. for a hotfix range)
+    #[arg(
+        global = true,
+        long = "since",
+        value_name = "REV",
+        conflicts_with = "no_base"
+    )]
+    since: Option<String>,
+
+    /// Plan up to this revision instead of HEAD (`plan` only)
+    #[arg(global = true, long = "until", value_name = "REV")]
+    until: Option<String>,
+
+    /// Release only these crates (comma-separated); the first is the main crate unless the main crate is listed
+    #[arg(
+        global = true,
+        long = "only",
+        value_name = "CRATES",
+        value_delimiter = ','
+    )]
+    only: Vec<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -40,24 +142,284 @@ enum Commands {
     /// Start a release Discussion
     Start,
     /// Prepare a prerelease: bump versions, changelogs, tag rc, upload assets
-    Prerelease,
+    Prerelease {
+        /// Post the Markdown report as a comment on the kickoff Discussion
+        #[arg(long = "post-report", default_value_t = false)]
+        post_report: bool,
+        /// Use this rc number instead of one past the highest existing rc tag
+        #[arg(long = "rc", value_name = "N", conflicts_with = "rc_tag")]
+        rc: Option<u32>,
+        /// Use this full rc tag (e.g. v0.2.0-rc.3); must match the planned version
+        #[arg(long = "rc-tag", value_name = "TAG")]
+        rc_tag: Option<String>,
+        /// Show why each commit was classified and why each bump level was chosen
+        #[arg(long, default_value_t = false)]
+        explain: bool,
+        /// Apply the release edits in a temporary worktree and fast-forward only after packaging succeeds
+        #[arg(long, default_value_t = false)]
+        isolated: bool,
+    },
+    /// Print the planned version bumps and why, without touching the tree
+    Plan {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = plan_cmd::PlanFormat::Text)]
+        format: plan_cmd::PlanFormat,
+        /// Show why each commit was classified and why each bump level was chosen
+        #[arg(long, default_value_t = false)]
+        explain: bool,
+    },
     /// Sync latest rc assets to ASF dist/dev SVN
     Sync,
     /// Open a vote Discussion
     Vote,
+    /// Record how the rc vote ended, post the RESULT discussion, and print the next steps
+    Result {
+        /// Whether the vote passed or failed (decided from the tally and `[vote]` quota when omitted)
+        #[arg(value_enum)]
+        outcome: Option<rc_release::VoteOutcome>,
+        /// rc tag the vote was on (defaults to the latest rc release)
+        #[arg(long = "rc-tag", value_name = "TAG")]
+        rc_tag: Option<String>,
+        /// A vote cast on the rc as `<apache id>=<+1|0|-1>`; repeat once per voter. Votes
+        /// of PMC members on the project roster are binding
+        #[arg(long = "vote", value_name = "ID=VOTE")]
+        votes: Vec<result_cmd::Vote>,
+        /// Why the vote failed (derived from the tally when it falls short)
+        #[arg(long)]
+        reason: Option<String>,
+    },
     /// Push final tag and open release Discussion
-    Release,
+    Release {
+        /// Create the GitHub release as a draft and stop for review
+        #[arg(long, conflicts_with = "finalize")]
+        draft: bool,
+        /// Publish the draft GitHub release and finish the release
+        #[arg(long)]
+        finalize: bool,
+        /// Release although no vote result is recorded on the rc
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print when each release step completed, with the time spent between them
+    Status {
+        /// Version to summarize (default: the one with the most recent step)
+        version: Option<String>,
+    },
+    /// List the discussions, releases, and dist/dev directory of the current release and
+    /// open the one for the current phase
+    Open {
+        /// Page to open instead of the one for the current phase
+        #[arg(value_enum)]
+        page: Option<open_cmd::Page>,
+        /// Print the URLs without starting a browser
+        #[arg(long)]
+        print: bool,
+    },
+    /// Abandon an rc whose vote failed by removing it from dist/dev
+    #[command(name = "abort-rc")]
+    AbortRc {
+        /// rc tag to abandon (defaults to the latest rc release)
+        #[arg(long = "rc-tag", value_name = "TAG")]
+        rc_tag: Option<String>,
+    },
+    /// Retract a bad release: yank crates, mark the GitHub release, remove dist files
+    Retract {
+        /// Released version to retract (e.g. 0.2.0 or v0.2.0)
+        version: String,
+        /// Why the release is retracted; shown on GitHub and in the announcement
+        #[arg(long)]
+        reason: String,
+        /// Also delete the assets attached to the GitHub release
+        #[arg(long = "delete-assets", default_value_t = false)]
+        delete_assets: bool,
+        /// Skip the interactive confirmation
+        #[arg(long, default_value_t = false)]
+        yes: bool,
+    },
+    /// Publish a release's crates to crates.io, independent crates in parallel
+    Publish {
+        /// Released version (defaults to the last stable tag); HEAD must be at its tag
+        version: Option<String>,
+        /// How many crates to publish at once
+        #[arg(long, short = 'j', default_value_t = 4)]
+        jobs: usize,
+        /// Skip crates whose version is already on crates.io, to resume a failed run
+        #[arg(long = "skip-published", default_value_t = false)]
+        skip_published: bool,
+        /// Minutes to wait for docs.rs to build the published crates; 0 skips the check
+        #[arg(long = "docs-timeout", value_name = "MINUTES", default_value_t = 30)]
+        docs_timeout: u64,
+    },
+    /// Verify a release's crates on crates.io: version, index entry, and .crate checksum
+    CheckPublished {
+        /// Released version (defaults to the last stable tag)
+        version: Option<String>,
+    },
+    /// Render a discussion template with current data without posting it
+    Preview {
+        /// Template to render (`announce` is an alias for `release`)
+        #[arg(value_enum)]
+        kind: preview::PreviewKind,
+        /// Write the rendered Markdown to this file instead of stdout
+        #[arg(long, short = 'o', value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Create and push a single rc or stable tag, without the rest of the pipeline
+    Tag {
+        /// Create the tag locally without pushing it to origin
+        #[arg(global = true, long = "no-push", default_value_t = false)]
+        no_push: bool,
+        #[command(subcommand)]
+        kind: TagKind,
+    },
+    /// Compare the assets and unpacked contents of two release candidates
+    DiffRc {
+        /// Older rc tag (defaults to the rc preceding `to`)
+        from: Option<String>,
+        /// Newer rc tag (defaults to the latest rc release)
+        to: Option<String>,
+    },
+    /// Check an rc's assets against ASF naming and signing rules and print a checklist
+    Verify {
+        /// rc tag to check (defaults to the latest rc release)
+        #[arg(long = "rc-tag", value_name = "TAG")]
+        rc_tag: Option<String>,
+    },
+    /// Create or check `.asfship.toml`
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Ask for the main crate, discussion category, ASF project, vote duration, and gpg key,
+    /// then write a commented `.asfship.toml`
+    Init {
+        /// Replace an existing `.asfship.toml`
+        #[arg(long = "force", default_value_t = false)]
+        force: bool,
+    },
+    /// Check `.asfship.toml` for unknown keys, invalid values, and crates or files that do
+    /// not exist
+    Validate,
+}
+
+#[derive(Subcommand, Debug)]
+enum TagKind {
+    /// Tag HEAD as the next rc of the main crate's current version
+    Rc {
+        /// Use this rc number instead of one past the highest existing rc tag
+        #[arg(long = "rc", value_name = "N", conflicts_with = "rc_tag")]
+        rc: Option<u32>,
+        /// Use this full rc tag (e.g. v0.2.0-rc.3); must match the main crate version
+        #[arg(long = "rc-tag", value_name = "TAG")]
+        rc_tag: Option<String>,
+    },
+    /// Tag the commit of an rc as its stable release
+    Stable {
+        /// rc tag to promote (defaults to the newest rc of the main crate version)
+        #[arg(long = "from-rc", value_name = "TAG")]
+        from_rc: Option<String>,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
+    redact::register_env();
+    redact::install_panic_hook();
     init_tracing();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let span =
+        tracing::info_span!("asfship", version = env!("CARGO_PKG_VERSION"), args = %args.join(" "));
+    let result = run().instrument(span).await;
+    run_log::finish();
+    if let Err(e) = result {
+        eprintln!("Error: {}", redact::redact(&format!("{:?}", e)));
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<()> {
     let cli = Cli::parse();
+    if let Some(dir) = &cli.chdir {
+        std::env::set_current_dir(dir)
+            .with_context(|| format!("cannot change directory to {}", dir.display()))?;
+    }
+    if let Some(until) = &cli.until
+        && !matches!(cli.command, Commands::Plan { .. })
+    {
+        bail!(
+            "--until {:?} only applies to `plan`; the other commands always release HEAD",
+            until
+        );
+    }
+    if cli.local_assets && matches!(cli.command, Commands::Tag { .. }) {
+        bail!("--local-assets does not apply to `tag`; use `tag --no-push` to keep the tag local");
+    }
+    cancel::install();
+    // Until a command prepares a tag's run directory, the log belongs in the artifacts root.
+    if let Ok(location) = infer::locate(cli.git_dir.as_deref()).await {
+        let root = location.path().to_path_buf();
+        let configured = config::load_minimal_config(&root)
+            .await
+            .ok()
+            .and_then(|c| c.artifacts.dir);
+        let artifact_dir = cli.artifact_dir.clone().or(configured);
+        run_log::set_fallback(&artifacts::root_in(&root, artifact_dir.as_deref()));
+    }
 
     // Shared preflight and inference used by all commands in Phase 1
-    let ctx = preflight::run_preflight()
+    let infer_opts = infer::InferOptions {
+        no_base: cli.no_base,
+        repo: cli.repo.clone(),
+        git_dir: cli.git_dir.clone(),
+        allow_dirty: matches!(cli.command, Commands::Plan { .. }),
+        read_only: cli.dry_run || matches!(cli.command, Commands::Plan { .. }),
+        allow_bare: matches!(cli.command, Commands::Plan { .. }),
+        release_gate: matches!(cli.command, Commands::Release { .. }),
+        network: config::NetworkTuning {
+            connect_timeout_secs: cli.connect_timeout,
+            timeout_secs: cli.github_timeout,
+            retries: cli.network_retries,
+        },
+        only: cli.only.clone(),
+        since: cli.since.clone(),
+        until: cli.until.clone(),
+    };
+    // Writing the config must work before inference can succeed.
+    if let Commands::Config { action } = &cli.command {
+        return match action {
+            ConfigAction::Init { force } => config_cmd::run_init(&infer_opts, *force).await,
+            ConfigAction::Validate => config_cmd::run_validate(&infer_opts).await,
+        };
+    }
+    // The timeline is local state; reading it needs no inference or clean tree.
+    if let Commands::Status { version } = &cli.command {
+        // A bare mirror has no worktree config; its state sits next to the objects.
+        let root = infer::locate(infer_opts.git_dir.as_deref())
+            .await?
+            .path()
+            .to_path_buf();
+        let config = config::load_minimal_config(&root).await?;
+        let artifact_dir = cli.artifact_dir.clone().or(config.artifacts.dir.clone());
+        let artifact_root = artifacts::root_in(&root, artifact_dir.as_deref());
+        return timeline::run_status(
+            &root,
+            version.as_deref(),
+            &artifact_root,
+            &config.tags(),
+            &config.vote,
+        )
+        .await;
+    }
+    let mut ctx = preflight::run_preflight(&infer_opts)
         .await
         .context("preflight checks failed")?;
+    if let Some(dir) = &cli.artifact_dir {
+        ctx.config.artifacts.dir = Some(dir.clone());
+    }
 
     match cli.command {
         Commands::Start => {
@@ -83,64 +445,255 @@ async fn main() -> Result<()> {
                     }
                 }
                 Err(err) => {
-                    eprintln!("Error: {}", err);
+                    eprintln!("Error: {}", redact::redact(&err.to_string()));
                     tracing::error!(error=%err, "start command failed");
-                    std::process::exit(1);
+                    exit_failure(&ctx, &err).await;
                 }
             }
         }
-        Commands::Prerelease => {
-            tracing::info!("prerelease: begin base_tag={:?}", ctx.last_stable_tag);
+        Commands::Prerelease {
+            post_report,
+            rc,
+            rc_tag,
+            explain,
+            isolated,
+        } => {
+            tracing::info!("prerelease: begin base_tag={:?}", ctx.plan_base());
             let opts = versioning::PrereleaseOptions {
                 dry_run: cli.dry_run,
-                artifact_dir: cli.artifact_dir.as_deref(),
                 upload: !cli.local_assets,
+                post_report,
+                rc: match (rc, rc_tag) {
+                    (_, Some(tag)) => versioning::RcSelection::Tag(tag),
+                    (Some(n), None) => versioning::RcSelection::Number(n),
+                    (None, None) => versioning::RcSelection::Next,
+                },
+                explain,
+                isolated: isolated || ctx.config.prerelease.isolated,
             };
             match versioning::run_prerelease(&ctx, opts).await {
                 Ok(report) => {
                     println!("{}", report.render_text());
                 }
                 Err(e) => {
-                    eprintln!("Error: {}", e);
+                    eprintln!("Error: {}", redact::redact(&e.to_string()));
                     tracing::error!(error=%e, "prerelease failed");
-                    std::process::exit(1);
+                    exit_failure(&ctx, &e).await;
                 }
             }
         }
+        Commands::Plan { format, explain } => {
+            tracing::info!("plan: begin base_tag={:?}", ctx.plan_base());
+            if let Err(e) = plan_cmd::run_plan(&ctx, format, explain).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "plan failed");
+                exit_failure(&ctx, &e).await;
+            }
+        }
         Commands::Sync => {
             tracing::info!("sync: begin");
             if let Err(e) = sync::run_sync(&ctx, cli.dry_run).await {
-                eprintln!("Error: {}", e);
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
                 tracing::error!(error=%e, "sync failed");
-                std::process::exit(1);
+                exit_failure(&ctx, &e).await;
             }
         }
         Commands::Vote => {
             tracing::info!("vote: begin");
             if let Err(e) = vote::run_vote(&ctx, cli.dry_run).await {
-                eprintln!("Error: {}", e);
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
                 tracing::error!(error=%e, "vote failed");
-                std::process::exit(1);
+                exit_failure(&ctx, &e).await;
+            }
+        }
+        Commands::Result {
+            outcome,
+            rc_tag,
+            votes,
+            reason,
+        } => {
+            tracing::info!("result: begin outcome={:?}", outcome);
+            let opts = result_cmd::ResultOptions {
+                outcome,
+                rc_tag: rc_tag.as_deref(),
+                votes,
+                reason: reason.as_deref(),
+                dry_run: cli.dry_run,
+            };
+            if let Err(e) = result_cmd::run_result(&ctx, opts).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "result failed");
+                exit_failure(&ctx, &e).await;
             }
         }
-        Commands::Release => {
+        Commands::Release {
+            draft,
+            finalize,
+            force,
+        } => {
             tracing::info!("release: begin");
-            if let Err(e) = release_cmd::run_release(&ctx, cli.dry_run).await {
-                eprintln!("Error: {}", e);
+            let opts = release_cmd::ReleaseOptions {
+                dry_run: cli.dry_run,
+                draft,
+                finalize,
+                force,
+            };
+            if let Err(e) = release_cmd::run_release(&ctx, opts).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
                 tracing::error!(error=%e, "release failed");
-                std::process::exit(1);
+                exit_failure(&ctx, &e).await;
+            }
+        }
+        Commands::AbortRc { rc_tag } => {
+            tracing::info!("abort-rc: begin rc_tag={:?}", rc_tag);
+            if let Err(e) = retract::run_abort_rc(&ctx, rc_tag.as_deref(), cli.dry_run).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "abort-rc failed");
+                exit_failure(&ctx, &e).await;
             }
         }
+        Commands::Retract {
+            version,
+            reason,
+            delete_assets,
+            yes,
+        } => {
+            tracing::info!("retract: begin version={}", version);
+            let opts = retract::RetractOptions {
+                version: &version,
+                reason: &reason,
+                delete_assets,
+                yes,
+                dry_run: cli.dry_run,
+            };
+            if let Err(e) = retract::run_retract(&ctx, opts).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "retract failed");
+                exit_failure(&ctx, &e).await;
+            }
+        }
+        Commands::Publish {
+            version,
+            jobs,
+            skip_published,
+            docs_timeout,
+        } => {
+            tracing::info!("publish: begin");
+            let opts = publish::PublishOptions {
+                version: version.as_deref(),
+                jobs,
+                skip_published,
+                docs_timeout: std::time::Duration::from_secs(docs_timeout * 60),
+                dry_run: cli.dry_run,
+            };
+            if let Err(e) = publish::run_publish(&ctx, opts).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "publish failed");
+                exit_failure(&ctx, &e).await;
+            }
+        }
+        Commands::CheckPublished { version } => {
+            tracing::info!("check-published: begin");
+            if let Err(e) = crates_io::run_check_published(&ctx, version.as_deref()).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "check-published failed");
+                exit_failure(&ctx, &e).await;
+            }
+        }
+        Commands::Preview { kind, output } => {
+            tracing::info!("preview: begin kind={:?}", kind);
+            if let Err(e) = preview::run_preview(&ctx, kind, output.as_deref()).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "preview failed");
+                exit_failure(&ctx, &e).await;
+            }
+        }
+        Commands::Tag { no_push, kind } => {
+            tracing::info!("tag: begin");
+            let request = match kind {
+                TagKind::Rc { rc, rc_tag } => tag_cmd::TagRequest::Rc(match (rc, rc_tag) {
+                    (_, Some(tag)) => versioning::RcSelection::Tag(tag),
+                    (Some(n), None) => versioning::RcSelection::Number(n),
+                    (None, None) => versioning::RcSelection::Next,
+                }),
+                TagKind::Stable { from_rc } => tag_cmd::TagRequest::Stable { from_rc },
+            };
+            let opts = tag_cmd::TagOptions {
+                request,
+                push: !no_push,
+                dry_run: cli.dry_run,
+            };
+            if let Err(e) = tag_cmd::run_tag(&ctx, opts).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "tag failed");
+                exit_failure(&ctx, &e).await;
+            }
+        }
+        Commands::DiffRc { from, to } => {
+            tracing::info!("diff-rc: begin");
+            if let Err(e) = diff_rc::run_diff_rc(&ctx, from.as_deref(), to.as_deref()).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "diff-rc failed");
+                exit_failure(&ctx, &e).await;
+            }
+        }
+        Commands::Open { page, print } => {
+            tracing::info!("open: begin");
+            let opts = open_cmd::OpenOptions {
+                page,
+                print: print || cli.dry_run,
+            };
+            if let Err(e) = open_cmd::run_open(&ctx, opts).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "open failed");
+                exit_failure(&ctx, &e).await;
+            }
+        }
+        Commands::Verify { rc_tag } => {
+            tracing::info!("verify: begin");
+            if let Err(e) = verify_cmd::run_verify(&ctx, rc_tag.as_deref()).await {
+                eprintln!("Error: {}", redact::redact(&e.to_string()));
+                tracing::error!(error=%e, "verify failed");
+                exit_failure(&ctx, &e).await;
+            }
+        }
+        Commands::Config { .. } | Commands::Status { .. } => {
+            unreachable!("handled before preflight")
+        }
     }
 
+    audit::comment_on_kickoff(&ctx).await;
     Ok(())
 }
 
+/// Exit after a failed command; an interrupted run also lists what it completed.
+async fn exit_failure(ctx: &infer::InferredContext, err: &anyhow::Error) -> ! {
+    audit::comment_on_kickoff(ctx).await;
+    run_log::finish();
+    if err.downcast_ref::<cancel::Interrupted>().is_none() {
+        std::process::exit(1);
+    }
+    let done = audit::completed_in_run(ctx).await;
+    if done.is_empty() {
+        eprintln!("Nothing was changed before the interrupt.");
+    } else {
+        eprintln!("Completed before the interrupt:");
+        for step in &done {
+            eprintln!("  - {}", step);
+        }
+    }
+    eprintln!("Check the steps above before re-running the command.");
+    std::process::exit(130);
+}
+
 fn init_tracing() {
-    // Only initialize if RUST_LOG (or env filter) is set; otherwise keep logs off by default.
+    // Only log to stderr if RUST_LOG (or env filter) is set; the run log always records.
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("off"));
-    let _ = fmt()
-        .with_env_filter(filter)
-        .with_writer(std::io::stderr)
+    let stderr = fmt::layer()
+        .with_writer(redact::RedactingStderr)
+        .with_filter(filter);
+    let _ = tracing_subscriber::registry()
+        .with(stderr)
+        .with(run_log::layer())
         .try_init();
 }
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use tokio::fs as async_fs;
+
+use crate::checksum;
+use crate::provenance;
+use crate::rc_release::RcReleaseInfo;
+use crate::schema;
+
+/// Name of the manifest in each run directory and on the rc release.
+pub const FILE_NAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactKind {
+    SourceTarball,
+    SourceZip,
+    Checksum,
+}
+
+impl ArtifactKind {
+    fn of(name: &str) -> Result<Self> {
+        if name.ends_with(".sha512") {
+            Ok(Self::Checksum)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tar.zst") {
+            Ok(Self::SourceTarball)
+        } else if name.ends_with(".zip") {
+            Ok(Self::SourceZip)
+        } else {
+            bail!("unknown artifact kind for {}", name)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub sha512: String,
+    pub size: u64,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub kind: ArtifactKind,
+}
+
+/// Every file one prerelease run produced, so later commands never guess from directory
+/// contents or stray release assets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunManifest {
+    #[serde(default = "schema::current")]
+    pub schema_version: u32,
+    pub tag: String,
+    pub commit: String,
+    pub files: Vec<ManifestEntry>,
+}
+
+impl RunManifest {
+    pub fn new(tag: &str, commit: &str) -> Self {
+        Self {
+            schema_version: schema::SCHEMA_VERSION,
+            tag: tag.to_string(),
+            commit: commit.to_string(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Record `path` as an artifact of `crate_name`.
+    pub async fn add(&mut self, crate_name: &str, path: &Path) -> Result<()> {
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .with_context(|| format!("invalid artifact path {}", path.display()))?
+            .to_string();
+        self.files.push(ManifestEntry {
+            kind: ArtifactKind::of(&name)?,
+            sha512: checksum::sha512_file(path).await?,
+            size: async_fs::metadata(path).await?.len(),
+            crate_name: crate_name.to_string(),
+            name,
+        });
+        Ok(())
+    }
+
+    /// Write the manifest into `dir`, replacing any previous one atomically.
+    pub async fn write(&self, dir: &Path) -> Result<PathBuf> {
+        let path = dir.join(FILE_NAME);
+        let partial = dir.join(format!("{}.partial", FILE_NAME));
+        async_fs::write(&partial, serde_json::to_vec_pretty(self)?).await?;
+        async_fs::rename(&partial, &path).await?;
+        Ok(path)
+    }
+
+    /// Keep only the release assets this manifest lists, attaching their digests.
+    ///
+    /// Detached `.asc` signatures of listed files are kept too, since release managers
+    /// upload them by hand after the run. Fails when a listed file is missing from the
+    /// release or has a different size.
+    pub fn restrict(&self, release: &mut RcReleaseInfo) -> Result<()> {
+        schema::ensure_supported(
+            &format!("{} on {}", FILE_NAME, release.tag),
+            self.schema_version,
+        )?;
+        if self.tag != release.tag {
+            bail!(
+                "{} on {} describes {}; re-run `asfship prerelease`",
+                FILE_NAME,
+                release.tag,
+                self.tag
+            );
+        }
+        let mut assets: HashMap<String, _> = release
+            .assets
+            .drain(..)
+            .map(|a| (a.name.clone(), a))
+            .collect();
+        for entry in &self.files {
+            let Some(mut asset) = assets.remove(&entry.name) else {
+                bail!(
+                    "{} lists {} but the {} release does not have it",
+                    FILE_NAME,
+                    entry.name,
+                    self.tag
+                );
+            };
+            if asset.size != entry.size {
+                bail!(
+                    "{} on the {} release is {} bytes; {} records {}",
+                    entry.name,
+                    self.tag,
+                    asset.size,
+                    FILE_NAME,
+                    entry.size
+                );
+            }
+            asset.sha512 = Some(entry.sha512.clone());
+            release.assets.push(asset);
+        }
+        // The manifest and provenance statement describe the run but are not artifacts to
+        // sync or vote on; neither are their signatures.
+        for name in [FILE_NAME, provenance::FILE_NAME] {
+            assets.remove(name);
+            assets.remove(&format!("{}.asc", name));
+        }
+        let signatures: Vec<String> = assets
+            .keys()
+            .filter(|name| {
+                name.strip_suffix(".asc")
+                    .is_some_and(|base| self.files.iter().any(|f| f.name == base))
+            })
+            .cloned()
+            .collect();
+        for name in signatures {
+            release.assets.extend(assets.remove(&name));
+        }
+        if !assets.is_empty() {
+            let mut extra: Vec<_> = assets.into_keys().collect();
+            extra.sort();
+            tracing::warn!(
+                "{}: ignoring release assets not in {}: {}",
+                self.tag,
+                FILE_NAME,
+                extra.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc_release::RcAsset;
+
+    fn asset(name: &str, size: u64) -> RcAsset {
+        RcAsset {
+            name: name.to_string(),
+            download_url: format!("https://example.com/{}", name),
+            size,
+            sha512: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn manifest_restricts_release_assets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let tar = dir.path().join("apache-foo-0.1.0-rc1-src.tar.gz");
+        std::fs::write(&tar, b"tarball").unwrap();
+        let mut manifest = RunManifest::new("v0.1.0-rc.1", "abc123");
+        manifest.add("foo", &tar).await.unwrap();
+        let path = manifest.write(dir.path()).await.unwrap();
+        let read: RunManifest = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(read, manifest);
+        assert_eq!(read.files[0].kind, ArtifactKind::SourceTarball);
+
+        let mut release = RcReleaseInfo {
+            tag: "v0.1.0-rc.1".into(),
+            version: semver::Version::new(0, 1, 0),
+            rc_number: 1,
+            assets: vec![
+                asset("apache-foo-0.1.0-rc1-src.tar.gz", 7),
+                asset("apache-foo-0.1.0-rc0-src.tar.gz", 7),
+                asset("apache-foo-0.1.0-rc1-src.tar.gz.asc", 833),
+                asset(FILE_NAME, 100),
+                asset("manifest.json.asc", 833),
+            ],
+            vote: None,
+        };
+        manifest.restrict(&mut release).unwrap();
+        assert_eq!(release.assets.len(), 2);
+        assert!(release.assets[1].sha512.is_none());
+        assert_eq!(
+            release.assets[0].sha512.as_deref(),
+            Some(manifest.files[0].sha512.as_str())
+        );
+
+        let mut truncated = RcReleaseInfo {
+            assets: vec![asset("apache-foo-0.1.0-rc1-src.tar.gz", 3)],
+            ..release
+        };
+        assert!(manifest.restrict(&mut truncated).is_err());
+    }
+}
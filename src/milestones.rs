@@ -0,0 +1,185 @@
+use anyhow::{Context, Result};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::config::{NextMilestone, ReleaseMilestonesConfig};
+use crate::github;
+use crate::infer::InferredContext;
+
+/// Print what `publish` would do to the milestones of `version`.
+pub fn describe(ctx: &InferredContext, version: &Version) {
+    let cfg = &ctx.config.release.milestones;
+    if !cfg.close {
+        return;
+    }
+    println!(
+        "  milestones: close {:?}, move its open issues to {:?} (created if missing)",
+        cfg.title(version),
+        cfg.title(&next_version(version, cfg.next))
+    );
+}
+
+/// Close the milestone named after `version` and roll its open issues over to the
+/// next version's milestone, creating that one when needed.
+///
+/// Runs after the release is public, so failures are logged and never fail it.
+/// Re-running picks up where an earlier run stopped.
+pub async fn publish(ctx: &InferredContext, version: &Version, audit: &AuditLog) {
+    let cfg = &ctx.config.release.milestones;
+    if !cfg.close {
+        return;
+    }
+    if let Err(err) = roll_over(ctx, cfg, version, audit).await {
+        tracing::warn!(
+            "milestones: {:#}; close the {:?} milestone manually",
+            err,
+            cfg.title(version)
+        );
+    }
+}
+
+fn next_version(version: &Version, next: NextMilestone) -> Version {
+    match next {
+        NextMilestone::Minor => Version::new(version.major, version.minor + 1, 0),
+        NextMilestone::Patch => Version::new(version.major, version.minor, version.patch + 1),
+    }
+}
+
+#[derive(Deserialize)]
+struct MilestoneInfo {
+    number: u64,
+    title: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct IssueInfo {
+    number: u64,
+}
+
+#[derive(Serialize)]
+struct CreatePayload<'a> {
+    title: &'a str,
+}
+
+#[derive(Serialize)]
+struct StatePayload {
+    state: &'static str,
+}
+
+#[derive(Serialize)]
+struct IssuePayload {
+    milestone: u64,
+}
+
+async fn roll_over(
+    ctx: &InferredContext,
+    cfg: &ReleaseMilestonesConfig,
+    version: &Version,
+    audit: &AuditLog,
+) -> Result<()> {
+    let gh = github::client()?;
+    let repo = format!("/repos/{}/{}", ctx.repo_owner, ctx.repo_name);
+    let route = format!("{}/milestones", repo);
+    let milestones: Vec<MilestoneInfo> = gh
+        .get(&route, Some(&[("state", "all"), ("per_page", "100")]))
+        .await
+        .context("failed to list milestones")?;
+    let title = cfg.title(version);
+    let Some(current) = milestones.iter().find(|m| m.title == title) else {
+        println!("milestones: no milestone named {:?}; skipped", title);
+        return Ok(());
+    };
+
+    let next_title = cfg.title(&next_version(version, cfg.next));
+    let next = match milestones.iter().find(|m| m.title == next_title) {
+        Some(m) => m.number,
+        None => {
+            let created: MilestoneInfo = gh
+                .post(&route, Some(&CreatePayload { title: &next_title }))
+                .await
+                .with_context(|| format!("failed to create milestone {:?}", next_title))?;
+            audit
+                .record(AuditAction::MilestoneCreated, &next_title)
+                .await;
+            created.number
+        }
+    };
+
+    let milestone = current.number.to_string();
+    let mut open = Vec::new();
+    for page in 1.. {
+        let page = page.to_string();
+        let issues: Vec<IssueInfo> = gh
+            .get(
+                format!("{}/issues", repo),
+                Some(&[
+                    ("milestone", milestone.as_str()),
+                    ("state", "open"),
+                    ("per_page", "100"),
+                    ("page", page.as_str()),
+                ]),
+            )
+            .await
+            .context("failed to list open issues")?;
+        let last = issues.len() < 100;
+        open.extend(issues.into_iter().map(|i| i.number));
+        if last {
+            break;
+        }
+    }
+    for number in &open {
+        let _: serde_json::Value = gh
+            .patch(
+                format!("{}/issues/{}", repo, number),
+                Some(&IssuePayload { milestone: next }),
+            )
+            .await
+            .with_context(|| format!("failed to move #{} to {:?}", number, next_title))?;
+        audit
+            .record(AuditAction::IssueMilestoneChanged, &format!("#{}", number))
+            .await;
+    }
+
+    if current.state != "closed" {
+        let _: serde_json::Value = gh
+            .patch(
+                format!("{}/{}", route, current.number),
+                Some(&StatePayload { state: "closed" }),
+            )
+            .await
+            .context("failed to close the milestone")?;
+        audit.record(AuditAction::MilestoneClosed, &title).await;
+    }
+    println!(
+        "milestones: closed {:?}, moved {} open issue(s) to {:?}",
+        title,
+        open.len(),
+        next_title
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_milestone_follows_the_configured_bump() {
+        let version = Version::new(0, 4, 2);
+        assert_eq!(
+            next_version(&version, NextMilestone::Minor),
+            Version::new(0, 5, 0)
+        );
+        assert_eq!(
+            next_version(&version, NextMilestone::Patch),
+            Version::new(0, 4, 3)
+        );
+        let cfg = ReleaseMilestonesConfig {
+            title: Some("v{version}".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(cfg.title(&version), "v0.4.2");
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::{NotificationConfig, NotificationKind};
+use crate::infer::InferredContext;
+use crate::net;
+
+/// Release milestones that can be announced to chat rooms and webhooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Milestone {
+    RcCut,
+    VoteOpened,
+    ReleaseCompleted,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notice {
+    pub milestone: Milestone,
+    pub tag: String,
+    /// One line for humans, e.g. "Vote opened for foo 0.2.0-rc1".
+    pub message: String,
+    pub url: Option<String>,
+}
+
+/// Send `notice` to every configured endpoint subscribed to its milestone.
+///
+/// Failures only warn: a broken webhook must not fail a release step that already happened.
+pub async fn send(ctx: &InferredContext, notice: Notice) {
+    for endpoint in &ctx.config.notifications {
+        if !endpoint.events.is_empty() && !endpoint.events.contains(&notice.milestone) {
+            continue;
+        }
+        if let Err(err) = deliver(ctx, endpoint, &notice).await {
+            tracing::warn!("notify: {:?} endpoint failed: {:#}", endpoint.kind, err);
+            eprintln!(
+                "warning: failed to send {:?} notification: {:#}",
+                endpoint.kind, err
+            );
+        }
+    }
+}
+
+async fn deliver(
+    ctx: &InferredContext,
+    endpoint: &NotificationConfig,
+    notice: &Notice,
+) -> Result<()> {
+    let client = net::client()?;
+    let url = endpoint.resolve_url()?;
+    let text = match &notice.url {
+        Some(link) => format!("{}: {}", notice.message, link),
+        None => notice.message.clone(),
+    };
+    let request = match endpoint.kind {
+        NotificationKind::Slack => client.post(&url).body(json!({ "text": text }).to_string()),
+        NotificationKind::Json => client.post(&url).body(
+            json!({
+                "event": notice.milestone,
+                "repo": format!("{}/{}", ctx.repo_owner, ctx.repo_name),
+                "tag": notice.tag,
+                "message": notice.message,
+                "url": notice.url,
+            })
+            .to_string(),
+        ),
+        NotificationKind::Matrix => {
+            let room = endpoint
+                .room
+                .as_deref()
+                .context("matrix notifications need `room`")?;
+            let token = endpoint.resolve_token()?;
+            let txn = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+            let send_url = format!(
+                "{}/_matrix/client/v3/rooms/{}/send/m.room.message/asfship-{}",
+                url.trim_end_matches('/'),
+                urlencoding::encode(room),
+                txn
+            );
+            client
+                .put(send_url)
+                .bearer_auth(token)
+                .body(json!({ "msgtype": "m.text", "body": text }).to_string())
+        }
+    };
+    let resp = request
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        bail!("endpoint answered {}", resp.status());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MinimalConfig;
+
+    #[test]
+    fn notifications_parse_from_config() {
+        let cfg: MinimalConfig = toml::from_str(
+            r#"
+[[notifications]]
+kind = "slack"
+url_env = "SLACK_RELEASE_WEBHOOK"
+events = ["vote_opened", "release_completed"]
+
+[[notifications]]
+kind = "matrix"
+url = "https://matrix.org"
+room = "!release:matrix.org"
+"#,
+        )
+        .unwrap();
+        let [slack, matrix] = cfg.notifications.as_slice() else {
+            panic!("expected two endpoints");
+        };
+        assert_eq!(slack.kind, NotificationKind::Slack);
+        assert!(!slack.events.contains(&Milestone::RcCut));
+        assert!(matrix.events.is_empty());
+        assert_eq!(matrix.token_env, "ASFSHIP_MATRIX_TOKEN");
+        assert_eq!(matrix.resolve_url().unwrap(), "https://matrix.org");
+    }
+}
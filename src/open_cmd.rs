@@ -0,0 +1,193 @@
+use std::fmt;
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use tokio::process::Command;
+
+use crate::artifacts;
+use crate::discussion;
+use crate::github;
+use crate::infer::InferredContext;
+use crate::rc_release::{RcReleaseInfo, fetch_latest_rc_release};
+use crate::release_cmd;
+use crate::result_cmd;
+use crate::start;
+use crate::timeline::{self, Phase};
+use crate::vote;
+
+/// Pages `open` can find, in the order a release visits them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Page {
+    /// Kickoff discussion posted by `start`
+    Kickoff,
+    /// GitHub release of the latest rc
+    Rc,
+    /// dist/dev directory of the latest rc
+    Dist,
+    /// Vote discussion of the latest rc
+    Vote,
+    /// RESULT discussion closing the vote
+    Result,
+    /// Release announcement discussion
+    Release,
+}
+
+const PAGES: [Page; 6] = [
+    Page::Kickoff,
+    Page::Rc,
+    Page::Dist,
+    Page::Vote,
+    Page::Result,
+    Page::Release,
+];
+
+impl Page {
+    /// The page to look at once `phase` is the last step recorded in the timeline.
+    fn after(phase: Option<Phase>) -> Self {
+        match phase {
+            None => Page::Kickoff,
+            Some(Phase::RcCut) => Page::Rc,
+            Some(Phase::Synced) => Page::Dist,
+            Some(Phase::VoteOpened) => Page::Vote,
+            // dist/release has no page of its own; the RESULT discussion stays current
+            // until the announcement is posted.
+            Some(Phase::VoteClosed | Phase::Promoted) => Page::Result,
+            Some(Phase::Released) => Page::Release,
+        }
+    }
+}
+
+impl fmt::Display for Page {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Page::Kickoff => "kickoff",
+            Page::Rc => "rc",
+            Page::Dist => "dist",
+            Page::Vote => "vote",
+            Page::Result => "result",
+            Page::Release => "release",
+        })
+    }
+}
+
+pub struct OpenOptions {
+    /// Page to open; defaults to the one for the current phase.
+    pub page: Option<Page>,
+    /// Print the URLs without starting a browser.
+    pub print: bool,
+}
+
+/// List the URLs of the current release and open the one for the current phase (or the
+/// requested page) in a browser.
+pub async fn run_open(ctx: &InferredContext, opts: OpenOptions) -> Result<()> {
+    let current = Page::after(timeline::current_phase(&artifacts::root(ctx)).await?);
+    let selected = opts.page.unwrap_or(current);
+    let resolver = Resolver::new(ctx).await;
+    let mut target = None;
+    for page in PAGES {
+        let marker = if page == current { "*" } else { " " };
+        match resolver.url(page).await {
+            Ok(Some(url)) => {
+                println!("{} {:8} {}", marker, page.to_string(), url);
+                if page == selected {
+                    target = Some(url);
+                }
+            }
+            Ok(None) => println!("{} {:8} (not posted yet)", marker, page.to_string()),
+            Err(err) => println!("{} {:8} (unavailable: {:#})", marker, page.to_string(), err),
+        }
+    }
+    let Some(url) = target else {
+        bail!("no {} page found for {}", selected, ctx.repo_name);
+    };
+    if opts.print {
+        return Ok(());
+    }
+    println!("open: {} {}", selected, url);
+    launch(&url).await
+}
+
+/// Looks up each page's URL, sharing the latest rc between the pages that need it.
+struct Resolver<'a> {
+    ctx: &'a InferredContext,
+    rc: Result<RcReleaseInfo>,
+}
+
+impl<'a> Resolver<'a> {
+    async fn new(ctx: &'a InferredContext) -> Self {
+        Self {
+            ctx,
+            rc: fetch_latest_rc_release(ctx).await,
+        }
+    }
+
+    fn rc(&self) -> Result<&RcReleaseInfo> {
+        self.rc.as_ref().map_err(|err| anyhow::anyhow!("{:#}", err))
+    }
+
+    async fn url(&self, page: Page) -> Result<Option<String>> {
+        let ctx = self.ctx;
+        let title = match page {
+            Page::Kickoff => start::kickoff_title(&ctx.repo_name),
+            Page::Rc => {
+                return Ok(Some(format!(
+                    "https://github.com/{}/{}/releases/tag/{}",
+                    ctx.repo_owner,
+                    ctx.repo_name,
+                    self.rc()?.tag
+                )));
+            }
+            Page::Dist => return Ok(Some(vote::svn_url(ctx, self.rc()?))),
+            Page::Vote => vote::title(ctx, self.rc()?),
+            Page::Result => result_cmd::title(ctx, self.rc()?),
+            Page::Release => release_cmd::release_title(ctx, self.rc()?),
+        };
+        let gh = github::client()?;
+        let found = discussion::find_by_title(&gh, &ctx.repo_owner, &ctx.repo_name, &title).await?;
+        Ok(found.map(|d| d.html_url))
+    }
+}
+
+/// Open `url` with `$BROWSER`, or the platform's default handler.
+async fn launch(url: &str) -> Result<()> {
+    let mut cmd = match std::env::var("BROWSER") {
+        Ok(browser) if !browser.trim().is_empty() => Command::new(browser.trim()),
+        _ if cfg!(target_os = "macos") => Command::new("open"),
+        _ if cfg!(windows) => {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", "start", ""]);
+            cmd
+        }
+        _ => Command::new("xdg-open"),
+    };
+    let status = cmd
+        .arg(url)
+        .status()
+        .await
+        .context("failed to start a browser; use --print to only list the URLs")?;
+    if !status.success() {
+        bail!("the browser command exited with {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_phase_points_at_the_next_page_to_check() {
+        let pages: Vec<Page> = [
+            None,
+            Some(Phase::RcCut),
+            Some(Phase::Synced),
+            Some(Phase::VoteOpened),
+            Some(Phase::VoteClosed),
+            Some(Phase::Released),
+        ]
+        .into_iter()
+        .map(Page::after)
+        .collect();
+        assert_eq!(pages, PAGES);
+    }
+}
@@ -0,0 +1,217 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+use git2::{Blob, Oid, Repository};
+
+/// Missing paths named in an error before the rest are only counted.
+const LISTED_PATHS: usize = 3;
+
+/// Remote that lazily serves the objects a partial clone (`--filter=blob:none`) left out,
+/// or `None` for a full clone.
+pub(crate) fn promisor_remote(repo: &Repository) -> Option<String> {
+    let config = repo.config().ok()?;
+    if let Ok(remote) = config.get_string("extensions.partialclone") {
+        return Some(remote);
+    }
+    let remotes = repo.remotes().ok()?;
+    remotes
+        .iter()
+        .flatten()
+        .find(|name| {
+            config
+                .get_bool(&format!("remote.{}.promisor", name))
+                .unwrap_or(false)
+        })
+        .map(str::to_string)
+}
+
+/// Fetch the blobs under `roots` of `commit` that a partial clone has not downloaded yet.
+///
+/// libgit2 cannot fetch missing objects on demand the way git does, so packaging would
+/// otherwise fail on the first blob it reads. A full clone returns right away.
+pub(crate) fn ensure_blobs(repo: &Repository, commit: Oid, roots: &[PathBuf]) -> Result<()> {
+    let Some(remote) = promisor_remote(repo) else {
+        return Ok(());
+    };
+    let missing = missing_blobs(repo, commit, roots)?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+    tracing::info!(
+        "package: partial clone; fetching {} missing blobs from {}",
+        missing.len(),
+        remote
+    );
+    let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+    let ids: Vec<Oid> = missing.iter().map(|(_, oid)| *oid).collect();
+    let fetched = fetch(repo, &remote, &ids);
+    repo.odb()?.refresh()?;
+    let still_missing = missing_blobs(repo, commit, roots)?;
+    if still_missing.is_empty() {
+        return Ok(());
+    }
+    let mut paths: Vec<String> = still_missing
+        .iter()
+        .take(LISTED_PATHS)
+        .map(|(path, _)| path.display().to_string())
+        .collect();
+    if still_missing.len() > LISTED_PATHS {
+        paths.push(format!("{} more", still_missing.len() - LISTED_PATHS));
+    }
+    let reason = match fetched {
+        Ok(()) => String::from("the fetch did not provide them"),
+        Err(err) => format!("{:#}", err),
+    };
+    bail!(
+        "this partial clone is missing blobs needed for the source archives ({}) and fetching them from {} failed: {}; \
+         run `git -C {} rev-list --objects --missing=print --no-walk {} | sed -n 's/^?//p' | git -C {} fetch {} --stdin --no-tags` \
+         or release from a full clone",
+        paths.join(", "),
+        remote,
+        reason,
+        workdir.display(),
+        commit,
+        workdir.display(),
+        remote
+    );
+}
+
+/// Read blob `oid` (found at `path`), fetching it first when a partial clone left it out.
+pub(crate) fn find_blob<'r>(repo: &'r Repository, oid: Oid, path: &Path) -> Result<Blob<'r>> {
+    if let Ok(blob) = repo.find_blob(oid) {
+        return Ok(blob);
+    }
+    let Some(remote) = promisor_remote(repo) else {
+        return repo
+            .find_blob(oid)
+            .with_context(|| format!("failed to read {} ({})", path.display(), oid));
+    };
+    tracing::debug!("git: fetching {} ({}) from {}", path.display(), oid, remote);
+    if let Err(err) = fetch(repo, &remote, &[oid]) {
+        tracing::warn!("git: {:#}", err);
+    }
+    repo.odb()?.refresh()?;
+    repo.find_blob(oid).with_context(|| {
+        let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+        format!(
+            "failed to read {} ({}) from this partial clone; run `git -C {} fetch {} {}`",
+            path.display(),
+            oid,
+            workdir.display(),
+            remote,
+            oid
+        )
+    })
+}
+
+/// Blobs under `roots` of `commit` that are not in the local object database.
+fn missing_blobs(repo: &Repository, commit: Oid, roots: &[PathBuf]) -> Result<Vec<(PathBuf, Oid)>> {
+    let tree = repo.find_commit(commit)?.tree()?;
+    let odb = repo.odb()?;
+    let mut missing = Vec::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() != Some(git2::ObjectType::Blob) {
+            return 0;
+        }
+        let path = Path::new(dir).join(entry.name().unwrap_or_default());
+        if roots.iter().any(|root| path.starts_with(root)) && !odb.exists(entry.id()) {
+            missing.push((path, entry.id()));
+        }
+        0
+    })?;
+    Ok(missing)
+}
+
+/// Fetch `blobs` by id, the way git backfills a partial clone.
+fn fetch(repo: &Repository, remote: &str, blobs: &[Oid]) -> Result<()> {
+    let mut child = Command::new("git")
+        .current_dir(repo.workdir().unwrap_or_else(|| repo.path()))
+        .args([
+            "-c",
+            "fetch.negotiationAlgorithm=noop",
+            "fetch",
+            remote,
+            "--no-tags",
+            "--no-write-fetch-head",
+            "--recurse-submodules=no",
+            "--filter=blob:none",
+            "--stdin",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run `git fetch`")?;
+    let mut stdin = child.stdin.take().context("git stdin is not piped")?;
+    let ids: String = blobs.iter().map(|oid| format!("{}\n", oid)).collect();
+    stdin.write_all(ids.as_bytes())?;
+    drop(stdin);
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "`git fetch` exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[test]
+    fn missing_blobs_are_fetched_from_the_promisor_remote() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let origin = tmp.path().join("origin");
+        fs::create_dir_all(origin.join("crates/foo")).unwrap();
+        git(&origin, &["init", "-q"]);
+        fs::write(origin.join("README.md"), "readme").unwrap();
+        fs::write(origin.join("crates/foo/lib.rs"), "pub fn f() {}").unwrap();
+        git(&origin, &["add", "."]);
+        git(&origin, &["commit", "-qm", "init"]);
+        git(&origin, &["config", "uploadpack.allowfilter", "true"]);
+        let url = format!("file://{}", origin.display());
+        git(
+            tmp.path(),
+            &[
+                "clone",
+                "-q",
+                "--no-checkout",
+                "--filter=blob:none",
+                &url,
+                "clone",
+            ],
+        );
+
+        let repo = Repository::open(tmp.path().join("clone")).unwrap();
+        assert_eq!(promisor_remote(&repo).as_deref(), Some("origin"));
+        let head = repo.head().unwrap().peel_to_commit().unwrap().id();
+        let roots = [PathBuf::from("crates/foo")];
+        let missing = missing_blobs(&repo, head, &roots).unwrap();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].0, Path::new("crates/foo/lib.rs"));
+
+        ensure_blobs(&repo, head, &roots).unwrap();
+        assert!(missing_blobs(&repo, head, &roots).unwrap().is_empty());
+        // Blobs outside the packaged crates stay on the server until read.
+        let readme = missing_blobs(&repo, head, &[PathBuf::new()]).unwrap();
+        assert_eq!(readme.len(), 1);
+        let blob = find_blob(&repo, readme[0].1, &readme[0].0).unwrap();
+        assert_eq!(blob.content(), b"readme");
+    }
+}
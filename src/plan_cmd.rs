@@ -0,0 +1,166 @@
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use git2::Repository;
+use serde::Serialize;
+
+use crate::infer::InferredContext;
+use crate::pr_labels;
+use crate::schema;
+use crate::versioning::{self, BumpKind, CommitKind, CratePlan};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PlanFormat {
+    Text,
+    Json,
+    /// The changelog sections `prerelease` would write, without touching the tree.
+    Changelog,
+}
+
+#[derive(Debug, Serialize)]
+struct PlanOutput {
+    schema_version: u32,
+    base_tag: Option<String>,
+    main_crate: String,
+    crates: Vec<PlannedCrate>,
+    /// Commits that touch no crate and so reach no changelog.
+    unattributed: Vec<PlannedCommit>,
+}
+
+#[derive(Debug, Serialize)]
+struct PlannedCrate {
+    name: String,
+    current: semver::Version,
+    next: semver::Version,
+    bump: BumpKind,
+    reason: String,
+    /// The SemVer rule behind `bump`, printed by `--explain`.
+    rule: String,
+    trigger: PlannedCommit,
+    commits: Vec<PlannedCommit>,
+}
+
+#[derive(Debug, Serialize)]
+struct PlannedCommit {
+    sha: String,
+    kind: CommitKind,
+    /// Why the commit got `kind`.
+    classified_by: String,
+    subject: String,
+    /// False when a skip label keeps the commit out of changelogs.
+    in_changelog: bool,
+    highlight: bool,
+}
+
+/// Print the version plan for the commits since the base tag without touching the tree.
+pub async fn run_plan(ctx: &InferredContext, format: PlanFormat, explain: bool) -> Result<()> {
+    let repo = Repository::discover(&ctx.repo_root)?;
+    let plan = pr_labels::apply(ctx, versioning::compute_plan(&repo, ctx)?).await?;
+    let output = PlanOutput {
+        schema_version: schema::SCHEMA_VERSION,
+        base_tag: ctx.plan_base().map(String::from),
+        main_crate: ctx.main_crate.clone(),
+        crates: plan
+            .iter()
+            .map(|(name, cp)| planned_crate(name, cp))
+            .collect(),
+        unattributed: plan.unattributed().iter().map(planned_commit).collect(),
+    };
+    match format {
+        PlanFormat::Text => print!("{}", render_text(&output, explain)),
+        PlanFormat::Json => println!("{}", serde_json::to_string_pretty(&output)?),
+        PlanFormat::Changelog => print!("{}", versioning::preview_changelogs(ctx, &plan)?),
+    }
+    Ok(())
+}
+
+fn planned_commit(c: &versioning::ChangeEntry) -> PlannedCommit {
+    PlannedCommit {
+        sha: c.sha().to_string(),
+        kind: c.kind(),
+        classified_by: c.classification().to_string(),
+        subject: c.subject().to_string(),
+        in_changelog: c.in_changelog(),
+        highlight: c.is_highlight(),
+    }
+}
+
+fn planned_crate(name: &str, cp: &CratePlan) -> PlannedCrate {
+    PlannedCrate {
+        name: name.to_string(),
+        current: cp.previous_version().clone(),
+        next: cp.new_version().clone(),
+        bump: cp.bump(),
+        reason: cp.rationale().to_string(),
+        rule: cp.bump_rule(),
+        trigger: planned_commit(cp.trigger()),
+        commits: cp.changes().iter().map(planned_commit).collect(),
+    }
+}
+
+/// Marks the effect of `[changelog.labels]` skip and highlight labels.
+fn label_note(commit: &PlannedCommit) -> &'static str {
+    match (commit.in_changelog, commit.highlight) {
+        (false, _) => " [not in changelog]",
+        (true, true) => " [highlight]",
+        (true, false) => "",
+    }
+}
+
+fn render_text(output: &PlanOutput, explain: bool) -> String {
+    let mut out = String::new();
+    writeln!(
+        &mut out,
+        "base tag: {}",
+        output.base_tag.as_deref().unwrap_or("<none>")
+    )
+    .unwrap();
+    writeln!(&mut out, "main crate: {}", output.main_crate).unwrap();
+    if output.crates.is_empty() {
+        writeln!(&mut out, "changed crates: <none>").unwrap();
+    } else {
+        writeln!(&mut out, "changed crates:").unwrap();
+    }
+    for c in &output.crates {
+        writeln!(
+            &mut out,
+            "* {} {} -> {} ({}: {}, from {} {})",
+            c.name, c.current, c.next, c.bump, c.reason, c.trigger.sha, c.trigger.subject
+        )
+        .unwrap();
+        if !explain {
+            for commit in &c.commits {
+                writeln!(
+                    &mut out,
+                    "  - {} {}{}",
+                    commit.sha,
+                    commit.subject,
+                    label_note(commit)
+                )
+                .unwrap();
+            }
+            continue;
+        }
+        writeln!(&mut out, "  {}: {}", c.bump, c.rule).unwrap();
+        for commit in &c.commits {
+            writeln!(
+                &mut out,
+                "  - {} {} ({}): {}{}",
+                commit.sha,
+                commit.kind,
+                commit.classified_by,
+                commit.subject,
+                label_note(commit)
+            )
+            .unwrap();
+        }
+    }
+    if !output.unattributed.is_empty() {
+        writeln!(&mut out, "unattributed commits (in no changelog):").unwrap();
+        for commit in &output.unattributed {
+            writeln!(&mut out, "  - {} {}", commit.sha, commit.subject).unwrap();
+        }
+    }
+    out
+}
@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::github;
+use crate::infer::InferredContext;
+use crate::versioning::Plan;
+
+#[derive(Deserialize)]
+struct Label {
+    name: String,
+}
+
+/// Apply `[changelog.labels]` to `plan` using the labels on each planned pull request.
+///
+/// Returns the plan unchanged when the section is disabled or no commit maps to a pull
+/// request, so only projects that opted in talk to GitHub while planning.
+pub async fn apply(ctx: &InferredContext, plan: Plan) -> Result<Plan> {
+    if !ctx.config.changelog.labels.enabled {
+        return Ok(plan);
+    }
+    let numbers = plan.pull_requests();
+    if numbers.is_empty() {
+        return Ok(plan);
+    }
+    let gh = github::client()?;
+    let mut labels = HashMap::new();
+    for number in numbers {
+        let found: Vec<Label> = gh
+            .get(
+                format!(
+                    "/repos/{}/{}/issues/{}/labels",
+                    ctx.repo_owner, ctx.repo_name, number
+                ),
+                Some(&[("per_page", "100")]),
+            )
+            .await
+            .with_context(|| format!("failed to read the labels of pull request #{}", number))?;
+        labels.insert(number, found.into_iter().map(|l| l.name).collect());
+    }
+    plan.with_labels(ctx, &labels)
+}
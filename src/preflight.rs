@@ -1,12 +1,42 @@
 use anyhow::Result;
 
-use crate::infer::{InferredContext, build_context};
+use crate::asf;
+use crate::git_identity::GitIdentity;
+use crate::github;
+use crate::infer::{InferOptions, InferredContext, build_context};
+use crate::net;
+use crate::release_gate;
 
-pub async fn run_preflight() -> Result<InferredContext> {
+pub async fn run_preflight(opts: &InferOptions) -> Result<InferredContext> {
     // Phase 1 preflight: ensure clean repo, infer remote, owner/name, workspace crates,
     // main crate, and the last stable tag. Execute blocking work off the async runtime.
     tracing::debug!("preflight: start");
-    let ctx = build_context().await?;
+    let mut ctx = build_context(opts).await?;
+    ctx.config.network.tuning.override_with(&opts.network);
+    net::init(&ctx.repo_root, &ctx.config.network)?;
+    github::init(
+        &ctx.repo_root,
+        &ctx.config.github,
+        &ctx.repo_owner,
+        &ctx.repo_name,
+    )
+    .await?;
+    asf::release_manager(&ctx.config.asf)?;
+    // Read-only commands never commit or tag; the others fail later without an identity.
+    if !opts.allow_dirty
+        && let Err(err) = GitIdentity::resolve(&ctx.repo_root, &ctx.config)
+    {
+        tracing::warn!("preflight: {:#}", err);
+        eprintln!("warning: {:#}", err);
+    }
+    // `release` stops before tagging while a gate is unmet; say so before any other work.
+    if opts.release_gate
+        && github::has_token()
+        && let Err(err) = release_gate::preflight(&ctx).await
+    {
+        tracing::warn!("preflight: {:#}", err);
+        eprintln!("warning: {:#}", err);
+    }
     tracing::debug!(
         "preflight: done repo={}/{} main={}",
         ctx.repo_owner,
@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+
+use crate::discussion::Draft;
+use crate::infer::InferredContext;
+use crate::{release_cmd, start, vote};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PreviewKind {
+    /// Release kickoff discussion
+    Start,
+    /// Vote discussion for the latest rc
+    Vote,
+    /// Release announcement for the latest rc
+    #[value(alias = "announce")]
+    Release,
+}
+
+/// Render a discussion template with live data, printing it or writing it to `output`.
+///
+/// Nothing is tagged, uploaded, or posted.
+pub async fn run_preview(
+    ctx: &InferredContext,
+    kind: PreviewKind,
+    output: Option<&Path>,
+) -> Result<()> {
+    let draft = match kind {
+        PreviewKind::Start => start::draft(ctx)?,
+        PreviewKind::Vote => vote::draft(ctx).await?,
+        PreviewKind::Release => release_cmd::draft(ctx).await?,
+    };
+    match output {
+        Some(path) => {
+            tokio::fs::write(path, render(&draft))
+                .await
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            println!(
+                "preview: wrote {} (title={}{})",
+                path.display(),
+                draft.title,
+                draft.front.summary()
+            );
+        }
+        None => {
+            println!("preview: title={}{}", draft.title, draft.front.summary());
+            println!("---\n{}", render(&draft));
+        }
+    }
+    Ok(())
+}
+
+/// The body followed by any follow-up comments, separated by `---` rules.
+fn render(draft: &Draft) -> String {
+    let mut out = draft.body.clone();
+    for comment in &draft.comments {
+        out.push_str("\n---\n\n");
+        out.push_str(comment);
+    }
+    out
+}
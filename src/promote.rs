@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use regex::Regex;
+use tokio::fs as async_fs;
+
+use crate::artifacts;
+use crate::checksum::{self, ChecksumFormat};
+use crate::downloads::final_asset_name;
+use crate::infer::InferredContext;
+
+/// Suffixes of the files that vouch for an archive and follow it in and out of a release.
+const COMPANIONS: [&str; 2] = [".sha512", ".asc"];
+
+/// Copy the rc assets in `files` into the run directory of the stable `tag` under the
+/// names the stable GitHub Release ships them with, leaving out `release.exclude_assets`.
+pub async fn stable_assets(
+    ctx: &InferredContext,
+    tag: &str,
+    files: &[PathBuf],
+) -> Result<Vec<PathBuf>> {
+    let exclude = exclude_patterns(&ctx.config.release.exclude_assets)?;
+    let dir = artifacts::run_dir(ctx, tag);
+    copy_renamed(files, &exclude, &dir, ctx.config.checksum_format).await
+}
+
+/// Copy `files` into `dir` under their final names and return the copies.
+///
+/// Assets matching `exclude`, and the checksums and signatures of those, are left out.
+/// The rest lose their `-rcN` marker. A detached signature covers only the content, so it
+/// is copied as is; a `.sha512` file names the archive, so it is written again for the
+/// new name in `format`.
+pub async fn copy_renamed(
+    files: &[PathBuf],
+    exclude: &[Regex],
+    dir: &Path,
+    format: ChecksumFormat,
+) -> Result<Vec<PathBuf>> {
+    let names: Vec<&str> = files.iter().filter_map(|f| file_name(f)).collect();
+    let kept = final_names(&names, exclude)?;
+    async_fs::create_dir_all(dir).await?;
+
+    let mut promoted = Vec::with_capacity(kept.len());
+    // Archives first, so the checksums below are computed over the copies.
+    let (checksums, others): (Vec<_>, Vec<_>) =
+        kept.iter().partition(|(_, name)| name.ends_with(".sha512"));
+    for (rc_name, name) in others.into_iter().chain(checksums) {
+        let source = files
+            .iter()
+            .find(|f| file_name(f) == Some(*rc_name))
+            .expect("kept names come from files");
+        let target = dir.join(name);
+        match name.strip_suffix(".sha512") {
+            Some(archive) if kept.iter().any(|(_, n)| n == archive) => {
+                let digest = checksum::sha512_file(&dir.join(archive)).await?;
+                async_fs::write(&target, format.render(&digest, archive)).await?;
+            }
+            _ => {
+                async_fs::copy(source, &target).await.with_context(|| {
+                    format!(
+                        "failed to copy {} to {}",
+                        source.display(),
+                        target.display()
+                    )
+                })?;
+            }
+        }
+        promoted.push(target);
+    }
+    promoted.sort();
+    Ok(promoted)
+}
+
+/// Compile `release.exclude_assets`.
+pub fn exclude_patterns(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|p| {
+            Regex::new(p)
+                .map_err(|err| anyhow!("invalid release.exclude_assets pattern {:?}: {}", p, err))
+        })
+        .collect()
+}
+
+/// The rc asset names the stable release keeps, each with its final name, in input order.
+///
+/// Fails when two kept assets would end up with the same final name.
+pub fn final_names<'a>(names: &[&'a str], exclude: &[Regex]) -> Result<Vec<(&'a str, String)>> {
+    let excluded = |name: &str| {
+        let base = COMPANIONS
+            .iter()
+            .find_map(|suffix| name.strip_suffix(suffix))
+            .unwrap_or(name);
+        exclude
+            .iter()
+            .any(|re| re.is_match(name) || re.is_match(base))
+    };
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    let mut kept = Vec::new();
+    for name in names.iter().copied().filter(|n| !excluded(n)) {
+        let final_name = final_asset_name(name);
+        if let Some(other) = seen.insert(final_name.clone(), name) {
+            bail!(
+                "rc assets {} and {} would both be released as {}; leave one out with release.exclude_assets",
+                other,
+                name,
+                final_name
+            );
+        }
+        kept.push((name, final_name));
+    }
+    Ok(kept)
+}
+
+fn file_name(path: &Path) -> Option<&str> {
+    path.file_name().and_then(|n| n.to_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rc_markers_are_dropped_and_exclusions_take_companions_along() {
+        let names = [
+            "apache-foo-0.1.1-rc2-src.tar.gz",
+            "apache-foo-0.1.1-rc2-src.tar.gz.asc",
+            "apache-foo-0.1.1-rc2-src.tar.gz.sha512",
+            "apache-foo-0.1.1-rc2-src.zip",
+            "apache-foo-0.1.1-rc2-src.zip.sha512",
+        ];
+        let exclude = exclude_patterns(&[r"\.zip$".to_string()]).unwrap();
+        assert_eq!(
+            final_names(&names, &exclude).unwrap(),
+            [
+                (names[0], "apache-foo-0.1.1-src.tar.gz".to_string()),
+                (names[1], "apache-foo-0.1.1-src.tar.gz.asc".to_string()),
+                (names[2], "apache-foo-0.1.1-src.tar.gz.sha512".to_string()),
+            ]
+        );
+
+        let err = final_names(
+            &[
+                "apache-foo-0.1.1-rc2-src.tar.gz",
+                "apache-foo-0.1.1-src.tar.gz",
+            ],
+            &[],
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("would both be released as apache-foo-0.1.1-src.tar.gz"),
+            "{}",
+            err
+        );
+        assert!(exclude_patterns(&["(".to_string()]).is_err());
+    }
+}
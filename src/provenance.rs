@@ -0,0 +1,208 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Value, json};
+use tokio::fs as async_fs;
+
+use crate::asf::ReleaseManager;
+use crate::gpg;
+use crate::infer::InferredContext;
+use crate::manifest::{ArtifactKind, RunManifest};
+
+/// Name of the provenance statement on the rc release; its signature adds `.asc`.
+pub const FILE_NAME: &str = "provenance.intoto.json";
+const BUILD_TYPE: &str = "https://github.com/Xuanwo/asfship/source-archive/v1";
+
+/// Who produced the archives: a GitHub Actions run, or asfship on a release manager's machine.
+#[derive(Debug, PartialEq, Eq)]
+struct Builder {
+    id: String,
+    invocation: Option<String>,
+}
+
+impl Builder {
+    fn detect(env: impl Fn(&str) -> Option<String>) -> Self {
+        if env("GITHUB_ACTIONS").as_deref() == Some("true")
+            && let (Some(server), Some(workflow), Some(repo), Some(run)) = (
+                env("GITHUB_SERVER_URL"),
+                env("GITHUB_WORKFLOW_REF"),
+                env("GITHUB_REPOSITORY"),
+                env("GITHUB_RUN_ID"),
+            )
+        {
+            let attempt = env("GITHUB_RUN_ATTEMPT").unwrap_or_else(|| "1".to_string());
+            return Self {
+                id: format!("{}/{}", server, workflow),
+                invocation: Some(format!(
+                    "{}/{}/actions/runs/{}/attempts/{}",
+                    server, repo, run, attempt
+                )),
+            };
+        }
+        Self {
+            id: format!(
+                "https://github.com/Xuanwo/asfship@v{}",
+                env!("CARGO_PKG_VERSION")
+            ),
+            invocation: None,
+        }
+    }
+}
+
+/// in-toto statement with an SLSA v1 provenance predicate covering every archive in `manifest`.
+fn statement(
+    ctx: &InferredContext,
+    manifest: &RunManifest,
+    builder: &Builder,
+    rm: Option<&ReleaseManager>,
+    started: DateTime<Utc>,
+    finished: DateTime<Utc>,
+) -> Value {
+    let repo_url = format!("https://github.com/{}/{}", ctx.repo_owner, ctx.repo_name);
+    let subject: Vec<Value> = manifest
+        .files
+        .iter()
+        .filter(|f| f.kind != ArtifactKind::Checksum)
+        .map(|f| json!({ "name": f.name, "digest": { "sha512": f.sha512 } }))
+        .collect();
+    let mut metadata = json!({
+        "startedOn": started.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "finishedOn": finished.to_rfc3339_opts(SecondsFormat::Secs, true),
+    });
+    if let Some(invocation) = &builder.invocation {
+        metadata["invocationId"] = json!(invocation);
+    }
+    json!({
+        "_type": "https://in-toto.io/Statement/v1",
+        "subject": subject,
+        "predicateType": "https://slsa.dev/provenance/v1",
+        "predicate": {
+            "buildDefinition": {
+                "buildType": BUILD_TYPE,
+                "externalParameters": {
+                    "repository": repo_url,
+                    "ref": format!("refs/tags/{}", manifest.tag),
+                },
+                "internalParameters": {
+                    "compression": ctx.config.package.compression.formats
+                        .iter()
+                        .map(|f| f.extension())
+                        .collect::<Vec<_>>(),
+                    "releaseManager": rm.map(|rm| rm.email.as_str()),
+                },
+                "resolvedDependencies": [{
+                    "uri": format!("git+{}@refs/tags/{}", repo_url, manifest.tag),
+                    "digest": { "gitCommit": manifest.commit },
+                }],
+            },
+            "runDetails": {
+                "builder": {
+                    "id": builder.id,
+                    "version": { "asfship": env!("CARGO_PKG_VERSION") },
+                },
+                "metadata": metadata,
+            },
+        },
+    })
+}
+
+/// Write the provenance statement for `manifest` into `dir` and sign it with gpg.
+///
+/// Returns the statement and its detached `.asc` signature, ready for upload.
+pub async fn write_signed(
+    ctx: &InferredContext,
+    manifest: &RunManifest,
+    rm: Option<&ReleaseManager>,
+    started: DateTime<Utc>,
+    dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let builder = Builder::detect(|key| std::env::var(key).ok());
+    let doc = statement(ctx, manifest, &builder, rm, started, Utc::now());
+    let path = dir.join(FILE_NAME);
+    async_fs::write(&path, serde_json::to_vec_pretty(&doc)?).await?;
+    let signature = gpg::sign(&path, rm.and_then(|rm| rm.gpg_fingerprint.as_deref())).await?;
+    tracing::info!("provenance: wrote and signed {}", path.display());
+    Ok(vec![path, signature])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::ManifestEntry;
+
+    #[test]
+    fn statement_covers_archives_and_source_commit() {
+        let ctx = InferredContext {
+            repo_root: PathBuf::from("."),
+            repo_owner: "apache".into(),
+            repo_name: "foo".into(),
+            crates: Vec::new(),
+            main_crate: "foo".into(),
+            last_stable_tag: None,
+            since: None,
+            until: None,
+            config: Default::default(),
+            snapshot: None,
+        };
+        let entry = |name: &str, kind| ManifestEntry {
+            name: name.into(),
+            sha512: "ab".repeat(64),
+            size: 10,
+            crate_name: "foo".into(),
+            kind,
+        };
+        let manifest = RunManifest {
+            schema_version: crate::schema::SCHEMA_VERSION,
+            tag: "v0.2.0-rc.1".into(),
+            commit: "0123456789abcdef0123456789abcdef01234567".into(),
+            files: vec![
+                entry(
+                    "apache-foo-0.2.0-rc1-src.tar.gz",
+                    ArtifactKind::SourceTarball,
+                ),
+                entry(
+                    "apache-foo-0.2.0-rc1-src.tar.gz.sha512",
+                    ArtifactKind::Checksum,
+                ),
+            ],
+        };
+        let builder = Builder::detect(|key| {
+            match key {
+                "GITHUB_ACTIONS" => Some("true"),
+                "GITHUB_SERVER_URL" => Some("https://github.com"),
+                "GITHUB_WORKFLOW_REF" => {
+                    Some("apache/foo/.github/workflows/release.yml@refs/heads/main")
+                }
+                "GITHUB_REPOSITORY" => Some("apache/foo"),
+                "GITHUB_RUN_ID" => Some("42"),
+                _ => None,
+            }
+            .map(String::from)
+        });
+        let now = Utc::now();
+        let doc = statement(&ctx, &manifest, &builder, None, now, now);
+
+        let subject = doc["subject"].as_array().unwrap();
+        assert_eq!(subject.len(), 1);
+        assert_eq!(subject[0]["name"], "apache-foo-0.2.0-rc1-src.tar.gz");
+        assert_eq!(subject[0]["digest"]["sha512"], "ab".repeat(64));
+        let predicate = &doc["predicate"];
+        assert_eq!(
+            predicate["buildDefinition"]["resolvedDependencies"][0]["digest"]["gitCommit"],
+            manifest.commit.as_str()
+        );
+        assert_eq!(
+            predicate["runDetails"]["builder"]["id"],
+            "https://github.com/apache/foo/.github/workflows/release.yml@refs/heads/main"
+        );
+        assert_eq!(
+            predicate["runDetails"]["metadata"]["invocationId"],
+            "https://github.com/apache/foo/actions/runs/42/attempts/1"
+        );
+
+        let local = Builder::detect(|_| None);
+        assert!(local.id.starts_with("https://github.com/Xuanwo/asfship@v"));
+        assert_eq!(local.invocation, None);
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use cargo_metadata::DependencyKind;
+use git2::Repository;
+use tokio::process::Command;
+use tokio::task::JoinSet;
+
+use crate::artifacts;
+use crate::audit::{AuditAction, AuditLog};
+use crate::cancel;
+use crate::crates_io::{self, PublishStatus};
+use crate::docs_rs;
+use crate::infer::{self, InferredContext};
+use crate::released::{ReleasedCrate, released_crates};
+
+/// How long a published version may take to show up in the sparse index.
+const INDEX_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+pub struct PublishOptions<'a> {
+    pub version: Option<&'a str>,
+    pub jobs: usize,
+    pub skip_published: bool,
+    /// How long to wait for docs.rs builds after publishing; zero skips the check.
+    pub docs_timeout: Duration,
+    pub dry_run: bool,
+}
+
+/// Workspace crates each released crate needs on crates.io before it can be published.
+type Deps = BTreeMap<String, BTreeSet<String>>;
+
+pub async fn run_publish(ctx: &InferredContext, opts: PublishOptions<'_>) -> Result<()> {
+    if opts.jobs == 0 {
+        bail!("--jobs must be at least 1");
+    }
+    let version = crates_io::release_version(ctx, opts.version)?;
+    let tag = ctx.config.tags().stable(&version);
+    let repo = Repository::discover(&ctx.repo_root)?;
+    let crates = released_crates(&repo, ctx, &version)?;
+    if crates.is_empty() {
+        bail!("no publishable crate was released at {}", tag);
+    }
+    let deps = internal_deps(ctx, &crates).await?;
+    let levels = levels(&crates, &deps)?;
+
+    println!(
+        "publish: {} ({} crates, up to {} at a time)",
+        tag,
+        crates.len(),
+        opts.jobs
+    );
+    for (i, level) in levels.iter().enumerate() {
+        let names: Vec<String> = level
+            .iter()
+            .map(|c| format!("{} {}", c.name, c.version))
+            .collect();
+        println!("- level {}: {}", i + 1, names.join(", "));
+    }
+    if opts.dry_run {
+        if !opts.docs_timeout.is_zero() {
+            println!(
+                "- then wait up to {} minutes for docs.rs builds",
+                opts.docs_timeout.as_secs() / 60
+            );
+        }
+        println!("publish: dry-run, nothing published");
+        return Ok(());
+    }
+    ensure_head_at(&repo, &tag)?;
+
+    let client = crates_io::client()?;
+    let mut skipped = BTreeSet::new();
+    for c in &crates {
+        let status = crates_io::check_crate(&client, c).await?;
+        if status == PublishStatus::Missing {
+            continue;
+        }
+        if !opts.skip_published {
+            bail!(
+                "{} {} is already on crates.io ({}); pass --skip-published to resume",
+                c.name,
+                c.version,
+                status
+            );
+        }
+        println!("- skip {} {}: {}", c.name, c.version, status);
+        skipped.insert(c.name.clone());
+    }
+
+    let audit = AuditLog::open(ctx).await;
+    let mut pending: Vec<&ReleasedCrate> = levels.into_iter().flatten().collect();
+    let mut ready = BTreeSet::new();
+    let mut running = JoinSet::new();
+    let mut failure = None;
+    loop {
+        while failure.is_none() && running.len() < opts.jobs {
+            let Some(pos) = pending.iter().position(|c| {
+                deps.get(&c.name)
+                    .is_none_or(|d| d.iter().all(|name| ready.contains(name)))
+            }) else {
+                break;
+            };
+            let c = pending.remove(pos).clone();
+            let publish = !skipped.contains(&c.name);
+            if publish
+                && let Err(e) = cancel::checkpoint(&format!("publishing {} {}", c.name, c.version))
+            {
+                failure = Some(e);
+                break;
+            }
+            let client = client.clone();
+            let root = ctx.repo_root.clone();
+            // A target directory per crate keeps concurrent verification builds from
+            // waiting on each other's build lock.
+            let target_dir = artifacts::root(ctx).join("publish").join(&c.name);
+            running.spawn(async move {
+                if publish {
+                    cargo_publish(&root, &target_dir, &c).await?;
+                }
+                crates_io::wait_for_index(&client, &c, INDEX_TIMEOUT).await?;
+                Ok::<_, anyhow::Error>((c, publish))
+            });
+        }
+        // Let crates already being uploaded finish even after a failure, so the
+        // summary below matches what reached crates.io.
+        let Some(joined) = running.join_next().await else {
+            break;
+        };
+        match joined.map_err(|e| anyhow!("publish task join error: {}", e))? {
+            Ok((c, published)) => {
+                if published {
+                    audit
+                        .record(
+                            AuditAction::CratePublished,
+                            &format!("{}@{}", c.name, c.version),
+                        )
+                        .await;
+                    println!("- published {} {}", c.name, c.version);
+                }
+                ready.insert(c.name);
+            }
+            Err(e) => {
+                failure.get_or_insert(e);
+            }
+        }
+    }
+    if let Some(e) = failure {
+        return Err(e.context(format!(
+            "{} of {} crates are on crates.io; fix the error and re-run with --skip-published",
+            ready.len(),
+            crates.len()
+        )));
+    }
+
+    println!("publish: completed; verifying on crates.io");
+    for c in &crates {
+        println!(
+            "- {} {}: {}",
+            c.name,
+            c.version,
+            crates_io::check_crate(&client, c).await?
+        );
+    }
+    if opts.docs_timeout.is_zero() {
+        return Ok(());
+    }
+    println!(
+        "publish: waiting up to {} minutes for docs.rs",
+        opts.docs_timeout.as_secs() / 60
+    );
+    let statuses = docs_rs::wait_for_builds(&client, &crates, opts.docs_timeout).await?;
+    let mut failed = 0;
+    for (c, status) in &statuses {
+        println!("- {} {}: docs.rs {}", c.name, c.version, status);
+        match status {
+            docs_rs::DocsStatus::Failed { .. } => failed += 1,
+            docs_rs::DocsStatus::Pending => {
+                tracing::warn!("docs.rs: {} {} not built in time", c.name, c.version);
+                eprintln!(
+                    "warning: docs.rs has not built {} {} yet; check it later with `asfship check-published`",
+                    c.name, c.version
+                );
+            }
+            docs_rs::DocsStatus::Built => {}
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "docs.rs failed to build {} of {} crates; they are published, so fix the documentation build in the next release",
+            failed,
+            crates.len()
+        );
+    }
+    Ok(())
+}
+
+/// Normal and build dependencies between the released crates. Dev-dependencies are
+/// left out because `cargo publish` does not need them on the registry.
+async fn internal_deps(ctx: &InferredContext, crates: &[ReleasedCrate]) -> Result<Deps> {
+    let meta = infer::load_metadata(&ctx.repo_root).await?;
+    let names: BTreeSet<&str> = crates.iter().map(|c| c.name.as_str()).collect();
+    let mut deps = Deps::new();
+    for c in crates {
+        let pkg = meta
+            .workspace_packages()
+            .into_iter()
+            .find(|p| p.name == c.name)
+            .with_context(|| format!("{} is not a workspace member", c.name))?;
+        let needs = pkg
+            .dependencies
+            .iter()
+            .filter(|d| d.kind != DependencyKind::Development)
+            .filter(|d| d.name != c.name && names.contains(d.name.as_str()))
+            .map(|d| d.name.clone())
+            .collect();
+        deps.insert(c.name.clone(), needs);
+    }
+    Ok(deps)
+}
+
+/// Group crates so that each level only depends on earlier ones.
+fn levels<'a>(crates: &'a [ReleasedCrate], deps: &Deps) -> Result<Vec<Vec<&'a ReleasedCrate>>> {
+    let mut placed = BTreeSet::new();
+    let mut remaining: Vec<&ReleasedCrate> = crates.iter().collect();
+    let mut levels = Vec::new();
+    while !remaining.is_empty() {
+        let (level, rest): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|c| {
+            deps.get(&c.name)
+                .is_none_or(|d| d.iter().all(|name| placed.contains(name)))
+        });
+        if level.is_empty() {
+            let names: Vec<&str> = rest.iter().map(|c| c.name.as_str()).collect();
+            bail!("dependency cycle between {}", names.join(", "));
+        }
+        placed.extend(level.iter().map(|c| c.name.clone()));
+        levels.push(level);
+        remaining = rest;
+    }
+    Ok(levels)
+}
+
+/// `cargo publish` uploads the working tree, so it must be the release tag.
+fn ensure_head_at(repo: &Repository, tag: &str) -> Result<()> {
+    let tagged = repo
+        .revparse_single(&format!("refs/tags/{}", tag))
+        .with_context(|| format!("release tag {} not found", tag))?
+        .peel_to_commit()?
+        .id();
+    let head = repo.head()?.peel_to_commit()?.id();
+    if head != tagged {
+        bail!(
+            "HEAD is at {} but {} points to {}; check out {} before publishing",
+            head,
+            tag,
+            tagged,
+            tag
+        );
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip_all, fields(krate = %c.name))]
+async fn cargo_publish(root: &Path, target_dir: &Path, c: &ReleasedCrate) -> Result<()> {
+    tracing::info!("cargo: publishing {} {}", c.name, c.version);
+    let output = cancel::output(
+        Command::new("cargo")
+            .args(["publish", "-p", &c.name, "--target-dir"])
+            .arg(target_dir)
+            .current_dir(root),
+    )
+    .await
+    .context("failed to run cargo publish")?;
+    if !output.status.success() {
+        bail!(
+            "cargo publish {} {} failed: {}",
+            c.name,
+            c.version,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn krate(name: &str) -> ReleasedCrate {
+        ReleasedCrate {
+            name: name.to_string(),
+            version: "0.2.0".to_string(),
+        }
+    }
+
+    fn deps(edges: &[(&str, &[&str])]) -> Deps {
+        edges
+            .iter()
+            .map(|(name, needs)| {
+                (
+                    name.to_string(),
+                    needs.iter().map(|n| n.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn levels_follow_dependencies() {
+        let crates = [krate("app"), krate("core"), krate("derive"), krate("http")];
+        let graph = deps(&[("app", &["core", "http"]), ("core", &["derive"])]);
+        let names: Vec<Vec<&str>> = levels(&crates, &graph)
+            .unwrap()
+            .iter()
+            .map(|l| l.iter().map(|c| c.name.as_str()).collect())
+            .collect();
+        assert_eq!(
+            names,
+            vec![vec!["derive", "http"], vec!["core"], vec!["app"]]
+        );
+
+        let cycle = deps(&[("core", &["http"]), ("http", &["core"])]);
+        let err = levels(&crates, &cycle).unwrap_err().to_string();
+        assert!(err.contains("core, http"), "{}", err);
+    }
+}
@@ -1,12 +1,23 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use std::fmt;
+
 use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
 use octocrab::models::repos::Release;
-use regex::Regex;
 use semver::Version;
 use tokio::fs as async_fs;
 
+use crate::asset_cache::AssetCache;
+use crate::audit::{AuditAction, AuditLog};
+use crate::cancel;
+use crate::checksum;
 use crate::github;
+use crate::infer::InferredContext;
+use crate::manifest::{self, RunManifest};
+use crate::net;
+use crate::tags::TagFormat;
 
 #[derive(Debug, Clone)]
 pub struct RcReleaseInfo {
@@ -14,6 +25,42 @@ pub struct RcReleaseInfo {
     pub version: Version,
     pub rc_number: u32,
     pub assets: Vec<RcAsset>,
+    /// Vote outcome `asfship result` recorded on the release; `None` while voting.
+    pub vote: Option<VoteOutcome>,
+}
+
+/// How the vote on an rc ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VoteOutcome {
+    Passed,
+    Failed,
+}
+
+impl fmt::Display for VoteOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            VoteOutcome::Passed => "passed",
+            VoteOutcome::Failed => "failed",
+        })
+    }
+}
+
+impl VoteOutcome {
+    /// First line of the release notes recording this outcome.
+    fn marker(self) -> &'static str {
+        match self {
+            VoteOutcome::Passed => "> **Vote passed**",
+            VoteOutcome::Failed => "> **Vote failed**",
+        }
+    }
+
+    /// The outcome recorded at the top of `notes`, if any.
+    fn recorded(notes: &str) -> Option<Self> {
+        let first = notes.lines().next()?;
+        [VoteOutcome::Passed, VoteOutcome::Failed]
+            .into_iter()
+            .find(|o| first.starts_with(o.marker()))
+    }
 }
 
 impl RcReleaseInfo {
@@ -25,8 +72,8 @@ impl RcReleaseInfo {
         self.version.to_string()
     }
 
-    pub fn stable_tag(&self) -> String {
-        format!("v{}", self.base_version_string())
+    pub fn stable_tag(&self, tags: &TagFormat) -> String {
+        tags.stable(&self.version)
     }
 
     pub fn svn_path_component(&self, repo_name: &str) -> String {
@@ -44,18 +91,40 @@ pub struct RcAsset {
     pub name: String,
     pub download_url: String,
     pub size: u64,
+    /// Digest recorded in the run manifest, when the release has one.
+    pub sha512: Option<String>,
 }
 
 impl RcAsset {
     pub fn is_checksum(&self) -> bool {
         self.name.ends_with(".sha512")
     }
+
+    pub fn human_size(&self) -> String {
+        human_size(self.size)
+    }
 }
 
-pub async fn fetch_latest_rc_release(owner: &str, repo: &str) -> Result<RcReleaseInfo> {
+/// Byte count in binary units with one decimal, e.g. `1.5 MiB`; plain bytes below 1 KiB.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+pub async fn fetch_latest_rc_release(ctx: &InferredContext) -> Result<RcReleaseInfo> {
+    let tags = ctx.config.tags();
     let gh = github::client()?;
     let releases = gh
-        .repos(owner.to_string(), repo.to_string())
+        .repos(&ctx.repo_owner, &ctx.repo_name)
         .releases()
         .list()
         .per_page(25)
@@ -64,8 +133,8 @@ pub async fn fetch_latest_rc_release(owner: &str, repo: &str) -> Result<RcReleas
 
     let mut page = releases;
     loop {
-        if let Some(info) = select_rc_release(&page.items)? {
-            return Ok(info);
+        if let Some(info) = select_rc_release(&page.items, &tags)? {
+            return attach_manifest(info).await;
         }
         if let Some(next) = gh.get_page::<Release>(&page.next).await? {
             page = next;
@@ -74,38 +143,117 @@ pub async fn fetch_latest_rc_release(owner: &str, repo: &str) -> Result<RcReleas
         }
     }
 
-    bail!("no rc release found for {}/{}", owner, repo)
+    bail!(
+        "no rc release found for {}/{}",
+        ctx.repo_owner,
+        ctx.repo_name
+    )
+}
+
+pub async fn fetch_rc_release_by_tag(ctx: &InferredContext, tag: &str) -> Result<RcReleaseInfo> {
+    let tags = ctx.config.tags();
+    let gh = github::client()?;
+    let release = gh
+        .repos(&ctx.repo_owner, &ctx.repo_name)
+        .releases()
+        .get_by_tag(tag)
+        .await
+        .with_context(|| format!("failed to load GitHub release for {}", tag))?;
+    let info = try_build_rc_release(&release, &tags)?.with_context(|| {
+        format!(
+            "{} is not a published rc release (expected {})",
+            tag,
+            tags.rc_pattern()
+        )
+    })?;
+    attach_manifest(info).await
+}
+
+/// Narrow the release assets to those in its run manifest.
+///
+/// Releases made before manifests existed keep every asset.
+async fn attach_manifest(mut info: RcReleaseInfo) -> Result<RcReleaseInfo> {
+    let Some(asset) = info.assets.iter().find(|a| a.name == manifest::FILE_NAME) else {
+        tracing::warn!(
+            "{} has no {}; using every release asset",
+            info.tag,
+            manifest::FILE_NAME
+        );
+        return Ok(info);
+    };
+    let resp = net::client()?.get(&asset.download_url).send().await?;
+    if !resp.status().is_success() {
+        bail!(
+            "failed to download {} for {}: {}",
+            manifest::FILE_NAME,
+            info.tag,
+            resp.status()
+        );
+    }
+    let run: RunManifest = serde_json::from_slice(&resp.bytes().await?)
+        .with_context(|| format!("invalid {} on {}", manifest::FILE_NAME, info.tag))?;
+    run.restrict(&mut info)?;
+    Ok(info)
+}
+
+/// Download the run manifest of the `tag` release and its detached signature into `dir`.
+///
+/// Returns both paths, or `None` when the release carries no `manifest.json.asc`.
+pub async fn download_signed_manifest(
+    ctx: &InferredContext,
+    tag: &str,
+    dir: &Path,
+) -> Result<Option<(PathBuf, PathBuf)>> {
+    let release = github::client()?
+        .repos(&ctx.repo_owner, &ctx.repo_name)
+        .releases()
+        .get_by_tag(tag)
+        .await
+        .with_context(|| format!("failed to load GitHub release for {}", tag))?;
+    let find = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name == name)
+            .map(|a| RcAsset {
+                name: a.name.clone(),
+                download_url: a.browser_download_url.to_string(),
+                size: a.size as u64,
+                sha512: None,
+            })
+    };
+    let (Some(manifest), Some(signature)) = (
+        find(manifest::FILE_NAME),
+        find(&format!("{}.asc", manifest::FILE_NAME)),
+    ) else {
+        return Ok(None);
+    };
+    let client = net::client()?;
+    async_fs::create_dir_all(dir).await?;
+    let manifest_path = dir.join(&manifest.name);
+    let signature_path = dir.join(&signature.name);
+    download_to(&client, &manifest, &manifest_path).await?;
+    download_to(&client, &signature, &signature_path).await?;
+    Ok(Some((manifest_path, signature_path)))
 }
 
-fn select_rc_release(releases: &[Release]) -> Result<Option<RcReleaseInfo>> {
+fn select_rc_release(releases: &[Release], tags: &TagFormat) -> Result<Option<RcReleaseInfo>> {
     for release in releases {
-        if let Some(info) = try_build_rc_release(release)? {
+        if let Some(info) = try_build_rc_release(release, tags)? {
             return Ok(Some(info));
         }
     }
     Ok(None)
 }
 
-fn try_build_rc_release(release: &Release) -> Result<Option<RcReleaseInfo>> {
+fn try_build_rc_release(release: &Release, tags: &TagFormat) -> Result<Option<RcReleaseInfo>> {
     if release.draft {
         return Ok(None);
     }
     let tag = release.tag_name.clone();
-    let rc_re = Regex::new(r"^v(?P<version>\d+\.\d+\.\d+)-rc\.(?P<rc>\d+)$").unwrap();
-    let caps = match rc_re.captures(&tag) {
-        Some(caps) => caps,
-        None => return Ok(None),
+    let Some((version, rc_number)) = tags.parse_rc(&tag) else {
+        return Ok(None);
     };
-    let version_str = caps
-        .name("version")
-        .map(|m| m.as_str())
-        .context("rc capture missing version")?;
-    let rc_number: u32 = caps
-        .name("rc")
-        .map(|m| m.as_str())
-        .context("rc capture missing rc number")?
-        .parse()?;
-    let version = Version::parse(version_str)?;
 
     let assets = release
         .assets
@@ -114,6 +262,7 @@ fn try_build_rc_release(release: &Release) -> Result<Option<RcReleaseInfo>> {
             name: asset.name.clone(),
             download_url: asset.browser_download_url.to_string(),
             size: asset.size as u64,
+            sha512: None,
         })
         .collect();
 
@@ -122,22 +271,189 @@ fn try_build_rc_release(release: &Release) -> Result<Option<RcReleaseInfo>> {
         version,
         rc_number,
         assets,
+        vote: release.body.as_deref().and_then(VoteOutcome::recorded),
     }))
 }
 
-pub async fn download_assets(info: &RcReleaseInfo, dir: &Path) -> Result<Vec<PathBuf>> {
-    let client = reqwest::Client::new();
+/// Download release assets into `dir`, reusing cached copies whose digest matches the
+/// run manifest or the published `.sha512` companion. Downloads are verified against
+/// that digest when present.
+#[tracing::instrument(skip_all)]
+pub async fn download_assets(
+    info: &RcReleaseInfo,
+    dir: &Path,
+    cache: &AssetCache,
+) -> Result<Vec<PathBuf>> {
+    let client = net::client()?;
+    async_fs::create_dir_all(dir).await?;
+
+    // Checksums are tiny and always fetched fresh; they tell us which cached objects apply.
+    let mut digests = HashMap::new();
+    for asset in info.assets.iter().filter(|a| a.is_checksum()) {
+        let target = dir.join(&asset.name);
+        cancel::abortable(
+            &format!("downloading {}", asset.name),
+            download_to(&client, asset, &target),
+        )
+        .await?;
+        if let Some(digest) = &asset.sha512 {
+            ensure_digest(&asset.name, &target, digest).await?;
+        }
+        let base = asset.name.trim_end_matches(".sha512");
+        let content = async_fs::read_to_string(&target).await?;
+        if let Some(digest) = checksum::parse_sha512(&content, base) {
+            digests.insert(base.to_string(), digest);
+        }
+    }
+
+    for asset in info.assets.iter().filter(|a| !a.is_checksum()) {
+        let target = dir.join(&asset.name);
+        let expected = asset.sha512.as_ref().or_else(|| digests.get(&asset.name));
+        // The run directory is shared, so what `prerelease` packaged here is reused as is.
+        if let Some(digest) = expected
+            && async_fs::try_exists(&target).await?
+            && checksum::sha512_file(&target).await? == *digest
+        {
+            tracing::debug!("assets: reusing {}", target.display());
+            continue;
+        }
+        if let Some(digest) = expected
+            && cache.materialize(digest, &target).await?
+        {
+            tracing::debug!("assets: cache hit for {}", asset.name);
+            continue;
+        }
+        cancel::abortable(
+            &format!("downloading {}", asset.name),
+            download_to(&client, asset, &target),
+        )
+        .await?;
+        if let Some(digest) = expected {
+            ensure_digest(&asset.name, &target, digest).await?;
+            cache.insert(digest, &target).await?;
+        }
+    }
+
+    Ok(info.assets.iter().map(|a| dir.join(&a.name)).collect())
+}
+
+/// Download every asset of `info` into `dir` and check each against its `sha512`,
+/// ignoring files already there and the asset cache, so the result reflects what GitHub
+/// serves.
+pub async fn download_fresh(info: &RcReleaseInfo, dir: &Path) -> Result<()> {
+    let client = net::client()?;
     async_fs::create_dir_all(dir).await?;
-    let mut paths = Vec::new();
     for asset in &info.assets {
         let target = dir.join(&asset.name);
-        let resp = client.get(&asset.download_url).send().await?;
-        if !resp.status().is_success() {
-            bail!("failed to download {}: {}", asset.name, resp.status());
+        cancel::abortable(
+            &format!("downloading {}", asset.name),
+            download_to(&client, asset, &target),
+        )
+        .await?;
+        if let Some(digest) = &asset.sha512 {
+            ensure_digest(&asset.name, &target, digest).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn ensure_digest(name: &str, path: &Path, expected: &str) -> Result<()> {
+    let actual = checksum::sha512_file(path).await?;
+    if actual != expected {
+        bail!(
+            "sha512 mismatch for {}: expected {}, downloaded {}",
+            name,
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
+
+async fn download_to(client: &reqwest::Client, asset: &RcAsset, target: &Path) -> Result<()> {
+    let resp = client.get(&asset.download_url).send().await?;
+    if !resp.status().is_success() {
+        bail!("failed to download {}: {}", asset.name, resp.status());
+    }
+    let bytes = resp.bytes().await?;
+    async_fs::write(target, &bytes).await?;
+    Ok(())
+}
+
+/// Record `outcome` as the first line of the `tag` release notes, replacing an earlier
+/// record of the same outcome. Returns whether the notes changed.
+pub async fn record_vote(
+    ctx: &InferredContext,
+    tag: &str,
+    outcome: VoteOutcome,
+    summary: &str,
+    audit: &AuditLog,
+) -> Result<bool> {
+    let gh = github::client()?;
+    let repos = gh.repos(&ctx.repo_owner, &ctx.repo_name);
+    let releases = repos.releases();
+    let release = releases
+        .get_by_tag(tag)
+        .await
+        .with_context(|| format!("failed to load GitHub release for {}", tag))?;
+    let old = release.body.unwrap_or_default();
+    let notes = vote_notes(&old, outcome, summary)?;
+    if notes == old {
+        return Ok(false);
+    }
+    releases
+        .update(release.id.into_inner())
+        .body(&notes)
+        .send()
+        .await
+        .with_context(|| format!("failed to update GitHub release {}", tag))?;
+    audit.record(AuditAction::ReleaseEdited, tag).await;
+    Ok(true)
+}
+
+fn vote_notes(old: &str, outcome: VoteOutcome, summary: &str) -> Result<String> {
+    let rest = match VoteOutcome::recorded(old) {
+        Some(recorded) if recorded != outcome => {
+            bail!("the vote is already recorded as {}", recorded)
         }
-        let bytes = resp.bytes().await?;
-        async_fs::write(&target, &bytes).await?;
-        paths.push(target);
+        Some(_) => old
+            .split_once('\n')
+            .map_or("", |(_, rest)| rest.trim_start_matches('\n')),
+        None => old,
+    };
+    let line = format!("{}: {}", outcome.marker(), summary);
+    Ok(if rest.is_empty() {
+        line
+    } else {
+        format!("{}\n\n{}", line, rest)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vote_outcome_is_recorded_once_at_the_top_of_the_notes() {
+        let notes = vote_notes("rc notes", VoteOutcome::Passed, "+1 binding: 3").unwrap();
+        assert_eq!(notes, "> **Vote passed**: +1 binding: 3\n\nrc notes");
+        assert_eq!(VoteOutcome::recorded(&notes), Some(VoteOutcome::Passed));
+        assert_eq!(VoteOutcome::recorded("rc notes"), None);
+
+        let recount = vote_notes(&notes, VoteOutcome::Passed, "+1 binding: 4").unwrap();
+        assert_eq!(recount, "> **Vote passed**: +1 binding: 4\n\nrc notes");
+        assert!(vote_notes(&notes, VoteOutcome::Failed, "vetoed").is_err());
+        assert_eq!(
+            vote_notes("", VoteOutcome::Failed, "vetoed").unwrap(),
+            "> **Vote failed**: vetoed"
+        );
+    }
+
+    #[test]
+    fn human_size_uses_binary_units() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(3 * 1024 * 1024 + 200 * 1024), "3.2 MiB");
+        assert_eq!(human_size(5 << 40), "5.0 TiB");
     }
-    Ok(paths)
 }
@@ -1,34 +1,67 @@
+use std::borrow::Cow;
 use std::path::Path;
+use std::time::Duration;
 
 use anyhow::{Context, Result, anyhow, bail};
 use git2::{ObjectType, Oid, Repository};
 use serde::Serialize;
-use tera::{Context as TeraContext, Tera};
+use tera::Context as TeraContext;
 use tokio::process::Command;
 
-use crate::discussion;
+use crate::artifacts;
+use crate::asf;
+use crate::asset_cache::AssetCache;
+use crate::audit::{AuditAction, AuditLog};
+use crate::cancel;
+use crate::component;
+use crate::discussion::{self, Draft};
+use crate::downloads::{self, DownloadLink};
+use crate::feed;
+use crate::git_identity::GitIdentity;
 use crate::github;
 use crate::infer::InferredContext;
-use crate::rc_release::{RcReleaseInfo, download_assets, fetch_latest_rc_release};
-use crate::versioning::rc::upload_assets_with_retry;
-use crate::versioning::{Plan, compute_plan};
-use reqwest::StatusCode;
+use crate::milestones;
+use crate::notify::{self, Milestone, Notice};
+use crate::pr_labels;
+use crate::promote;
+use crate::rc_release::{RcReleaseInfo, VoteOutcome, download_assets, fetch_latest_rc_release};
+use crate::release_gate;
+use crate::released::{ReleasedCrate, crates_released_by};
+use crate::shipped_prs;
+use crate::sync;
+use crate::templates::Templates;
+use crate::timeline::{self, Phase};
+use crate::token_scope::{self, Permission};
+use crate::translate::{self, Announcement};
+use crate::versioning::rc::{self, upload_assets_with_retry};
+use crate::versioning::{EntryFormat, Plan, compute_plan};
+use crate::vote;
+use crate::website;
 
-const RELEASE_TEMPLATE: &str = include_str!("../templates/release.md");
+pub struct ReleaseOptions {
+    pub dry_run: bool,
+    /// Create the GitHub Release as a draft and stop before announcing.
+    pub draft: bool,
+    /// Publish the draft left by a draft run, then finish the release.
+    pub finalize: bool,
+    /// Release although no vote result is recorded on the rc.
+    pub force: bool,
+}
 
-pub async fn run_release(ctx: &InferredContext, dry_run: bool) -> Result<()> {
+pub async fn run_release(ctx: &InferredContext, opts: ReleaseOptions) -> Result<()> {
     if !github::has_token() {
         bail!("missing ASFSHIP_GITHUB_TOKEN for release command");
     }
 
     let repo = Repository::discover(&ctx.repo_root)?;
-    let plan = compute_plan(&repo, ctx)?;
+    let release = fetch_latest_rc_release(ctx).await?;
+    let vote = check_vote(&release, opts.force);
+    let stable_tag = release.stable_tag(&ctx.config.tags());
+    let plan_ctx = plan_context(ctx, &repo, &stable_tag)?;
+    let plan = pr_labels::apply(ctx, compute_plan(&repo, &plan_ctx)?).await?;
     if plan.changed_count() == 0 {
         bail!("no changed crates detected; nothing to release");
     }
-
-    let release = fetch_latest_rc_release(&ctx.repo_owner, &ctx.repo_name).await?;
-    let stable_tag = release.stable_tag();
     let rc_tag_ref = format!("refs/tags/{}", release.tag);
     let rc_obj = repo
         .revparse_single(&rc_tag_ref)
@@ -37,14 +70,27 @@ pub async fn run_release(ctx: &InferredContext, dry_run: bool) -> Result<()> {
         .peel_to_commit()
         .context("rc tag does not point to a commit")?;
 
-    let summaries = collect_summaries(&plan);
+    let published = crates_released_by(&repo, ctx, &release.tag, &release.version)?;
+    let summaries = collect_summaries(&plan, &published);
+    let links = release_links(ctx, &release);
 
-    if dry_run {
+    let templates = Templates::load(&ctx.repo_root)?;
+    let front = discussion::template_front_matter(ctx, &templates, "release");
+    let gate = release_gate::check(ctx, &rc_commit.id().to_string()).await;
+    let draft = !opts.finalize && (opts.draft || ctx.config.release.draft);
+    if opts.dry_run {
+        if let Err(err) = &vote {
+            println!("release: {}", err);
+        }
+        if let Err(err) = &gate {
+            println!("release: {}", err);
+        }
         println!(
-            "release: dry-run (rc_tag={} stable_tag={} crates={})",
+            "release: dry-run (rc_tag={} stable_tag={} crates={}{})",
             release.tag,
             stable_tag,
-            summaries.len()
+            summaries.len(),
+            front.summary()
         );
         for summary in &summaries {
             println!(
@@ -52,124 +98,507 @@ pub async fn run_release(ctx: &InferredContext, dry_run: bool) -> Result<()> {
                 summary.name, summary.old_version, summary.new_version
             );
         }
+        for link in &links {
+            println!("  download: {}", link.url);
+        }
+        let exclude = promote::exclude_patterns(&ctx.config.release.exclude_assets)?;
+        let names: Vec<&str> = release.assets.iter().map(|a| a.name.as_str()).collect();
+        let kept = promote::final_names(&names, &exclude)?;
+        for name in &names {
+            match kept.iter().find(|(rc_name, _)| rc_name == name) {
+                Some((_, final_name)) => println!("  asset: {} -> {}", name, final_name),
+                None => println!("  asset: {} (excluded)", name),
+            }
+        }
+        if ctx.config.release.promote_dist {
+            println!("  dist/release: {}", sync::release_dir_url(ctx, &release));
+        }
+        if draft {
+            println!(
+                "  github release: draft; stops for review until `asfship release --finalize`"
+            );
+        }
+        let version = release.base_version_string();
+        let entry = feed::entry(ctx, &version, &stable_tag, &plan, &links, None);
+        feed::describe(&ctx.config.release.feed, &entry);
+        if let Err(err) = website::describe(ctx, &entry).await {
+            println!("release: website: {:#}", err);
+        }
+        shipped_prs::describe(ctx, &plan, &stable_tag);
+        milestones::describe(ctx, &release.version);
+        translate::describe(ctx);
         return Ok(());
     }
 
-    ensure_tag_absent(&repo, &stable_tag)?;
-    create_stable_tag(&repo, &stable_tag, rc_commit.id()).await?;
+    vote?;
+    gate?;
+    let website = &ctx.config.release.website;
+    let mut needed = vec![Permission::Contents, Permission::Discussions];
+    if (website.pull_request && !website.files.is_empty())
+        || ctx.config.release.pull_requests.enabled()
+    {
+        needed.push(Permission::PullRequests);
+    }
+    if ctx.config.release.milestones.close {
+        needed.push(Permission::Issues);
+    }
+    token_scope::verify(ctx, &needed).await?;
+    let audit = AuditLog::open(ctx).await;
+    // Every step below checks what an earlier, failed run already did, so re-running
+    // `release` continues where that run stopped.
+    let identity = GitIdentity::resolve(&ctx.repo_root, &ctx.config)?;
+    if stable_tag_exists(&repo, &stable_tag, rc_commit.id())? {
+        println!(
+            "release: {} already points at {}",
+            stable_tag,
+            rc_commit.id()
+        );
+    } else {
+        cancel::checkpoint("creating the release tag")?;
+        let rm = asf::release_manager(&ctx.config.asf)?;
+        let signing_key = rm.as_ref().and_then(|rm| rm.gpg_fingerprint.as_deref());
+        create_stable_tag(
+            &repo,
+            &stable_tag,
+            rc_commit.id(),
+            identity.clone(),
+            signing_key,
+        )
+        .await?;
+        audit.record(AuditAction::TagCreated, &stable_tag).await;
+    }
+    cancel::checkpoint("pushing the release tag")?;
+    // Pushing a tag origin already has at the same object is a no-op.
     push_tag(&ctx.repo_root, &stable_tag).await?;
+    audit.record(AuditAction::TagPushed, &stable_tag).await;
 
     let gh = github::client()?;
     let repos_api = gh.repos(ctx.repo_owner.clone(), ctx.repo_name.clone());
     let releases_api = repos_api.releases();
-    match releases_api.get_by_tag(&stable_tag).await {
-        Ok(_) => bail!("GitHub release already exists for {}", stable_tag),
-        Err(err) => {
-            if !is_not_found(&err) {
-                return Err(err.into());
-            }
+    let gh_release = match github::release_by_tag(&gh, &ctx.repo_owner, &ctx.repo_name, &stable_tag)
+        .await?
+    {
+        Some(existing) if existing.draft && !draft && !opts.finalize => bail!(
+            "GitHub release {} is a draft awaiting review at {}; publish it with `asfship release --finalize`",
+            stable_tag,
+            existing.html_url
+        ),
+        Some(existing) => {
+            let state = if existing.draft { "draft " } else { "" };
+            println!(
+                "release: {}GitHub release {} already exists",
+                state, stable_tag
+            );
+            existing
+        }
+        None if opts.finalize => bail!(
+            "no draft GitHub release for {}; run `asfship release --draft` first",
+            stable_tag
+        ),
+        None => {
+            cancel::checkpoint("creating the GitHub release")?;
+            let (name, body) = component::github_release(ctx, &plan, &stable_tag)?;
+            let links = links_section(ctx, &release, &summaries);
+            let body = if body.is_empty() {
+                links
+            } else {
+                format!("{}\n\n{}", body, links)
+            };
+            let created = releases_api
+                .create(&stable_tag)
+                .name(&name)
+                .prerelease(false)
+                .draft(draft)
+                .body(&body)
+                .send()
+                .await?;
+            audit.record(AuditAction::ReleaseCreated, &stable_tag).await;
+            created
         }
+    };
+
+    let asset_dir = artifacts::prepare(ctx, &release.tag).await?;
+    let downloaded = download_assets(
+        &release,
+        &asset_dir,
+        &AssetCache::new(&artifacts::root(ctx)),
+    )
+    .await?;
+    let files = promote::stable_assets(ctx, &stable_tag, &downloaded).await?;
+    upload_assets_with_retry(&ctx.repo_owner, &ctx.repo_name, &stable_tag, &files, &audit).await?;
+    if draft {
+        println!(
+            "release: draft GitHub release {} is ready for review at {}",
+            stable_tag, gh_release.html_url
+        );
+        println!(
+            "next: check its notes and assets, then `asfship release --finalize` publishes it and posts the announcement"
+        );
+        return Ok(());
+    }
+    if gh_release.draft {
+        cancel::checkpoint("publishing the GitHub release")?;
+        releases_api
+            .update(gh_release.id.into_inner())
+            .draft(false)
+            .send()
+            .await
+            .with_context(|| format!("failed to publish the draft release {}", stable_tag))?;
+        audit.record(AuditAction::ReleaseEdited, &stable_tag).await;
+        println!("release: published draft GitHub release {}", stable_tag);
     }
 
-    let _ = releases_api
-        .create(&stable_tag)
-        .name(&stable_tag)
-        .prerelease(false)
-        .draft(false)
-        .body("")
-        .send()
-        .await?;
+    if ctx.config.release.promote_dist {
+        sync::promote_to_release(ctx, &release, &stable_tag, &downloaded, &audit).await?;
+        timeline::record(
+            &artifacts::root(ctx),
+            &release.version,
+            Phase::Promoted,
+            &stable_tag,
+        )
+        .await;
+    }
 
-    let asset_dir = ctx
-        .repo_root
-        .join("target")
-        .join("asfship")
-        .join("release")
-        .join(stable_tag.replace('/', "_"));
-    let files = download_assets(&release, &asset_dir).await?;
-    upload_assets_with_retry(&ctx.repo_owner, &ctx.repo_name, &stable_tag, &files).await?;
-
-    let body = render_release_body(ctx, &release, &summaries)?;
-    let title = format!(
-        "{} {} released",
-        ctx.repo_name,
-        release.base_version_string()
+    let highlights = component::highlights(&EntryFormat::new(&ctx.config.changelog)?, &plan)?;
+    let body = render_release_body(ctx, &templates, &release, &summaries, &highlights, &links)?;
+    match ctx.config.release.downloads_timeout_minutes {
+        _ if !ctx.config.release.promote_dist => tracing::warn!(
+            "release: release.promote_dist is off, so the announcement links the rc on dist/dev; update them once the artifacts reach dist/release"
+        ),
+        Some(minutes) => {
+            downloads::wait_until_available(&links, Duration::from_secs(minutes * 60)).await?
+        }
+        None => tracing::warn!(
+            "release: downloads.apache.org links not verified; set release.downloads_timeout_minutes to wait for them"
+        ),
+    }
+    let title = release_title(ctx, &release);
+    let discussion_url =
+        match discussion::find_by_title(&gh, &ctx.repo_owner, &ctx.repo_name, &title).await? {
+            Some(existing) => {
+                println!(
+                    "release: announcement already posted at {}",
+                    existing.html_url
+                );
+                existing.html_url
+            }
+            None => {
+                let (_, created) =
+                    discussion::create(&gh, &ctx.repo_owner, &ctx.repo_name, &title, &body, &front)
+                        .await?;
+                audit
+                    .record(AuditAction::DiscussionCreated, &created.html_url)
+                    .await;
+                timeline::record(
+                    &artifacts::root(ctx),
+                    &release.version,
+                    Phase::Released,
+                    &stable_tag,
+                )
+                .await;
+                let announcement = Announcement {
+                    tag: &stable_tag,
+                    title: &title,
+                    body: &body,
+                };
+                translate::publish(ctx, &announcement, &created, &audit).await;
+                notify::send(
+                    ctx,
+                    Notice {
+                        milestone: Milestone::ReleaseCompleted,
+                        tag: stable_tag.clone(),
+                        message: format!(
+                            "{} {} released",
+                            ctx.repo_name,
+                            release.base_version_string()
+                        ),
+                        url: Some(created.html_url.clone()),
+                    },
+                )
+                .await;
+                created.html_url
+            }
+        };
+    let entry = feed::entry(
+        ctx,
+        &release.base_version_string(),
+        &stable_tag,
+        &plan,
+        &links,
+        Some(&discussion_url),
     );
-    let category = discussion::fetch_default_category(&gh, &ctx.repo_owner, &ctx.repo_name).await?;
-    let payload = discussion::CreateDiscussionPayload {
-        title: &title,
-        body: &body,
-        category_id: category.id,
-    };
-    let discussion: discussion::DiscussionResponse = gh
-        .post(
-            format!("repos/{}/{}/discussions", ctx.repo_owner, ctx.repo_name),
-            Some(&payload),
-        )
-        .await?;
+    // The release is public at this point; a feed that fails to update is fixed by hand.
+    if let Err(err) = feed::publish(ctx, &entry, &identity, &audit).await {
+        tracing::warn!(
+            "feed: {:#}; add {} to the release feed manually",
+            err,
+            entry.version
+        );
+    }
+    if let Err(err) = website::publish(ctx, &entry, &identity, &audit).await {
+        tracing::warn!(
+            "website: {:#}; update the website for {} manually",
+            err,
+            entry.version
+        );
+    }
+
+    shipped_prs::publish(ctx, &plan, &stable_tag, &audit).await;
+    milestones::publish(ctx, &release.version, &audit).await;
 
     println!(
         "release: completed (stable_tag={} discussion={})",
-        stable_tag, discussion.html_url
+        stable_tag, discussion_url
     );
+    timeline::print_release_summary(&artifacts::root(ctx), &release.version).await;
 
     Ok(())
 }
 
+/// Whether the vote clears `release` for promotion. Only a vote `result` recorded as
+/// passed does; `force` stands in for a result tallied outside asfship, but never
+/// overrides a failed vote.
+fn check_vote(release: &RcReleaseInfo, force: bool) -> Result<()> {
+    match release.vote {
+        Some(VoteOutcome::Passed) => Ok(()),
+        Some(VoteOutcome::Failed) => bail!(
+            "the vote on {} failed; cut a new rc with `asfship prerelease`",
+            release.tag
+        ),
+        None if force => {
+            tracing::warn!(
+                "release: no vote result recorded on {}; forced",
+                release.tag
+            );
+            eprintln!(
+                "warning: no vote result is recorded on {}; releasing because of --force",
+                release.tag
+            );
+            Ok(())
+        }
+        None => bail!(
+            "no vote result is recorded on {}; run `asfship result` once the vote closes, or pass --force",
+            release.tag
+        ),
+    }
+}
+
+/// Render the release announcement for the latest rc without tagging or posting.
+pub async fn draft(ctx: &InferredContext) -> Result<Draft> {
+    let repo = Repository::discover(&ctx.repo_root)?;
+    let release = fetch_latest_rc_release(ctx).await?;
+    let plan_ctx = plan_context(ctx, &repo, &release.stable_tag(&ctx.config.tags()))?;
+    let plan = pr_labels::apply(ctx, compute_plan(&repo, &plan_ctx)?).await?;
+    if plan.changed_count() == 0 {
+        bail!("no changed crates detected; nothing to release");
+    }
+    let templates = Templates::load(&ctx.repo_root)?;
+    let body = render_release_body(
+        ctx,
+        &templates,
+        &release,
+        &collect_summaries(
+            &plan,
+            &crates_released_by(&repo, ctx, &release.tag, &release.version)?,
+        ),
+        &component::highlights(&EntryFormat::new(&ctx.config.changelog)?, &plan)?,
+        &release_links(ctx, &release),
+    )?;
+    Ok(Draft {
+        title: release_title(ctx, &release),
+        front: discussion::template_front_matter(ctx, &templates, "release"),
+        body,
+        comments: Vec::new(),
+    })
+}
+
+pub(crate) fn release_title(ctx: &InferredContext, release: &RcReleaseInfo) -> String {
+    format!(
+        "{} {} released",
+        ctx.repo_name,
+        release.base_version_string()
+    )
+}
+
+/// Where the release's files are served. Only `release.promote_dist` puts them on
+/// downloads.apache.org under their final names; otherwise the links stay on the
+/// dist/dev directory of the voted rc.
+fn release_links(ctx: &InferredContext, release: &RcReleaseInfo) -> Vec<DownloadLink> {
+    let asset_names: Vec<String> = release.assets.iter().map(|a| a.name.clone()).collect();
+    if ctx.config.release.promote_dist {
+        downloads::download_links(
+            &asf::project_name(ctx),
+            &release.base_version_string(),
+            &asset_names,
+        )
+    } else {
+        downloads::rc_links(&vote::svn_url(ctx, release), &asset_names)
+    }
+}
+
+/// Directory holding every file of the release, matching `release_links`.
+fn downloads_dir(ctx: &InferredContext, release: &RcReleaseInfo) -> String {
+    if ctx.config.release.promote_dist {
+        downloads::downloads_dir_url(&asf::project_name(ctx), &release.base_version_string())
+    } else {
+        format!("{}/", vote::svn_url(ctx, release))
+    }
+}
+
 #[derive(Serialize)]
 struct ReleaseCrateSummary {
     name: String,
     old_version: String,
     new_version: String,
+    /// Set for crates the release publishes to crates.io.
+    crates_io: Option<String>,
+    docs_rs: Option<String>,
+    /// The version those links point at, as tagged by the rc.
+    published_version: Option<String>,
 }
 
-fn collect_summaries(plan: &Plan) -> Vec<ReleaseCrateSummary> {
+/// One summary per planned crate, linked to crates.io and docs.rs when it is among the
+/// `published` crates of the release.
+fn collect_summaries(plan: &Plan, published: &[ReleasedCrate]) -> Vec<ReleaseCrateSummary> {
     let mut result = Vec::new();
     for (name, crate_plan) in plan.iter() {
+        let version = published
+            .iter()
+            .find(|c| c.name == *name)
+            .map(|c| c.version.clone());
         result.push(ReleaseCrateSummary {
             name: name.clone(),
             old_version: crate_plan.previous_version().to_string(),
             new_version: crate_plan.new_version().to_string(),
+            crates_io: version
+                .as_deref()
+                .map(|v| downloads::crates_io_url(name, v)),
+            docs_rs: version.as_deref().map(|v| downloads::docs_rs_url(name, v)),
+            published_version: version,
         });
     }
     result
 }
 
+/// Where the release can be found, appended to the stable GitHub Release body so it
+/// points at the same canonical locations as the announcement.
+fn links_section(
+    ctx: &InferredContext,
+    release: &RcReleaseInfo,
+    crates: &[ReleaseCrateSummary],
+) -> String {
+    let mut lines = vec![format!("- Source release: {}", downloads_dir(ctx, release))];
+    for c in crates {
+        if let (Some(version), Some(crates_io), Some(docs_rs)) =
+            (&c.published_version, &c.crates_io, &c.docs_rs)
+        {
+            lines.push(format!(
+                "- {} {}: [crates.io]({}), [docs.rs]({})",
+                c.name, version, crates_io, docs_rs
+            ));
+        }
+    }
+    format!("### Links\n\n{}", lines.join("\n"))
+}
+
 fn render_release_body(
     ctx: &InferredContext,
+    templates: &Templates,
     release: &RcReleaseInfo,
     crates: &[ReleaseCrateSummary],
+    highlights: &[String],
+    downloads: &[DownloadLink],
 ) -> Result<String> {
     let mut tera_ctx = TeraContext::new();
     tera_ctx.insert("repo", &ctx.repo_name);
     tera_ctx.insert("version", &release.base_version_string());
-    tera_ctx.insert("tag", &release.stable_tag());
+    tera_ctx.insert("tag", &release.stable_tag(&ctx.config.tags()));
     tera_ctx.insert("rc_tag", &release.tag);
     tera_ctx.insert("crates", crates);
-    Tera::one_off(RELEASE_TEMPLATE, &tera_ctx, false)
-        .map_err(|err| anyhow!("failed to render release template: {}", err))
+    tera_ctx.insert("highlights", highlights);
+    tera_ctx.insert("downloads", downloads);
+    tera_ctx.insert("downloads_url", &downloads_dir(ctx, release));
+    tera_ctx.insert("release_manager", &asf::release_manager(&ctx.config.asf)?);
+    templates.render("release", &tera_ctx)
 }
 
-fn ensure_tag_absent(repo: &Repository, tag: &str) -> Result<()> {
-    if repo.refname_to_id(&format!("refs/tags/{}", tag)).is_ok() {
-        bail!("stable tag already exists: {}", tag);
+/// Context to plan the release from. Once an earlier run created `stable_tag`, it is
+/// the last stable tag and planning from it would find nothing to release, so the
+/// stable tag before it is used instead.
+fn plan_context<'a>(
+    ctx: &'a InferredContext,
+    repo: &Repository,
+    stable_tag: &str,
+) -> Result<Cow<'a, InferredContext>> {
+    if ctx.last_stable_tag.as_deref() != Some(stable_tag) {
+        return Ok(Cow::Borrowed(ctx));
     }
-    Ok(())
+    let tags = ctx.config.tags();
+    let released = tags.parse_stable(stable_tag);
+    let mut previous: Option<(semver::Version, String)> = None;
+    for r in repo.references_glob("refs/tags/*")?.flatten() {
+        if let Some(name) = r.shorthand()
+            && let Some(version) = tags.parse_stable(name)
+            && released.as_ref().is_some_and(|r| version < *r)
+            && previous.as_ref().is_none_or(|(v, _)| version > *v)
+        {
+            previous = Some((version, name.to_string()));
+        }
+    }
+    let mut ctx = ctx.clone();
+    ctx.last_stable_tag = previous.map(|(_, name)| name);
+    Ok(Cow::Owned(ctx))
+}
+
+/// Whether an earlier run already created `tag` on `target`; on any other commit it is
+/// an error, since the release must be the voted rc.
+fn stable_tag_exists(repo: &Repository, tag: &str, target: Oid) -> Result<bool> {
+    let Ok(object) = repo.revparse_single(&format!("refs/tags/{}", tag)) else {
+        return Ok(false);
+    };
+    let commit = object
+        .peel_to_commit()
+        .with_context(|| format!("stable tag {} does not point to a commit", tag))?
+        .id();
+    if commit != target {
+        bail!(
+            "stable tag {} already exists on {}, not on the rc commit {}; delete it before releasing",
+            tag,
+            commit,
+            target
+        );
+    }
+    Ok(true)
 }
 
-async fn create_stable_tag(repo: &Repository, tag: &str, target: Oid) -> Result<()> {
-    let repo_path = repo
-        .path()
-        .parent()
-        .unwrap_or_else(|| Path::new("."))
-        .to_path_buf();
+#[tracing::instrument(skip_all)]
+/// Create the annotated stable `tag` on `target`, signed like rc tags when a
+/// `signing_key` (`asf.gpg_fingerprint`) is given.
+pub(crate) async fn create_stable_tag(
+    repo: &Repository,
+    tag: &str,
+    target: Oid,
+    identity: GitIdentity,
+    signing_key: Option<&str>,
+) -> Result<()> {
+    let repo_path = rc::reopen_path(repo);
+    if let Some(key) = signing_key {
+        let message = format!("asfship release {}", tag);
+        return rc::sign_tag(
+            &repo_path,
+            tag,
+            &target.to_string(),
+            &message,
+            &identity,
+            key,
+        )
+        .await;
+    }
     let tag_name = tag.to_string();
     tokio::task::spawn_blocking(move || {
-        let repo = Repository::discover(repo_path)?;
+        let repo = Repository::open(repo_path)?;
         let object = repo.find_object(target, Some(ObjectType::Commit))?;
-        let sig = repo
-            .signature()
-            .or_else(|_| git2::Signature::now("asfship", "asfship@users.noreply.github.com"))?;
+        let sig = identity.signature()?;
         let msg = format!("asfship release {}", tag_name);
         repo.tag(&tag_name, &object, &sig, &msg, true)?;
         Ok::<_, anyhow::Error>(())
@@ -179,28 +608,23 @@ async fn create_stable_tag(repo: &Repository, tag: &str, target: Oid) -> Result<
     Ok(())
 }
 
-async fn push_tag(repo_root: &Path, tag: &str) -> Result<()> {
-    let status = Command::new("git")
-        .arg("-C")
-        .arg(repo_root)
-        .arg("push")
-        .arg("origin")
-        .arg(format!("refs/tags/{}", tag))
-        .status()
-        .await?;
+#[tracing::instrument(skip_all)]
+pub(crate) async fn push_tag(repo_root: &Path, tag: &str) -> Result<()> {
+    let status = cancel::status(
+        Command::new("git")
+            .arg("-C")
+            .arg(repo_root)
+            .arg("push")
+            .arg("origin")
+            .arg(format!("refs/tags/{}", tag)),
+    )
+    .await?;
     if !status.success() {
         bail!("git push tag failed with status: {}", status);
     }
     Ok(())
 }
 
-fn is_not_found(err: &octocrab::Error) -> bool {
-    if let octocrab::Error::GitHub { source, .. } = err {
-        return source.status_code == StatusCode::NOT_FOUND;
-    }
-    false
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,13 +635,17 @@ mod tests {
 
     #[test]
     fn render_release_body_lists_crates() {
-        let ctx = InferredContext {
+        let mut ctx = InferredContext {
             repo_root: PathBuf::from("."),
             repo_owner: "apache".into(),
             repo_name: "foo".into(),
             crates: Vec::new(),
             main_crate: "foo".into(),
             last_stable_tag: Some("v0.1.0".into()),
+            since: None,
+            until: None,
+            config: Default::default(),
+            snapshot: None,
         };
         let release = RcReleaseInfo {
             tag: "v0.1.1-rc.1".into(),
@@ -227,16 +655,110 @@ mod tests {
                 name: "apache-foo-0.1.1-rc1-src.tar.gz".into(),
                 download_url: "https://example.com/tar".into(),
                 size: 10,
+                sha512: None,
             }],
+            vote: None,
         };
         let crates = vec![ReleaseCrateSummary {
             name: "foo".into(),
             old_version: "0.1.0".into(),
             new_version: "0.1.1".into(),
+            crates_io: Some(downloads::crates_io_url("foo", "0.1.1")),
+            docs_rs: Some(downloads::docs_rs_url("foo", "0.1.1")),
+            published_version: Some("0.1.1".into()),
         }];
 
-        let body = render_release_body(&ctx, &release, &crates).unwrap();
-        assert!(body.contains("foo: 0.1.0 → 0.1.1"));
+        let templates = Templates::load(&ctx.repo_root).unwrap();
+
+        // Without promotion the files only exist on dist/dev, under their rc names.
+        let body = render_release_body(
+            &ctx,
+            &templates,
+            &release,
+            &crates,
+            &[],
+            &release_links(&ctx, &release),
+        )
+        .unwrap();
+        assert!(!body.contains("Highlights"));
+        assert!(
+            body.contains("Downloads (https://dist.apache.org/repos/dist/dev/foo/foo-0.1.1-rc1/):")
+        );
+        assert!(body.contains(
+            "https://dist.apache.org/repos/dist/dev/foo/foo-0.1.1-rc1/apache-foo-0.1.1-rc1-src.tar.gz"
+        ));
+        assert!(!body.contains("downloads.apache.org"));
+
+        ctx.config.release.promote_dist = true;
+        let body = render_release_body(
+            &ctx,
+            &templates,
+            &release,
+            &crates,
+            &["Streaming reads".to_string()],
+            &release_links(&ctx, &release),
+        )
+        .unwrap();
+        assert!(
+            body.contains("Highlights:\n- Streaming reads\n"),
+            "{}",
+            body
+        );
+        assert!(body.contains(
+            "foo: 0.1.0 → 0.1.1 (https://crates.io/crates/foo/0.1.1, docs: https://docs.rs/foo/0.1.1)"
+        ));
+        assert!(body.contains("Downloads (https://downloads.apache.org/foo/0.1.1/):"));
+        assert!(body.contains(
+            "https://www.apache.org/dyn/closer.lua/foo/0.1.1/apache-foo-0.1.1-src.tar.gz"
+        ));
         assert!(body.contains("v0.1.1"));
     }
+
+    #[test]
+    fn links_section_follows_promote_dist() {
+        let mut ctx = InferredContext {
+            repo_root: PathBuf::from("."),
+            repo_owner: "apache".into(),
+            repo_name: "foo".into(),
+            crates: Vec::new(),
+            main_crate: "foo".into(),
+            last_stable_tag: Some("v0.1.0".into()),
+            since: None,
+            until: None,
+            config: Default::default(),
+            snapshot: None,
+        };
+        let release = RcReleaseInfo {
+            tag: "v0.1.1-rc.1".into(),
+            version: Version::parse("0.1.1").unwrap(),
+            rc_number: 1,
+            assets: Vec::new(),
+            vote: None,
+        };
+        let crates = vec![ReleaseCrateSummary {
+            name: "foo".into(),
+            old_version: "0.1.0".into(),
+            new_version: "0.1.1".into(),
+            crates_io: Some(downloads::crates_io_url("foo", "0.1.1")),
+            docs_rs: Some(downloads::docs_rs_url("foo", "0.1.1")),
+            published_version: Some("0.1.1".into()),
+        }];
+
+        assert_eq!(
+            links_section(&ctx, &release, &crates),
+            "### Links\n\n\
+             - Source release: https://dist.apache.org/repos/dist/dev/foo/foo-0.1.1-rc1/\n\
+             - foo 0.1.1: [crates.io](https://crates.io/crates/foo/0.1.1), [docs.rs](https://docs.rs/foo/0.1.1)"
+        );
+
+        ctx.config.release.promote_dist = true;
+        let links = links_section(&ctx, &release, &crates);
+        assert!(
+            links.starts_with(
+                "### Links\n\n- Source release: https://downloads.apache.org/foo/0.1.1/\n"
+            ),
+            "{}",
+            links
+        );
+    }
 }
@@ -0,0 +1,168 @@
+use anyhow::{Context, Result, bail};
+use octocrab::Octocrab;
+use serde::Deserialize;
+
+use crate::config::ReleaseGateConfig;
+use crate::github;
+use crate::infer::InferredContext;
+
+#[derive(Debug, Deserialize)]
+struct Deployment {
+    id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeploymentStatus {
+    state: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    html_url: String,
+    labels: Vec<Label>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Label {
+    name: String,
+}
+
+/// Refuse to promote `sha` until every approval in `[release.gate]` is in place.
+pub async fn check(ctx: &InferredContext, sha: &str) -> Result<()> {
+    let gate = &ctx.config.release.gate;
+    if gate.environment.is_none() && gate.label.is_none() {
+        return Ok(());
+    }
+    let gh = github::client()?;
+    if let Some(environment) = &gate.environment {
+        check_environment(&gh, ctx, environment, sha).await?;
+    }
+    if let Some(label) = &gate.label {
+        check_label(&gh, ctx, gate, label).await?;
+    }
+    Ok(())
+}
+
+/// Check the approvals that do not depend on the rc commit, for preflight: the environment
+/// exists and the tracking issue carries its label.
+pub async fn preflight(ctx: &InferredContext) -> Result<()> {
+    let gate = &ctx.config.release.gate;
+    if gate.environment.is_none() && gate.label.is_none() {
+        return Ok(());
+    }
+    let gh = github::client()?;
+    if let Some(environment) = &gate.environment {
+        let _: serde_json::Value = gh
+            .get(
+                format!(
+                    "/repos/{}/{}/environments/{}",
+                    ctx.repo_owner, ctx.repo_name, environment
+                ),
+                None::<&()>,
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "release gate: environment `{}` is missing or not readable",
+                    environment
+                )
+            })?;
+    }
+    if let Some(label) = &gate.label {
+        check_label(&gh, ctx, gate, label).await?;
+    }
+    Ok(())
+}
+
+/// Require a successful deployment of `sha` to `environment`.
+///
+/// Protected environments only let a deployment finish once a reviewer approves it.
+async fn check_environment(
+    gh: &Octocrab,
+    ctx: &InferredContext,
+    environment: &str,
+    sha: &str,
+) -> Result<()> {
+    let deployments: Vec<Deployment> = gh
+        .get(
+            format!("/repos/{}/{}/deployments", ctx.repo_owner, ctx.repo_name),
+            Some(&[
+                ("environment", environment),
+                ("sha", sha),
+                ("per_page", "100"),
+            ]),
+        )
+        .await
+        .with_context(|| format!("failed to list deployments to {}", environment))?;
+    let mut latest = None;
+    for deployment in &deployments {
+        let statuses: Vec<DeploymentStatus> = gh
+            .get(
+                format!(
+                    "/repos/{}/{}/deployments/{}/statuses",
+                    ctx.repo_owner, ctx.repo_name, deployment.id
+                ),
+                Some(&[("per_page", "1")]),
+            )
+            .await
+            .with_context(|| format!("failed to read deployment {}", deployment.id))?;
+        let Some(status) = statuses.into_iter().next() else {
+            continue;
+        };
+        if status.state == "success" {
+            tracing::info!(
+                "release: deployment {} to {} approved",
+                deployment.id,
+                environment
+            );
+            return Ok(());
+        }
+        latest.get_or_insert(status.state);
+    }
+    match latest {
+        Some(state) => bail!(
+            "release gate: the latest deployment of {} to environment `{}` is `{}`, not `success`; wait for a reviewer to approve it",
+            short_sha(sha),
+            environment,
+            state
+        ),
+        None => bail!(
+            "release gate: no deployment of {} to environment `{}`; run the approval workflow for the rc tag",
+            short_sha(sha),
+            environment
+        ),
+    }
+}
+
+async fn check_label(
+    gh: &Octocrab,
+    ctx: &InferredContext,
+    gate: &ReleaseGateConfig,
+    label: &str,
+) -> Result<()> {
+    let number = gate
+        .issue
+        .context("release.gate.label requires release.gate.issue")?;
+    let issue: Issue = gh
+        .get(
+            format!(
+                "/repos/{}/{}/issues/{}",
+                ctx.repo_owner, ctx.repo_name, number
+            ),
+            None::<&()>,
+        )
+        .await
+        .with_context(|| format!("failed to load tracking issue #{}", number))?;
+    if !issue.labels.iter().any(|l| l.name == label) {
+        bail!(
+            "release gate: tracking issue {} does not carry the `{}` label yet",
+            issue.html_url,
+            label
+        );
+    }
+    Ok(())
+}
+
+fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(12)]
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use git2::Repository;
+use serde::Serialize;
+
+use crate::infer::InferredContext;
+use crate::partial_clone;
+use crate::tags::TagFormat;
+
+/// A crate version shipped by a stable release tag.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ReleasedCrate {
+    pub name: String,
+    pub version: String,
+}
+
+/// Publishable crates whose manifest version changed between the previous stable tag and `version`.
+pub fn released_crates(
+    repo: &Repository,
+    ctx: &InferredContext,
+    version: &semver::Version,
+) -> Result<Vec<ReleasedCrate>> {
+    let tag = ctx.config.tags().stable(version);
+    crates_released_by(repo, ctx, &tag, version)
+}
+
+/// Like `released_crates`, with versions read from `tag` rather than the stable tag, so
+/// a release can name its crates from the rc tag before the stable tag exists.
+pub fn crates_released_by(
+    repo: &Repository,
+    ctx: &InferredContext,
+    tag: &str,
+    version: &semver::Version,
+) -> Result<Vec<ReleasedCrate>> {
+    let tags = ctx.config.tags();
+    let tree = repo
+        .revparse_single(&format!("refs/tags/{}", tag))
+        .with_context(|| format!("release tag {} not found", tag))?
+        .peel_to_tree()?;
+    let previous = previous_stable_tag(repo, &tags, version)?
+        .map(|t| {
+            repo.revparse_single(&format!("refs/tags/{}", t))
+                .and_then(|o| o.peel_to_tree())
+        })
+        .transpose()?;
+
+    let mut crates = Vec::new();
+    for c in ctx.crates.iter().filter(|c| c.publish) {
+        let rel = c
+            .manifest_path
+            .strip_prefix(&ctx.repo_root)
+            .unwrap_or(&c.manifest_path);
+        let Some(released) = manifest_version(repo, &tree, rel)? else {
+            continue;
+        };
+        let before = match &previous {
+            Some(prev) => manifest_version(repo, prev, rel)?,
+            None => None,
+        };
+        if before.as_deref() != Some(released.as_str()) {
+            crates.push(ReleasedCrate {
+                name: c.name.clone(),
+                version: released,
+            });
+        }
+    }
+    Ok(crates)
+}
+
+fn previous_stable_tag(
+    repo: &Repository,
+    tags: &TagFormat,
+    version: &semver::Version,
+) -> Result<Option<String>> {
+    let mut best: Option<semver::Version> = None;
+    for r in repo.references_glob("refs/tags/*")?.flatten() {
+        if let Some(name) = r.shorthand()
+            && let Some(v) = tags.parse_stable(name)
+            && v < *version
+            && best.as_ref().is_none_or(|b| v > *b)
+        {
+            best = Some(v);
+        }
+    }
+    Ok(best.map(|v| tags.stable(&v)))
+}
+
+fn manifest_version(
+    repo: &Repository,
+    tree: &git2::Tree<'_>,
+    rel: &Path,
+) -> Result<Option<String>> {
+    let Ok(entry) = tree.get_path(rel) else {
+        return Ok(None);
+    };
+    let blob = partial_clone::find_blob(repo, entry.id(), rel)?;
+    let content = std::str::from_utf8(blob.content())
+        .with_context(|| format!("{} is not UTF-8", rel.display()))?;
+    let doc: toml::Value =
+        toml::from_str(content).with_context(|| format!("failed to parse {}", rel.display()))?;
+    Ok(doc
+        .get("package")
+        .and_then(|p| p.get("version"))
+        .and_then(|v| v.as_str())
+        .map(str::to_string))
+}
@@ -0,0 +1,526 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use tera::Context as TeraContext;
+
+use crate::artifacts;
+use crate::asf::{self, AsfRole, ProjectRoster};
+use crate::audit::{AuditAction, AuditLog};
+use crate::cancel;
+use crate::config::VoteConfig;
+use crate::discussion;
+use crate::github;
+use crate::infer::InferredContext;
+use crate::rc_release::{
+    self, RcReleaseInfo, VoteOutcome, fetch_latest_rc_release, fetch_rc_release_by_tag,
+};
+use crate::templates::Templates;
+use crate::timeline;
+use crate::token_scope::{self, Permission};
+use crate::vote::{self, MIN_BINDING_VOTES};
+
+/// One vote cast on an rc: `<apache id>=<+1|0|-1>` on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vote {
+    pub voter: String,
+    pub value: i8,
+}
+
+impl FromStr for Vote {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (voter, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected <apache id>=<+1|0|-1>, got {:?}", s))?;
+        let value = match value.trim() {
+            "+1" | "1" => 1,
+            "0" | "+0" | "-0" => 0,
+            "-1" => -1,
+            other => bail!("vote of {} must be +1, 0, or -1, got {:?}", voter, other),
+        };
+        let voter = voter.trim().to_ascii_lowercase();
+        if voter.is_empty() {
+            bail!("expected <apache id>=<+1|0|-1>, got {:?}", s);
+        }
+        Ok(Self { voter, value })
+    }
+}
+
+/// A vote as the RESULT discussion lists it, with whether the roster makes it binding.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CountedVote {
+    pub voter: String,
+    /// `+1`, `0`, or `-1`.
+    pub vote: String,
+    pub binding: bool,
+}
+
+/// The votes cast on an rc and their tally.
+#[derive(Debug, Clone)]
+pub struct Counted {
+    pub tally: Tally,
+    pub votes: Vec<CountedVote>,
+}
+
+/// Votes cast on an rc, counted from the roster.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Tally {
+    /// +1 votes from PMC members.
+    pub binding: u32,
+    pub non_binding: u32,
+    /// -1 votes from PMC members.
+    pub against: u32,
+}
+
+impl Tally {
+    /// Count `votes`, binding when the roster lists the voter on the PMC. Other -1 votes
+    /// are listed but do not count against the release.
+    pub fn count(votes: &[Vote], roster: &ProjectRoster) -> Result<Counted> {
+        let mut tally = Self::default();
+        let mut counted: Vec<CountedVote> = Vec::with_capacity(votes.len());
+        for vote in votes {
+            if counted.iter().any(|c| c.voter == vote.voter) {
+                bail!(
+                    "{} voted more than once; pass one --vote per voter",
+                    vote.voter
+                );
+            }
+            let binding = roster.role_of(&vote.voter) == Some(AsfRole::Pmc);
+            match (vote.value, binding) {
+                (1, true) => tally.binding += 1,
+                (1, false) => tally.non_binding += 1,
+                (-1, true) => tally.against += 1,
+                _ => {}
+            }
+            counted.push(CountedVote {
+                voter: vote.voter.clone(),
+                vote: match vote.value {
+                    1 => "+1",
+                    -1 => "-1",
+                    _ => "0",
+                }
+                .to_string(),
+                binding,
+            });
+        }
+        Ok(Counted {
+            tally,
+            votes: counted,
+        })
+    }
+
+    /// Why the tally does not pass under `quota`, or `None` when it does.
+    fn shortfall(&self, quota: &Quota) -> Option<String> {
+        if self.binding < quota.required_binding {
+            Some(format!(
+                "fewer than {} binding +1 votes",
+                quota.required_binding
+            ))
+        } else if !quota.allow_negative && self.against > 0 {
+            Some("a binding -1 vote".to_string())
+        } else if self.binding <= self.against {
+            Some("no more binding +1 than -1 votes".to_string())
+        } else {
+            None
+        }
+    }
+}
+
+/// What a vote needs to pass, from `[vote] required_binding` and `allow_negative`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Quota {
+    required_binding: u32,
+    allow_negative: bool,
+}
+
+impl Quota {
+    fn new(cfg: &VoteConfig) -> Result<Self> {
+        let quota = Self::configured(cfg);
+        if quota.required_binding < MIN_BINDING_VOTES {
+            bail!(
+                "vote.required_binding = {} is below the {} binding +1 votes required by ASF policy",
+                quota.required_binding,
+                MIN_BINDING_VOTES
+            );
+        }
+        Ok(quota)
+    }
+
+    /// The quota as `cfg` states it, without checking it against ASF policy.
+    pub(crate) fn configured(cfg: &VoteConfig) -> Self {
+        Self {
+            required_binding: cfg.required_binding.unwrap_or(MIN_BINDING_VOTES),
+            allow_negative: cfg.allow_negative.unwrap_or(true),
+        }
+    }
+}
+
+impl fmt::Display for Quota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "at least {} binding +1 votes and ",
+            self.required_binding
+        )?;
+        if self.allow_negative {
+            f.write_str("more binding +1 than -1 votes")
+        } else {
+            f.write_str("no binding -1 votes")
+        }
+    }
+}
+
+impl fmt::Display for Tally {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "+1 binding: {}, +1 non-binding: {}, -1 binding: {}",
+            self.binding, self.non_binding, self.against
+        )
+    }
+}
+
+pub struct ResultOptions<'a> {
+    /// Decided from the tally and `[vote]` quota when not given.
+    pub outcome: Option<VoteOutcome>,
+    pub rc_tag: Option<&'a str>,
+    pub votes: Vec<Vote>,
+    pub reason: Option<&'a str>,
+    pub dry_run: bool,
+}
+
+/// Close the vote on an rc: record the outcome on its GitHub release, post the RESULT
+/// discussion, and point at what comes next (promotion, or a new rc).
+pub async fn run_result(ctx: &InferredContext, opts: ResultOptions<'_>) -> Result<()> {
+    if !github::has_token() {
+        bail!("missing ASFSHIP_GITHUB_TOKEN for result command");
+    }
+    let release = match opts.rc_tag {
+        Some(tag) => fetch_rc_release_by_tag(ctx, tag).await?,
+        None => fetch_latest_rc_release(ctx).await?,
+    };
+    let quota = Quota::new(&ctx.config.vote)?;
+    let roster = asf::load_roster(ctx).await?;
+    let ctx = &*asf::dev_list_from(ctx, &roster);
+    let counted = Tally::count(&opts.votes, &roster)?;
+    let tally = counted.tally;
+    let (outcome, reason) = reason(opts.outcome, &tally, &quota, opts.reason)?;
+    if let Some(recorded) = release.vote
+        && recorded != outcome
+    {
+        bail!(
+            "the vote on {} is already recorded as {}",
+            release.tag,
+            recorded
+        );
+    }
+    let summary = match &reason {
+        Some(reason) => format!("{} ({})", tally, reason),
+        None => tally.to_string(),
+    };
+
+    let gh = github::client()?;
+    let vote_discussion = discussion::find_by_title(
+        &gh,
+        &ctx.repo_owner,
+        &ctx.repo_name,
+        &vote::title(ctx, &release),
+    )
+    .await?;
+    let templates = Templates::load(&ctx.repo_root)?;
+    let front = discussion::template_front_matter(ctx, &templates, "result");
+    let title = title(ctx, &release);
+    let body = render_result(
+        ctx,
+        &templates,
+        &release,
+        outcome,
+        &counted,
+        reason.as_deref(),
+        vote_discussion.as_ref().map(|d| d.html_url.as_str()),
+    )?;
+
+    if opts.dry_run {
+        println!(
+            "result: dry-run (rc_tag={} outcome={} title={}{})",
+            release.tag,
+            outcome,
+            title,
+            front.summary()
+        );
+        println!("---\n{}", body);
+        print_next_steps(ctx, &release, outcome);
+        return Ok(());
+    }
+
+    token_scope::verify(ctx, &[Permission::Contents, Permission::Discussions]).await?;
+    let audit = AuditLog::open(ctx).await;
+    cancel::checkpoint("recording the vote result")?;
+    if rc_release::record_vote(ctx, &release.tag, outcome, &summary, &audit).await? {
+        println!("result: recorded {} on release {}", outcome, release.tag);
+    }
+    timeline::record_vote_closed(
+        &artifacts::root(ctx),
+        &release.version,
+        &release.tag,
+        outcome == VoteOutcome::Passed,
+    )
+    .await;
+
+    // A re-run after a partial failure reuses the discussion it already posted.
+    match discussion::find_by_title(&gh, &ctx.repo_owner, &ctx.repo_name, &title).await? {
+        Some(existing) => println!("result: already posted at {}", existing.html_url),
+        None => {
+            cancel::checkpoint("posting the result discussion")?;
+            let (category, created) =
+                discussion::create(&gh, &ctx.repo_owner, &ctx.repo_name, &title, &body, &front)
+                    .await?;
+            audit
+                .record(AuditAction::DiscussionCreated, &created.html_url)
+                .await;
+            if let Some(vote_discussion) = &vote_discussion {
+                let comment = format!("The vote has {}: {}", outcome, created.html_url);
+                discussion::post_comment(
+                    &gh,
+                    &ctx.repo_owner,
+                    &ctx.repo_name,
+                    vote_discussion.number,
+                    &comment,
+                )
+                .await?;
+                audit
+                    .record(AuditAction::DiscussionCommented, &vote_discussion.html_url)
+                    .await;
+            }
+            println!(
+                "result: discussion created (category={} url={})",
+                category.name, created.html_url
+            );
+        }
+    }
+    print_next_steps(ctx, &release, outcome);
+    Ok(())
+}
+
+/// Title of the RESULT discussion closing the vote on `release`.
+pub(crate) fn title(ctx: &InferredContext, release: &RcReleaseInfo) -> String {
+    format!("[RESULT] {}", vote::title(ctx, release))
+}
+
+/// Decide the outcome from the tally when none is claimed, check a claimed one against
+/// it, and settle the reason a failed vote gives.
+fn reason(
+    claimed: Option<VoteOutcome>,
+    tally: &Tally,
+    quota: &Quota,
+    given: Option<&str>,
+) -> Result<(VoteOutcome, Option<String>)> {
+    let shortfall = tally.shortfall(quota);
+    let outcome = claimed.unwrap_or(if shortfall.is_none() {
+        VoteOutcome::Passed
+    } else {
+        VoteOutcome::Failed
+    });
+    let reason = match outcome {
+        VoteOutcome::Passed if shortfall.is_some() => {
+            bail!("a vote passes with {}; got {}", quota, tally)
+        }
+        VoteOutcome::Passed => given.map(str::to_string),
+        VoteOutcome::Failed => match (given, shortfall) {
+            (Some(reason), _) => Some(reason.to_string()),
+            (None, Some(shortfall)) => Some(shortfall),
+            (None, None) => bail!(
+                "the tally ({}) approves the release; pass --reason to say why the vote failed",
+                tally
+            ),
+        },
+    };
+    Ok((outcome, reason))
+}
+
+fn render_result(
+    ctx: &InferredContext,
+    templates: &Templates,
+    release: &RcReleaseInfo,
+    outcome: VoteOutcome,
+    counted: &Counted,
+    reason: Option<&str>,
+    vote_url: Option<&str>,
+) -> Result<String> {
+    let mut tera_ctx = TeraContext::new();
+    tera_ctx.insert("repo", &ctx.repo_name);
+    tera_ctx.insert("version", &release.base_version_string());
+    tera_ctx.insert("rc_suffix", &release.rc_suffix());
+    tera_ctx.insert("tag", &release.tag);
+    tera_ctx.insert("stable_tag", &release.stable_tag(&ctx.config.tags()));
+    tera_ctx.insert("outcome", &outcome.to_string());
+    tera_ctx.insert("passed", &(outcome == VoteOutcome::Passed));
+    tera_ctx.insert("tally", &counted.tally);
+    tera_ctx.insert("votes", &counted.votes);
+    tera_ctx.insert("reason", &reason);
+    tera_ctx.insert("vote_url", &vote_url);
+    tera_ctx.insert("dev_list", &ctx.config.asf.dev_list);
+    templates.render("result", &tera_ctx)
+}
+
+fn print_next_steps(ctx: &InferredContext, release: &RcReleaseInfo, outcome: VoteOutcome) {
+    match outcome {
+        VoteOutcome::Passed => println!(
+            "next: `asfship release` promotes {} to {}",
+            release.tag,
+            release.stable_tag(&ctx.config.tags())
+        ),
+        VoteOutcome::Failed => {
+            let next = ctx
+                .config
+                .tags()
+                .rc(&release.version, release.rc_number + 1);
+            println!("next: abandon {} and roll {}:", release.tag, next);
+            println!(
+                "  1. `asfship abort-rc --rc-tag {}` removes it from dist/dev",
+                release.tag
+            );
+            println!("  2. fix the problems the vote found and merge the fixes");
+            println!(
+                "  3. `asfship prerelease` to cut {}, then `asfship sync` and `asfship vote`",
+                next
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tally_must_match_the_claimed_outcome() {
+        let tally = |binding, against| Tally {
+            binding,
+            non_binding: 2,
+            against,
+        };
+        let quota = Quota::new(&VoteConfig::default()).unwrap();
+        let passed = Some(VoteOutcome::Passed);
+        let failed = Some(VoteOutcome::Failed);
+        assert_eq!(
+            reason(passed, &tally(3, 0), &quota, None).unwrap(),
+            (VoteOutcome::Passed, None)
+        );
+        assert!(reason(passed, &tally(2, 0), &quota, None).is_err());
+        assert!(reason(passed, &tally(3, 3), &quota, None).is_err());
+        assert_eq!(
+            reason(failed, &tally(2, 0), &quota, None)
+                .unwrap()
+                .1
+                .as_deref(),
+            Some("fewer than 3 binding +1 votes")
+        );
+        assert_eq!(
+            reason(failed, &tally(4, 4), &quota, None)
+                .unwrap()
+                .1
+                .as_deref(),
+            Some("no more binding +1 than -1 votes")
+        );
+        assert!(reason(failed, &tally(3, 0), &quota, None).is_err());
+        assert_eq!(
+            reason(failed, &tally(3, 0), &quota, Some("license issue"))
+                .unwrap()
+                .1
+                .as_deref(),
+            Some("license issue")
+        );
+        assert_eq!(
+            tally(3, 1).to_string(),
+            "+1 binding: 3, +1 non-binding: 2, -1 binding: 1"
+        );
+    }
+
+    #[test]
+    fn votes_are_binding_for_pmc_members_only() {
+        let roster = ProjectRoster {
+            pmc: ["alice", "bob", "carol"].map(String::from).into(),
+            committers: ["alice", "bob", "carol", "dave"].map(String::from).into(),
+            mail_list: "foo".into(),
+        };
+        let votes: Vec<Vote> = [
+            "Alice=+1", "bob=+1", "carol=-1", "dave=+1", "erin=-1", "frank=0",
+        ]
+        .iter()
+        .map(|v| v.parse().unwrap())
+        .collect();
+        let Counted { tally, votes } = Tally::count(&votes, &roster).unwrap();
+        assert_eq!(
+            tally,
+            Tally {
+                binding: 2,
+                non_binding: 1,
+                against: 1
+            }
+        );
+        assert_eq!(
+            votes[0],
+            CountedVote {
+                voter: "alice".into(),
+                vote: "+1".into(),
+                binding: true
+            }
+        );
+        assert!(!votes[4].binding);
+
+        let twice: Vec<Vote> = ["bob=+1", "bob=-1"]
+            .iter()
+            .map(|v| v.parse().unwrap())
+            .collect();
+        assert!(Tally::count(&twice, &roster).is_err());
+        assert!("bob".parse::<Vote>().is_err());
+        assert!("bob=+2".parse::<Vote>().is_err());
+    }
+
+    #[test]
+    fn quota_decides_the_outcome_when_none_is_claimed() {
+        let tally = |binding, against| Tally {
+            binding,
+            non_binding: 0,
+            against,
+        };
+        let asf = Quota::new(&VoteConfig::default()).unwrap();
+        assert_eq!(
+            reason(None, &tally(3, 1), &asf, None).unwrap(),
+            (VoteOutcome::Passed, None)
+        );
+
+        let strict = Quota::new(&VoteConfig {
+            required_binding: Some(4),
+            allow_negative: Some(false),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            reason(None, &tally(3, 0), &strict, None).unwrap(),
+            (
+                VoteOutcome::Failed,
+                Some("fewer than 4 binding +1 votes".into())
+            )
+        );
+        assert_eq!(
+            reason(None, &tally(5, 1), &strict, None).unwrap(),
+            (VoteOutcome::Failed, Some("a binding -1 vote".into()))
+        );
+        let err = reason(Some(VoteOutcome::Passed), &tally(5, 1), &strict, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "a vote passes with at least 4 binding +1 votes and no binding -1 votes; got +1 binding: 5, +1 non-binding: 0, -1 binding: 1"
+        );
+
+        let lax = VoteConfig {
+            required_binding: Some(2),
+            ..Default::default()
+        };
+        assert!(Quota::new(&lax).is_err());
+    }
+}
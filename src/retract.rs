@@ -0,0 +1,291 @@
+use std::io::Write as _;
+
+use anyhow::{Context, Result, anyhow, bail};
+use git2::Repository;
+use tera::Context as TeraContext;
+use tokio::process::Command;
+
+use crate::artifacts;
+use crate::asf;
+use crate::audit::{AuditAction, AuditLog};
+use crate::cancel;
+use crate::endpoints;
+use crate::github;
+use crate::infer::InferredContext;
+use crate::net;
+use crate::rc_release::{VoteOutcome, fetch_latest_rc_release, fetch_rc_release_by_tag};
+use crate::released::{ReleasedCrate, released_crates};
+use crate::templates::Templates;
+use crate::token_scope::{self, Permission};
+use crate::vote;
+
+pub struct RetractOptions<'a> {
+    pub version: &'a str,
+    pub reason: &'a str,
+    pub delete_assets: bool,
+    pub yes: bool,
+    pub dry_run: bool,
+}
+
+pub async fn run_retract(ctx: &InferredContext, opts: RetractOptions<'_>) -> Result<()> {
+    let tags = ctx.config.tags();
+    let version = tags
+        .parse_version_arg(opts.version)
+        .with_context(|| format!("invalid release version {:?}", opts.version))?;
+    let tag = tags.stable(&version);
+
+    let repo = Repository::discover(&ctx.repo_root)?;
+    let crates = released_crates(&repo, ctx, &version)?;
+    if crates.is_empty() {
+        bail!(
+            "no publishable crate was released at {}; nothing to retract",
+            tag
+        );
+    }
+    let svn_url = format!(
+        "{}/{}/{}",
+        endpoints::dist_release(),
+        asf::project_name(ctx),
+        version
+    );
+    let body = render_announcement(
+        &*asf::with_dev_list(ctx).await?,
+        &version,
+        &crates,
+        opts.reason,
+    )?;
+
+    println!("retract: {} ({})", tag, opts.reason);
+    for c in &crates {
+        println!("- yank {} {} from crates.io", c.name, c.version);
+    }
+    println!(
+        "- mark GitHub release {} as retracted{}",
+        tag,
+        if opts.delete_assets {
+            " and delete its assets"
+        } else {
+            ""
+        }
+    );
+    println!("- svn rm {}", svn_url);
+
+    if opts.dry_run {
+        println!("retract: dry-run, announcement draft:\n---\n{}", body);
+        return Ok(());
+    }
+    if !github::has_token() {
+        bail!("missing ASFSHIP_GITHUB_TOKEN for retract command");
+    }
+    token_scope::verify(ctx, &[Permission::Contents]).await?;
+    if !opts.yes {
+        confirm(&version.to_string()).await?;
+    }
+
+    let audit = AuditLog::open(ctx).await;
+    for c in &crates {
+        cancel::checkpoint(&format!("yanking {} {}", c.name, c.version))?;
+        yank_crate(c).await?;
+        audit
+            .record(
+                AuditAction::CrateYanked,
+                &format!("{}@{}", c.name, c.version),
+            )
+            .await;
+    }
+    cancel::checkpoint("marking the GitHub release")?;
+    mark_github_release(ctx, &tag, opts.reason, opts.delete_assets, &audit).await?;
+    cancel::checkpoint("removing the release from dist")?;
+    remove_from_dist(
+        &svn_url,
+        &format!("Retract {}", tag),
+        "removing from dist/release requires PMC write access",
+        &audit,
+    )
+    .await?;
+
+    let dir = artifacts::prepare(ctx, &tag).await?;
+    let path = dir.join("ANNOUNCEMENT.md");
+    tokio::fs::write(&path, &body).await?;
+    println!(
+        "retract: completed; announcement draft written to {}",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Abandon an rc whose vote failed: remove its `dist/dev` directory so the next rc starts clean.
+pub async fn run_abort_rc(
+    ctx: &InferredContext,
+    rc_tag: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    if !github::has_token() {
+        bail!("missing ASFSHIP_GITHUB_TOKEN for abort-rc command");
+    }
+    let release = match rc_tag {
+        Some(tag) => fetch_rc_release_by_tag(ctx, tag).await?,
+        None => fetch_latest_rc_release(ctx).await?,
+    };
+    match release.vote {
+        Some(VoteOutcome::Failed) => {}
+        Some(VoteOutcome::Passed) => bail!(
+            "the vote on {} passed; run `asfship release` instead of aborting it",
+            release.tag
+        ),
+        None => bail!(
+            "no failed vote is recorded on {}; run `asfship result failed` first",
+            release.tag
+        ),
+    }
+    let svn_url = vote::svn_url(ctx, &release);
+    println!("abort-rc: {}", release.tag);
+    println!("- svn rm {}", svn_url);
+    if dry_run {
+        println!("abort-rc: dry-run, nothing removed");
+        return Ok(());
+    }
+
+    let audit = AuditLog::open(ctx).await;
+    cancel::checkpoint("removing the rc from dist/dev")?;
+    remove_from_dist(
+        &svn_url,
+        &format!(
+            "Remove failed {} {}{}",
+            ctx.repo_name,
+            release.base_version_string(),
+            release.rc_suffix()
+        ),
+        "removing from dist/dev requires committer write access",
+        &audit,
+    )
+    .await?;
+    println!(
+        "abort-rc: completed; fix the problems the vote found, then `asfship prerelease` to cut {}",
+        ctx.config
+            .tags()
+            .rc(&release.version, release.rc_number + 1)
+    );
+    Ok(())
+}
+
+fn render_announcement(
+    ctx: &InferredContext,
+    version: &semver::Version,
+    crates: &[ReleasedCrate],
+    reason: &str,
+) -> Result<String> {
+    let mut tera_ctx = TeraContext::new();
+    tera_ctx.insert("repo", &ctx.repo_name);
+    tera_ctx.insert("version", &version.to_string());
+    tera_ctx.insert("tag", &ctx.config.tags().stable(version));
+    tera_ctx.insert("reason", reason);
+    tera_ctx.insert("crates", crates);
+    tera_ctx.insert("dev_list", &ctx.config.asf.dev_list);
+    Templates::load(&ctx.repo_root)?.render("retract", &tera_ctx)
+}
+
+async fn confirm(version: &str) -> Result<()> {
+    let expected = version.to_string();
+    let answer = tokio::task::spawn_blocking(move || -> Result<String> {
+        print!(
+            "This yanks crates and deletes release files. Type {} to continue: ",
+            expected
+        );
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        Ok(line.trim().to_string())
+    })
+    .await
+    .map_err(|e| anyhow!("confirmation task join error: {}", e))??;
+    if answer != version {
+        bail!("retraction aborted: confirmation did not match {}", version);
+    }
+    Ok(())
+}
+
+async fn yank_crate(c: &ReleasedCrate) -> Result<()> {
+    tracing::info!("cargo: yanking {} {}", c.name, c.version);
+    let output =
+        cancel::output(Command::new("cargo").args(["yank", "--version", &c.version, &c.name]))
+            .await
+            .context("failed to run cargo yank")?;
+    if !output.status.success() {
+        bail!(
+            "cargo yank {} {} failed: {}",
+            c.name,
+            c.version,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+async fn mark_github_release(
+    ctx: &InferredContext,
+    tag: &str,
+    reason: &str,
+    delete_assets: bool,
+    audit: &AuditLog,
+) -> Result<()> {
+    let gh = github::client()?;
+    let repos = gh.repos(ctx.repo_owner.clone(), ctx.repo_name.clone());
+    let releases = repos.releases();
+    let release = releases
+        .get_by_tag(tag)
+        .await
+        .with_context(|| format!("failed to fetch GitHub release {}", tag))?;
+    let old_body = release.body.clone().unwrap_or_default();
+    if !old_body.starts_with("> **Retracted**") {
+        let body = format!("> **Retracted**: {}\n\n{}", reason, old_body);
+        releases
+            .update(release.id.into_inner())
+            .body(&body)
+            .send()
+            .await
+            .with_context(|| format!("failed to update GitHub release {}", tag))?;
+        audit.record(AuditAction::ReleaseEdited, tag).await;
+    }
+
+    if delete_assets {
+        let client = net::client()?;
+        for asset in &release.assets {
+            let token = github::token().await?;
+            github::delete_release_asset(
+                &client,
+                &token,
+                &ctx.repo_owner,
+                &ctx.repo_name,
+                asset.id.into_inner(),
+            )
+            .await?;
+            audit.record(AuditAction::AssetDeleted, &asset.name).await;
+        }
+    }
+    Ok(())
+}
+
+async fn remove_from_dist(
+    svn_url: &str,
+    message: &str,
+    hint: &str,
+    audit: &AuditLog,
+) -> Result<()> {
+    let info = net::svn()?.args(["info", svn_url]).output().await?;
+    if !info.status.success() {
+        tracing::info!("svn: {} not present; nothing to remove", svn_url);
+        return Ok(());
+    }
+    let output = cancel::output(net::svn()?.args(["rm", "-m", message, svn_url])).await?;
+    if !output.status.success() {
+        bail!(
+            "svn rm {} failed: {}; {}",
+            svn_url,
+            String::from_utf8_lossy(&output.stderr).trim(),
+            hint
+        );
+    }
+    audit.record(AuditAction::SvnRemoved, svn_url).await;
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Layer, fmt};
+
+use crate::redact;
+
+/// Name of the debug log each run appends to in its artifact directory.
+pub const FILE_NAME: &str = "asfship.log";
+/// Events kept in memory before a command knows its run directory; later ones are dropped.
+const BUFFER_LIMIT: usize = 8 << 20;
+
+struct State {
+    file: Option<File>,
+    buffer: Vec<u8>,
+    dropped: bool,
+    /// Directory for the log when the run never prepares a run directory of its own.
+    fallback: Option<PathBuf>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    file: None,
+    buffer: Vec::new(),
+    dropped: false,
+    fallback: None,
+});
+
+/// Debug-level, timestamped layer recording when every step span closes, independent of
+/// `RUST_LOG`, so a failed release can be diagnosed from `asfship.log` afterwards.
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fmt::layer()
+        .with_writer(RunLog)
+        .with_ansi(false)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_filter(EnvFilter::new("info,asfship=debug"))
+}
+
+/// Start writing the log to `dir`, including everything recorded so far.
+///
+/// Only the first directory of a run is used, so a command touching several tags keeps
+/// one log.
+pub fn attach(dir: &Path) {
+    if attached() {
+        return;
+    }
+    let path = dir.join(FILE_NAME);
+    let mut file = match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            tracing::warn!("run log: cannot write {}: {}", path.display(), err);
+            return;
+        }
+    };
+    let Ok(mut state) = STATE.lock() else {
+        return;
+    };
+    let mut buffered = std::mem::take(&mut state.buffer);
+    if std::mem::take(&mut state.dropped) {
+        buffered.extend_from_slice(b"[events dropped here: log buffer full]\n");
+    }
+    if file.write_all(&buffered).is_ok() {
+        state.file = Some(file);
+    }
+}
+
+/// Write the log to `dir` at the end of the run unless a run directory took it first;
+/// set before dispatching, so every run leaves an `asfship.log`.
+pub fn set_fallback(dir: &Path) {
+    if let Ok(mut state) = STATE.lock() {
+        state.fallback = Some(dir.to_path_buf());
+    }
+}
+
+/// Flush a log still held in memory to the fallback directory; call before exiting.
+pub fn finish() {
+    if attached() {
+        return;
+    }
+    let Some(dir) = STATE.lock().ok().and_then(|state| state.fallback.clone()) else {
+        return;
+    };
+    if std::fs::create_dir_all(&dir).is_ok() {
+        attach(&dir);
+    }
+}
+
+/// Whether this run's log already has a home.
+pub fn attached() -> bool {
+    STATE.lock().is_ok_and(|state| state.file.is_some())
+}
+
+struct RunLog;
+
+impl<'a> MakeWriter<'a> for RunLog {
+    type Writer = RunLogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RunLogWriter
+    }
+}
+
+struct RunLogWriter;
+
+impl Write for RunLogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = redact::redact(&String::from_utf8_lossy(buf));
+        let Ok(mut state) = STATE.lock() else {
+            return Ok(buf.len());
+        };
+        if let Some(file) = &mut state.file {
+            file.write_all(text.as_bytes())?;
+        } else if state.buffer.len() + text.len() <= BUFFER_LIMIT {
+            state.buffer.extend_from_slice(text.as_bytes());
+        } else {
+            state.dropped = true;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
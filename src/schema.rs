@@ -0,0 +1,40 @@
+use anyhow::{Result, bail};
+
+/// Version of the JSON documents asfship writes for other tools: `plan --format json`,
+/// run manifests, the release feed, the release timeline, and audit log entries.
+///
+/// Adding a field keeps the version; removing, renaming, or changing the meaning of one
+/// bumps it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Serde default for documents written before they carried a version, which match version 1.
+pub fn current() -> u32 {
+    SCHEMA_VERSION
+}
+
+/// Refuse a document from a newer asfship rather than misreading it.
+pub fn ensure_supported(what: &str, version: u32) -> Result<()> {
+    if version > SCHEMA_VERSION {
+        bail!(
+            "{} uses schema version {}, but this asfship understands up to {}; upgrade asfship",
+            what,
+            version,
+            SCHEMA_VERSION
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newer_documents_are_rejected() {
+        assert!(ensure_supported("manifest.json", SCHEMA_VERSION).is_ok());
+        let err = ensure_supported("manifest.json", SCHEMA_VERSION + 1)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("upgrade asfship"), "{}", err);
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::audit::{AuditAction, AuditLog};
+use crate::github;
+use crate::infer::InferredContext;
+use crate::versioning::Plan;
+
+/// Print which pull requests `publish` would comment on or label.
+pub fn describe(ctx: &InferredContext, plan: &Plan, tag: &str) {
+    let cfg = &ctx.config.release.pull_requests;
+    if !cfg.enabled() {
+        return;
+    }
+    let numbers = plan.pull_requests();
+    let list: Vec<String> = numbers.iter().map(|n| format!("#{}", n)).collect();
+    println!(
+        "  pull requests: {} for {}: {}",
+        actions(cfg.comment, cfg.label.as_deref()),
+        tag,
+        if list.is_empty() {
+            "<none>".to_string()
+        } else {
+            list.join(", ")
+        }
+    );
+}
+
+fn actions(comment: bool, label: Option<&str>) -> String {
+    match (comment, label) {
+        (true, Some(label)) => format!("comment and label {:?}", label),
+        (true, None) => "comment".to_string(),
+        (false, Some(label)) => format!("label {:?}", label),
+        (false, None) => "nothing".to_string(),
+    }
+}
+
+/// Tell each pull request that shipped in `tag` about the release.
+///
+/// A courtesy only: failures are logged and never fail the release. Pull requests that
+/// already carry the comment from an earlier run are not commented on again.
+pub async fn publish(ctx: &InferredContext, plan: &Plan, tag: &str, audit: &AuditLog) {
+    let cfg = &ctx.config.release.pull_requests;
+    if !cfg.enabled() {
+        return;
+    }
+    let marker = format!("<!-- asfship:released {} -->", tag);
+    let body = format!(
+        "Released in [{tag}](https://github.com/{}/{}/releases/tag/{tag}).\n\n{}",
+        ctx.repo_owner,
+        ctx.repo_name,
+        marker,
+        tag = tag
+    );
+    for number in plan.pull_requests() {
+        if cfg.comment {
+            match comment(ctx, number, &body, &marker).await {
+                Ok(true) => {
+                    audit
+                        .record(AuditAction::PullRequestCommented, &format!("#{}", number))
+                        .await
+                }
+                Ok(false) => tracing::debug!("pull requests: #{} already commented", number),
+                Err(err) => tracing::warn!("pull requests: comment on #{}: {:#}", number, err),
+            }
+        }
+        if let Some(label) = &cfg.label {
+            match add_label(ctx, number, label).await {
+                Ok(_) => {
+                    audit
+                        .record(AuditAction::PullRequestLabeled, &format!("#{}", number))
+                        .await
+                }
+                Err(err) => tracing::warn!("pull requests: label #{}: {:#}", number, err),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct IssueComment {
+    body: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CommentPayload<'a> {
+    body: &'a str,
+}
+
+#[derive(Serialize)]
+struct LabelsPayload<'a> {
+    labels: [&'a str; 1],
+}
+
+/// Comment unless an earlier run already did, returning whether a comment was posted.
+async fn comment(ctx: &InferredContext, number: u64, body: &str, marker: &str) -> Result<bool> {
+    let gh = github::client()?;
+    let route = format!(
+        "/repos/{}/{}/issues/{}/comments",
+        ctx.repo_owner, ctx.repo_name, number
+    );
+    let existing: Vec<IssueComment> = gh
+        .get(&route, Some(&[("per_page", "100")]))
+        .await
+        .context("failed to list comments")?;
+    if existing
+        .iter()
+        .any(|c| c.body.as_deref().is_some_and(|b| b.contains(marker)))
+    {
+        return Ok(false);
+    }
+    let _: serde_json::Value = gh
+        .post(&route, Some(&CommentPayload { body }))
+        .await
+        .context("failed to comment")?;
+    Ok(true)
+}
+
+async fn add_label(ctx: &InferredContext, number: u64, label: &str) -> Result<()> {
+    let _: serde_json::Value = github::client()?
+        .post(
+            format!(
+                "/repos/{}/{}/issues/{}/labels",
+                ctx.repo_owner, ctx.repo_name, number
+            ),
+            Some(&LabelsPayload { labels: [label] }),
+        )
+        .await
+        .context("failed to add the label")?;
+    Ok(())
+}
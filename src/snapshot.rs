@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use git2::Repository;
+use git2::build::CheckoutBuilder;
+use tempfile::TempDir;
+
+/// Files of HEAD exported from a bare repository, standing in for the worktree that
+/// read-only commands (`plan`, `status`) otherwise read manifests and config from.
+///
+/// A `.git` file points back at the bare repository, so history, tags, and remotes still
+/// come from it. Nothing is written to the repository itself, which may be a read-only
+/// mirror. Removed on drop.
+#[derive(Debug)]
+pub struct Snapshot {
+    dir: TempDir,
+    git_dir: PathBuf,
+}
+
+impl Snapshot {
+    /// Export the HEAD tree of the bare repository at `git_dir` to a temporary directory
+    /// outside it, where Cargo cannot mistake it for a member of an enclosing workspace.
+    pub fn export(git_dir: &Path) -> Result<Self> {
+        let repo = Repository::open_bare(git_dir)
+            .with_context(|| format!("{} is not a bare repository", git_dir.display()))?;
+        let head = repo
+            .head()
+            .and_then(|h| h.peel_to_commit())
+            .with_context(|| format!("{} has no HEAD commit", git_dir.display()))?;
+        // Own the directory before filling it, so a failed export is cleaned up too.
+        let snapshot = Self {
+            dir: tempfile::Builder::new()
+                .prefix("asfship-snapshot-")
+                .tempdir()
+                .context("failed to create snapshot directory")?,
+            git_dir: repo.path().to_path_buf(),
+        };
+        let mut checkout = CheckoutBuilder::new();
+        checkout
+            .target_dir(snapshot.dir())
+            .force()
+            .update_index(false);
+        repo.checkout_tree(head.as_object(), Some(&mut checkout))
+            .with_context(|| format!("failed to export HEAD of {}", git_dir.display()))?;
+        std::fs::write(
+            snapshot.dir().join(".git"),
+            format!("gitdir: {}\n", snapshot.git_dir.display()),
+        )?;
+        tracing::info!(
+            "infer: bare repository; reading files of {} from {}",
+            head.id(),
+            snapshot.dir().display()
+        );
+        Ok(snapshot)
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The bare repository the files were exported from.
+    pub fn git_dir(&self) -> &Path {
+        &self.git_dir
+    }
+}
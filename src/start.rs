@@ -1,12 +1,16 @@
 use anyhow::{Context, Result, bail};
+use octocrab::Octocrab;
 use serde::Serialize;
-use tera::{Context as TeraContext, Tera};
+use tera::Context as TeraContext;
 
-use crate::discussion::{self, CreateDiscussionPayload, DiscussionResponse};
+use crate::artifacts;
+use crate::audit::{AuditAction, AuditLog};
+use crate::checklist::{self, Progress};
+use crate::discussion::{self, DiscussionSummary, Draft};
 use crate::github;
 use crate::infer::InferredContext;
-
-const START_TEMPLATE: &str = include_str!("../templates/start.md");
+use crate::templates::Templates;
+use crate::token_scope::{self, Permission};
 
 #[derive(Debug)]
 pub struct StartResult {
@@ -23,14 +27,15 @@ struct TemplateCrate<'a> {
 }
 
 pub async fn run_start(ctx: &InferredContext, dry_run: bool) -> Result<StartResult> {
-    let title = format!("{} Release Kickoff", ctx.repo_name);
-    let body = render_body(ctx)?;
+    let Draft {
+        title, front, body, ..
+    } = draft(ctx)?;
 
     if dry_run {
         return Ok(StartResult {
             title,
             body,
-            category: String::from("Releases"),
+            category: front.category.unwrap_or_else(|| String::from("Releases")),
             discussion_url: None,
         });
     }
@@ -39,28 +44,15 @@ pub async fn run_start(ctx: &InferredContext, dry_run: bool) -> Result<StartResu
         bail!("missing ASFSHIP_GITHUB_TOKEN for GitHub Discussions");
     }
 
+    token_scope::verify(ctx, &[Permission::Discussions]).await?;
     let gh = github::client()?;
-    let category = discussion::fetch_default_category(&gh, &ctx.repo_owner, &ctx.repo_name).await?;
-    tracing::info!(category=%category.name, "start: using discussion category");
-
-    let payload = CreateDiscussionPayload {
-        title: &title,
-        body: &body,
-        category_id: category.id,
-    };
-
-    let discussion: DiscussionResponse = gh
-        .post(
-            format!("repos/{}/{}/discussions", ctx.repo_owner, ctx.repo_name),
-            Some(&payload),
-        )
+    let (category, discussion) =
+        discussion::create(&gh, &ctx.repo_owner, &ctx.repo_name, &title, &body, &front).await?;
+    tracing::info!(category=%category.name, "start: used discussion category");
+    AuditLog::open(ctx)
         .await
-        .with_context(|| {
-            format!(
-                "failed to create discussion in {}/{}",
-                ctx.repo_owner, ctx.repo_name
-            )
-        })?;
+        .record(AuditAction::DiscussionCreated, &discussion.html_url)
+        .await;
 
     Ok(StartResult {
         title,
@@ -70,7 +62,35 @@ pub async fn run_start(ctx: &InferredContext, dry_run: bool) -> Result<StartResu
     })
 }
 
-fn render_body(ctx: &InferredContext) -> Result<String> {
+/// Render the kickoff discussion without posting it.
+pub fn draft(ctx: &InferredContext) -> Result<Draft> {
+    let templates = Templates::load(&ctx.repo_root)?;
+    Ok(Draft {
+        title: kickoff_title(&ctx.repo_name),
+        front: discussion::template_front_matter(ctx, &templates, "start"),
+        body: render_body(ctx, &templates)?,
+        comments: Vec::new(),
+    })
+}
+
+pub fn kickoff_title(repo_name: &str) -> String {
+    format!("{} Release Kickoff", repo_name)
+}
+
+/// The kickoff discussion posted by `asfship start`.
+pub async fn find_kickoff(gh: &Octocrab, ctx: &InferredContext) -> Result<DiscussionSummary> {
+    let title = kickoff_title(&ctx.repo_name);
+    discussion::find_by_title(gh, &ctx.repo_owner, &ctx.repo_name, &title)
+        .await?
+        .with_context(|| {
+            format!(
+                "no kickoff discussion titled {:?}; run `asfship start` first",
+                title
+            )
+        })
+}
+
+fn render_body(ctx: &InferredContext, templates: &Templates) -> Result<String> {
     let base_tag = ctx
         .last_stable_tag
         .clone()
@@ -91,7 +111,14 @@ fn render_body(ctx: &InferredContext) -> Result<String> {
         })
         .collect();
     tera_ctx.insert("crates", &crates);
+    let checklist = checklist::evaluate(
+        &ctx.repo_root,
+        &artifacts::root(ctx),
+        &ctx.config.vote,
+        &Progress::default(),
+        chrono::Utc::now(),
+    );
+    tera_ctx.insert("checklist", &checklist);
 
-    Tera::one_off(START_TEMPLATE, &tera_ctx, false)
-        .map_err(|err| anyhow::anyhow!("failed to render start template: {}", err))
+    templates.render("start", &tera_ctx)
 }
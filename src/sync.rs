@@ -1,24 +1,36 @@
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Result, anyhow, bail};
 use tokio::fs as async_fs;
-use tokio::process::Command;
+use tokio::time::sleep;
 
+use crate::artifacts;
+use crate::asf;
+use crate::asset_cache::AssetCache;
+use crate::audit::{AuditAction, AuditLog};
+use crate::cancel;
+use crate::checksum;
+use crate::endpoints;
 use crate::github;
 use crate::infer::InferredContext;
-use crate::rc_release::{RcReleaseInfo, download_assets, fetch_latest_rc_release};
+use crate::net;
+use crate::promote;
+use crate::rc_release::{RcReleaseInfo, download_assets, fetch_latest_rc_release, human_size};
+use crate::timeline::{self, Phase};
 
-const SVN_BASE: &str = "https://dist.apache.org/repos/dist/dev";
+const SVN_COMMIT_RETRIES: usize = 3;
 
 pub async fn run_sync(ctx: &InferredContext, dry_run: bool) -> Result<()> {
     if !github::has_token() {
         bail!("missing ASFSHIP_GITHUB_TOKEN for sync command");
     }
 
-    let release = fetch_latest_rc_release(&ctx.repo_owner, &ctx.repo_name).await?;
+    let release = fetch_latest_rc_release(ctx).await?;
     let svn_target = format!(
         "{}/{}/{}",
-        SVN_BASE,
+        endpoints::dist_dev(),
         ctx.repo_name,
         release.svn_path_component(&ctx.repo_name)
     );
@@ -31,22 +43,29 @@ pub async fn run_sync(ctx: &InferredContext, dry_run: bool) -> Result<()> {
             svn_target
         );
         for asset in &release.assets {
-            println!("- {} ({} bytes)", asset.name, asset.size);
+            println!("- {} ({})", asset.name, asset.human_size());
         }
+        let total: u64 = release.assets.iter().map(|a| a.size).sum();
+        println!(
+            "total: {} files, {}",
+            release.assets.len(),
+            human_size(total)
+        );
         return Ok(());
     }
 
-    let download_dir = ctx
-        .repo_root
-        .join("target")
-        .join("asfship")
-        .join("sync")
-        .join(release.tag.replace('/', "_"));
-    let files = download_assets(&release, &download_dir).await?;
+    let download_dir = artifacts::prepare(ctx, &release.tag).await?;
+    let files = download_assets(
+        &release,
+        &download_dir,
+        &AssetCache::new(&artifacts::root(ctx)),
+    )
+    .await?;
     perform_svn_sync(&svn_target, &download_dir, &files, &release, ctx).await?;
     Ok(())
 }
 
+#[tracing::instrument(skip_all)]
 async fn perform_svn_sync(
     svn_url: &str,
     download_dir: &Path,
@@ -54,18 +73,22 @@ async fn perform_svn_sync(
     release: &RcReleaseInfo,
     ctx: &InferredContext,
 ) -> Result<()> {
-    let checkout_dir = download_dir.join("svn");
-    async_fs::create_dir_all(&checkout_dir).await?;
+    let audit = AuditLog::open(ctx).await;
+    let message = format!(
+        "Create {} {}{} directory (created by asfship)",
+        ctx.repo_name,
+        release.base_version_string(),
+        release.rc_suffix()
+    );
+    if ensure_remote_dir(svn_url, &message).await? {
+        audit
+            .record(AuditAction::SvnDirectoryCreated, svn_url)
+            .await;
+    }
 
-    run_svn([
-        "checkout",
-        "--depth",
-        "empty",
-        svn_url,
-        checkout_dir.to_str().unwrap(),
-    ])
-    .await?;
-    run_svn(["update", checkout_dir.to_str().unwrap()]).await?;
+    let checkout_dir = download_dir.join("svn");
+    prepare_checkout(svn_url, &checkout_dir).await?;
+    update_working_copy(&checkout_dir).await?;
 
     for file in files {
         let file_name = file
@@ -84,18 +107,313 @@ async fn perform_svn_sync(
         release.base_version_string(),
         release.rc_suffix()
     );
-    run_svn_in(&checkout_dir, ["commit", "-m", &message]).await?;
+    cancel::checkpoint("committing to svn")?;
+    commit_with_retry(&checkout_dir, &message).await?;
+    audit.record(AuditAction::SvnCommitted, svn_url).await;
+    timeline::record(
+        &artifacts::root(ctx),
+        &release.version,
+        Phase::Synced,
+        &release.tag,
+    )
+    .await;
+    verify_remote(svn_url, &checkout_dir, files).await?;
+
+    let total: u64 = release.assets.iter().map(|a| a.size).sum();
+    println!(
+        "sync: committed {} assets ({}) to {}",
+        files.len(),
+        human_size(total),
+        svn_url
+    );
+    Ok(())
+}
+
+/// The dist/release directory a voted rc is promoted into.
+pub fn release_dir_url(ctx: &InferredContext, release: &RcReleaseInfo) -> String {
+    format!(
+        "{}/{}/{}",
+        endpoints::dist_release(),
+        asf::project_name(ctx),
+        release.base_version_string()
+    )
+}
+
+/// Commit the voted rc artifacts in `files` to dist/release under their final names,
+/// with checksums regenerated for those names. Skipped when the directory already holds
+/// every file, so a rerun after a later failure does not commit again.
+#[tracing::instrument(skip_all)]
+pub async fn promote_to_release(
+    ctx: &InferredContext,
+    release: &RcReleaseInfo,
+    stable_tag: &str,
+    files: &[PathBuf],
+    audit: &AuditLog,
+) -> Result<()> {
+    let svn_url = release_dir_url(ctx, release);
+    let run_dir = artifacts::run_dir(ctx, stable_tag);
+    let exclude = promote::exclude_patterns(&ctx.config.release.exclude_assets)?;
+    let promoted = promote::copy_renamed(
+        files,
+        &exclude,
+        &run_dir.join("dist-release"),
+        ctx.config.checksum_format,
+    )
+    .await?;
+    let message = format!(
+        "Create {} {} release directory (created by asfship)",
+        ctx.repo_name,
+        release.base_version_string()
+    );
+    if ensure_remote_dir(&svn_url, &message).await? {
+        audit
+            .record(AuditAction::SvnDirectoryCreated, &svn_url)
+            .await;
+    }
+    let checkout_dir = run_dir.join("svn");
+    prepare_checkout(&svn_url, &checkout_dir).await?;
+    update_working_copy(&checkout_dir).await?;
+    if remote_problems(&svn_url, &checkout_dir, &promoted)
+        .await?
+        .is_empty()
+    {
+        println!("release: {} already holds the promoted artifacts", svn_url);
+        return Ok(());
+    }
+
+    for file in &promoted {
+        let file_name = file
+            .file_name()
+            .ok_or_else(|| anyhow!("invalid file name"))?;
+        async_fs::copy(file, checkout_dir.join(file_name)).await?;
+    }
+    run_svn_in(&checkout_dir, ["add", "--force", "."]).await?;
+
+    let message = format!(
+        "Release {} {} (promoted from {} by asfship)",
+        ctx.repo_name,
+        release.base_version_string(),
+        release.svn_path_component(&ctx.repo_name)
+    );
+    cancel::checkpoint("committing to svn")?;
+    commit_with_retry(&checkout_dir, &message).await?;
+    audit.record(AuditAction::SvnCommitted, &svn_url).await;
+    let problems = remote_problems(&svn_url, &checkout_dir, &promoted).await?;
+    if !problems.is_empty() {
+        bail!(
+            "{} does not match the promoted artifacts: {}; fix the svn directory before announcing the release",
+            svn_url,
+            problems.join(", ")
+        );
+    }
+    println!(
+        "release: promoted {} files from {} to {}",
+        promoted.len(),
+        release.tag,
+        svn_url
+    );
+    Ok(())
+}
+
+/// Compare what `svn_url` holds after the commit with the local files, so a partial
+/// commit or drift between dist/dev and GitHub is caught before the vote starts.
+#[tracing::instrument(skip_all)]
+async fn verify_remote(svn_url: &str, checkout_dir: &Path, files: &[PathBuf]) -> Result<()> {
+    let problems = remote_problems(svn_url, checkout_dir, files).await?;
+    if !problems.is_empty() {
+        bail!(
+            "{} does not match the local artifacts: {}; rerun sync or fix the svn directory before starting the vote",
+            svn_url,
+            problems.join(", ")
+        );
+    }
+    println!(
+        "sync: verified {} files at {} against local checksums",
+        files.len(),
+        svn_url
+    );
+    Ok(())
+}
 
-    println!("sync: committed {} assets to {}", files.len(), svn_url);
+/// Files missing from `svn_url`, and checksums there that do not vouch for the local archives.
+async fn remote_problems(
+    svn_url: &str,
+    checkout_dir: &Path,
+    files: &[PathBuf],
+) -> Result<Vec<String>> {
+    let listing = svn_output_in(checkout_dir, ["list", "--non-interactive", svn_url]).await?;
+    let names: Vec<&str> = files
+        .iter()
+        .filter_map(|f| f.file_name().and_then(|n| n.to_str()))
+        .collect();
+    let missing = missing_from_listing(&listing, &names);
+    let mut problems: Vec<String> = missing
+        .iter()
+        .map(|name| format!("{} is missing", name))
+        .collect();
+    for file in files {
+        let Some(name) = file.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Some(archive) = name.strip_suffix(".sha512") else {
+            continue;
+        };
+        if missing.contains(&name) {
+            continue;
+        }
+        let local = checksum::sha512_file(&file.with_file_name(archive)).await?;
+        let remote = svn_output_in(
+            checkout_dir,
+            ["cat", "--non-interactive", &format!("{}/{}", svn_url, name)],
+        )
+        .await?;
+        problems.extend(checksum_problem(name, archive, &local, &remote));
+    }
+    Ok(problems)
+}
+
+/// Names in `expected` that `svn list` output does not contain.
+fn missing_from_listing<'a>(listing: &str, expected: &[&'a str]) -> Vec<&'a str> {
+    let listed: BTreeSet<&str> = listing.lines().map(str::trim).collect();
+    expected
+        .iter()
+        .copied()
+        .filter(|name| !listed.contains(name))
+        .collect()
+}
+
+/// Why the remote checksum file `name` does not vouch for the local `archive`, if it does not.
+fn checksum_problem(name: &str, archive: &str, local: &str, remote: &str) -> Option<String> {
+    match checksum::parse_sha512(remote, archive) {
+        Some(digest) if digest == local => None,
+        Some(_) => Some(format!("{} does not match the local {}", name, archive)),
+        None => Some(format!("{} has no SHA-512 digest for {}", name, archive)),
+    }
+}
+
+/// Create `svn_url` (and any missing parents such as `dist/dev/<repo>`) when absent.
+///
+/// Returns true when the directory had to be created.
+async fn ensure_remote_dir(svn_url: &str, message: &str) -> Result<bool> {
+    let probe = net::svn()?
+        .args(["info", "--non-interactive", svn_url])
+        .output()
+        .await?;
+    if probe.status.success() {
+        return Ok(false);
+    }
+    let stderr = String::from_utf8_lossy(&probe.stderr);
+    if !is_missing_url_error(&stderr) {
+        bail!("svn info {} failed: {}", svn_url, stderr.trim());
+    }
+
+    tracing::info!("sync: creating missing svn directory {}", svn_url);
+    let output =
+        cancel::output(net::svn()?.args(["mkdir", "--parents", "-m", message, svn_url])).await?;
+    if !output.status.success() {
+        bail!(
+            "svn mkdir {} failed: {}\nCreating directories under dist requires ASF committer credentials with write access to the project's dist area (PMC members only for dist/release); ask a PMC member or ASF Infra to create `{}` if access is denied",
+            svn_url,
+            String::from_utf8_lossy(&output.stderr).trim(),
+            svn_url
+        );
+    }
+    Ok(true)
+}
+
+/// `svn info` reports E170000 (illegal URL) or "path not found" for missing URLs.
+fn is_missing_url_error(stderr: &str) -> bool {
+    stderr.contains("E170000")
+        || stderr.contains("E160013")
+        || stderr.to_ascii_lowercase().contains("path not found")
+}
+
+/// Reuse an existing checkout of `svn_url` after cleaning it, or check out afresh.
+async fn prepare_checkout(svn_url: &str, checkout_dir: &Path) -> Result<()> {
+    if checkout_dir.join(".svn").exists() {
+        let url = svn_output_in(checkout_dir, ["info", "--show-item", "url"]).await;
+        match url {
+            Ok(url) if url.trim() == svn_url => {
+                tracing::info!("sync: reusing svn checkout {}", checkout_dir.display());
+                run_svn_in(checkout_dir, ["cleanup"]).await?;
+                run_svn_in(checkout_dir, ["revert", "-R", "."]).await?;
+                return Ok(());
+            }
+            _ => {
+                tracing::warn!(
+                    "sync: discarding stale svn checkout {}",
+                    checkout_dir.display()
+                );
+                async_fs::remove_dir_all(checkout_dir).await?;
+            }
+        }
+    }
+
+    async_fs::create_dir_all(checkout_dir).await?;
+    run_svn([
+        "checkout",
+        "--depth",
+        "empty",
+        svn_url,
+        checkout_dir.to_str().unwrap(),
+    ])
+    .await
+}
+
+async fn update_working_copy(checkout_dir: &Path) -> Result<()> {
+    run_svn_in(checkout_dir, ["update", "--accept", "postpone"]).await?;
+    let status = svn_output_in(checkout_dir, ["status"]).await?;
+    let conflicts = conflicted_paths(&status);
+    if !conflicts.is_empty() {
+        bail!(
+            "svn working copy {} has conflicts: {}; resolve them or delete the checkout and rerun sync",
+            checkout_dir.display(),
+            conflicts.join(", ")
+        );
+    }
     Ok(())
 }
 
+async fn commit_with_retry(checkout_dir: &Path, message: &str) -> Result<()> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match run_svn_in(checkout_dir, ["commit", "-m", message]).await {
+            Ok(()) => return Ok(()),
+            Err(err) if attempt < SVN_COMMIT_RETRIES => {
+                tracing::warn!(
+                    "svn commit failed: {} (attempt {}/{})",
+                    err,
+                    attempt,
+                    SVN_COMMIT_RETRIES
+                );
+                sleep(Duration::from_millis(500 * attempt as u64)).await;
+                run_svn_in(checkout_dir, ["cleanup"]).await?;
+                update_working_copy(checkout_dir).await?;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Paths reported by `svn status` with a tree, text, or property conflict.
+fn conflicted_paths(status: &str) -> Vec<String> {
+    status
+        .lines()
+        .filter(|line| {
+            let cols: Vec<char> = line.chars().take(7).collect();
+            [0, 1, 6].iter().any(|&i| cols.get(i) == Some(&'C'))
+        })
+        .filter_map(|line| line.get(8..).map(|p| p.trim().to_string()))
+        .collect()
+}
+
 async fn run_svn<I, S>(args: I) -> Result<()>
 where
     I: IntoIterator<Item = S>,
     S: AsRef<std::ffi::OsStr>,
 {
-    let status = Command::new("svn").args(args).status().await?;
+    let status = cancel::status(net::svn()?.args(args)).await?;
     if !status.success() {
         bail!("svn command failed with status: {}", status);
     }
@@ -107,13 +425,73 @@ where
     I: IntoIterator<Item = S>,
     S: AsRef<std::ffi::OsStr>,
 {
-    let status = Command::new("svn")
-        .current_dir(dir)
-        .args(args)
-        .status()
-        .await?;
+    let status = cancel::status(net::svn()?.current_dir(dir).args(args)).await?;
     if !status.success() {
         bail!("svn command failed with status: {}", status);
     }
     Ok(())
 }
+
+async fn svn_output_in<I, S>(dir: &Path, args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    let output = cancel::output(net::svn()?.current_dir(dir).args(args)).await?;
+    if !output.status.success() {
+        bail!(
+            "svn command failed with status: {} ({})",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum_problem, conflicted_paths, missing_from_listing};
+
+    #[test]
+    fn conflicted_paths_detects_text_and_tree_conflicts() {
+        let status = "\
+C       apache-foo-0.1.0-rc1-src.tar.gz
+M       apache-foo-0.1.0-rc1-src.zip
+      C apache-foo-0.1.0-rc1-src.tar.gz.sha512
+?       notes.txt
+";
+        assert_eq!(
+            conflicted_paths(status),
+            vec![
+                "apache-foo-0.1.0-rc1-src.tar.gz".to_string(),
+                "apache-foo-0.1.0-rc1-src.tar.gz.sha512".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn remote_listing_and_checksums_are_compared() {
+        let listing = "apache-foo-0.1.0-rc1-src.tar.gz\napache-foo-0.1.0-rc1-src.tar.gz.sha512\n";
+        assert_eq!(
+            missing_from_listing(
+                listing,
+                &[
+                    "apache-foo-0.1.0-rc1-src.tar.gz",
+                    "apache-foo-0.1.0-rc1-src.tar.gz.asc",
+                ]
+            ),
+            vec!["apache-foo-0.1.0-rc1-src.tar.gz.asc"]
+        );
+
+        let archive = "apache-foo-0.1.0-rc1-src.tar.gz";
+        let name = "apache-foo-0.1.0-rc1-src.tar.gz.sha512";
+        let digest = "ab".repeat(64);
+        let remote = format!("{}  {}\n", digest, archive);
+        assert_eq!(checksum_problem(name, archive, &digest, &remote), None);
+        assert_eq!(
+            checksum_problem(name, archive, &"cd".repeat(64), &remote),
+            Some(format!("{} does not match the local {}", name, archive))
+        );
+        assert!(checksum_problem(name, archive, &digest, "").is_some());
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::{Context, Result, bail};
+use git2::{Oid, Repository};
+
+use crate::asf;
+use crate::audit::{AuditAction, AuditLog};
+use crate::cancel;
+use crate::git_identity::GitIdentity;
+use crate::infer::{self, InferredContext};
+use crate::release_cmd;
+use crate::tags::TagFormat;
+use crate::versioning::RcSelection;
+use crate::versioning::rc;
+
+/// Which tag `asfship tag` creates.
+#[derive(Debug, Clone)]
+pub enum TagRequest {
+    /// An rc tag on HEAD for the main crate's current version.
+    Rc(RcSelection),
+    /// The stable tag on an rc's commit; the newest rc of the current version by default.
+    Stable { from_rc: Option<String> },
+}
+
+pub struct TagOptions {
+    pub request: TagRequest,
+    pub push: bool,
+    pub dry_run: bool,
+}
+
+/// Create (and push) one rc or stable tag the way `prerelease` and `release` do, for
+/// projects that drive the rest of their release themselves.
+///
+/// Re-running is safe: a tag that already points at the intended commit is kept and
+/// only pushed, while one pointing elsewhere is an error.
+pub async fn run_tag(ctx: &InferredContext, opts: TagOptions) -> Result<()> {
+    let repo = Repository::discover(&ctx.repo_root)?;
+    let tags = ctx.config.tags();
+    let version = ctx
+        .crates
+        .iter()
+        .find(|c| c.name == ctx.main_crate)
+        .map(|c| c.version.clone())
+        .context("main crate is not in the workspace")?;
+
+    let (tag, target) = match &opts.request {
+        TagRequest::Rc(selection) => {
+            let (tag, _) = rc::select_rc_tag(&repo, &tags, &version, selection)?;
+            (tag, repo.head()?.peel_to_commit()?.id())
+        }
+        TagRequest::Stable { from_rc } => {
+            let rc_tag = match from_rc {
+                Some(tag) => tag.clone(),
+                None => latest_rc_tag(&repo, &tags, &version)?,
+            };
+            let Some((rc_version, _)) = tags.parse_rc(&rc_tag) else {
+                bail!(
+                    "invalid rc tag {:?}; expected {}",
+                    rc_tag,
+                    tags.rc_pattern()
+                );
+            };
+            (tags.stable(&rc_version), tag_commit(&repo, &rc_tag)?)
+        }
+    };
+
+    let audit = AuditLog::open(ctx).await;
+    let existing = repo
+        .refname_to_id(&format!("refs/tags/{}", tag))
+        .is_ok()
+        .then(|| tag_commit(&repo, &tag))
+        .transpose()?;
+    match existing {
+        Some(commit) if commit == target => {
+            println!("tag: {} already points at {}", tag, target);
+        }
+        Some(commit) => bail!(
+            "tag {} already exists on {}, not on {}; delete it or pick another tag",
+            tag,
+            commit,
+            target
+        ),
+        None => {
+            if let TagRequest::Rc(_) = opts.request {
+                rc::ensure_rc_available(&repo, ctx, &tag, opts.push).await?;
+            }
+            if opts.dry_run {
+                println!(
+                    "tag: dry-run, would create {} on {}{}",
+                    tag,
+                    target,
+                    if opts.push { " and push it" } else { "" }
+                );
+                return Ok(());
+            }
+            if opts.push && matches!(opts.request, TagRequest::Rc(_)) {
+                infer::ensure_head_pushed(&ctx.repo_root).await?;
+            }
+            cancel::checkpoint("creating the tag")?;
+            let identity = GitIdentity::resolve(&ctx.repo_root, &ctx.config)?;
+            let rm = asf::release_manager(&ctx.config.asf)?;
+            let signing_key = rm.as_ref().and_then(|rm| rm.gpg_fingerprint.as_deref());
+            match opts.request {
+                TagRequest::Rc(_) => rc::create_rc_tag(&repo, &tag, identity, signing_key).await?,
+                TagRequest::Stable { .. } => {
+                    release_cmd::create_stable_tag(&repo, &tag, target, identity, signing_key)
+                        .await?
+                }
+            }
+            audit.record(AuditAction::TagCreated, &tag).await;
+            println!("tag: created {} on {}", tag, target);
+        }
+    }
+
+    if !opts.push {
+        return Ok(());
+    }
+    if opts.dry_run {
+        println!("tag: dry-run, would push {}", tag);
+        return Ok(());
+    }
+    cancel::checkpoint("pushing the tag")?;
+    // Pushing a tag origin already has at the same object is a no-op, so re-runs are fine.
+    release_cmd::push_tag(&ctx.repo_root, &tag).await?;
+    audit.record(AuditAction::TagPushed, &tag).await;
+    println!("tag: pushed {} to origin", tag);
+    Ok(())
+}
+
+/// Highest-numbered local rc tag for `version`.
+fn latest_rc_tag(repo: &Repository, tags: &TagFormat, version: &semver::Version) -> Result<String> {
+    let mut latest: Option<(u32, String)> = None;
+    for r in repo.references_glob("refs/tags/*")?.flatten() {
+        if let Some(name) = r.shorthand()
+            && let Some((v, n)) = tags.parse_rc(name)
+            && v == *version
+            && latest.as_ref().is_none_or(|(best, _)| n > *best)
+        {
+            latest = Some((n, name.to_string()));
+        }
+    }
+    latest.map(|(_, tag)| tag).with_context(|| {
+        format!(
+            "no rc tag for {} in this clone; fetch tags or pass --from-rc",
+            version
+        )
+    })
+}
+
+fn tag_commit(repo: &Repository, tag: &str) -> Result<Oid> {
+    Ok(repo
+        .revparse_single(&format!("refs/tags/{}", tag))
+        .with_context(|| format!("tag {} not found", tag))?
+        .peel_to_commit()
+        .with_context(|| format!("tag {} does not point to a commit", tag))?
+        .id())
+}
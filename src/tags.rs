@@ -0,0 +1,103 @@
+use semver::Version;
+
+/// How release tags are spelled: `<prefix>X.Y.Z` for stable releases and
+/// `<prefix>X.Y.Z-rc.N` for release candidates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFormat {
+    prefix: String,
+}
+
+impl Default for TagFormat {
+    fn default() -> Self {
+        Self::new("v")
+    }
+}
+
+impl TagFormat {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+        }
+    }
+
+    pub fn stable(&self, version: &Version) -> String {
+        format!(
+            "{}{}.{}.{}",
+            self.prefix, version.major, version.minor, version.patch
+        )
+    }
+
+    pub fn rc(&self, version: &Version, n: u32) -> String {
+        format!("{}-rc.{}", self.stable(version), n)
+    }
+
+    /// Shape of a stable tag for error messages, e.g. `vX.Y.Z`.
+    pub fn stable_pattern(&self) -> String {
+        format!("{}X.Y.Z", self.prefix)
+    }
+
+    /// Shape of an rc tag for error messages, e.g. `vX.Y.Z-rc.N`.
+    pub fn rc_pattern(&self) -> String {
+        format!("{}X.Y.Z-rc.N", self.prefix)
+    }
+
+    /// The tag without its prefix, e.g. `0.3.1-rc.2`.
+    pub fn strip_prefix<'a>(&self, tag: &'a str) -> Option<&'a str> {
+        tag.strip_prefix(&self.prefix)
+    }
+
+    /// Version of a stable tag; `None` for rc tags and tags in another format.
+    pub fn parse_stable(&self, tag: &str) -> Option<Version> {
+        parse_plain(tag.strip_prefix(&self.prefix)?)
+    }
+
+    /// Version given on the command line, either as a stable tag or bare (`1.2.3`).
+    pub fn parse_version_arg(&self, arg: &str) -> Option<Version> {
+        self.parse_stable(arg).or_else(|| Version::parse(arg).ok())
+    }
+
+    /// Version and rc number of an rc tag.
+    pub fn parse_rc(&self, tag: &str) -> Option<(Version, u32)> {
+        let (version, n) = tag.strip_prefix(&self.prefix)?.split_once("-rc.")?;
+        if n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some((parse_plain(version)?, n.parse().ok()?))
+    }
+}
+
+/// `X.Y.Z` with no pre-release or build metadata.
+fn parse_plain(s: &str) -> Option<Version> {
+    let version = Version::parse(s).ok()?;
+    (version.pre.is_empty() && version.build.is_empty()).then_some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_custom_prefixes() {
+        let version = Version::new(1, 2, 3);
+        for prefix in ["v", "", "release-", "bar-v"] {
+            let tags = TagFormat::new(prefix);
+            let stable = tags.stable(&version);
+            let rc = tags.rc(&version, 4);
+            assert_eq!(stable, format!("{}1.2.3", prefix));
+            assert_eq!(tags.parse_stable(&stable), Some(version.clone()));
+            assert_eq!(tags.parse_stable(&rc), None);
+            assert_eq!(tags.parse_rc(&rc), Some((version.clone(), 4)));
+            assert_eq!(tags.parse_rc(&stable), None);
+            assert_eq!(tags.strip_prefix(&rc), Some("1.2.3-rc.4"));
+        }
+        let release = TagFormat::new("release-");
+        assert_eq!(release.parse_stable("v1.2.3"), None);
+        assert_eq!(release.parse_rc("release-1.2.3-rc.+1"), None);
+        assert_eq!(TagFormat::new("").parse_stable("v1.2.3"), None);
+        assert_eq!(
+            release.parse_version_arg("release-1.2.3"),
+            Some(version.clone())
+        );
+        assert_eq!(release.parse_version_arg("1.2.3"), Some(version));
+    }
+}
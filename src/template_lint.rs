@@ -0,0 +1,108 @@
+use std::sync::LazyLock;
+
+use anyhow::{Result, bail};
+use regex::Regex;
+
+use crate::net;
+
+/// An http(s) URL, up to whitespace or a closing bracket.
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"https?://[^\s)\]]*").unwrap());
+
+/// Reject rendered template output that still looks unfinished.
+///
+/// Flags leftover Tera delimiters, `Label:` lines whose section has no content, and
+/// URLs that cannot be valid (placeholders or embedded whitespace).
+pub fn lint(kind: &str, body: &str) -> Result<()> {
+    let mut problems = Vec::new();
+
+    for (idx, line) in body.lines().enumerate() {
+        for marker in ["{{", "}}", "{%", "%}"] {
+            if line.contains(marker) {
+                problems.push(format!("line {}: unreplaced `{}`", idx + 1, marker));
+            }
+        }
+    }
+
+    let lines: Vec<&str> = body.lines().collect();
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if !is_section_label(trimmed) {
+            continue;
+        }
+        let next = lines[idx + 1..]
+            .iter()
+            .map(|l| l.trim())
+            .find(|l| !l.is_empty());
+        match next {
+            None => problems.push(format!("line {}: section `{}` is empty", idx + 1, trimmed)),
+            Some(next) if is_section_label(next) || next.starts_with('#') => {
+                problems.push(format!("line {}: section `{}` is empty", idx + 1, trimmed))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for url in extract_urls(body) {
+        if url.contains('<') || url.contains('>') || url.ends_with("//") {
+            problems.push(format!("malformed URL `{}`", url));
+        }
+    }
+
+    if !problems.is_empty() {
+        bail!(
+            "rendered {} template failed validation:\n  - {}",
+            kind,
+            problems.join("\n  - ")
+        );
+    }
+    Ok(())
+}
+
+/// Confirm each URL answers with a success status.
+pub async fn check_links(urls: &[&str]) -> Result<()> {
+    let client = net::client()?;
+    for url in urls {
+        let resp = client.head(*url).send().await?;
+        if !resp.status().is_success() {
+            bail!("link {} is not reachable: {}", url, resp.status());
+        }
+    }
+    Ok(())
+}
+
+fn is_section_label(line: &str) -> bool {
+    line.ends_with(':') && !line.starts_with('-') && !line.starts_with('*')
+}
+
+fn extract_urls(body: &str) -> Vec<&str> {
+    URL.find_iter(body).map(|m| m.as_str()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lint;
+
+    #[test]
+    fn lint_accepts_complete_body() {
+        let body =
+            "# Title\n\nChanged crates:\n- foo: 0.1.0 → 0.1.1\n\nSee https://example.com/a.\n";
+        assert!(lint("release", body).is_ok());
+    }
+
+    #[test]
+    fn lint_reports_leftovers_and_empty_sections() {
+        let body = "# {{ repo }}\n\nArtifacts:\n\nChanged crates:\n";
+        let err = lint("vote", body).unwrap_err().to_string();
+        assert!(err.contains("unreplaced `{{`"));
+        assert!(err.contains("section `Artifacts:` is empty"));
+        assert!(err.contains("section `Changed crates:` is empty"));
+    }
+
+    #[test]
+    fn lint_rejects_placeholder_urls() {
+        let err = lint("vote", "SVN: https://dist.apache.org/<none>/\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("malformed URL"));
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tera::{Context as TeraContext, Tera};
+
+use crate::discussion::{self, FrontMatter};
+use crate::template_lint;
+
+/// Repository directory whose files replace or extend the compiled-in templates.
+pub const DIR: &str = ".asfship/templates";
+
+/// Compiled-in templates. Each is also registered as `default/<name>`, so an override
+/// can `{% extends %}` or `{% include %}` the template it replaces.
+const DEFAULTS: [(&str, &str); 8] = [
+    ("start.md", include_str!("../templates/start.md")),
+    ("vote.md", include_str!("../templates/vote.md")),
+    ("release.md", include_str!("../templates/release.md")),
+    ("retract.md", include_str!("../templates/retract.md")),
+    ("result.md", include_str!("../templates/result.md")),
+    (
+        "partials/header.md",
+        include_str!("../templates/partials/header.md"),
+    ),
+    (
+        "partials/footer.md",
+        include_str!("../templates/partials/footer.md"),
+    ),
+    (
+        "partials/artifacts.md",
+        include_str!("../templates/partials/artifacts.md"),
+    ),
+];
+
+/// The discussion and announcement templates of a repository: the compiled-in defaults
+/// with every file under [`DIR`] layered on top, by path relative to it.
+pub struct Templates {
+    tera: Tera,
+    fronts: HashMap<String, FrontMatter>,
+}
+
+impl Templates {
+    pub fn load(repo_root: &Path) -> Result<Self> {
+        let mut sources: HashMap<String, String> = HashMap::new();
+        for (name, source) in DEFAULTS {
+            sources.insert(name.to_string(), source.to_string());
+            sources.insert(format!("default/{}", name), source.to_string());
+        }
+        let dir = repo_root.join(DIR);
+        if dir.is_dir() {
+            read_overrides(&dir, &dir, &mut sources)?;
+        }
+
+        let mut fronts = HashMap::new();
+        let mut bodies = Vec::with_capacity(sources.len());
+        for (name, source) in &sources {
+            let body = match name.strip_suffix(".md") {
+                Some(kind) if !name.contains('/') => {
+                    let (front, body) = discussion::split_front_matter(kind, source)?;
+                    fronts.insert(kind.to_string(), front);
+                    body
+                }
+                _ => source.as_str(),
+            };
+            bodies.push((name.as_str(), body));
+        }
+        let mut tera = Tera::default();
+        tera.autoescape_on(Vec::new());
+        tera.add_raw_templates(bodies)
+            .with_context(|| format!("invalid templates in {}", DIR))?;
+        Ok(Self { tera, fronts })
+    }
+
+    /// Front-matter of the `kind` template.
+    pub fn front_matter(&self, kind: &str) -> FrontMatter {
+        self.fronts.get(kind).cloned().unwrap_or_default()
+    }
+
+    /// Render the `kind` template and lint the result.
+    pub fn render(&self, kind: &str, ctx: &TeraContext) -> Result<String> {
+        let body = self
+            .tera
+            .render(&format!("{}.md", kind), ctx)
+            .with_context(|| format!("failed to render {} template", kind))?;
+        template_lint::lint(kind, &body)?;
+        Ok(body)
+    }
+
+    /// Render `partials/<name>.md` on its own, for text posted outside a template body.
+    pub fn render_partial(&self, name: &str, ctx: &TeraContext) -> Result<String> {
+        self.tera
+            .render(&format!("partials/{}.md", name), ctx)
+            .with_context(|| format!("failed to render partials/{}.md", name))
+    }
+}
+
+fn read_overrides(root: &Path, dir: &Path, sources: &mut HashMap<String, String>) -> Result<()> {
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            read_overrides(root, &path, sources)?;
+            continue;
+        }
+        let Ok(rel) = path.strip_prefix(root) else {
+            continue;
+        };
+        let name = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        tracing::debug!("templates: {} overrides {}", path.display(), name);
+        sources.insert(name, source);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn overrides_replace_partials_and_extend_defaults() {
+        let mut ctx = TeraContext::new();
+        ctx.insert("repo", "demo");
+        ctx.insert("version", "1.0.0");
+        ctx.insert("tag", "v1.0.0");
+        ctx.insert("reason", "broken build");
+        ctx.insert("crates", &Vec::<String>::new());
+        ctx.insert("dev_list", &None::<String>);
+
+        let dir = TempDir::new().unwrap();
+        let builtin = Templates::load(dir.path()).unwrap();
+        let plain = builtin.render("retract", &ctx).unwrap();
+        assert!(plain.starts_with(
+            "# [ANNOUNCE] demo 1.0.0 retracted\n\nThe demo 1.0.0 release (v1.0.0) has been retracted."
+        ));
+        assert!(plain.ends_with("stay on an earlier one.\n"), "{}", plain);
+
+        let overrides = dir.path().join(DIR);
+        std::fs::create_dir_all(overrides.join("partials")).unwrap();
+        std::fs::write(
+            overrides.join("partials/header.md"),
+            "Demo is a {{ repo }} blurb.\n\n",
+        )
+        .unwrap();
+        std::fs::write(
+            overrides.join("retract.md"),
+            "+++\nlabels = [\"retracted\"]\n+++\n{% extends \"default/retract.md\" %}\
+             {% block footer %}\n\nSee SECURITY.md.{% endblock footer %}",
+        )
+        .unwrap();
+        let custom = Templates::load(dir.path()).unwrap();
+        assert_eq!(custom.front_matter("retract").labels, ["retracted"]);
+        let body = custom.render("retract", &ctx).unwrap();
+        assert!(
+            body.starts_with(
+                "# [ANNOUNCE] demo 1.0.0 retracted\n\nDemo is a demo blurb.\n\nThe demo"
+            ),
+            "{}",
+            body
+        );
+        assert!(
+            body.ends_with("earlier one.\n\nSee SECURITY.md.\n"),
+            "{}",
+            body
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::checklist::{self, Progress};
+use crate::config::VoteConfig;
+use crate::schema;
+use crate::tags::TagFormat;
+
+/// Steps of a release whose completion time is kept in `timeline.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    RcCut,
+    Synced,
+    VoteOpened,
+    VoteClosed,
+    /// `release` copied the voted artifacts to `dist/release` (`release.promote_dist`).
+    Promoted,
+    Released,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Phase::RcCut => "rc cut",
+            Phase::Synced => "synced",
+            Phase::VoteOpened => "vote opened",
+            Phase::VoteClosed => "vote closed",
+            Phase::Promoted => "promoted",
+            Phase::Released => "released",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Event {
+    phase: Phase,
+    tag: String,
+    /// RFC 3339 UTC timestamp.
+    at: String,
+    /// Whether the vote passed, on `vote_closed` events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    passed: Option<bool>,
+}
+
+impl Event {
+    fn time(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.at)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    }
+}
+
+/// Release state kept at `timeline.json` in the artifacts root (`target/asfship/` by
+/// default): when each phase of each version completed, for `status` and the summary
+/// `release` prints.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Timeline {
+    #[serde(default = "schema::current")]
+    schema_version: u32,
+    /// Events per release version, oldest first.
+    #[serde(default)]
+    versions: BTreeMap<String, Vec<Event>>,
+}
+
+/// The timeline file under `artifact_root` (see [`crate::artifacts::root`]).
+pub fn path(artifact_root: &Path) -> PathBuf {
+    artifact_root.join("timeline.json")
+}
+
+async fn load(artifact_root: &Path) -> Result<Timeline> {
+    let path = path(artifact_root);
+    let content = match tokio::fs::read_to_string(&path).await {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Timeline::default());
+        }
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", path.display()));
+        }
+    };
+    let timeline: Timeline = serde_json::from_str(&content)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    schema::ensure_supported("timeline.json", timeline.schema_version)?;
+    Ok(timeline)
+}
+
+/// Note that `phase` of `version` completed now for `tag`.
+///
+/// Only the first completion counts, so a re-run after a partial failure keeps the
+/// original time. Failures are logged and never abort the caller.
+pub async fn record(artifact_root: &Path, version: &semver::Version, phase: Phase, tag: &str) {
+    if let Err(err) = try_record(artifact_root, version, phase, tag, None, Utc::now()).await {
+        tracing::warn!("timeline: failed to record {} of {}: {:#}", phase, tag, err);
+    }
+}
+
+/// [`record`] the close of the vote on `tag` along with its outcome.
+pub async fn record_vote_closed(
+    artifact_root: &Path,
+    version: &semver::Version,
+    tag: &str,
+    passed: bool,
+) {
+    let phase = Phase::VoteClosed;
+    if let Err(err) = try_record(artifact_root, version, phase, tag, Some(passed), Utc::now()).await
+    {
+        tracing::warn!("timeline: failed to record {} of {}: {:#}", phase, tag, err);
+    }
+}
+
+async fn try_record(
+    artifact_root: &Path,
+    version: &semver::Version,
+    phase: Phase,
+    tag: &str,
+    passed: Option<bool>,
+    at: DateTime<Utc>,
+) -> Result<()> {
+    let mut timeline = load(artifact_root).await?;
+    timeline.schema_version = schema::SCHEMA_VERSION;
+    let events = timeline.versions.entry(version.to_string()).or_default();
+    if events.iter().any(|e| e.phase == phase && e.tag == tag) {
+        return Ok(());
+    }
+    events.push(Event {
+        phase,
+        tag: tag.to_string(),
+        at: at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        passed,
+    });
+    let path = path(artifact_root);
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let mut json = serde_json::to_string_pretty(&timeline)?;
+    json.push('\n');
+    tokio::fs::write(&path, json)
+        .await
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Print the timeline of `version`, or of the version with the most recent event, and
+/// where it stands on the ASF release checklist.
+pub async fn run_status(
+    repo_root: &Path,
+    version: Option<&str>,
+    artifact_root: &Path,
+    tags: &TagFormat,
+    vote: &VoteConfig,
+) -> Result<()> {
+    let timeline = load(artifact_root).await?;
+    let selected = match version {
+        Some(arg) => {
+            let version = tags.parse_version_arg(arg).with_context(|| {
+                format!(
+                    "{:?} is neither a version nor a {} tag",
+                    arg,
+                    tags.stable_pattern()
+                )
+            })?;
+            timeline
+                .versions
+                .get_key_value(&version.to_string())
+                .with_context(|| format!("no release steps recorded for {}", version))?
+        }
+        None => {
+            let Some(latest) = timeline
+                .versions
+                .iter()
+                .max_by_key(|(_, events)| events.iter().filter_map(Event::time).max())
+            else {
+                bail!(
+                    "no release steps recorded yet in {}",
+                    path(artifact_root).display()
+                );
+            };
+            latest
+        }
+    };
+    let now = Utc::now();
+    print_summary(selected.0, selected.1, now);
+    let items = checklist::evaluate(repo_root, artifact_root, vote, &progress(selected.1), now);
+    for line in checklist::render_text(&items) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Checklist input from the events of one version: the latest rc and its vote.
+fn progress(events: &[Event]) -> Progress {
+    let latest = |phase: Phase, tag: Option<&str>| {
+        events
+            .iter()
+            .filter(|e| e.phase == phase && tag.is_none_or(|t| e.tag == t))
+            .filter_map(|e| Some((e, e.time()?)))
+            .max_by_key(|(_, at)| *at)
+    };
+    let rc_tag = latest(Phase::RcCut, None).map(|(e, _)| e.tag.clone());
+    let closed = latest(Phase::VoteClosed, rc_tag.as_deref());
+    Progress {
+        vote_opened: latest(Phase::VoteOpened, rc_tag.as_deref()).map(|(_, at)| at),
+        vote_closed: closed.map(|(_, at)| at),
+        vote_passed: closed.and_then(|(e, _)| e.passed),
+        promoted: events.iter().any(|e| e.phase == Phase::Promoted),
+        released: events.iter().any(|e| e.phase == Phase::Released),
+        rc_tag,
+    }
+}
+
+/// The most recent phase recorded for any version, or `None` before the first rc.
+pub async fn current_phase(artifact_root: &Path) -> Result<Option<Phase>> {
+    let timeline = load(artifact_root).await?;
+    Ok(timeline
+        .versions
+        .values()
+        .flatten()
+        .filter_map(|e| Some((e.time()?, e.phase)))
+        .max_by_key(|(at, _)| *at)
+        .map(|(_, phase)| phase))
+}
+
+/// Print the timeline of `version` after `release` completes it.
+pub async fn print_release_summary(artifact_root: &Path, version: &semver::Version) {
+    match load(artifact_root).await {
+        Ok(timeline) => {
+            if let Some(events) = timeline.versions.get(&version.to_string()) {
+                print_summary(&version.to_string(), events, Utc::now());
+            }
+        }
+        Err(err) => tracing::warn!("timeline: {:#}", err),
+    }
+}
+
+fn print_summary(version: &str, events: &[Event], now: DateTime<Utc>) {
+    for line in summary(version, events, now) {
+        println!("{}", line);
+    }
+}
+
+fn summary(version: &str, events: &[Event], now: DateTime<Utc>) -> Vec<String> {
+    let mut events: Vec<(&Event, DateTime<Utc>)> =
+        events.iter().filter_map(|e| Some((e, e.time()?))).collect();
+    events.sort_by_key(|(_, at)| *at);
+    let mut lines = vec![format!("timeline: {}", version)];
+    let (Some((_, first)), Some((last_event, last))) = (events.first(), events.last()) else {
+        return lines;
+    };
+    let width = events
+        .iter()
+        .map(|(e, _)| e.phase.to_string().len())
+        .max()
+        .unwrap_or_default();
+    let mut previous: Option<DateTime<Utc>> = None;
+    for (event, at) in &events {
+        let mut line = format!(
+            "  {}  {:width$}  {}",
+            at.format("%Y-%m-%d %H:%M UTC"),
+            event.phase.to_string(),
+            event.tag,
+            width = width
+        );
+        if let Some(previous) = previous {
+            line.push_str(&format!("  (+{})", human_duration(*at - previous)));
+        }
+        lines.push(line);
+        previous = Some(*at);
+    }
+
+    let rcs = events
+        .iter()
+        .filter(|(e, _)| e.phase == Phase::RcCut)
+        .count();
+    let votes: Vec<TimeDelta> = events
+        .iter()
+        .filter(|(e, _)| e.phase == Phase::VoteOpened)
+        .filter_map(|(opened, start)| {
+            let (_, end) = events
+                .iter()
+                .find(|(e, _)| e.phase == Phase::VoteClosed && e.tag == opened.tag)?;
+            Some(*end - *start)
+        })
+        .collect();
+    let mut totals = vec![format!("{} rc{}", rcs, if rcs == 1 { "" } else { "s" })];
+    if !votes.is_empty() {
+        let voting = votes.into_iter().fold(TimeDelta::zero(), |sum, d| sum + d);
+        totals.push(format!("{} voting", human_duration(voting)));
+    }
+    if last_event.phase == Phase::Released {
+        lines.push(format!(
+            "  total: {} from first step to release ({})",
+            human_duration(*last - *first),
+            totals.join(", ")
+        ));
+    } else {
+        lines.push(format!(
+            "  in progress: {} since the first step ({}); {} ago: {}",
+            human_duration(now - *first),
+            totals.join(", "),
+            human_duration(now - *last),
+            last_event.phase
+        ));
+    }
+    lines
+}
+
+/// Coarse duration for humans: the two largest of days, hours, and minutes.
+pub(crate) fn human_duration(d: TimeDelta) -> String {
+    let minutes = d.num_minutes().max(0);
+    let (days, hours, mins) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) if mins == 0 => "<1m".to_string(),
+        (0, 0) => format!("{}m", mins),
+        (0, _) => format!("{}h {}m", hours, mins),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn phases_are_recorded_once_and_summarized() {
+        let dir = TempDir::new().unwrap();
+        let version = semver::Version::new(0, 2, 0);
+        let start = DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let steps = [
+            (0, Phase::RcCut, "v0.2.0-rc.1"),
+            (20, Phase::Synced, "v0.2.0-rc.1"),
+            (60, Phase::VoteOpened, "v0.2.0-rc.1"),
+            (90, Phase::RcCut, "v0.2.0-rc.1"),
+            (60 + 72 * 60, Phase::VoteClosed, "v0.2.0-rc.1"),
+            (61 + 73 * 60, Phase::Released, "v0.2.0"),
+        ];
+        for (minutes, phase, tag) in steps {
+            let at = start + TimeDelta::minutes(minutes);
+            try_record(dir.path(), &version, phase, tag, None, at)
+                .await
+                .unwrap();
+        }
+        let timeline = load(dir.path()).await.unwrap();
+        let events = &timeline.versions["0.2.0"];
+        assert_eq!(events.len(), 5);
+        assert_eq!(
+            summary("0.2.0", events, Utc::now()),
+            [
+                "timeline: 0.2.0",
+                "  2024-05-01 10:00 UTC  rc cut       v0.2.0-rc.1",
+                "  2024-05-01 10:20 UTC  synced       v0.2.0-rc.1  (+20m)",
+                "  2024-05-01 11:00 UTC  vote opened  v0.2.0-rc.1  (+40m)",
+                "  2024-05-04 11:00 UTC  vote closed  v0.2.0-rc.1  (+3d 0h)",
+                "  2024-05-04 12:01 UTC  released     v0.2.0  (+1h 1m)",
+                "  total: 3d 2h from first step to release (1 rc, 3d 0h voting)",
+            ]
+        );
+
+        let open = &events[..3];
+        let now = start + TimeDelta::hours(5);
+        assert_eq!(
+            summary("0.2.0", open, now).last().unwrap(),
+            "  in progress: 5h 0m since the first step (1 rc); 4h 0m ago: vote opened"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+
+use anyhow::{Context, Result, bail};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+use crate::endpoints;
+use crate::github;
+use crate::infer::InferredContext;
+use crate::net;
+
+/// Write access a command needs from the GitHub token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Tags, branches, releases, and release assets.
+    Contents,
+    Discussions,
+    PullRequests,
+    /// Issues and milestones.
+    Issues,
+    Attestations,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Contents => "contents:write",
+            Self::Discussions => "discussions:write",
+            Self::PullRequests => "pull-requests:write",
+            Self::Issues => "issues:write",
+            Self::Attestations => "attestations:write",
+        })
+    }
+}
+
+/// Fail unless the token may perform every write in `needed` on the repository.
+///
+/// Each permission is probed with a deliberately invalid write request: GitHub
+/// rejects the payload (422) when the token is allowed, and answers 403/404 when it
+/// is not, so nothing is created. Discussions are read first, since a write probe
+/// into a repository without readable categories fails on the missing category
+/// rather than on permission. Runs before a command mutates anything, so a missing
+/// permission never surfaces halfway through a release.
+pub async fn verify(ctx: &InferredContext, needed: &[Permission]) -> Result<()> {
+    if needed.is_empty() {
+        return Ok(());
+    }
+    let token = github::token().await?;
+    let client = net::client()?;
+    let api = endpoints::github_api();
+    let repo_url = format!("{}/repos/{}/{}", api, ctx.repo_owner, ctx.repo_name);
+    let resp = client
+        .get(&repo_url)
+        .bearer_auth(&token)
+        .header(reqwest::header::USER_AGENT, "asfship")
+        .send()
+        .await
+        .context("failed to look up the repository for the token check")?;
+    if !resp.status().is_success() {
+        bail!(
+            "{} cannot read {}/{} ({})",
+            github::token_source(),
+            ctx.repo_owner,
+            ctx.repo_name,
+            resp.status()
+        );
+    }
+    let scopes = resp
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let repo: Value = serde_json::from_slice(&resp.bytes().await?)?;
+    if repo["permissions"]["push"] == json!(false) {
+        bail!(
+            "the account behind {} has no write access to {}/{}",
+            github::token_source(),
+            ctx.repo_owner,
+            ctx.repo_name
+        );
+    }
+
+    let mut missing = Vec::new();
+    for &permission in needed {
+        if permission == Permission::Discussions {
+            let resp = client
+                .get(format!("{}/discussions/categories", repo_url))
+                .bearer_auth(&token)
+                .header(reqwest::header::USER_AGENT, "asfship")
+                .send()
+                .await
+                .with_context(|| format!("failed to probe {}", permission))?;
+            if !allowed(resp.status()) {
+                missing.push(permission);
+                continue;
+            }
+        }
+        let (url, body) = match permission {
+            Permission::Contents => (
+                format!("{}/git/refs", repo_url),
+                json!({ "ref": "refs/heads/asfship-permission-probe", "sha": "0".repeat(40) }),
+            ),
+            Permission::PullRequests => (format!("{}/pulls", repo_url), json!({})),
+            Permission::Issues => (format!("{}/milestones", repo_url), json!({})),
+            Permission::Attestations => (format!("{}/attestations", repo_url), json!({})),
+            Permission::Discussions => (format!("{}/discussions", repo_url), json!({})),
+        };
+        let resp = client
+            .post(&url)
+            .bearer_auth(&token)
+            .header(reqwest::header::USER_AGENT, "asfship")
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .with_context(|| format!("failed to probe {}", permission))?;
+        if !allowed(resp.status()) {
+            missing.push(permission);
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<String> = missing.iter().map(|p| p.to_string()).collect();
+    let hint = match scopes {
+        Some(scopes) => format!(
+            "; classic tokens need the `repo` (or `public_repo`) scope, this one has `{}`",
+            scopes
+        ),
+        None => "; grant it on the fine-grained token, GitHub App, or workflow `permissions:`"
+            .to_string(),
+    };
+    bail!(
+        "{} is missing {} on {}/{}{}",
+        github::token_source(),
+        names.join(", "),
+        ctx.repo_owner,
+        ctx.repo_name,
+        hint
+    )
+}
+
+/// Whether a probe response shows the request was authorized (and rejected only as invalid).
+fn allowed(status: StatusCode) -> bool {
+    !matches!(status, StatusCode::FORBIDDEN | StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejected_payloads_count_as_allowed() {
+        assert!(allowed(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(allowed(StatusCode::OK));
+        assert!(!allowed(StatusCode::FORBIDDEN));
+        assert!(!allowed(StatusCode::NOT_FOUND));
+    }
+}
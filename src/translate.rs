@@ -0,0 +1,193 @@
+use std::process::Stdio;
+
+use anyhow::{Context, Result, bail};
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::artifacts;
+use crate::audit::{AuditAction, AuditLog};
+use crate::config::{TranslationConfig, TranslationOutput};
+use crate::discussion::{self, DiscussionResponse};
+use crate::github;
+use crate::infer::InferredContext;
+use crate::net;
+
+/// The release announcement a translation hook works on.
+pub struct Announcement<'a> {
+    pub tag: &'a str,
+    pub title: &'a str,
+    pub body: &'a str,
+}
+
+/// Print the translations `publish` would produce.
+pub fn describe(ctx: &InferredContext) {
+    for hook in &ctx.config.release.translations {
+        let source = match (&hook.command, &hook.url_env) {
+            (Some(command), _) => format!("command `{}`", command),
+            (None, Some(var)) => format!("endpoint from {}", var),
+            (None, None) => "endpoint".to_string(),
+        };
+        let output = match hook.output {
+            TranslationOutput::Comment => "discussion comment",
+            TranslationOutput::File => "file",
+        };
+        println!(
+            "  translation: {} via {} as a {}",
+            hook.language, source, output
+        );
+    }
+}
+
+/// Run every `[[release.translations]]` hook on the announcement and post each result as
+/// a comment on the release `discussion`, or write it next to the run's other files.
+///
+/// Like the other follow-ups of a published release, failures only warn.
+pub async fn publish(
+    ctx: &InferredContext,
+    announcement: &Announcement<'_>,
+    discussion: &DiscussionResponse,
+    audit: &AuditLog,
+) {
+    for hook in &ctx.config.release.translations {
+        if let Err(err) = publish_one(ctx, hook, announcement, discussion, audit).await {
+            tracing::warn!("translations: {}: {:#}", hook.language, err);
+            eprintln!(
+                "warning: {} translation of the announcement failed: {:#}",
+                hook.language, err
+            );
+        }
+    }
+}
+
+async fn publish_one(
+    ctx: &InferredContext,
+    hook: &TranslationConfig,
+    announcement: &Announcement<'_>,
+    discussion: &DiscussionResponse,
+    audit: &AuditLog,
+) -> Result<()> {
+    let text = translate(ctx, hook, announcement).await?;
+    match hook.output {
+        TranslationOutput::Comment => {
+            let number = discussion
+                .number
+                .context("the release discussion has no number to comment on")?;
+            let body = format!("Translation ({}):\n\n{}", hook.language, text);
+            let gh = github::client()?;
+            discussion::post_comment(&gh, &ctx.repo_owner, &ctx.repo_name, number, &body).await?;
+            audit
+                .record(AuditAction::DiscussionCommented, &discussion.html_url)
+                .await;
+            println!(
+                "release: {} translation posted on {}",
+                hook.language, discussion.html_url
+            );
+        }
+        TranslationOutput::File => {
+            let dir = artifacts::prepare(ctx, announcement.tag).await?;
+            let path = dir.join(format!("ANNOUNCEMENT.{}.md", hook.language));
+            tokio::fs::write(&path, &text)
+                .await
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            println!(
+                "release: {} translation written to {}",
+                hook.language,
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Translate the announcement with `hook`; the result must not be empty.
+async fn translate(
+    ctx: &InferredContext,
+    hook: &TranslationConfig,
+    announcement: &Announcement<'_>,
+) -> Result<String> {
+    let text = match (&hook.command, hook.resolve_url()?) {
+        (Some(command), _) => run_command(ctx, command, &hook.language, announcement).await?,
+        (None, Some(url)) => post(ctx, &url, &hook.language, announcement).await?,
+        (None, None) => bail!("no `command` or `url` configured"),
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        bail!("the hook returned no text");
+    }
+    Ok(format!("{}\n", text))
+}
+
+/// Run `command` through the shell in the repository root with the announcement on stdin.
+async fn run_command(
+    ctx: &InferredContext,
+    command: &str,
+    language: &str,
+    announcement: &Announcement<'_>,
+) -> Result<String> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    let mut child = cmd
+        .arg(command)
+        .current_dir(&ctx.repo_root)
+        .env("ASFSHIP_LANGUAGE", language)
+        .env("ASFSHIP_TAG", announcement.tag)
+        .env("ASFSHIP_TITLE", announcement.title)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run `{}`", command))?;
+    let mut stdin = child.stdin.take().context("hook stdin is not piped")?;
+    let input = announcement.body.to_string();
+    // Feed stdin alongside reading stdout so a hook that streams does not deadlock.
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(input.as_bytes()).await;
+    });
+    let output = child.wait_with_output().await?;
+    let _ = writer.await;
+    if !output.status.success() {
+        bail!(
+            "`{}` exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context("the hook did not print UTF-8")
+}
+
+/// POST the announcement as JSON; the response body is the translation.
+async fn post(
+    ctx: &InferredContext,
+    url: &str,
+    language: &str,
+    announcement: &Announcement<'_>,
+) -> Result<String> {
+    let resp = net::client()?
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(
+            json!({
+                "language": language,
+                "repo": format!("{}/{}", ctx.repo_owner, ctx.repo_name),
+                "tag": announcement.tag,
+                "title": announcement.title,
+                "body": announcement.body,
+            })
+            .to_string(),
+        )
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        bail!("endpoint answered {}", resp.status());
+    }
+    Ok(resp.text().await?)
+}
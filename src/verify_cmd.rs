@@ -0,0 +1,389 @@
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+use anyhow::{Context, Result, bail};
+use tokio::fs as async_fs;
+
+use crate::artifacts;
+use crate::asf::{self, ARCHIVE_EXTENSIONS, ArchiveNaming, is_archive};
+use crate::endpoints;
+use crate::gpg;
+use crate::infer::InferredContext;
+use crate::manifest::{self, RunManifest};
+use crate::net;
+use crate::provenance;
+use crate::rc_release::{
+    RcReleaseInfo, download_fresh, download_signed_manifest, fetch_latest_rc_release,
+    fetch_rc_release_by_tag,
+};
+
+/// One line of the compliance checklist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Check {
+    ok: bool,
+    text: String,
+}
+
+impl Check {
+    fn new(ok: bool, text: String) -> Self {
+        Self { ok, text }
+    }
+}
+
+/// Check an rc release's assets against the ASF release naming and signing rules and
+/// print a Markdown checklist for the vote thread; fails when any item does.
+pub async fn run_verify(ctx: &InferredContext, rc_tag: Option<&str>) -> Result<()> {
+    let release = match rc_tag {
+        Some(tag) => fetch_rc_release_by_tag(ctx, tag).await?,
+        None => fetch_latest_rc_release(ctx).await?,
+    };
+    let mut checks = check_assets(&ArchiveNaming::of(ctx), &release);
+    checks.extend(manifest_checks(ctx, &release).await?);
+    print!("{}", render(&release.tag, &checks));
+    let failed = checks.iter().filter(|c| !c.ok).count();
+    if failed > 0 {
+        bail!("{} of {} compliance checks failed", failed, checks.len());
+    }
+    Ok(())
+}
+
+fn check_assets(naming: &ArchiveNaming, release: &RcReleaseInfo) -> Vec<Check> {
+    let pattern = naming.pattern(&ARCHIVE_EXTENSIONS);
+    let names: BTreeSet<&str> = release.assets.iter().map(|a| a.name.as_str()).collect();
+    let expected = naming.template(release.rc_number, &ARCHIVE_EXTENSIONS);
+
+    let mut checks = Vec::new();
+    let archives: Vec<&str> = names.iter().copied().filter(|n| is_archive(n)).collect();
+    checks.push(Check::new(
+        !archives.is_empty(),
+        "the release has at least one source archive".to_string(),
+    ));
+    for name in &archives {
+        let matched = pattern.captures(name).is_some_and(|c| {
+            c.name("rc")
+                .is_some_and(|rc| rc.as_str() == release.rc_number.to_string())
+        });
+        checks.push(Check::new(
+            matched,
+            format!("`{}` is named `{}`", name, expected),
+        ));
+        for (suffix, what) in [
+            ("sha512", "SHA-512 checksum"),
+            ("asc", "detached signature"),
+        ] {
+            checks.push(Check::new(
+                names.contains(format!("{}.{}", name, suffix).as_str()),
+                format!("`{}` has a {} (`.{}`)", name, what, suffix),
+            ));
+        }
+    }
+
+    let provenance = provenance::FILE_NAME;
+    let disallowed: Vec<&str> = names
+        .iter()
+        .copied()
+        .filter(|name| {
+            let signed = name
+                .strip_suffix(".sha512")
+                .or_else(|| name.strip_suffix(".asc"))
+                .unwrap_or(name);
+            !(is_archive(signed) || signed == manifest::FILE_NAME || signed == provenance)
+        })
+        .collect();
+    checks.push(Check::new(
+        disallowed.is_empty(),
+        if disallowed.is_empty() {
+            "no disallowed files (MD5/SHA-1 checksums, binaries, or other extensions)".to_string()
+        } else {
+            format!("disallowed files: {}", disallowed.join(", "))
+        },
+    ));
+    checks
+}
+
+/// Check the gpg signature of the run manifest, which pins every artifact digest and the
+/// commit the release manager built from, then the artifacts against those digests.
+///
+/// Releases without `manifest.json.asc` are only flagged when `prerelease.sign_manifest`
+/// says they should have one.
+async fn manifest_checks(ctx: &InferredContext, release: &RcReleaseInfo) -> Result<Vec<Check>> {
+    let dir = artifacts::run_dir(ctx, &release.tag);
+    let Some((manifest_path, signature_path)) =
+        download_signed_manifest(ctx, &release.tag, &dir).await?
+    else {
+        let missing = Check::new(
+            false,
+            format!(
+                "`{}` has a detached signature (`.asc`)",
+                manifest::FILE_NAME
+            ),
+        );
+        return Ok(ctx
+            .config
+            .prerelease
+            .sign_manifest
+            .then_some(missing)
+            .into_iter()
+            .collect());
+    };
+    let run: RunManifest = serde_json::from_slice(&async_fs::read(&manifest_path).await?)
+        .with_context(|| format!("invalid {} on {}", manifest::FILE_NAME, release.tag))?;
+    let signer = asf::release_manager(&ctx.config.asf)?.and_then(|rm| rm.gpg_fingerprint);
+    let verified = match trusted_keys(ctx, signer.as_deref()).await {
+        Ok(keys) => {
+            let keyring = dir.join("keyring");
+            gpg::verify(
+                &signature_path,
+                &manifest_path,
+                &keyring,
+                &keys,
+                signer.as_deref(),
+            )
+            .await
+        }
+        Err(err) => Err(err),
+    };
+    Ok(vec![
+        signature_check(&run, verified),
+        digest_check(release, &run).await,
+    ])
+}
+
+/// The project's `KEYS` file from dist/release, the only keys a release may be signed
+/// with; without one, the public key of `asf.gpg_fingerprint` from the local keyring.
+async fn trusted_keys(ctx: &InferredContext, signer: Option<&str>) -> Result<Vec<u8>> {
+    let url = format!(
+        "{}/{}/KEYS",
+        endpoints::dist_release(),
+        asf::project_name(ctx)
+    );
+    let resp = net::client()?.get(&url).send().await;
+    match resp {
+        Ok(resp) if resp.status().is_success() => Ok(resp.bytes().await?.to_vec()),
+        _ => match signer {
+            Some(fpr) => gpg::export_public_key(fpr).await,
+            None => bail!(
+                "no KEYS file at {} and no asf.gpg_fingerprint to check against",
+                url
+            ),
+        },
+    }
+}
+
+/// Download the artifacts the manifest lists and compare each with its pinned digest.
+///
+/// The files go to a scratch directory rather than the run directory, where the release
+/// manager's own copies would be compared with themselves.
+async fn digest_check(release: &RcReleaseInfo, run: &RunManifest) -> Check {
+    let mut pinned = release.clone();
+    pinned.assets.retain_mut(|asset| {
+        let entry = run.files.iter().find(|f| f.name == asset.name);
+        asset.sha512 = entry.map(|f| f.sha512.clone());
+        entry.is_some()
+    });
+    let missing: Vec<&str> = run
+        .files
+        .iter()
+        .filter(|f| !pinned.assets.iter().any(|a| a.name == f.name))
+        .map(|f| f.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Check::new(
+            false,
+            format!(
+                "`{}` lists files the release lacks: {}",
+                manifest::FILE_NAME,
+                missing.join(", ")
+            ),
+        );
+    }
+    let downloaded = match tempfile::TempDir::new() {
+        Ok(scratch) => download_fresh(&pinned, scratch.path()).await,
+        Err(err) => Err(err.into()),
+    };
+    match downloaded {
+        Ok(()) => Check::new(
+            true,
+            format!(
+                "all {} files match their digests in `{}`",
+                run.files.len(),
+                manifest::FILE_NAME
+            ),
+        ),
+        Err(err) => Check::new(
+            false,
+            format!("files do not match `{}`: {}", manifest::FILE_NAME, err),
+        ),
+    }
+}
+
+fn signature_check(run: &RunManifest, verified: Result<String>) -> Check {
+    match verified {
+        Ok(signer) => Check::new(
+            true,
+            format!(
+                "`{}` ({} files from commit `{}`) is signed: {}",
+                manifest::FILE_NAME,
+                run.files.len(),
+                run.commit,
+                signer
+            ),
+        ),
+        Err(err) => Check::new(
+            false,
+            format!("`{}.asc` does not verify: {}", manifest::FILE_NAME, err),
+        ),
+    }
+}
+
+fn render(tag: &str, checks: &[Check]) -> String {
+    let mut out = format!("### ASF release compliance for {}\n\n", tag);
+    for check in checks {
+        writeln!(
+            &mut out,
+            "- [{}] {}",
+            if check.ok { "x" } else { " " },
+            check.text
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc_release::RcAsset;
+
+    fn release(names: &[&str]) -> RcReleaseInfo {
+        RcReleaseInfo {
+            tag: "v0.2.0-rc.3".into(),
+            version: semver::Version::new(0, 2, 0),
+            rc_number: 3,
+            assets: names
+                .iter()
+                .map(|name| RcAsset {
+                    name: name.to_string(),
+                    download_url: String::new(),
+                    size: 1,
+                    sha512: None,
+                })
+                .collect(),
+            vote: None,
+        }
+    }
+
+    fn failed(checks: &[Check]) -> Vec<&str> {
+        checks
+            .iter()
+            .filter(|c| !c.ok)
+            .map(|c| c.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn archive_names_checksums_and_signatures_are_checked() {
+        let ok = release(&[
+            "apache-foo-0.2.0-rc3-src.tar.gz",
+            "apache-foo-0.2.0-rc3-src.tar.gz.sha512",
+            "apache-foo-0.2.0-rc3-src.tar.gz.asc",
+            "apache-foo-foo-core-0.4.1-rc3-src.zip",
+            "apache-foo-foo-core-0.4.1-rc3-src.zip.sha512",
+            "apache-foo-foo-core-0.4.1-rc3-src.zip.asc",
+            "manifest.json",
+        ]);
+        let foo = ArchiveNaming::new("foo", false);
+        assert_eq!(failed(&check_assets(&foo, &ok)), Vec::<&str>::new());
+
+        let bad = release(&[
+            "apache-foo-0.2.0-rc2-src.tar.gz",
+            "apache-foo-0.2.0-rc2-src.tar.gz.sha512",
+            "apache-foo-0.2.0-rc2-src.tar.gz.md5",
+            "foo-0.2.0.crate",
+        ]);
+        let checks = check_assets(&foo, &bad);
+        assert_eq!(
+            failed(&checks),
+            [
+                "`apache-foo-0.2.0-rc2-src.tar.gz` is named `apache-foo-<version>-rc3-src.<tar.gz|tar.zst|zip>`",
+                "`apache-foo-0.2.0-rc2-src.tar.gz` has a detached signature (`.asc`)",
+                "disallowed files: apache-foo-0.2.0-rc2-src.tar.gz.md5, foo-0.2.0.crate",
+            ]
+        );
+        assert!(render("v0.2.0-rc.3", &checks).contains("\n- [ ] disallowed files: "));
+
+        let podling = ArchiveNaming::new("foo", true);
+        assert_eq!(failed(&check_assets(&podling, &ok)).len(), 2);
+    }
+
+    #[test]
+    fn podling_archives_from_packaging_pass_verify() {
+        let podling = ArchiveNaming::new("foo", true);
+        let main = podling.base_name(None, &semver::Version::new(0, 2, 0), Some(3));
+        let core = podling.base_name(Some("foo-core"), &semver::Version::new(0, 4, 1), Some(3));
+        assert_eq!(main, "apache-foo-0.2.0-incubating-rc3-src");
+        assert_eq!(core, "apache-foo-foo-core-0.4.1-incubating-rc3-src");
+        let names: Vec<String> = [format!("{}.tar.gz", main), format!("{}.zip", core)]
+            .into_iter()
+            .flat_map(|a| [format!("{}.sha512", a), format!("{}.asc", a), a])
+            .collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let podling_release = release(&names);
+        assert_eq!(
+            failed(&check_assets(&podling, &podling_release)),
+            Vec::<&str>::new()
+        );
+        let checks = check_assets(&ArchiveNaming::new("foo", false), &podling_release);
+        assert_eq!(failed(&checks).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn digest_check_downloads_instead_of_trusting_local_files() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let name = "apache-foo-0.2.0-rc3-src.tar.gz";
+        let dir = tempfile::TempDir::new().unwrap();
+        let local = dir.path().join(name);
+        std::fs::write(&local, b"what the release manager packaged").unwrap();
+        let mut run = RunManifest::new("v0.2.0-rc.3", "0123abc");
+        run.add("foo", &local).await.unwrap();
+
+        let server = MockServer::start().await;
+        Mock::given(path(format!("/{}", name)))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"what github serves"))
+            .mount(&server)
+            .await;
+        let mut remote = release(&[name]);
+        remote.assets[0].download_url = format!("{}/{}", server.uri(), name);
+
+        let check = digest_check(&remote, &run).await;
+        assert!(!check.ok, "{}", check.text);
+        assert!(check.text.starts_with("files do not match `manifest.json`"));
+
+        std::fs::write(&local, b"what github serves").unwrap();
+        let mut run = RunManifest::new("v0.2.0-rc.3", "0123abc");
+        run.add("foo", &local).await.unwrap();
+        assert!(digest_check(&remote, &run).await.ok);
+    }
+
+    #[test]
+    fn manifest_signature_names_commit_or_gpg_error() {
+        let run = RunManifest::new("v0.2.0-rc.3", "0123abc");
+        let good = signature_check(
+            &run,
+            Ok("Good signature from \"Alice <alice@apache.org>\"".into()),
+        );
+        assert!(good.ok);
+        assert_eq!(
+            good.text,
+            "`manifest.json` (0 files from commit `0123abc`) is signed: Good signature from \"Alice <alice@apache.org>\""
+        );
+        let bad = signature_check(&run, Err(anyhow::anyhow!("BAD signature from \"Mallory\"")));
+        assert!(!bad.ok);
+        assert_eq!(
+            bad.text,
+            "`manifest.json.asc` does not verify: BAD signature from \"Mallory\""
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt;
+
+use regex::Regex;
+use tokio::process::Command;
+
+use crate::infer::InferredContext;
+
+use super::plan::Plan;
+
+/// Result of comparing a crate's public API against the base tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ApiOutcome {
+    /// Detected API breaks agree with the commit-derived bump.
+    Consistent,
+    /// The API broke but no commit is marked breaking.
+    UnmarkedBreak,
+    /// Commits are marked breaking but no API break was found.
+    BumpWithoutBreak,
+    Skipped(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ApiCheck {
+    pub crate_name: String,
+    pub outcome: ApiOutcome,
+}
+
+impl fmt::Display for ApiCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            ApiOutcome::Consistent => {
+                write!(f, "{}: consistent with planned bump", self.crate_name)
+            }
+            ApiOutcome::UnmarkedBreak => write!(
+                f,
+                "{}: WARNING public API broke but no commit is marked breaking",
+                self.crate_name
+            ),
+            ApiOutcome::BumpWithoutBreak => write!(
+                f,
+                "{}: note breaking bump planned but no API break detected",
+                self.crate_name
+            ),
+            ApiOutcome::Skipped(reason) => write!(f, "{}: skipped ({})", self.crate_name, reason),
+        }
+    }
+}
+
+/// Run `cargo semver-checks` for each changed crate against the base tag.
+pub(crate) async fn check_api(ctx: &InferredContext, plan: &Plan) -> Vec<ApiCheck> {
+    let Some(base) = &ctx.last_stable_tag else {
+        return Vec::new();
+    };
+    let mut checks = Vec::new();
+    for (name, crate_plan) in plan.iter() {
+        let marked_breaking = crate_plan.changes().iter().any(|c| c.is_breaking());
+        let outcome = match detect_break(ctx, name, base, crate_plan.previous_version()).await {
+            Ok(broke) => match (broke, marked_breaking) {
+                (true, false) => ApiOutcome::UnmarkedBreak,
+                (false, true) => ApiOutcome::BumpWithoutBreak,
+                _ => ApiOutcome::Consistent,
+            },
+            Err(reason) => ApiOutcome::Skipped(reason),
+        };
+        if outcome == ApiOutcome::UnmarkedBreak {
+            tracing::warn!("api-check: {} has unmarked API breaks", name);
+        }
+        checks.push(ApiCheck {
+            crate_name: name.clone(),
+            outcome,
+        });
+    }
+    checks
+}
+
+/// Whether the API changed in a way that needs a breaking bump.
+async fn detect_break(
+    ctx: &InferredContext,
+    name: &str,
+    base: &str,
+    previous: &semver::Version,
+) -> std::result::Result<bool, String> {
+    // Ask whether a non-breaking bump would be enough.
+    let release_type = if previous.major >= 1 {
+        "minor"
+    } else {
+        "patch"
+    };
+    let output = Command::new("cargo")
+        .current_dir(&ctx.repo_root)
+        .args(["semver-checks", "check-release", "-p", name])
+        .args(["--baseline-rev", base, "--release-type", release_type])
+        .output()
+        .await
+        .map_err(|err| format!("failed to run cargo: {}", err))?;
+    if output.status.success() {
+        return Ok(false);
+    }
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    match required_bump(&text) {
+        Some("major") => Ok(true),
+        Some(_) => Ok(previous.major == 0),
+        None if text.contains("no such command") => {
+            Err("cargo-semver-checks is not installed".to_string())
+        }
+        None => Err(text
+            .lines()
+            .last()
+            .unwrap_or("unknown error")
+            .trim()
+            .to_string()),
+    }
+}
+
+fn required_bump(output: &str) -> Option<&str> {
+    let re = Regex::new(r"semver requires new (major|minor) version").unwrap();
+    re.captures(output)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::required_bump;
+
+    #[test]
+    fn required_bump_reads_summary_line() {
+        let out =
+            "     Summary semver requires new major version: 1 major and 0 minor checks failed";
+        assert_eq!(required_bump(out), Some("major"));
+        assert_eq!(
+            required_bump("     Summary no semver update required"),
+            None
+        );
+    }
+}
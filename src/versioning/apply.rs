@@ -1,22 +1,35 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::process::Command;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use chrono::Utc;
 use git2::Repository;
 use toml_edit::{DocumentMut, value};
 
+use crate::config::ChangelogSection;
+use crate::git_identity::GitIdentity;
 use crate::infer::InferredContext;
 
-use super::plan::{ChangeEntry, CommitKind, Plan};
+use super::dep_check;
+use super::entry::{EntryFormat, HIGHLIGHTS};
+use super::plan::{ChangeEntry, Plan, changelog_sections};
+use super::rc_delta::RcDelta;
 
-pub(crate) fn apply_changes(ctx: &InferredContext, plan: &Plan) -> Result<()> {
+#[tracing::instrument(skip_all)]
+pub(crate) fn apply_changes(
+    ctx: &InferredContext,
+    plan: &Plan,
+    delta: Option<&RcDelta>,
+) -> Result<git2::Oid> {
     let mut changed_versions: HashMap<&str, semver::Version> = HashMap::new();
     for (name, crate_plan) in plan.iter() {
         changed_versions.insert(name.as_str(), crate_plan.new_version().clone());
     }
 
+    let sections = changelog_sections(&ctx.config.changelog);
+    let format = EntryFormat::new(&ctx.config.changelog)?;
     for c in &ctx.crates {
         if let Some(crate_plan) = plan.crate_plan(&c.name) {
             tracing::debug!(
@@ -26,11 +39,19 @@ pub(crate) fn apply_changes(ctx: &InferredContext, plan: &Plan) -> Result<()> {
                 crate_plan.new_version()
             );
             update_package_version(&c.manifest_path, crate_plan.new_version())?;
+            let changes = if ctx.plan_base().is_none() && ctx.config.bootstrap.initial_changelog {
+                None
+            } else {
+                Some(crate_plan.changes())
+            };
             update_changelog(
                 &c.package_root,
                 &c.name,
                 crate_plan.new_version(),
-                crate_plan.changes(),
+                changes,
+                &sections,
+                &format,
+                delta,
             )?;
         }
     }
@@ -46,10 +67,34 @@ pub(crate) fn apply_changes(ctx: &InferredContext, plan: &Plan) -> Result<()> {
         }
     }
 
+    dep_check::check(&ctx.repo_root)?;
+    refresh_lockfile(&ctx.repo_root)?;
+
     let new_main = plan
         .main_crate_version(&ctx.main_crate)
         .expect("main crate must be present once we reach apply_changes");
-    commit_all(&ctx.repo_root, new_main)
+    let identity = GitIdentity::resolve(&ctx.repo_root, &ctx.config)?;
+    commit_all(&ctx.repo_root, new_main, &identity)
+}
+
+/// Re-resolve workspace members in `Cargo.lock` so the release commit keeps `--locked` builds green.
+fn refresh_lockfile(repo_root: &Path) -> Result<()> {
+    if !repo_root.join("Cargo.lock").exists() {
+        return Ok(());
+    }
+    tracing::debug!("versioning: refreshing Cargo.lock");
+    let output = Command::new("cargo")
+        .current_dir(repo_root)
+        .args(["update", "--workspace"])
+        .output()
+        .context("failed to run cargo update")?;
+    if !output.status.success() {
+        bail!(
+            "cargo update --workspace failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
 }
 
 fn read_toml(path: &Path) -> Result<DocumentMut> {
@@ -104,85 +149,270 @@ fn update_changelog(
     crate_root: &Path,
     crate_name: &str,
     new_version: &semver::Version,
-    changes: &[ChangeEntry],
+    changes: Option<&[ChangeEntry]>,
+    sections: &[ChangelogSection],
+    format: &EntryFormat,
+    delta: Option<&RcDelta>,
 ) -> Result<()> {
     let path = crate_root.join("CHANGELOG.md");
     let old = fs::read_to_string(&path).unwrap_or_default();
-    let date = Utc::now().date_naive();
+    let (content, _) = render_changelog(
+        &old,
+        crate_name,
+        new_version,
+        changes,
+        sections,
+        format,
+        delta,
+    )?;
+    fs::write(&path, content)?;
+    Ok(())
+}
+
+/// Changelog sections `prerelease` would add for `plan`, one per changed crate, rendered
+/// against the current `CHANGELOG.md` files without writing them.
+pub(crate) fn preview_changelogs(ctx: &InferredContext, plan: &Plan) -> Result<String> {
+    let sections = changelog_sections(&ctx.config.changelog);
+    let format = EntryFormat::new(&ctx.config.changelog)?;
     let mut out = String::new();
+    for c in &ctx.crates {
+        let Some(crate_plan) = plan.crate_plan(&c.name) else {
+            continue;
+        };
+        let changes = if ctx.plan_base().is_none() && ctx.config.bootstrap.initial_changelog {
+            None
+        } else {
+            Some(crate_plan.changes())
+        };
+        let old = fs::read_to_string(c.package_root.join("CHANGELOG.md")).unwrap_or_default();
+        let (_, section) = render_changelog(
+            &old,
+            &c.name,
+            crate_plan.new_version(),
+            changes,
+            &sections,
+            &format,
+            None,
+        )?;
+        out.push_str(&section);
+    }
+    Ok(out)
+}
+
+/// `old` with a section for `new_version` in place of its `## Unreleased` notes. Returns the
+/// whole changelog and the new section alone.
+fn render_changelog(
+    old: &str,
+    crate_name: &str,
+    new_version: &semver::Version,
+    changes: Option<&[ChangeEntry]>,
+    sections: &[ChangelogSection],
+    format: &EntryFormat,
+    delta: Option<&RcDelta>,
+) -> Result<(String, String)> {
+    let (before, mut curated, after) = match split_unreleased(old) {
+        Some((before, curated, after)) => (before, curated, after),
+        None => ("", Unreleased::default(), old),
+    };
+    let date = Utc::now().date_naive();
+    let mut out = before.to_string();
+    if curated.found {
+        // Leave a fresh section for the next round of hand-written notes.
+        out.push_str("## Unreleased\n\n");
+    }
+    let section_start = out.len();
     out.push_str(&format!(
         "## {} v{} - {}\n\n",
         crate_name, new_version, date
     ));
-
-    write_group(
-        &mut out,
-        "Breaking Changes",
-        changes
-            .iter()
-            .filter(|c| matches!(c.kind(), CommitKind::Breaking)),
-    );
-    write_group(
-        &mut out,
-        "Features",
-        changes
-            .iter()
-            .filter(|c| matches!(c.kind(), CommitKind::Feat)),
-    );
-    write_group(
-        &mut out,
-        "Fixes",
-        changes
-            .iter()
-            .filter(|c| matches!(c.kind(), CommitKind::Fix)),
-    );
+    if !curated.preamble.is_empty() {
+        out.push_str(&curated.preamble.join("\n"));
+        out.push_str("\n\n");
+    }
+    let mut highlights = curated.take(HIGHLIGHTS);
+    for c in changes
+        .unwrap_or_default()
+        .iter()
+        .filter(|c| c.in_changelog() && c.is_highlight())
+    {
+        highlights.push(format!("- {}", format.render_highlight(c)?));
+    }
     write_group(
         &mut out,
-        "Refactor/Perf",
-        changes
+        HIGHLIGHTS,
+        highlights,
+        std::iter::empty(),
+        format,
+        None,
+    )?;
+
+    match changes {
+        Some(changes) => {
+            for section in sections {
+                write_group(
+                    &mut out,
+                    &section.title,
+                    curated.take(&section.title),
+                    changes
+                        .iter()
+                        .filter(|c| c.in_changelog() && section.kinds.contains(&c.kind())),
+                    format,
+                    delta,
+                )?;
+            }
+        }
+        // A bootstrapped first release: one entry instead of the whole history.
+        None => out.push_str("Initial release.\n\n"),
+    }
+    for (title, lines) in std::mem::take(&mut curated.groups) {
+        write_group(&mut out, &title, lines, std::iter::empty(), format, None)?;
+    }
+    if let Some(delta) = delta
+        && changes.is_some_and(|changes| {
+            changes
+                .iter()
+                .any(|c| c.in_changelog() && delta.is_new(c.sha()))
+        })
+    {
+        out.push_str(&format!(
+            "{}: New since {}.\n\n",
+            delta.footnote(),
+            delta.previous_tag
+        ));
+    }
+
+    let section = out[section_start..].to_string();
+    out.push_str(after);
+    Ok((out, section))
+}
+
+/// Hand-written notes under a `## Unreleased` heading, grouped by their `###` headings.
+#[derive(Default)]
+struct Unreleased {
+    found: bool,
+    /// Lines before the first `###` heading.
+    preamble: Vec<String>,
+    groups: Vec<(String, Vec<String>)>,
+}
+
+impl Unreleased {
+    /// Curated lines filed under `title`, matched case-insensitively.
+    fn take(&mut self, title: &str) -> Vec<String> {
+        match self
+            .groups
             .iter()
-            .filter(|c| matches!(c.kind(), CommitKind::Refactor | CommitKind::Perf)),
-    );
-    write_group(
-        &mut out,
-        "Others",
-        changes.iter().filter(|c| {
-            matches!(
-                c.kind(),
-                CommitKind::Docs | CommitKind::Build | CommitKind::Chore | CommitKind::Other
-            )
-        }),
-    );
+            .position(|(t, _)| t.eq_ignore_ascii_case(title))
+        {
+            Some(i) => self.groups.remove(i).1,
+            None => Vec::new(),
+        }
+    }
+}
 
-    out.push('\n');
-    out.push_str(&old);
-    fs::write(&path, out)?;
-    Ok(())
+/// Split a changelog around its `## Unreleased` (or `## [Unreleased]`) section, returning
+/// the text before it, its notes, and the text from the next `##` heading on.
+fn split_unreleased(old: &str) -> Option<(&str, Unreleased, &str)> {
+    let mut offset = 0;
+    let mut start = None;
+    for line in old.split_inclusive('\n') {
+        let title = line.trim().strip_prefix("## ").map(|t| {
+            t.trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_ascii_lowercase()
+        });
+        match (start, title.as_deref()) {
+            (None, Some("unreleased")) => start = Some((offset, offset + line.len())),
+            (Some((begin, body)), Some(_)) => {
+                return Some((
+                    &old[..begin],
+                    parse_unreleased(&old[body..offset]),
+                    &old[offset..],
+                ));
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    let (begin, body) = start?;
+    Some((&old[..begin], parse_unreleased(&old[body..]), ""))
 }
 
-fn write_group<'a, I: Iterator<Item = &'a ChangeEntry>>(out: &mut String, title: &str, iter: I) {
+fn parse_unreleased(body: &str) -> Unreleased {
+    let mut notes = Unreleased {
+        found: true,
+        ..Unreleased::default()
+    };
+    for line in body.lines() {
+        if let Some(title) = line.trim().strip_prefix("### ") {
+            notes.groups.push((title.trim().to_string(), Vec::new()));
+            continue;
+        }
+        let lines = match notes.groups.last_mut() {
+            Some((_, lines)) => lines,
+            None => &mut notes.preamble,
+        };
+        if !(lines.is_empty() && line.trim().is_empty()) {
+            lines.push(line.trim_end().to_string());
+        }
+    }
+    for lines in
+        std::iter::once(&mut notes.preamble).chain(notes.groups.iter_mut().map(|g| &mut g.1))
+    {
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+    }
+    notes
+}
+
+fn write_group<'a, I: Iterator<Item = &'a ChangeEntry>>(
+    out: &mut String,
+    title: &str,
+    curated: Vec<String>,
+    iter: I,
+    format: &EntryFormat,
+    delta: Option<&RcDelta>,
+) -> Result<()> {
     let list: Vec<&ChangeEntry> = iter.collect();
-    if list.is_empty() {
-        return;
+    if list.is_empty() && curated.is_empty() {
+        return Ok(());
     }
     out.push_str(&format!("### {}\n", title));
+    for line in curated {
+        out.push_str(&line);
+        out.push('\n');
+    }
     for c in list {
-        out.push_str(&format!("- {} ({})\n", c.subject(), c.sha()));
+        let footnote = match delta {
+            Some(delta) if delta.is_new(c.sha()) => delta.footnote(),
+            _ => String::new(),
+        };
+        out.push_str(&format!("- {}{}\n", format.render(c)?, footnote));
     }
     out.push('\n');
+    Ok(())
 }
 
-fn commit_all(repo_root: &Path, new_version: &semver::Version) -> Result<()> {
+fn commit_all(
+    repo_root: &Path,
+    new_version: &semver::Version,
+    identity: &GitIdentity,
+) -> Result<git2::Oid> {
     let repo = Repository::discover(repo_root)?;
     let mut idx = repo.index()?;
-    idx.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+    // asfship keeps its own state under target/; never sweep it into the release commit.
+    let mut skip_target =
+        |path: &Path, _: &[u8]| -> i32 { if path.starts_with("target") { 1 } else { 0 } };
+    idx.add_all(
+        ["*"].iter(),
+        git2::IndexAddOption::DEFAULT,
+        Some(&mut skip_target),
+    )?;
     idx.write()?;
     let tree_oid = idx.write_tree()?;
     let tree = repo.find_tree(tree_oid)?;
-    let sig = repo
-        .signature()
-        .or_else(|_| git2::Signature::now("asfship", "asfship@users.noreply.github.com"))
-        .context("failed to build git signature")?;
+    let sig = identity.signature()?;
     let head = repo.head().ok();
     let parents = if let Some(h) = head {
         vec![repo.find_commit(h.target().unwrap())?]
@@ -190,7 +420,7 @@ fn commit_all(repo_root: &Path, new_version: &semver::Version) -> Result<()> {
         vec![]
     };
     let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
-    repo.commit(
+    let oid = repo.commit(
         Some("HEAD"),
         &sig,
         &sig,
@@ -199,5 +429,26 @@ fn commit_all(repo_root: &Path, new_version: &semver::Version) -> Result<()> {
         &parent_refs,
     )?;
     tracing::info!("versioning: committed release prep version={}", new_version);
-    Ok(())
+    Ok(oid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreleased_notes_are_split_by_heading() {
+        let old = "# Changelog\n\n## [Unreleased]\n\nSee the upgrade guide.\n\n### Features\n- curated feature\n\n### Security\n- curated fix\n\n## foo v0.1.0 - 2024-01-01\n\n- init\n";
+        let (before, mut notes, after) = split_unreleased(old).unwrap();
+        assert_eq!(before, "# Changelog\n\n");
+        assert_eq!(after, "## foo v0.1.0 - 2024-01-01\n\n- init\n");
+        assert_eq!(notes.preamble, ["See the upgrade guide."]);
+        assert_eq!(notes.take("features"), ["- curated feature"]);
+        assert_eq!(notes.take("Features"), Vec::<String>::new());
+        assert_eq!(
+            notes.groups,
+            [("Security".to_string(), vec!["- curated fix".to_string()])]
+        );
+        assert!(split_unreleased("## foo v0.1.0\n- init\n").is_none());
+    }
 }
@@ -0,0 +1,123 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::artifacts;
+use crate::infer::InferredContext;
+
+use super::plan::{Classification, CommitKind};
+
+/// Bumped whenever `CachedCommit` gains or changes fields.
+const CACHE_FORMAT: u32 = 4;
+
+/// Per-commit analysis result that does not depend on the base tag.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CachedCommit {
+    pub kind: CommitKind,
+    pub classification: Classification,
+    pub subject: String,
+    pub breaking: bool,
+    pub touched: Vec<String>,
+    /// Stable patch id of the first-parent diff, used to collapse cherry-picks.
+    pub patch_id: Option<String>,
+    /// Commit named by a `This reverts commit <sha>.` trailer.
+    pub reverts: Option<String>,
+    /// Text of a `Release-Highlight:` trailer.
+    pub highlight: Option<String>,
+}
+
+/// Commit classification cache stored at `plan-cache/<fingerprint>.json` under the artifacts root.
+///
+/// The fingerprint covers the cache format, asfship version, workspace layout, main crate, and
+/// `.asfship.toml`, so a change to any of them starts from an empty cache instead of reusing
+/// stale attributions. Only the commits of the latest planned range are kept, so the file
+/// stays the size of one release's history.
+pub(crate) struct PlanCache {
+    path: PathBuf,
+    entries: HashMap<String, CachedCommit>,
+    dirty: bool,
+}
+
+impl PlanCache {
+    pub(crate) fn load(ctx: &InferredContext) -> Self {
+        let path = artifacts::root(ctx)
+            .join("plan-cache")
+            .join(format!("{}.json", fingerprint(ctx)));
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries,
+            dirty: false,
+        }
+    }
+
+    pub(crate) fn get(&self, oid: &str) -> Option<&CachedCommit> {
+        self.entries.get(oid)
+    }
+
+    pub(crate) fn insert(&mut self, oid: String, entry: CachedCommit) {
+        self.entries.insert(oid, entry);
+        self.dirty = true;
+    }
+
+    /// Drop the entries of commits outside `planned`, such as those already released.
+    pub(crate) fn retain(&mut self, planned: &HashSet<&str>) {
+        let before = self.entries.len();
+        self.entries.retain(|oid, _| planned.contains(oid.as_str()));
+        self.dirty |= self.entries.len() != before;
+    }
+
+    /// Persist new entries; failures only cost speed on the next run.
+    pub(crate) fn save(&self) {
+        if !self.dirty {
+            return;
+        }
+        if let Err(err) = write_json(&self.path, &self.entries) {
+            tracing::warn!(error=%err, path=%self.path.display(), "plan: failed to write cache");
+        }
+    }
+}
+
+fn write_json(path: &Path, entries: &HashMap<String, CachedCommit>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("json.partial");
+    fs::write(&tmp, serde_json::to_vec(entries)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn fingerprint(ctx: &InferredContext) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(CACHE_FORMAT.to_le_bytes());
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    let mut crates: Vec<_> = ctx
+        .crates
+        .iter()
+        .map(|c| {
+            let rel = c
+                .package_root
+                .strip_prefix(&ctx.repo_root)
+                .unwrap_or(&c.package_root);
+            format!("{}={}", c.name, rel.display())
+        })
+        .collect();
+    crates.sort();
+    for entry in crates {
+        hasher.update(entry.as_bytes());
+        hasher.update([0]);
+    }
+    // `root_files = "main"` attributes by the inferred main crate.
+    hasher.update(ctx.main_crate.as_bytes());
+    hasher.update([0]);
+    hasher.update(fs::read(ctx.repo_root.join(".asfship.toml")).unwrap_or_default());
+    hex::encode(&hasher.finalize()[..8])
+}
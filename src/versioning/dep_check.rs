@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand};
+use semver::{Version, VersionReq};
+
+/// Re-read the bumped manifests with `cargo metadata` and check every requirement on
+/// a workspace crate, so a stale pin or a version-less path dependency fails here
+/// instead of during `cargo publish`.
+pub(super) fn check(repo_root: &Path) -> Result<()> {
+    let meta = MetadataCommand::new()
+        .current_dir(repo_root)
+        .no_deps()
+        .exec()?;
+    let problems = problems(&meta);
+    if !problems.is_empty() {
+        bail!(
+            "internal dependency requirements are inconsistent after the version bump:\n{}",
+            problems.join("\n")
+        );
+    }
+    Ok(())
+}
+
+fn problems(meta: &Metadata) -> Vec<String> {
+    let members: Vec<_> = meta.workspace_packages();
+    let versions: HashMap<&str, &Version> = members
+        .iter()
+        .map(|p| (p.name.as_str(), &p.version))
+        .collect();
+    let mut problems = Vec::new();
+    for pkg in &members {
+        let publishable = pkg.publish.as_ref().is_none_or(|r| !r.is_empty());
+        for dep in &pkg.dependencies {
+            let Some(version) = versions.get(dep.name.as_str()) else {
+                continue;
+            };
+            if dep.path.is_none() {
+                continue;
+            }
+            let section = match dep.kind {
+                DependencyKind::Development => "dev-dependencies",
+                DependencyKind::Build => "build-dependencies",
+                _ => "dependencies",
+            };
+            let location = format!(
+                "{}: [{}{}] {}",
+                pkg.manifest_path,
+                dep.target
+                    .as_ref()
+                    .map(|t| format!("target.'{}'.", t))
+                    .unwrap_or_default(),
+                section,
+                dep.rename.as_deref().unwrap_or(&dep.name)
+            );
+            if dep.req == VersionReq::STAR {
+                // Cargo drops version-less dev-dependencies when publishing.
+                if publishable && dep.kind != DependencyKind::Development {
+                    problems.push(format!(
+                        "  {}: path dependency has no version, so `cargo publish` rejects it; add version = \"{}\"",
+                        location, version
+                    ));
+                }
+            } else if !dep.req.matches(version) {
+                problems.push(format!(
+                    "  {}: requirement \"{}\" does not match {} {}",
+                    location, dep.req, dep.name, version
+                ));
+            }
+        }
+    }
+    problems
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use serde::Serialize;
+use tera::{Context as TeraContext, Tera};
+
+use crate::config::ChangelogConfig;
+
+use super::plan::{ChangeEntry, CommitKind};
+
+/// `type(scope)!: description`, the Conventional Commits header.
+static HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<type>[A-Za-z]+)(?:\((?P<scope>[^)]*)\))?!?:\s*(?P<description>.+)$").unwrap()
+});
+
+const DEFAULT: &str = "default";
+
+/// Title of the section listing highlighted changes above the grouped entries.
+pub(crate) const HIGHLIGHTS: &str = "Highlights";
+
+/// Renders one changelog entry with the `[changelog] entry` / `[changelog.entries]`
+/// Tera snippets, or as `<subject> (<sha>)` when none applies.
+pub(crate) struct EntryFormat {
+    tera: Tera,
+}
+
+/// Variables available to entry snippets.
+#[derive(Serialize)]
+struct EntryVars<'a> {
+    subject: &'a str,
+    /// Conventional type as written, e.g. `feat`; empty for other subjects.
+    r#type: &'a str,
+    scope: Option<&'a str>,
+    /// The subject without its `type(scope)!:` prefix.
+    description: &'a str,
+    kind: String,
+    sha: &'a str,
+    breaking: bool,
+    pr: Option<u64>,
+}
+
+impl EntryFormat {
+    pub(crate) fn new(cfg: &ChangelogConfig) -> Result<Self> {
+        let mut tera = Tera::default();
+        tera.register_filter("upper_first", upper_first);
+        let templates = cfg
+            .entry
+            .as_deref()
+            .map(|source| (DEFAULT.to_string(), "changelog.entry".to_string(), source))
+            .into_iter()
+            .chain(cfg.entries.iter().map(|(kind, source)| {
+                (
+                    kind.to_string(),
+                    format!("changelog.entries.{}", kind),
+                    source.as_str(),
+                )
+            }));
+        for (name, key, source) in templates {
+            tera.add_raw_template(&name, source)
+                .map_err(|err| anyhow!("invalid {} template: {:#}", key, err))?;
+        }
+        Ok(Self { tera })
+    }
+
+    /// The entry text after the list marker.
+    pub(crate) fn render(&self, change: &ChangeEntry) -> Result<String> {
+        let kind = change.kind();
+        let name = [kind.to_string(), DEFAULT.to_string()]
+            .into_iter()
+            .find(|name| self.tera.get_template_names().any(|n| n == name));
+        let Some(name) = name else {
+            return Ok(format!("{} ({})", change.subject(), change.sha()));
+        };
+        let header = HEADER.captures(change.subject());
+        let part = |group: &str| {
+            header
+                .as_ref()
+                .and_then(|c| c.name(group))
+                .map(|m| m.as_str())
+        };
+        let vars = EntryVars {
+            subject: change.subject(),
+            r#type: part("type").unwrap_or(""),
+            scope: part("scope").filter(|s| !s.is_empty()),
+            description: part("description").unwrap_or(change.subject()),
+            kind: kind.to_string(),
+            sha: change.sha(),
+            breaking: change.is_breaking() || kind == CommitKind::Breaking,
+            pr: change.pull_request(),
+        };
+        let key = if name == DEFAULT {
+            "changelog.entry".to_string()
+        } else {
+            format!("changelog.entries.{}", name)
+        };
+        let rendered = self
+            .tera
+            .render(&name, &TeraContext::from_serialize(&vars)?)
+            .map_err(|err| anyhow!("failed to render {} for {}: {:#}", key, change.sha(), err))?;
+        Ok(rendered.trim().to_string())
+    }
+
+    /// The Highlights line for `change`: its `Release-Highlight:` text, else the entry.
+    pub(crate) fn render_highlight(&self, change: &ChangeEntry) -> Result<String> {
+        match change.highlight_note() {
+            Some(note) => Ok(note.to_string()),
+            None => self.render(change),
+        }
+    }
+}
+
+/// Uppercase the first character and keep the rest, unlike Tera's `capitalize`.
+fn upper_first(value: &tera::Value, _: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+    let text = tera::try_get_value!("upper_first", "value", String, value);
+    let mut chars = text.chars();
+    Ok(tera::Value::String(match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippets_apply_per_kind_with_a_default() {
+        let entry = ChangeEntry::sample;
+        let mut cfg = ChangelogConfig {
+            entry: Some("{{ description | upper_first }} ({{ sha }})".into()),
+            ..Default::default()
+        };
+        cfg.entries.insert(
+            CommitKind::Feat,
+            "{% if scope %}**{{ scope }}:** {% endif %}{{ description }}".into(),
+        );
+        let format = EntryFormat::new(&cfg).unwrap();
+        assert_eq!(
+            format
+                .render(&entry(CommitKind::Feat, "feat(core): add retries (#12)"))
+                .unwrap(),
+            "**core:** add retries (#12)"
+        );
+        assert_eq!(
+            format
+                .render(&entry(CommitKind::Fix, "fix!: drop the old flag"))
+                .unwrap(),
+            "Drop the old flag (abc1234)"
+        );
+        assert_eq!(
+            format
+                .render(&entry(CommitKind::Other, "Update CI"))
+                .unwrap(),
+            "Update CI (abc1234)"
+        );
+
+        let plain = EntryFormat::new(&ChangelogConfig::default()).unwrap();
+        assert_eq!(
+            plain
+                .render(&entry(CommitKind::Fix, "fix: handle empty input"))
+                .unwrap(),
+            "fix: handle empty input (abc1234)"
+        );
+
+        cfg.entries.insert(CommitKind::Docs, "{{ oops".into());
+        let err = EntryFormat::new(&cfg).err().unwrap().to_string();
+        assert!(
+            err.starts_with("invalid changelog.entries.docs template"),
+            "{}",
+            err
+        );
+    }
+}
@@ -1,32 +1,60 @@
+mod api_check;
 mod apply;
+mod cache;
+mod dep_check;
+mod entry;
 mod plan;
 pub(crate) mod rc;
+mod rc_delta;
+mod sizes;
+mod verify;
+mod worktree;
 
-use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::fmt::Write as _;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 use anyhow::{Result, bail};
 use git2::Repository;
 
+use crate::artifacts;
+use crate::audit::{AuditAction, AuditLog};
+use crate::config::ChangelogSection;
+use crate::discussion;
 use crate::github;
-use crate::infer::InferredContext;
+use crate::infer::{self, InferredContext};
+use crate::notify::{self, Milestone, Notice};
+use crate::pr_labels;
+use crate::rc_release::human_size;
+use crate::start;
+use crate::timeline::{self, Phase};
+use crate::token_scope::{self, Permission};
 
-pub(crate) use plan::{Plan, compute_plan};
+pub(crate) use apply::preview_changelogs;
+pub(crate) use entry::{EntryFormat, HIGHLIGHTS};
+pub use plan::CommitKind;
+pub(crate) use plan::{BumpKind, ChangeEntry, CratePlan, Plan, compute_plan};
 use rc::RcMode;
+pub use rc::RcSelection;
+use rc_delta::RcDelta;
 
-pub struct PrereleaseOptions<'a> {
+pub struct PrereleaseOptions {
     pub dry_run: bool,
-    pub artifact_dir: Option<&'a Path>,
     pub upload: bool,
+    pub post_report: bool,
+    pub rc: RcSelection,
+    /// Explain each commit's classification and each crate's bump in the text summary.
+    pub explain: bool,
+    /// Apply the release edits in a temporary worktree; see `worktree::Worktree`.
+    pub isolated: bool,
 }
 
 pub async fn run_prerelease(
     ctx: &InferredContext,
-    opts: PrereleaseOptions<'_>,
+    opts: PrereleaseOptions,
 ) -> Result<PrereleaseReport> {
     let repo = Repository::discover(&ctx.repo_root)?;
-    let plan = plan::compute_plan(&repo, ctx)?;
+    let plan = pr_labels::apply(ctx, plan::compute_plan(&repo, ctx)?).await?;
     tracing::info!(
         "versioning: plan computed changed_crates={}",
         plan.changed_count()
@@ -37,20 +65,34 @@ pub async fn run_prerelease(
     }
 
     let mut report = build_report(ctx, &plan, opts.dry_run);
+    report.explain = opts.explain;
+    if ctx.config.prerelease.api_check {
+        report.set_api_checks(api_check::check_api(ctx, &plan).await);
+    }
+    report.set_lfs_patterns(rc::lfs_patterns(&repo)?);
+
+    let main_version = plan
+        .main_crate_version(&ctx.main_crate)
+        .expect("main crate plan checked above");
+    let (rc_tag, rc_n) = rc::select_rc_tag(&repo, &ctx.config.tags(), main_version, &opts.rc)?;
+    let delta = RcDelta::resolve(&repo, ctx, main_version, rc_n)?;
+    if let Some(delta) = &delta {
+        report.set_since_rc(delta);
+    }
 
     if opts.dry_run {
         tracing::debug!("versioning: dry-run, skip applying changes");
+        rc::ensure_rc_available(&repo, ctx, &rc_tag, false).await?;
+        report.set_planned_artifacts(rc::planned_artifact_names(ctx, &plan, rc_n));
+        report.set_rc_tag(Some(rc_tag));
         return Ok(report);
     }
 
-    tracing::info!("versioning: applying changes");
-    apply::apply_changes(ctx, &plan)?;
-
-    report.mark_applied();
-
     let mode = if opts.upload {
         if github::has_token() {
-            RcMode::Remote
+            // Checked before anything changes; the release commit is pushed to this ref.
+            let upstream = infer::ensure_head_pushed(&ctx.repo_root).await?;
+            RcMode::Remote { upstream }
         } else {
             tracing::warn!(
                 "rc: requested upload but missing ASFSHIP_GITHUB_TOKEN; producing local assets only"
@@ -60,22 +102,125 @@ pub async fn run_prerelease(
     } else {
         RcMode::LocalOnly
     };
+    if matches!(mode, RcMode::Remote { .. }) {
+        let mut needed = vec![Permission::Contents];
+        if opts.post_report {
+            needed.push(Permission::Discussions);
+        }
+        if ctx.config.prerelease.attest {
+            needed.push(Permission::Attestations);
+        }
+        token_scope::verify(ctx, &needed).await?;
+    }
+    // Check before touching the tree so a taken tag never leaves a stray release commit.
+    rc::ensure_rc_available(&repo, ctx, &rc_tag, matches!(mode, RcMode::Remote { .. })).await?;
+    // Creating the run directory now starts `asfship.log` there before any edits.
+    artifacts::prepare(ctx, &rc_tag).await?;
+
+    tracing::info!("versioning: applying changes");
+    let audit = AuditLog::open(ctx).await;
+    let worktree = if opts.isolated {
+        Some(worktree::Worktree::create(&ctx.repo_root)?)
+    } else {
+        None
+    };
+    let commit = match &worktree {
+        Some(worktree) => apply::apply_changes(&worktree.context(ctx), &plan, delta.as_ref())?,
+        None => apply::apply_changes(ctx, &plan, delta.as_ref())?,
+    };
+    audit
+        .record(AuditAction::CommitCreated, &commit.to_string())
+        .await;
 
-    let outcome = rc::execute_rc(&repo, ctx, &plan, opts.artifact_dir, mode).await?;
+    report.mark_applied();
+
+    let remote = matches!(mode, RcMode::Remote { .. });
+    let outcome = rc::execute_rc(&repo, ctx, &plan, mode, (rc_tag, rc_n), commit, &audit).await?;
+    drop(worktree);
+    if remote {
+        timeline::record(
+            &artifacts::root(ctx),
+            main_version,
+            Phase::RcCut,
+            &outcome.rc_tag,
+        )
+        .await;
+        notify::send(
+            ctx,
+            Notice {
+                milestone: Milestone::RcCut,
+                tag: outcome.rc_tag.clone(),
+                message: format!(
+                    "Release candidate {} of {} is up",
+                    outcome.rc_tag, ctx.repo_name
+                ),
+                url: Some(format!(
+                    "https://github.com/{}/{}/releases/tag/{}",
+                    ctx.repo_owner, ctx.repo_name, outcome.rc_tag
+                )),
+            },
+        )
+        .await;
+    }
     report.set_rc_tag(Some(outcome.rc_tag));
+    report.set_artifact_sizes(outcome.sizes);
+
+    let markdown = report.render_markdown();
+    let report_path = outcome.artifact_dir.join("REPORT.md");
+    tokio::fs::write(&report_path, &markdown).await?;
+    tracing::info!("versioning: wrote {}", report_path.display());
     report.set_artifact_dir(Some(outcome.artifact_dir));
 
+    if opts.post_report {
+        post_report_to_kickoff(ctx, &markdown, &audit).await?;
+    }
+
     Ok(report)
 }
 
+async fn post_report_to_kickoff(
+    ctx: &InferredContext,
+    markdown: &str,
+    audit: &AuditLog,
+) -> Result<()> {
+    if !github::has_token() {
+        bail!("missing ASFSHIP_GITHUB_TOKEN; cannot post prerelease report");
+    }
+    let gh = github::client()?;
+    let kickoff = start::find_kickoff(&gh, ctx).await?;
+    discussion::post_comment(
+        &gh,
+        &ctx.repo_owner,
+        &ctx.repo_name,
+        kickoff.number,
+        markdown,
+    )
+    .await?;
+    audit
+        .record(AuditAction::DiscussionCommented, &kickoff.html_url)
+        .await;
+    println!("prerelease: report posted to {}", kickoff.html_url);
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct PrereleaseReport {
     base_tag: Option<String>,
     main_crate: String,
     dry_run: bool,
+    explain: bool,
     changed_crates: Vec<ReportCrate>,
+    /// Commits that touch no crate, listed so a misconfigured package root is noticed.
+    unattributed: Vec<ReportChange>,
     rc_tag: Option<String>,
     artifact_dir: Option<PathBuf>,
+    planned_artifacts: Vec<String>,
+    sections: Vec<ChangelogSection>,
+    api_checks: Vec<api_check::ApiCheck>,
+    lfs_patterns: Vec<String>,
+    artifact_sizes: Vec<sizes::CrateSizes>,
+    /// What changed since the previous rc of the version, for rc.2 and later.
+    since_rc: Option<SinceRc>,
 }
 
 impl PrereleaseReport {
@@ -95,12 +240,11 @@ impl PrereleaseReport {
         )
         .unwrap();
         writeln!(&mut out, "main crate: {}", self.main_crate).unwrap();
-        let rc_status = if self.dry_run {
-            "<pending>"
-        } else if let Some(tag) = &self.rc_tag {
-            tag.as_str()
-        } else {
-            "<skipped>"
+        let rc_status = match (&self.rc_tag, self.dry_run) {
+            (Some(tag), true) => format!("{} (planned)", tag),
+            (Some(tag), false) => tag.clone(),
+            (None, true) => "<pending>".to_string(),
+            (None, false) => "<skipped>".to_string(),
         };
         writeln!(&mut out, "rc tag: {}", rc_status).unwrap();
         let artifacts_status = if self.dry_run {
@@ -111,6 +255,87 @@ impl PrereleaseReport {
             "<skipped>".to_string()
         };
         writeln!(&mut out, "artifacts dir: {}", artifacts_status).unwrap();
+        if !self.planned_artifacts.is_empty() {
+            writeln!(&mut out, "planned artifacts:").unwrap();
+            for name in &self.planned_artifacts {
+                writeln!(&mut out, "  - {}", name).unwrap();
+            }
+        }
+
+        if !self.artifact_sizes.is_empty() {
+            writeln!(&mut out, "artifact sizes:").unwrap();
+            for sizes in &self.artifact_sizes {
+                writeln!(&mut out, "  {}", sizes).unwrap();
+                for (name, size) in &sizes.archives {
+                    writeln!(
+                        &mut out,
+                        "    {}: {} ({} of uncompressed)",
+                        name,
+                        human_size(*size),
+                        sizes.ratio(*size)
+                    )
+                    .unwrap();
+                }
+                let largest: Vec<String> = sizes
+                    .largest
+                    .iter()
+                    .map(|(path, size)| {
+                        let flag = if *size >= sizes::LARGE_FILE_BYTES {
+                            " (large)"
+                        } else {
+                            ""
+                        };
+                        format!("{} {}{}", path, human_size(*size), flag)
+                    })
+                    .collect();
+                if !largest.is_empty() {
+                    writeln!(&mut out, "    largest: {}", largest.join(", ")).unwrap();
+                }
+            }
+        }
+
+        if !self.api_checks.is_empty() {
+            writeln!(&mut out, "api checks:").unwrap();
+            for check in &self.api_checks {
+                writeln!(&mut out, "  - {}", check).unwrap();
+            }
+        }
+        if !self.lfs_patterns.is_empty() {
+            writeln!(
+                &mut out,
+                "git lfs: {} (objects are packaged, not pointers)",
+                self.lfs_patterns.join(", ")
+            )
+            .unwrap();
+        }
+
+        if !self.unattributed.is_empty() {
+            writeln!(&mut out, "unattributed commits (in no changelog):").unwrap();
+            for change in &self.unattributed {
+                writeln!(&mut out, "  - {} {}", change.sha, change.subject).unwrap();
+            }
+        }
+
+        if let Some(since) = &self.since_rc {
+            writeln!(
+                &mut out,
+                "changes since {}: {} new, {} carried over",
+                since.previous_tag,
+                since.new.len(),
+                since.carried_over
+            )
+            .unwrap();
+            for (change, crates) in &since.new {
+                writeln!(
+                    &mut out,
+                    "  - {} {} ({})",
+                    change.sha,
+                    change.subject,
+                    crates.join(", ")
+                )
+                .unwrap();
+            }
+        }
 
         if self.changed_crates.is_empty() {
             writeln!(&mut out, "changed crates: <none>").unwrap();
@@ -125,21 +350,21 @@ impl PrereleaseReport {
                 crate_plan.name, crate_plan.old_version, crate_plan.new_version
             )
             .unwrap();
+            if self.explain {
+                writeln!(&mut out, "  {}", crate_plan.bump_rule).unwrap();
+            }
 
-            let mut grouped: BTreeMap<&'static str, Vec<&ReportChange>> = BTreeMap::new();
-            for change in &crate_plan.changes {
-                grouped
-                    .entry(group_label(change.kind))
-                    .or_default()
-                    .push(change);
-            }
-            for label in GROUP_ORDER {
-                if let Some(entries) = grouped.get(label) {
-                    if entries.is_empty() {
-                        continue;
-                    }
-                    writeln!(&mut out, "  {}:", label).unwrap();
-                    for change in entries {
+            for (label, entries) in crate_plan.grouped_changes(&self.sections) {
+                writeln!(&mut out, "  {}:", label).unwrap();
+                for change in entries {
+                    if self.explain {
+                        writeln!(
+                            &mut out,
+                            "    - {} {} ({}): {}",
+                            change.sha, change.kind, change.classified_by, change.subject
+                        )
+                        .unwrap();
+                    } else {
                         writeln!(&mut out, "    - {}", change.subject).unwrap();
                     }
                 }
@@ -149,6 +374,177 @@ impl PrereleaseReport {
         out
     }
 
+    /// Markdown rendering with one collapsible section per changed crate.
+    pub fn render_markdown(&self) -> String {
+        let mut out = String::new();
+        writeln!(&mut out, "## Prerelease summary\n").unwrap();
+        writeln!(&mut out, "| Field | Value |").unwrap();
+        writeln!(&mut out, "| --- | --- |").unwrap();
+        writeln!(
+            &mut out,
+            "| Mode | {} |",
+            if self.dry_run { "dry-run" } else { "applied" }
+        )
+        .unwrap();
+        writeln!(
+            &mut out,
+            "| Base tag | `{}` |",
+            self.base_tag.as_deref().unwrap_or("<none>")
+        )
+        .unwrap();
+        writeln!(&mut out, "| Main crate | `{}` |", self.main_crate).unwrap();
+        if let Some(tag) = &self.rc_tag {
+            writeln!(&mut out, "| RC tag | `{}` |", tag).unwrap();
+        }
+        writeln!(&mut out).unwrap();
+        if !self.planned_artifacts.is_empty() {
+            writeln!(&mut out, "### Planned artifacts\n").unwrap();
+            for name in &self.planned_artifacts {
+                writeln!(&mut out, "- `{}`", name).unwrap();
+            }
+            writeln!(&mut out).unwrap();
+        }
+
+        if !self.artifact_sizes.is_empty() {
+            self.render_sizes_markdown(&mut out);
+        }
+
+        if !self.api_checks.is_empty() {
+            writeln!(&mut out, "### API checks\n").unwrap();
+            for check in &self.api_checks {
+                writeln!(&mut out, "- {}", check).unwrap();
+            }
+            writeln!(&mut out).unwrap();
+        }
+
+        if !self.lfs_patterns.is_empty() {
+            writeln!(&mut out, "### Git LFS\n").unwrap();
+            writeln!(
+                &mut out,
+                "Archives contain the LFS objects for these tracked patterns:\n"
+            )
+            .unwrap();
+            for pattern in &self.lfs_patterns {
+                writeln!(&mut out, "- `{}`", pattern).unwrap();
+            }
+            writeln!(&mut out).unwrap();
+        }
+
+        if !self.unattributed.is_empty() {
+            writeln!(&mut out, "### Unattributed commits\n").unwrap();
+            writeln!(
+                &mut out,
+                "These commits touch no crate, so no changelog lists them:\n"
+            )
+            .unwrap();
+            for change in &self.unattributed {
+                writeln!(&mut out, "- {} ({})", change.subject, change.sha).unwrap();
+            }
+            writeln!(&mut out).unwrap();
+        }
+
+        if let Some(since) = &self.since_rc {
+            writeln!(&mut out, "### Changes since `{}`\n", since.previous_tag).unwrap();
+            writeln!(
+                &mut out,
+                "{} new commit(s); {} carried over from `{}`.\n",
+                since.new.len(),
+                since.carried_over,
+                since.previous_tag
+            )
+            .unwrap();
+            for (change, crates) in &since.new {
+                let crates: Vec<String> = crates.iter().map(|c| format!("`{}`", c)).collect();
+                writeln!(
+                    &mut out,
+                    "- {} ({}) in {}",
+                    change.subject,
+                    change.sha,
+                    crates.join(", ")
+                )
+                .unwrap();
+            }
+            writeln!(&mut out).unwrap();
+        }
+
+        if self.changed_crates.is_empty() {
+            writeln!(&mut out, "No crates changed.").unwrap();
+            return out;
+        }
+
+        writeln!(&mut out, "### Changed crates\n").unwrap();
+        for crate_plan in &self.changed_crates {
+            writeln!(&mut out, "<details>").unwrap();
+            writeln!(
+                &mut out,
+                "<summary><code>{}</code> {} → {}</summary>\n",
+                crate_plan.name, crate_plan.old_version, crate_plan.new_version
+            )
+            .unwrap();
+            for (label, entries) in crate_plan.grouped_changes(&self.sections) {
+                writeln!(&mut out, "#### {}\n", label).unwrap();
+                for change in entries {
+                    writeln!(&mut out, "- {} ({})", change.subject, change.sha).unwrap();
+                }
+                writeln!(&mut out).unwrap();
+            }
+            writeln!(&mut out, "</details>\n").unwrap();
+        }
+
+        out
+    }
+
+    fn render_sizes_markdown(&self, out: &mut String) {
+        writeln!(out, "### Artifact sizes\n").unwrap();
+        let large: usize = self.artifact_sizes.iter().map(|s| s.large_files).sum();
+        if large > 0 {
+            writeln!(
+                out,
+                "> **Warning**: {} packaged file(s) of at least {}; make sure no test fixture or build output slipped in.\n",
+                large,
+                human_size(sizes::LARGE_FILE_BYTES)
+            )
+            .unwrap();
+        }
+        writeln!(out, "| Archive | Size | Uncompressed | Ratio |").unwrap();
+        writeln!(out, "| --- | --- | --- | --- |").unwrap();
+        for sizes in &self.artifact_sizes {
+            for (name, size) in &sizes.archives {
+                writeln!(
+                    out,
+                    "| `{}` | {} | {} | {} |",
+                    name,
+                    human_size(*size),
+                    human_size(sizes.uncompressed),
+                    sizes.ratio(*size)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(out).unwrap();
+        for sizes in &self.artifact_sizes {
+            if sizes.largest.is_empty() {
+                continue;
+            }
+            writeln!(out, "<details>").unwrap();
+            writeln!(
+                out,
+                "<summary>Largest files in <code>{}</code> ({} files)</summary>\n",
+                sizes.crate_name, sizes.files
+            )
+            .unwrap();
+            for (path, size) in &sizes.largest {
+                let flag = if *size >= sizes::LARGE_FILE_BYTES {
+                    " **large**"
+                } else {
+                    ""
+                };
+                writeln!(out, "- `{}` {}{}", path, human_size(*size), flag).unwrap();
+            }
+            writeln!(out, "\n</details>\n").unwrap();
+        }
+    }
+
     fn mark_applied(&mut self) {
         self.dry_run = false;
     }
@@ -160,6 +556,55 @@ impl PrereleaseReport {
     fn set_artifact_dir(&mut self, dir: Option<PathBuf>) {
         self.artifact_dir = dir;
     }
+
+    fn set_planned_artifacts(&mut self, names: Vec<String>) {
+        self.planned_artifacts = names;
+    }
+
+    fn set_api_checks(&mut self, checks: Vec<api_check::ApiCheck>) {
+        self.api_checks = checks;
+    }
+
+    fn set_lfs_patterns(&mut self, patterns: Vec<String>) {
+        self.lfs_patterns = patterns;
+    }
+
+    fn set_artifact_sizes(&mut self, sizes: Vec<sizes::CrateSizes>) {
+        self.artifact_sizes = sizes;
+    }
+
+    /// Split the planned commits into those after `delta.previous_tag` and those the
+    /// previous rc already had.
+    fn set_since_rc(&mut self, delta: &RcDelta) {
+        let mut new: Vec<(ReportChange, Vec<String>)> = Vec::new();
+        let mut carried = BTreeSet::new();
+        for crate_plan in &self.changed_crates {
+            for change in &crate_plan.changes {
+                if !delta.is_new(&change.sha) {
+                    carried.insert(change.sha.as_str());
+                    continue;
+                }
+                match new.iter_mut().find(|(c, _)| c.sha == change.sha) {
+                    Some((_, crates)) => crates.push(crate_plan.name.clone()),
+                    None => new.push((change.clone(), vec![crate_plan.name.clone()])),
+                }
+            }
+        }
+        self.since_rc = Some(SinceRc {
+            previous_tag: delta.previous_tag.clone(),
+            new,
+            carried_over: carried.len(),
+        });
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SinceRc {
+    previous_tag: String,
+    /// Commits after `previous_tag`, each with the crates it touches.
+    new: Vec<(ReportChange, Vec<String>)>,
+    /// Commits `previous_tag` already contained.
+    carried_over: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -167,76 +612,145 @@ struct ReportCrate {
     name: String,
     old_version: semver::Version,
     new_version: semver::Version,
+    /// e.g. "minor: 0.1.0 is before 1.0, where a breaking commit bumps the minor version"
+    bump_rule: String,
     changes: Vec<ReportChange>,
 }
 
+impl ReportCrate {
+    fn grouped_changes<'a>(
+        &'a self,
+        sections: &'a [ChangelogSection],
+    ) -> Vec<(&'a str, Vec<&'a ReportChange>)> {
+        sections
+            .iter()
+            .filter_map(|section| {
+                let entries: Vec<&ReportChange> = self
+                    .changes
+                    .iter()
+                    .filter(|c| section.kinds.contains(&c.kind))
+                    .collect();
+                (!entries.is_empty()).then_some((section.title.as_str(), entries))
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 struct ReportChange {
     kind: plan::CommitKind,
+    classified_by: String,
     subject: String,
+    sha: String,
 }
 
-const GROUP_ORDER: [&str; 5] = [
-    "Breaking Changes",
-    "Features",
-    "Fixes",
-    "Refactor/Perf",
-    "Others",
-];
-
 fn build_report(ctx: &InferredContext, plan: &plan::Plan, dry_run: bool) -> PrereleaseReport {
+    let report_change = |change: &plan::ChangeEntry| ReportChange {
+        kind: change.kind(),
+        classified_by: change.classification().to_string(),
+        subject: change.subject().to_string(),
+        sha: change.sha().to_string(),
+    };
     let mut changed_crates = Vec::new();
     for (name, crate_plan) in plan.iter() {
-        let mut changes = Vec::new();
-        for change in crate_plan.changes() {
-            changes.push(ReportChange {
-                kind: change.kind(),
-                subject: change.subject().to_string(),
-            });
-        }
         changed_crates.push(ReportCrate {
             name: name.clone(),
             old_version: crate_plan.previous_version().clone(),
             new_version: crate_plan.new_version().clone(),
-            changes,
+            bump_rule: format!("{}: {}", crate_plan.bump(), crate_plan.bump_rule()),
+            changes: crate_plan.changes().iter().map(report_change).collect(),
         });
     }
 
     PrereleaseReport {
-        base_tag: ctx.last_stable_tag.clone(),
+        base_tag: ctx.plan_base().map(String::from),
         main_crate: ctx.main_crate.clone(),
         dry_run,
+        explain: false,
         changed_crates,
+        unattributed: plan.unattributed().iter().map(report_change).collect(),
         rc_tag: None,
         artifact_dir: None,
-    }
-}
-
-fn group_label(kind: plan::CommitKind) -> &'static str {
-    match kind {
-        plan::CommitKind::Breaking => "Breaking Changes",
-        plan::CommitKind::Feat => "Features",
-        plan::CommitKind::Fix => "Fixes",
-        plan::CommitKind::Perf | plan::CommitKind::Refactor => "Refactor/Perf",
-        plan::CommitKind::Docs
-        | plan::CommitKind::Build
-        | plan::CommitKind::Chore
-        | plan::CommitKind::Other => "Others",
+        planned_artifacts: Vec::new(),
+        sections: plan::changelog_sections(&ctx.config.changelog),
+        api_checks: Vec::new(),
+        lfs_patterns: Vec::new(),
+        artifact_sizes: Vec::new(),
+        since_rc: None,
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::GROUP_ORDER;
-    use super::group_label;
-    use super::plan::CommitKind;
+    use super::api_check::{ApiCheck, ApiOutcome};
+    use super::plan::{CommitKind, changelog_sections};
+    use super::sizes::CrateSizes;
+    use super::{PrereleaseReport, ReportChange, ReportCrate};
 
     #[test]
-    fn group_order_contains_all_labels() {
-        assert!(GROUP_ORDER.contains(&group_label(CommitKind::Breaking)));
-        assert!(GROUP_ORDER.contains(&group_label(CommitKind::Feat)));
-        assert!(GROUP_ORDER.contains(&group_label(CommitKind::Fix)));
-        assert!(GROUP_ORDER.contains(&group_label(CommitKind::Refactor)));
-        assert!(GROUP_ORDER.contains(&group_label(CommitKind::Docs)));
+    fn render_markdown_collapses_crates() {
+        let report = PrereleaseReport {
+            base_tag: Some("v0.1.0".into()),
+            main_crate: "foo".into(),
+            dry_run: false,
+            explain: false,
+            changed_crates: vec![ReportCrate {
+                name: "foo".into(),
+                old_version: semver::Version::new(0, 1, 0),
+                new_version: semver::Version::new(0, 1, 1),
+                bump_rule: "patch: 0.1.0 is before 1.0 and no commit is breaking".into(),
+                changes: vec![ReportChange {
+                    kind: CommitKind::Fix,
+                    classified_by: "type `fix`".into(),
+                    subject: "fix: handle empty input".into(),
+                    sha: "abc1234".into(),
+                }],
+            }],
+            unattributed: vec![ReportChange {
+                kind: CommitKind::Other,
+                classified_by: "no recognised type".into(),
+                subject: "Update CI".into(),
+                sha: "def5678".into(),
+            }],
+            rc_tag: Some("v0.1.1-rc.1".into()),
+            artifact_dir: None,
+            planned_artifacts: Vec::new(),
+            sections: changelog_sections(&Default::default()),
+            api_checks: vec![ApiCheck {
+                crate_name: "foo".into(),
+                outcome: ApiOutcome::UnmarkedBreak,
+            }],
+            lfs_patterns: Vec::new(),
+            artifact_sizes: vec![CrateSizes {
+                crate_name: "foo".into(),
+                files: 2,
+                uncompressed: 3 << 20,
+                largest: vec![
+                    ("tests/dump.bin".into(), 3 << 20),
+                    ("src/lib.rs".into(), 100),
+                ],
+                large_files: 1,
+                archives: vec![("apache-foo-0.1.1-rc1-src.tar.gz".into(), 3 << 19)],
+            }],
+            since_rc: None,
+        };
+        let md = report.render_markdown();
+        assert!(md.contains("> **Warning**: 1 packaged file(s) of at least 1.0 MiB"));
+        assert!(md.contains("| `apache-foo-0.1.1-rc1-src.tar.gz` | 1.5 MiB | 3.0 MiB | 50% |"));
+        assert!(md.contains("- `tests/dump.bin` 3.0 MiB **large**\n- `src/lib.rs` 100 B\n"));
+        let text = report.render_text();
+        assert!(text.contains(
+            "artifact sizes:\n  foo: 2 files, 3.0 MiB uncompressed\n    \
+             apache-foo-0.1.1-rc1-src.tar.gz: 1.5 MiB (50% of uncompressed)\n    \
+             largest: tests/dump.bin 3.0 MiB (large), src/lib.rs 100 B\n"
+        ));
+        assert!(md.contains("| RC tag | `v0.1.1-rc.1` |"));
+        assert!(md.contains("<summary><code>foo</code> 0.1.0 → 0.1.1</summary>"));
+        assert!(md.contains("#### Fixes\n\n- fix: handle empty input (abc1234)"));
+        assert!(md.contains("</details>"));
+        assert!(md.contains("no changelog lists them:\n\n- Update CI (def5678)"));
+        assert!(md.contains(
+            "### API checks\n\n- foo: WARNING public API broke but no commit is marked breaking"
+        ));
     }
 }
@@ -1,20 +1,43 @@
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
-use git2::{Repository, Sort};
+use anyhow::{Context, Result, bail};
+use git2::{Commit, Repository, Sort};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
 
+use crate::config::{
+    ChangelogConfig, ChangelogLabelsConfig, ChangelogSection, CrateConfig, RootFiles,
+};
 use crate::infer::{CrateInfo, InferredContext};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+use super::cache::{CachedCommit, PlanCache};
+
+/// A first release with more commits than this gets a hint about `[bootstrap]`.
+const BOOTSTRAP_WARN_COMMITS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub(crate) enum BumpKind {
     Major,
     Minor,
     Patch,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) enum CommitKind {
+impl fmt::Display for BumpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BumpKind::Major => "major",
+            BumpKind::Minor => "minor",
+            BumpKind::Patch => "patch",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitKind {
     Breaking,
     Feat,
     Fix,
@@ -26,12 +49,123 @@ pub(crate) enum CommitKind {
     Other,
 }
 
+impl fmt::Display for CommitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CommitKind::Breaking => "breaking",
+            CommitKind::Feat => "feat",
+            CommitKind::Fix => "fix",
+            CommitKind::Perf => "perf",
+            CommitKind::Refactor => "refactor",
+            CommitKind::Docs => "docs",
+            CommitKind::Build => "build",
+            CommitKind::Chore => "chore",
+            CommitKind::Other => "other",
+        })
+    }
+}
+
+impl CommitKind {
+    const ALL: [CommitKind; 9] = [
+        CommitKind::Breaking,
+        CommitKind::Feat,
+        CommitKind::Fix,
+        CommitKind::Perf,
+        CommitKind::Refactor,
+        CommitKind::Docs,
+        CommitKind::Build,
+        CommitKind::Chore,
+        CommitKind::Other,
+    ];
+}
+
+/// Why a commit was classified the way it was, for `--explain`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "by", content = "detail", rename_all = "kebab-case")]
+pub(crate) enum Classification {
+    /// `!` before the colon, e.g. `feat!:` or `feat(core)!:`.
+    BangMarker,
+    /// A `BREAKING CHANGE:` footer in the message body.
+    BreakingFooter,
+    /// A `[[changelog.rules]]` pattern from `.asfship.toml`.
+    Rule(String),
+    /// A label on the commit's pull request, from `[changelog.labels]`.
+    Label(String),
+    /// The Conventional Commit type before the colon.
+    Type(String),
+    /// No recognised Conventional Commit type.
+    Unrecognized,
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Classification::BangMarker => write!(f, "`!` marker in the header"),
+            Classification::BreakingFooter => write!(f, "BREAKING CHANGE footer"),
+            Classification::Rule(pattern) => write!(f, "changelog rule /{}/", pattern),
+            Classification::Label(label) => write!(f, "pull request label `{}`", label),
+            Classification::Type(ty) => write!(f, "type `{}`", ty),
+            Classification::Unrecognized => write!(f, "no Conventional Commit type"),
+        }
+    }
+}
+
+/// Changelog sections in output order.
+///
+/// Uses `[changelog].sections` when configured; kinds no section claims are
+/// collected in a trailing `Others` section so no commit is dropped.
+pub(crate) fn changelog_sections(cfg: &ChangelogConfig) -> Vec<ChangelogSection> {
+    if cfg.sections.is_empty() {
+        let section = |title: &str, kinds: &[CommitKind]| ChangelogSection {
+            title: title.to_string(),
+            kinds: kinds.to_vec(),
+        };
+        return vec![
+            section("Breaking Changes", &[CommitKind::Breaking]),
+            section("Features", &[CommitKind::Feat]),
+            section("Fixes", &[CommitKind::Fix]),
+            section("Refactor/Perf", &[CommitKind::Refactor, CommitKind::Perf]),
+            section(
+                "Others",
+                &[
+                    CommitKind::Docs,
+                    CommitKind::Build,
+                    CommitKind::Chore,
+                    CommitKind::Other,
+                ],
+            ),
+        ];
+    }
+    let mut sections = cfg.sections.clone();
+    let unclaimed: Vec<CommitKind> = CommitKind::ALL
+        .into_iter()
+        .filter(|k| !sections.iter().any(|s| s.kinds.contains(k)))
+        .collect();
+    if !unclaimed.is_empty() {
+        match sections.iter_mut().find(|s| s.title == "Others") {
+            Some(others) => others.kinds.extend(unclaimed),
+            None => sections.push(ChangelogSection {
+                title: "Others".to_string(),
+                kinds: unclaimed,
+            }),
+        }
+    }
+    sections
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct ChangeEntry {
     kind: CommitKind,
     subject: String,
     sha: String,
     breaking: bool,
+    classification: Classification,
+    /// Cleared by a skip label: the commit still counts toward the bump.
+    in_changelog: bool,
+    /// Set by a `Release-Highlight:` trailer or a highlight label.
+    highlight: bool,
+    /// Text of the `Release-Highlight:` trailer, used in place of the entry under Highlights.
+    highlight_note: Option<String>,
 }
 
 impl ChangeEntry {
@@ -50,12 +184,68 @@ impl ChangeEntry {
     pub(crate) fn is_breaking(&self) -> bool {
         self.breaking
     }
+
+    pub(crate) fn classification(&self) -> &Classification {
+        &self.classification
+    }
+
+    pub(crate) fn in_changelog(&self) -> bool {
+        self.in_changelog
+    }
+
+    pub(crate) fn is_highlight(&self) -> bool {
+        self.highlight
+    }
+
+    pub(crate) fn highlight_note(&self) -> Option<&str> {
+        self.highlight_note.as_deref()
+    }
+
+    /// Apply the `[changelog.labels]` overrides for the labels on the commit's pull request.
+    ///
+    /// A breaking label always wins; kind labels never downgrade a breaking commit.
+    fn relabel(&mut self, labels: &[String], cfg: &ChangelogLabelsConfig) {
+        let has = |name: &str| labels.iter().any(|l| l == name);
+        if has(cfg.breaking()) {
+            self.kind = CommitKind::Breaking;
+            self.classification = Classification::Label(cfg.breaking().to_string());
+        } else if !self.breaking
+            && let Some((label, kind)) = labels
+                .iter()
+                .find_map(|l| cfg.kinds.get(l).map(|kind| (l, *kind)))
+        {
+            self.kind = kind;
+            self.classification = Classification::Label(label.clone());
+        }
+        self.breaking = self.kind == CommitKind::Breaking;
+        self.in_changelog = !has(cfg.skip());
+        self.highlight = self.highlight_note.is_some() || has(cfg.highlight());
+    }
+
+    /// Pull request the commit landed through, from a `Merge pull request #N` subject or
+    /// the `(#N)` GitHub appends to squash merges.
+    pub(crate) fn pull_request(&self) -> Option<u64> {
+        pull_request_number(&self.subject)
+    }
+}
+
+fn pull_request_number(subject: &str) -> Option<u64> {
+    let digits = if let Some(rest) = subject.strip_prefix("Merge pull request #") {
+        rest.split(' ').next()?
+    } else {
+        subject.trim_end().strip_suffix(')')?.rsplit_once("(#")?.1
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct CratePlan {
     previous_version: semver::Version,
     new_version: semver::Version,
+    bump: BumpKind,
     changes: Vec<ChangeEntry>,
 }
 
@@ -71,11 +261,66 @@ impl CratePlan {
     pub(crate) fn changes(&self) -> &[ChangeEntry] {
         &self.changes
     }
+
+    pub(crate) fn bump(&self) -> BumpKind {
+        self.bump
+    }
+
+    /// The first commit that called for this bump.
+    pub(crate) fn trigger(&self) -> &ChangeEntry {
+        let post1 = self.previous_version.major >= 1;
+        self.changes
+            .iter()
+            .find(|c| match self.bump {
+                BumpKind::Major => c.is_breaking(),
+                BumpKind::Minor if post1 => c.kind() == CommitKind::Feat,
+                BumpKind::Minor => c.is_breaking(),
+                BumpKind::Patch => true,
+            })
+            .unwrap_or(&self.changes[0])
+    }
+
+    /// Short reason for the bump level.
+    pub(crate) fn rationale(&self) -> &'static str {
+        match (self.bump, self.previous_version.major >= 1) {
+            (BumpKind::Major, _) => "breaking change",
+            (BumpKind::Minor, true) => "new feature",
+            (BumpKind::Minor, false) => "breaking change before 1.0",
+            (BumpKind::Patch, true) => "no feature or breaking change",
+            (BumpKind::Patch, false) => "no breaking change before 1.0",
+        }
+    }
+
+    /// The SemVer rule that picked the bump level, spelled out for `--explain`.
+    pub(crate) fn bump_rule(&self) -> String {
+        let v = &self.previous_version;
+        match (self.bump, v.major >= 1) {
+            (BumpKind::Major, _) => format!("{} is 1.0 or later and a commit is breaking", v),
+            (BumpKind::Minor, true) => format!(
+                "{} is 1.0 or later, no commit is breaking, and a commit is a feature",
+                v
+            ),
+            (BumpKind::Minor, false) => format!(
+                "{} is before 1.0, where a breaking commit bumps the minor version",
+                v
+            ),
+            (BumpKind::Patch, true) => format!(
+                "{} is 1.0 or later and no commit is breaking or a feature",
+                v
+            ),
+            (BumpKind::Patch, false) => format!(
+                "{} is before 1.0 and no commit is breaking; features only bump the patch version",
+                v
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct Plan {
     per_crate: BTreeMap<String, CratePlan>,
+    /// Commits since the base tag that touch no crate, so no changelog lists them.
+    unattributed: Vec<ChangeEntry>,
 }
 
 impl Plan {
@@ -91,106 +336,179 @@ impl Plan {
         self.per_crate.iter()
     }
 
+    pub(crate) fn unattributed(&self) -> &[ChangeEntry] {
+        &self.unattributed
+    }
+
+    /// Pull requests behind the planned commits, in ascending order.
+    pub(crate) fn pull_requests(&self) -> Vec<u64> {
+        let changes = self.per_crate.values().flat_map(|cp| cp.changes.iter());
+        let numbers: BTreeSet<u64> = changes
+            .chain(&self.unattributed)
+            .filter_map(ChangeEntry::pull_request)
+            .collect();
+        numbers.into_iter().collect()
+    }
+
     pub(crate) fn main_crate_version(&self, main: &str) -> Option<&semver::Version> {
         self.crate_plan(main).map(|cp| cp.new_version())
     }
+
+    /// Re-plan with `[changelog.labels]` overrides from the labels of each pull request.
+    ///
+    /// Bumps are decided again, since a label can make a change breaking.
+    pub(crate) fn with_labels(
+        self,
+        ctx: &InferredContext,
+        labels: &HashMap<u64, Vec<String>>,
+    ) -> Result<Plan> {
+        let cfg = &ctx.config.changelog.labels;
+        let relabel = |mut entry: ChangeEntry| {
+            if let Some(found) = entry.pull_request().and_then(|n| labels.get(&n)) {
+                entry.relabel(found, cfg);
+            }
+            entry
+        };
+        let per_crate_changes = self
+            .per_crate
+            .into_iter()
+            .map(|(name, cp)| (name, cp.changes.into_iter().map(relabel).collect()))
+            .collect();
+        let unattributed = self.unattributed.into_iter().map(relabel).collect();
+        build_plan(ctx, per_crate_changes, unattributed)
+    }
 }
 
 pub(crate) fn compute_plan(repo: &Repository, ctx: &InferredContext) -> Result<Plan> {
-    let base_oid = if let Some(tag) = &ctx.last_stable_tag {
+    // The base is the last stable tag or any revision given with `--since`.
+    let base_oid = if let Some(tag) = ctx.plan_base() {
         let obj = repo
             .revparse_single(&format!("refs/tags/{}", tag))
             .or_else(|_| repo.revparse_single(tag))
-            .context("failed to resolve last stable tag")?;
+            .with_context(|| format!("failed to resolve plan base {}", tag))?;
         let commit = obj
             .peel_to_commit()
-            .context("tag does not point to a commit")?;
+            .with_context(|| format!("plan base {} does not point to a commit", tag))?;
         Some(commit.id())
     } else {
         None
     };
+    let tip = match &ctx.until {
+        Some(until) => repo
+            .revparse_single(until)
+            .and_then(|o| o.peel_to_commit())
+            .with_context(|| format!("--until {:?} is not a commit", until))?
+            .id(),
+        None => repo.head()?.peel_to_commit()?.id(),
+    };
 
     let mut roots: Vec<(PathBuf, &CrateInfo)> = ctx
         .crates
         .iter()
         .map(|c| (c.package_root.clone(), c))
         .collect();
-    roots.sort_by(|a, b| b.0.components().count().cmp(&a.0.components().count()));
+    roots.sort_by_key(|r| std::cmp::Reverse(r.0.components().count()));
 
     let mut per_crate_changes: HashMap<String, Vec<ChangeEntry>> = HashMap::new();
 
     let mut walk = repo.revwalk()?;
     walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
-    walk.push_head()?;
+    walk.push(tip)?;
     if let Some(base) = base_oid {
         walk.hide(base)?;
+    } else if let Some(since) = &ctx.config.bootstrap.since {
+        let start = repo
+            .revparse_single(since)
+            .and_then(|o| o.peel_to_commit())
+            .with_context(|| format!("bootstrap.since {:?} is not a commit", since))?;
+        if tip != start.id() && !repo.graph_descendant_of(tip, start.id())? {
+            bail!(
+                "bootstrap.since {:?} is not an ancestor of {}",
+                since,
+                ctx.until.as_deref().unwrap_or("HEAD")
+            );
+        }
+        for parent in start.parent_ids() {
+            walk.hide(parent)?;
+        }
     }
 
+    let rules = compile_rules(&ctx.config.changelog)?;
+    let mut cache = PlanCache::load(ctx);
+    let mut commits = Vec::new();
     for oid in walk {
         let oid = oid?;
-        let commit = repo.find_commit(oid)?;
-        let subject = commit
-            .summary()
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| String::from("<no subject>"));
-        let message = commit.message().unwrap_or("");
-        let short = oid.to_string()[..7].to_string();
-
-        let breaking_header = subject.contains("!:")
-            || subject.contains("(!):")
-            || subject.starts_with(|c: char| c.is_alphabetic())
-                && subject
-                    .split(':')
-                    .next()
-                    .map(|t| t.ends_with('!'))
-                    .unwrap_or(false);
-        let breaking_body = message.to_ascii_uppercase().contains("BREAKING CHANGE:");
-        let breaking = breaking_header || breaking_body;
-
-        let kind = classify_commit(&subject, breaking);
-
-        let diffs = if commit.parent_count() > 0 {
-            let parent = commit.parent(0)?;
-            repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&commit.tree()?), None)?
-        } else {
-            repo.diff_tree_to_tree(None, Some(&commit.tree()?), None)?
+        let key = oid.to_string();
+        let info = match cache.get(&key) {
+            Some(info) => info.clone(),
+            None => {
+                let info = analyze_commit(repo, &repo.find_commit(oid)?, ctx, &roots, &rules)?;
+                cache.insert(key.clone(), info.clone());
+                info
+            }
         };
+        commits.push((key, info));
+    }
+    cache.retain(&commits.iter().map(|(key, _)| key.as_str()).collect());
+    cache.save();
+    if base_oid.is_none()
+        && ctx.config.bootstrap.since.is_none()
+        && commits.len() > BOOTSTRAP_WARN_COMMITS
+    {
+        tracing::warn!(
+            "plan: no stable tag, so all {} commits count toward the first release; set bootstrap.since or bootstrap.initial_changelog to keep the changelog short",
+            commits.len()
+        );
+    }
 
-        let mut touched: HashSet<String> = HashSet::new();
-        diffs.foreach(
-            &mut |delta, _| {
-                if let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path())
-                    && let Some(name) = crate_for_path(&ctx.repo_root, &roots, path)
-                {
-                    touched.insert(name.to_string());
-                }
-                true
-            },
-            None,
-            None,
-            None,
-        )?;
-
-        for name in touched {
+    let mut unattributed = Vec::new();
+    for (key, info) in dedupe_commits(commits) {
+        let entry = ChangeEntry {
+            kind: info.kind,
+            subject: info.subject,
+            sha: key[..7].to_string(),
+            breaking: info.breaking,
+            classification: info.classification,
+            in_changelog: true,
+            highlight: info.highlight.is_some(),
+            highlight_note: info.highlight,
+        };
+        if info.touched.is_empty() {
+            unattributed.push(entry);
+            continue;
+        }
+        for name in info.touched {
             per_crate_changes
                 .entry(name)
                 .or_default()
-                .push(ChangeEntry {
-                    kind,
-                    subject: subject.clone(),
-                    sha: short.clone(),
-                    breaking,
-                });
+                .push(entry.clone());
         }
     }
 
+    build_plan(ctx, per_crate_changes, unattributed)
+}
+
+fn build_plan(
+    ctx: &InferredContext,
+    mut per_crate_changes: HashMap<String, Vec<ChangeEntry>>,
+    unattributed: Vec<ChangeEntry>,
+) -> Result<Plan> {
     let mut per_crate: BTreeMap<String, CratePlan> = BTreeMap::new();
     for c in &ctx.crates {
-        if let Some(changes) = per_crate_changes.get(&c.name) {
+        if !c.publish && !ctx.config.bump_unpublished {
+            tracing::debug!("plan: skipping publish = false crate {}", c.name);
+            continue;
+        }
+        if !ctx.config.only.is_empty() && !ctx.config.only.contains(&c.name) {
+            tracing::debug!("plan: skipping {}, not selected by only", c.name);
+            continue;
+        }
+        if let Some(mut changes) = per_crate_changes.remove(&c.name) {
             if changes.is_empty() {
                 continue;
             }
-            let bump = decide_bump(&c.version, changes);
+            changes.sort_by_key(|c| !c.highlight);
+            let bump = decide_bump(&c.version, &changes);
             let mut new = c.version.clone();
             match bump {
                 BumpKind::Major => {
@@ -206,18 +524,199 @@ pub(crate) fn compute_plan(repo: &Repository, ctx: &InferredContext) -> Result<P
                     new.patch += 1;
                 }
             }
+            if let Some(constraints) = ctx.config.crates.get(&c.name) {
+                check_constraints(&c.name, &new, constraints)?;
+            }
             per_crate.insert(
                 c.name.clone(),
                 CratePlan {
                     previous_version: c.version.clone(),
                     new_version: new,
-                    changes: changes.clone(),
+                    bump,
+                    changes,
                 },
             );
         }
     }
 
-    Ok(Plan { per_crate })
+    Ok(Plan {
+        per_crate,
+        unattributed,
+    })
+}
+
+fn analyze_commit(
+    repo: &Repository,
+    commit: &Commit<'_>,
+    ctx: &InferredContext,
+    roots: &[(PathBuf, &CrateInfo)],
+    rules: &[(Regex, CommitKind)],
+) -> Result<CachedCommit> {
+    let subject = commit
+        .summary()
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| String::from("<no subject>"));
+    let message = commit.message().unwrap_or("");
+
+    let breaking_header = subject.contains("!:")
+        || subject.contains("(!):")
+        || subject.starts_with(|c: char| c.is_alphabetic())
+            && subject
+                .split(':')
+                .next()
+                .map(|t| t.ends_with('!'))
+                .unwrap_or(false);
+    let breaking_body = message.to_ascii_uppercase().contains("BREAKING CHANGE:");
+    let breaking_marker = if breaking_header {
+        Some(Classification::BangMarker)
+    } else if breaking_body {
+        Some(Classification::BreakingFooter)
+    } else {
+        None
+    };
+    let (kind, classification) = classify_commit(&subject, breaking_marker, rules);
+    let breaking = kind == CommitKind::Breaking;
+
+    let reverts = Regex::new(r"This reverts commit ([0-9a-f]{7,40})")
+        .unwrap()
+        .captures(message)
+        .map(|c| c[1].to_string());
+    let highlight = release_highlight(message);
+
+    let diffs = if commit.parent_count() > 0 {
+        let parent = commit.parent(0)?;
+        repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&commit.tree()?), None)?
+    } else {
+        repo.diff_tree_to_tree(None, Some(&commit.tree()?), None)?
+    };
+
+    let mut touched: HashSet<String> = HashSet::new();
+    diffs.foreach(
+        &mut |delta, _| {
+            let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) else {
+                return true;
+            };
+            if let Some(name) = crate_for_path(&ctx.repo_root, roots, path) {
+                touched.insert(name.to_string());
+            } else if path.components().count() == 1 {
+                // Only reachable in a virtual workspace; a root package claims these itself.
+                match ctx.config.root_files {
+                    RootFiles::None => {}
+                    RootFiles::Main => {
+                        touched.insert(ctx.main_crate.clone());
+                    }
+                    RootFiles::All => touched.extend(ctx.crates.iter().map(|c| c.name.clone())),
+                }
+            }
+            true
+        },
+        None,
+        None,
+        None,
+    )?;
+    let mut touched: Vec<String> = touched.into_iter().collect();
+    touched.sort();
+
+    // Merge commits diff against the whole merged branch, so their patch id never
+    // identifies a single cherry-pickable change.
+    let patch_id = if commit.parent_count() <= 1 && diffs.deltas().len() > 0 {
+        Some(diffs.patchid(None)?.to_string())
+    } else {
+        None
+    };
+
+    Ok(CachedCommit {
+        kind,
+        classification,
+        subject,
+        breaking,
+        touched,
+        patch_id,
+        reverts,
+        highlight,
+    })
+}
+
+/// Text of the last non-empty `Release-Highlight:` trailer of `message`.
+fn release_highlight(message: &str) -> Option<String> {
+    let trailers = git2::message_trailers_strs(message).ok()?;
+    trailers
+        .iter()
+        .rev()
+        .find(|(key, value)| {
+            key.eq_ignore_ascii_case("Release-Highlight") && !value.trim().is_empty()
+        })
+        .map(|(_, value)| value.trim().to_string())
+}
+
+/// Drop commits that should not reach the changelog, keeping walk order.
+///
+/// A revert whose target is also in range cancels out together with it, and
+/// commits sharing a patch id (cherry-picks) collapse into the first occurrence.
+fn dedupe_commits(commits: Vec<(String, CachedCommit)>) -> Vec<(String, CachedCommit)> {
+    let mut dropped: HashSet<usize> = HashSet::new();
+    for (idx, (_, info)) in commits.iter().enumerate() {
+        let Some(target) = &info.reverts else {
+            continue;
+        };
+        let reverted = commits[..idx]
+            .iter()
+            .position(|(oid, _)| oid.starts_with(target.as_str()));
+        if let Some(pos) = reverted
+            && !dropped.contains(&pos)
+        {
+            tracing::debug!("plan: revert {} cancels {}", &commits[idx].0[..7], target);
+            dropped.insert(pos);
+            dropped.insert(idx);
+        }
+    }
+
+    let mut seen_patches: HashSet<&str> = HashSet::new();
+    for (idx, (oid, info)) in commits.iter().enumerate() {
+        if dropped.contains(&idx) {
+            continue;
+        }
+        if let Some(patch_id) = &info.patch_id
+            && !seen_patches.insert(patch_id.as_str())
+        {
+            tracing::debug!("plan: {} duplicates an earlier patch; skipping", &oid[..7]);
+            dropped.insert(idx);
+        }
+    }
+
+    commits
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| !dropped.contains(idx))
+        .map(|(_, commit)| commit)
+        .collect()
+}
+
+fn check_constraints(name: &str, planned: &semver::Version, cfg: &CrateConfig) -> Result<()> {
+    if let Some(min) = &cfg.min_version
+        && planned < min
+    {
+        bail!(
+            "crate {} would be released as {}, below crates.{}.min_version = {} in .asfship.toml",
+            name,
+            planned,
+            name,
+            min
+        );
+    }
+    if let Some(max) = &cfg.max_version
+        && planned >= max
+    {
+        bail!(
+            "crate {} would be released as {}, reaching crates.{}.max_version = {} (exclusive) in .asfship.toml; \
+             adjust the constraint or reword the breaking commits",
+            name,
+            planned,
+            name,
+            max
+        );
+    }
+    Ok(())
 }
 
 fn crate_for_path<'a>(
@@ -234,13 +733,38 @@ fn crate_for_path<'a>(
     None
 }
 
-fn classify_commit(subject: &str, breaking: bool) -> CommitKind {
-    if breaking {
-        return CommitKind::Breaking;
+fn compile_rules(cfg: &ChangelogConfig) -> Result<Vec<(Regex, CommitKind)>> {
+    cfg.rules
+        .iter()
+        .map(|rule| {
+            let re = RegexBuilder::new(&rule.pattern)
+                .case_insensitive(true)
+                .build()
+                .with_context(|| {
+                    format!(
+                        "invalid changelog.rules pattern {:?} in .asfship.toml",
+                        rule.pattern
+                    )
+                })?;
+            Ok((re, rule.kind))
+        })
+        .collect()
+}
+
+fn classify_commit(
+    subject: &str,
+    breaking: Option<Classification>,
+    rules: &[(Regex, CommitKind)],
+) -> (CommitKind, Classification) {
+    if let Some(marker) = breaking {
+        return (CommitKind::Breaking, marker);
+    }
+    if let Some((re, kind)) = rules.iter().find(|(re, _)| re.is_match(subject)) {
+        return (*kind, Classification::Rule(re.as_str().to_string()));
     }
     let lower = subject.to_ascii_lowercase();
     let ty = lower.split(':').next().unwrap_or("");
-    match ty {
+    let kind = match ty {
         t if t.starts_with("feat") => CommitKind::Feat,
         t if t.starts_with("fix") => CommitKind::Fix,
         t if t.starts_with("perf") => CommitKind::Perf,
@@ -248,8 +772,9 @@ fn classify_commit(subject: &str, breaking: bool) -> CommitKind {
         t if t.starts_with("docs") => CommitKind::Docs,
         t if t.starts_with("build") => CommitKind::Build,
         t if t.starts_with("chore") => CommitKind::Chore,
-        _ => CommitKind::Other,
-    }
+        _ => return (CommitKind::Other, Classification::Unrecognized),
+    };
+    (kind, Classification::Type(ty.to_string()))
 }
 
 fn decide_bump(current: &semver::Version, changes: &[ChangeEntry]) -> BumpKind {
@@ -269,3 +794,217 @@ fn decide_bump(current: &semver::Version, changes: &[ChangeEntry]) -> BumpKind {
         BumpKind::Patch
     }
 }
+
+#[cfg(test)]
+impl ChangeEntry {
+    /// An entry for `subject` on commit `abc1234`, as `compute_plan` would classify it.
+    pub(crate) fn sample(kind: CommitKind, subject: &str) -> Self {
+        Self {
+            kind,
+            subject: subject.to_string(),
+            sha: "abc1234".to_string(),
+            breaking: kind == CommitKind::Breaking,
+            classification: Classification::Unrecognized,
+            in_changelog: true,
+            highlight: false,
+            highlight_note: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClassifyRule;
+
+    #[test]
+    fn pull_request_numbers_come_from_merge_subjects() {
+        assert_eq!(
+            pull_request_number("fix: handle empty input (#123)"),
+            Some(123)
+        );
+        assert_eq!(
+            pull_request_number("Merge pull request #45 from user/branch"),
+            Some(45)
+        );
+        assert_eq!(pull_request_number("fix: see (#abc)"), None);
+        assert_eq!(pull_request_number("fix: issue #12 in parser"), None);
+    }
+
+    #[test]
+    fn classify_commit_applies_configured_rules_first() {
+        let cfg = ChangelogConfig {
+            rules: vec![
+                ClassifyRule {
+                    pattern: r"^deps(\(.*\))?:".into(),
+                    kind: CommitKind::Build,
+                },
+                ClassifyRule {
+                    pattern: r"^api:".into(),
+                    kind: CommitKind::Breaking,
+                },
+            ],
+            ..Default::default()
+        };
+        let rules = compile_rules(&cfg).unwrap();
+        assert_eq!(
+            classify_commit("deps(core): bump tokio", None, &rules),
+            (
+                CommitKind::Build,
+                Classification::Rule(r"^deps(\(.*\))?:".into())
+            )
+        );
+        assert_eq!(
+            classify_commit("API: drop old reader", None, &rules).0,
+            CommitKind::Breaking
+        );
+        assert_eq!(
+            classify_commit("fix(io): handle eof", None, &rules),
+            (CommitKind::Fix, Classification::Type("fix(io)".into()))
+        );
+        assert_eq!(
+            classify_commit(
+                "fix: handle eof",
+                Some(Classification::BreakingFooter),
+                &rules
+            ),
+            (CommitKind::Breaking, Classification::BreakingFooter)
+        );
+        assert_eq!(
+            classify_commit("Update README", None, &rules),
+            (CommitKind::Other, Classification::Unrecognized)
+        );
+    }
+
+    #[test]
+    fn pull_request_labels_override_classification() {
+        let cfg = ChangelogLabelsConfig {
+            enabled: true,
+            kinds: [("bug".to_string(), CommitKind::Fix)].into(),
+            ..Default::default()
+        };
+        let entry = |kind| ChangeEntry {
+            kind,
+            subject: "Update parser (#3)".into(),
+            sha: "abcdef0".into(),
+            breaking: kind == CommitKind::Breaking,
+            classification: Classification::Unrecognized,
+            in_changelog: true,
+            highlight: false,
+            highlight_note: None,
+        };
+        let labels = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let mut other = entry(CommitKind::Other);
+        other.relabel(&labels(&["bug", "highlight"]), &cfg);
+        assert_eq!(other.kind(), CommitKind::Fix);
+        assert_eq!(other.classification(), &Classification::Label("bug".into()));
+        assert!(other.is_highlight() && other.in_changelog());
+
+        let mut noted = entry(CommitKind::Feat);
+        noted.highlight_note = Some("Faster parsing".into());
+        noted.relabel(&labels(&["bug"]), &cfg);
+        assert!(noted.is_highlight());
+
+        let mut breaking = entry(CommitKind::Breaking);
+        breaking.relabel(&labels(&["bug", "skip-changelog"]), &cfg);
+        assert!(breaking.is_breaking());
+        assert!(!breaking.in_changelog());
+
+        let mut feat = entry(CommitKind::Feat);
+        feat.relabel(&labels(&["breaking-change"]), &cfg);
+        assert!(feat.is_breaking());
+        assert_eq!(
+            feat.classification(),
+            &Classification::Label("breaking-change".into())
+        );
+    }
+
+    #[test]
+    fn release_highlight_reads_the_trailer() {
+        assert_eq!(
+            release_highlight(
+                "feat: stream reads\n\nBody text.\n\nRelease-Highlight: Reads stream now\nSigned-off-by: A <a@example.com>\n"
+            )
+            .as_deref(),
+            Some("Reads stream now")
+        );
+        assert_eq!(
+            release_highlight("feat: stream reads\n\nRelease-Highlight:\n"),
+            None
+        );
+        assert_eq!(release_highlight("feat: stream reads\n"), None);
+    }
+
+    fn cached(subject: &str, patch_id: Option<&str>, reverts: Option<&str>) -> CachedCommit {
+        CachedCommit {
+            kind: CommitKind::Fix,
+            classification: Classification::Type("fix".into()),
+            subject: subject.into(),
+            breaking: false,
+            touched: vec!["foo".into()],
+            patch_id: patch_id.map(Into::into),
+            reverts: reverts.map(Into::into),
+            highlight: None,
+        }
+    }
+
+    #[test]
+    fn dedupe_commits_cancels_reverts_and_collapses_cherry_picks() {
+        let commits = vec![
+            ("aaaaaaa1".to_string(), cached("fix: a", Some("p1"), None)),
+            ("bbbbbbb2".to_string(), cached("fix: b", Some("p2"), None)),
+            (
+                "ccccccc3".to_string(),
+                cached("Revert \"fix: a\"", Some("p3"), Some("aaaaaaa")),
+            ),
+            ("ddddddd4".to_string(), cached("fix: b", Some("p2"), None)),
+            (
+                "eeeeeee5".to_string(),
+                cached("Revert \"fix: old\"", Some("p4"), Some("0123456")),
+            ),
+        ];
+        let kept: Vec<String> = dedupe_commits(commits)
+            .into_iter()
+            .map(|(oid, _)| oid)
+            .collect();
+        assert_eq!(kept, vec!["bbbbbbb2", "eeeeeee5"]);
+    }
+
+    #[test]
+    fn changelog_sections_keep_unclaimed_kinds() {
+        let cfg = ChangelogConfig {
+            rules: Vec::new(),
+            sections: vec![ChangelogSection {
+                title: "New".into(),
+                kinds: vec![CommitKind::Feat],
+            }],
+            ..Default::default()
+        };
+        let sections = changelog_sections(&cfg);
+        assert_eq!(sections[0].title, "New");
+        assert_eq!(sections[1].title, "Others");
+        assert!(sections[1].kinds.contains(&CommitKind::Fix));
+        for kind in CommitKind::ALL {
+            assert!(
+                changelog_sections(&ChangelogConfig::default())
+                    .iter()
+                    .any(|s| s.kinds.contains(&kind))
+            );
+        }
+    }
+
+    #[test]
+    fn check_constraints_enforces_floor_and_ceiling() {
+        let cfg = CrateConfig {
+            min_version: Some(semver::Version::new(0, 2, 0)),
+            max_version: Some(semver::Version::new(1, 0, 0)),
+            ..Default::default()
+        };
+        assert!(check_constraints("foo", &semver::Version::new(0, 5, 0), &cfg).is_ok());
+        let low = check_constraints("foo", &semver::Version::new(0, 1, 9), &cfg).unwrap_err();
+        assert!(low.to_string().contains("min_version"));
+        let high = check_constraints("foo", &semver::Version::new(1, 0, 0), &cfg).unwrap_err();
+        assert!(high.to_string().contains("max_version"));
+    }
+}
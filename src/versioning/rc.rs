@@ -1,45 +1,71 @@
 use std::collections::BTreeSet;
 use std::fs;
 use std::io::Cursor;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
 use flate2::Compression;
 use flate2::write::GzEncoder;
-use git2::{Commit, Repository};
+use git2::{Commit, Oid, Repository};
 use reqwest::StatusCode;
 use reqwest::header;
-use sha2::{Digest, Sha512};
 use tar::Builder as TarBuilder;
 use tokio::fs as async_fs;
-use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 use tokio::time::sleep;
+use tokio_util::io::ReaderStream;
 use urlencoding::encode as url_encode;
 use zip::CompressionMethod as ZipCompression;
 use zip::write::FileOptions as ZipOptions;
 
+use crate::artifacts;
+use crate::asf;
+use crate::asset_cache::AssetCache;
+use crate::attestation;
+use crate::audit::{AuditAction, AuditLog};
+use crate::cancel;
+use crate::checksum;
+use crate::component;
+use crate::config::{ArchiveFormat, CompressionConfig};
+use crate::git_identity::GitIdentity;
 use crate::github;
+use crate::gpg;
 use crate::infer::InferredContext;
+use crate::manifest::RunManifest;
+use crate::net;
+use crate::partial_clone;
+use crate::provenance;
+use crate::tags::TagFormat;
 
 use super::plan::Plan;
+use super::sizes::{self, CrateSizes};
+use super::verify;
+use super::worktree;
 
-const UPLOAD_RETRIES: usize = 3;
+const MAX_ASSET_BYTES: u64 = 2 * 1024 * 1024 * 1024;
 
 pub(crate) enum RcMode {
-    Remote,
+    /// Push to origin; `upstream` is the checked ref on origin the release branch tracks.
+    Remote {
+        upstream: String,
+    },
     LocalOnly,
 }
 
 pub(crate) struct RcOutcome {
     pub rc_tag: String,
     pub artifact_dir: PathBuf,
+    pub sizes: Vec<CrateSizes>,
 }
 
+#[derive(Clone)]
 pub(crate) struct PackagedCrate {
     pub name: String,
+    /// Crate directory inside the archives, relative to the repository root.
+    pub root: PathBuf,
     pub files: Vec<PathBuf>,
 }
 
@@ -47,74 +73,245 @@ pub(crate) async fn execute_rc(
     repo: &Repository,
     ctx: &InferredContext,
     plan: &Plan,
-    artifact_dir: Option<&Path>,
     mode: RcMode,
+    (rc_tag, rc_n): (String, u32),
+    release_commit: Oid,
+    audit: &AuditLog,
 ) -> Result<RcOutcome> {
-    let base_version = plan
-        .main_crate_version(&ctx.main_crate)
-        .expect("main crate plan must exist before RC steps");
-    let (rc_tag, rc_n) = next_rc_tag(repo, base_version)?;
-    tracing::info!("rc: choosing tag={} (rc={})", rc_tag, rc_n);
+    tracing::info!("rc: using tag={} (rc={})", rc_tag, rc_n);
+    let started = Utc::now();
 
     ensure_tag_absent(repo, &rc_tag)?;
 
-    let commit = repo.head()?.peel_to_commit()?;
+    let commit = repo.find_commit(release_commit)?;
 
-    create_rc_tag(repo, &rc_tag).await?;
+    // Package and verify before tagging so a bad tarball never leaves a pushed rc tag.
+    let run_dir = artifacts::prepare(ctx, &rc_tag).await?;
 
-    if matches!(mode, RcMode::Remote) {
-        push_head_and_tag(&ctx.repo_root, &rc_tag).await?;
-        create_github_prerelease(&ctx.repo_owner, &ctx.repo_name, &rc_tag).await?;
+    let packaged = package_changed_crates(repo, ctx, plan, &commit, &run_dir, rc_n).await?;
+    validate_packaged(ctx, plan, &packaged)?;
+    verify::verify_packaged(ctx, &packaged, &rc_tag).await?;
+    let to_measure = packaged.clone();
+    // Sizes only inform the report; an archive that cannot be read again is not fatal here.
+    let sizes = match tokio::task::spawn_blocking(move || sizes::measure(&to_measure)).await {
+        Ok(Ok(sizes)) => sizes,
+        Ok(Err(err)) => {
+            tracing::warn!("rc: measuring archive sizes failed: {:#}", err);
+            Vec::new()
+        }
+        Err(err) => {
+            tracing::warn!("rc: size task join error: {}", err);
+            Vec::new()
+        }
+    };
+    if repo.head()?.target() != Some(commit.id()) {
+        // Applied in an isolated worktree: move the checkout only now that packaging succeeded.
+        worktree::fast_forward(&ctx.repo_root, commit.id()).await?;
+    }
+
+    let mut run_manifest = RunManifest::new(&rc_tag, &commit.id().to_string());
+    for entry in &packaged {
+        for file in &entry.files {
+            run_manifest.add(&entry.name, file).await?;
+        }
     }
+    let manifest_path = run_manifest.write(&run_dir).await?;
+    let rm = asf::release_manager(&ctx.config.asf)?;
+    let manifest_signature = if ctx.config.prerelease.sign_manifest {
+        let fingerprint = rm.as_ref().and_then(|rm| rm.gpg_fingerprint.as_deref());
+        Some(gpg::sign(&manifest_path, fingerprint).await?)
+    } else {
+        None
+    };
+    let provenance_files = if ctx.config.prerelease.provenance {
+        provenance::write_signed(ctx, &run_manifest, rm.as_ref(), started, &run_dir).await?
+    } else {
+        Vec::new()
+    };
 
-    let artifact_root = resolve_artifact_root(ctx, artifact_dir);
-    let run_dir = artifact_root.join(rc_tag.replace('/', "_"));
-    async_fs::create_dir_all(&run_dir).await?;
+    cancel::checkpoint("creating the rc tag")?;
+    let identity = GitIdentity::resolve(&ctx.repo_root, &ctx.config)?;
+    let signing_key = rm.as_ref().and_then(|rm| rm.gpg_fingerprint.as_deref());
+    create_rc_tag(repo, &rc_tag, identity, signing_key).await?;
+    audit.record(AuditAction::TagCreated, &rc_tag).await;
 
-    let packaged = package_changed_crates(repo, ctx, plan, &commit, &run_dir, rc_n).await?;
-    validate_packaged(plan, &packaged)?;
+    if let RcMode::Remote { upstream } = &mode {
+        if let Err(err) = cancel::checkpoint("pushing the release branch and tag") {
+            // The tag never left this machine; drop it so a re-run can recreate it.
+            repo.tag_delete(&rc_tag)?;
+            return Err(err);
+        }
+        push_head_and_tag(&ctx.repo_root, upstream, &rc_tag, audit).await?;
+        cancel::checkpoint("creating the GitHub prerelease")?;
+        let (name, body) = component::github_release(ctx, plan, &rc_tag)?;
+        if create_github_prerelease(&ctx.repo_owner, &ctx.repo_name, &rc_tag, &name, &body).await? {
+            audit.record(AuditAction::ReleaseCreated, &rc_tag).await;
+        }
+    }
 
-    if matches!(mode, RcMode::Remote) {
+    if matches!(mode, RcMode::Remote { .. }) {
         let mut all_files: Vec<PathBuf> = packaged
             .iter()
             .flat_map(|p| p.files.iter().cloned())
             .collect();
         all_files.sort();
-        upload_assets_with_retry(&ctx.repo_owner, &ctx.repo_name, &rc_tag, &all_files).await?;
+        all_files.push(manifest_path);
+        all_files.extend(manifest_signature);
+        all_files.extend(provenance_files);
+        upload_assets_with_retry(&ctx.repo_owner, &ctx.repo_name, &rc_tag, &all_files, audit)
+            .await?;
+        if ctx.config.prerelease.attest {
+            let archives: Vec<PathBuf> = packaged
+                .iter()
+                .flat_map(|p| p.files.iter())
+                .filter(|f| f.extension().is_none_or(|e| e != "sha512"))
+                .cloned()
+                .collect();
+            attestation::attest(ctx, &run_dir, &archives, audit).await?;
+        }
     }
 
     Ok(RcOutcome {
         rc_tag,
         artifact_dir: run_dir,
+        sizes,
     })
 }
 
-fn resolve_artifact_root(ctx: &InferredContext, artifact_dir: Option<&Path>) -> PathBuf {
-    match artifact_dir {
-        Some(p) if p.is_absolute() => p.to_path_buf(),
-        Some(p) => ctx.repo_root.join(p),
-        None => ctx.repo_root.join("target").join("asfship"),
+fn artifact_base_name(
+    ctx: &InferredContext,
+    crate_name: &str,
+    version: &semver::Version,
+    rc_n: u32,
+) -> String {
+    let component = (crate_name != ctx.main_crate).then_some(crate_name);
+    asf::ArchiveNaming::of(ctx).base_name(component, version, Some(rc_n))
+}
+
+/// File names `package_changed_crates` would produce for `plan`, without touching disk.
+pub(crate) fn planned_artifact_names(ctx: &InferredContext, plan: &Plan, rc_n: u32) -> Vec<String> {
+    let mut names = Vec::new();
+    for c in &ctx.crates {
+        // `publish = false` crates may be versioned but never ship source archives.
+        if let Some(crate_plan) = plan.crate_plan(&c.name)
+            && c.publish
+        {
+            let base = artifact_base_name(ctx, &c.name, crate_plan.new_version(), rc_n);
+            for format in &ctx.config.package.compression.formats {
+                names.push(format!("{}.{}", base, format.extension()));
+                names.push(format!("{}.{}.sha512", base, format.extension()));
+            }
+        }
     }
+    names
 }
 
-fn next_rc_tag(repo: &Repository, base: &semver::Version) -> Result<(String, u32)> {
-    let pat = format!(
-        r"^v{}\.{}\.{}-rc\.(\d+)$",
-        base.major, base.minor, base.patch
-    );
-    let re = regex::Regex::new(&pat).unwrap();
+fn next_rc_tag(
+    repo: &Repository,
+    tags: &TagFormat,
+    base: &semver::Version,
+) -> Result<(String, u32)> {
     let mut max_n = 0u32;
     for r in repo.references_glob("refs/tags/*")?.flatten() {
         if let Some(name) = r.shorthand()
-            && let Some(m) = re.captures(name).and_then(|c| c.get(1))
-            && let Ok(n) = m.as_str().parse::<u32>()
+            && let Some((version, n)) = tags.parse_rc(name)
+            && version == *base
         {
             max_n = max_n.max(n);
         }
     }
     let next = max_n + 1;
-    let tag = format!("v{}.{}.{}-rc.{}", base.major, base.minor, base.patch, next);
-    Ok((tag, next))
+    Ok((tags.rc(base, next), next))
+}
+
+/// How the rc tag for a prerelease is chosen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RcSelection {
+    /// One past the highest existing rc tag for the planned version.
+    #[default]
+    Next,
+    /// A specific rc number, e.g. to recreate an rc after a local rollback.
+    Number(u32),
+    /// A full tag such as `v0.2.0-rc.3`; must match the planned main crate version.
+    Tag(String),
+}
+
+pub(crate) fn select_rc_tag(
+    repo: &Repository,
+    tags: &TagFormat,
+    base: &semver::Version,
+    selection: &RcSelection,
+) -> Result<(String, u32)> {
+    match selection {
+        RcSelection::Next => next_rc_tag(repo, tags, base),
+        RcSelection::Number(0) => bail!("rc numbers start at 1"),
+        RcSelection::Number(n) => Ok((tags.rc(base, *n), *n)),
+        RcSelection::Tag(tag) => {
+            let Some((version, n)) = tags.parse_rc(tag) else {
+                bail!("invalid rc tag {:?}; expected {}", tag, tags.rc_pattern());
+            };
+            if version != *base {
+                bail!(
+                    "rc tag {} does not match planned main crate version {}",
+                    tag,
+                    base
+                );
+            }
+            if n == 0 {
+                bail!("rc numbers start at 1");
+            }
+            Ok((tag.clone(), n))
+        }
+    }
+}
+
+/// Refuse an rc tag that already exists locally, on `origin`, or as a GitHub release.
+///
+/// The remote checks only run when `remote` is set, since local-only runs never push.
+pub(crate) async fn ensure_rc_available(
+    repo: &Repository,
+    ctx: &InferredContext,
+    tag: &str,
+    remote: bool,
+) -> Result<()> {
+    ensure_tag_absent(repo, tag)?;
+    if !remote {
+        return Ok(());
+    }
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(&ctx.repo_root)
+        .args(["ls-remote", "--tags", "origin"])
+        .arg(format!("refs/tags/{}", tag))
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!(
+            "git ls-remote failed while checking rc tag {}: {}",
+            tag,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    if !String::from_utf8_lossy(&output.stdout).trim().is_empty() {
+        bail!("rc tag already exists on origin: {}", tag);
+    }
+
+    let gh = github::client()?;
+    match gh
+        .repos(ctx.repo_owner.clone(), ctx.repo_name.clone())
+        .releases()
+        .get_by_tag(tag)
+        .await
+    {
+        Ok(release) => bail!(
+            "a GitHub release already exists for rc tag {}: {}",
+            tag,
+            release.html_url
+        ),
+        Err(err) if is_not_found(&err) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
 }
 
 fn ensure_tag_absent(repo: &Repository, tag: &str) -> Result<()> {
@@ -124,22 +321,67 @@ fn ensure_tag_absent(repo: &Repository, tag: &str) -> Result<()> {
     Ok(())
 }
 
-async fn create_rc_tag(repo: &Repository, tag: &str) -> Result<()> {
-    let repo_path = repo
-        .path()
-        .parent()
-        .unwrap_or_else(|| Path::new("."))
-        .to_path_buf();
+/// Where to open `repo` again on a blocking thread: the working tree, which in a linked
+/// worktree resolves to that worktree's HEAD, or the git dir of a bare repository.
+pub(crate) fn reopen_path(repo: &Repository) -> PathBuf {
+    repo.workdir().unwrap_or_else(|| repo.path()).to_path_buf()
+}
+
+/// Create the annotated `tag` on `target` with `git tag --sign`, signed by `key` and
+/// tagged by `identity`. git2 cannot sign tags, so this goes through the git CLI.
+pub(crate) async fn sign_tag(
+    repo_path: &Path,
+    tag: &str,
+    target: &str,
+    message: &str,
+    identity: &GitIdentity,
+    key: &str,
+) -> Result<()> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo_path)
+        .args(["tag", "--sign", "--local-user", key, "--message", message])
+        .args([tag, target])
+        .env("GIT_COMMITTER_NAME", &identity.name)
+        .env("GIT_COMMITTER_EMAIL", &identity.email)
+        .output()
+        .await
+        .context("failed to run git tag")?;
+    if !output.status.success() {
+        bail!(
+            "git could not sign tag {} with key {}: {}",
+            tag,
+            key,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+/// Create the annotated rc `tag` on HEAD. With a `signing_key` (the release manager's
+/// `asf.gpg_fingerprint`), git signs it with that key, so voters can `git tag -v` it.
+pub(crate) async fn create_rc_tag(
+    repo: &Repository,
+    tag: &str,
+    identity: GitIdentity,
+    signing_key: Option<&str>,
+) -> Result<()> {
+    let repo_path = reopen_path(repo);
+    if let Some(key) = signing_key {
+        let message = format!("asfship prerelease {}", tag);
+        sign_tag(&repo_path, tag, "HEAD", &message, &identity, key).await?;
+        tracing::info!("rc: created tag {} (signed by {})", tag, key);
+        return Ok(());
+    }
     let tag_name = tag.to_string();
     tokio::task::spawn_blocking(move || {
-        let repo = Repository::discover(repo_path)?;
+        let repo = Repository::open(repo_path)?;
         let obj = repo.head()?.peel(git2::ObjectType::Commit)?;
         let commit = obj
             .into_commit()
             .map_err(|_| anyhow::anyhow!("HEAD is not a commit"))?;
-        let sig = repo
-            .signature()
-            .or_else(|_| git2::Signature::now("asfship", "asfship@users.noreply.github.com"))?;
+        let sig = identity.signature()?;
         let msg = format!("asfship prerelease {}", tag_name);
         repo.tag(&tag_name, commit.as_object(), &sig, &msg, true)?;
         Ok::<_, anyhow::Error>(())
@@ -150,46 +392,55 @@ async fn create_rc_tag(repo: &Repository, tag: &str) -> Result<()> {
     Ok(())
 }
 
-async fn push_head_and_tag(repo_root: &Path, tag: &str) -> Result<()> {
-    let root = repo_root.to_path_buf();
-    let branch = tokio::task::spawn_blocking(move || -> Result<String> {
-        let repo = Repository::discover(root)?;
-        let head = repo.head()?;
-        let name = head
-            .shorthand()
-            .ok_or_else(|| anyhow::anyhow!("HEAD has no shorthand name"))?;
-        Ok(name.to_string())
-    })
-    .await
-    .map_err(|e| anyhow::anyhow!("branch detect task join error: {}", e))??;
-
+/// Push HEAD to `upstream` on origin, the ref `infer::ensure_head_pushed` compared it
+/// with, then the tag.
+#[tracing::instrument(skip_all)]
+async fn push_head_and_tag(
+    repo_root: &Path,
+    upstream: &str,
+    tag: &str,
+    audit: &AuditLog,
+) -> Result<()> {
+    let branch = upstream.strip_prefix("refs/heads/").unwrap_or(upstream);
     tracing::info!("git: pushing branch={} and tag={} to origin", branch, tag);
-    let status = Command::new("git")
-        .arg("-C")
-        .arg(repo_root)
-        .arg("push")
-        .arg("origin")
-        .arg(&branch)
-        .status()
-        .await?;
+    let status = cancel::status(
+        Command::new("git")
+            .arg("-C")
+            .arg(repo_root)
+            .arg("push")
+            .arg("origin")
+            .arg(format!("HEAD:{}", upstream)),
+    )
+    .await?;
     if !status.success() {
         bail!("git push branch failed with status: {}", status);
     }
-    let status = Command::new("git")
-        .arg("-C")
-        .arg(repo_root)
-        .arg("push")
-        .arg("origin")
-        .arg(format!("refs/tags/{}", tag))
-        .status()
-        .await?;
+    audit.record(AuditAction::BranchPushed, branch).await;
+    let status = cancel::status(
+        Command::new("git")
+            .arg("-C")
+            .arg(repo_root)
+            .arg("push")
+            .arg("origin")
+            .arg(format!("refs/tags/{}", tag)),
+    )
+    .await?;
     if !status.success() {
         bail!("git push tag failed with status: {}", status);
     }
+    audit.record(AuditAction::TagPushed, tag).await;
     Ok(())
 }
 
-async fn create_github_prerelease(owner: &str, repo: &str, tag: &str) -> Result<()> {
+/// Create the GitHub prerelease, returning false when it already existed.
+#[tracing::instrument(skip_all)]
+async fn create_github_prerelease(
+    owner: &str,
+    repo: &str,
+    tag: &str,
+    name: &str,
+    body: &str,
+) -> Result<bool> {
     tracing::info!("github: creating prerelease for tag={}", tag);
     let gh = github::client()?;
     let repos = gh.repos(owner.to_string(), repo.to_string());
@@ -197,7 +448,7 @@ async fn create_github_prerelease(owner: &str, repo: &str, tag: &str) -> Result<
     match rh.get_by_tag(tag).await {
         Ok(_) => {
             tracing::info!("github: release already exists for {}", tag);
-            return Ok(());
+            return Ok(false);
         }
         Err(err) => {
             if !is_not_found(&err) {
@@ -207,15 +458,16 @@ async fn create_github_prerelease(owner: &str, repo: &str, tag: &str) -> Result<
     }
     let _ = rh
         .create(tag)
-        .name(tag)
+        .name(name)
         .prerelease(true)
         .draft(false)
-        .body("")
+        .body(body)
         .send()
         .await?;
-    Ok(())
+    Ok(true)
 }
 
+#[tracing::instrument(skip_all)]
 async fn package_changed_crates(
     repo: &Repository,
     ctx: &InferredContext,
@@ -225,25 +477,35 @@ async fn package_changed_crates(
     rc_n: u32,
 ) -> Result<Vec<PackagedCrate>> {
     let tree = commit.tree()?;
+    // Seed the shared cache so later `sync`/`release` runs on this machine skip downloads.
+    let cache = AssetCache::new(&artifacts::root(ctx));
+    let exclude: Vec<PathBuf> = ctx
+        .config
+        .package
+        .exclude
+        .iter()
+        .map(|p| PathBuf::from(p.trim_end_matches('/')))
+        .collect();
+    let compression = &ctx.config.package.compression;
+    let roots: Vec<PathBuf> = ctx
+        .crates
+        .iter()
+        .filter(|c| c.publish && plan.crate_plan(&c.name).is_some())
+        .map(|c| {
+            normalize_relative(
+                c.package_root
+                    .strip_prefix(&ctx.repo_root)
+                    .unwrap_or(&c.package_root),
+            )
+        })
+        .collect();
+    partial_clone::ensure_blobs(repo, commit.id(), &roots)?;
     let mut packaged = Vec::new();
     for c in &ctx.crates {
-        if let Some(crate_plan) = plan.crate_plan(&c.name) {
-            let base = if c.name == ctx.main_crate {
-                format!(
-                    "apache-{}-{}-rc{}-src",
-                    ctx.repo_name,
-                    crate_plan.new_version(),
-                    rc_n
-                )
-            } else {
-                format!(
-                    "apache-{}-{}-{}-rc{}-src",
-                    ctx.repo_name,
-                    c.name,
-                    crate_plan.new_version(),
-                    rc_n
-                )
-            };
+        if let Some(crate_plan) = plan.crate_plan(&c.name)
+            && c.publish
+        {
+            let base = artifact_base_name(ctx, &c.name, crate_plan.new_version(), rc_n);
 
             let crate_rel = c
                 .package_root
@@ -251,24 +513,32 @@ async fn package_changed_crates(
                 .unwrap_or(&c.package_root)
                 .to_path_buf();
 
-            let tar_gz = out_dir.join(format!("{}.tar.gz", base));
-            let zip = out_dir.join(format!("{}.zip", base));
+            let archives = package_from_tree(
+                repo,
+                &tree,
+                &crate_rel,
+                &exclude,
+                &out_dir.join(&base),
+                compression,
+            )?;
+            let mut files = archives.clone();
 
-            package_from_tree(repo, &tree, &crate_rel, &tar_gz, &zip)?;
-            let mut files = vec![tar_gz.clone(), zip.clone()];
-
-            for f in [tar_gz, zip] {
-                let sha = compute_sha512(&f).await?;
-                let sha_path = f.with_file_name(format!(
-                    "{}.sha512",
-                    f.file_name().and_then(|n| n.to_str()).unwrap_or("artifact")
-                ));
-                async_fs::write(&sha_path, format!("{}\n", sha)).await?;
+            for f in archives {
+                let sha = checksum::sha512_file(&f).await?;
+                cache.insert(&sha, &f).await?;
+                let file_name = f.file_name().and_then(|n| n.to_str()).unwrap_or("artifact");
+                let sha_path = f.with_file_name(format!("{}.sha512", file_name));
+                async_fs::write(
+                    &sha_path,
+                    ctx.config.checksum_format.render(&sha, file_name),
+                )
+                .await?;
                 files.push(sha_path);
             }
 
             packaged.push(PackagedCrate {
                 name: c.name.clone(),
+                root: crate_rel,
                 files,
             });
         }
@@ -276,15 +546,20 @@ async fn package_changed_crates(
     Ok(packaged)
 }
 
-fn validate_packaged(plan: &Plan, packaged: &[PackagedCrate]) -> Result<()> {
-    if packaged.len() != plan.changed_count() {
+fn validate_packaged(ctx: &InferredContext, plan: &Plan, packaged: &[PackagedCrate]) -> Result<()> {
+    let expected: BTreeSet<_> = ctx
+        .crates
+        .iter()
+        .filter(|c| c.publish && plan.crate_plan(&c.name).is_some())
+        .map(|c| c.name.clone())
+        .collect();
+    if packaged.len() != expected.len() {
         bail!(
             "packaged crate count {} does not match plan {}",
             packaged.len(),
-            plan.changed_count()
+            expected.len()
         );
     }
-    let expected: BTreeSet<_> = plan.iter().map(|(name, _)| name.clone()).collect();
     let actual: BTreeSet<_> = packaged.iter().map(|p| p.name.clone()).collect();
     if expected != actual {
         bail!(
@@ -294,94 +569,113 @@ fn validate_packaged(plan: &Plan, packaged: &[PackagedCrate]) -> Result<()> {
         );
     }
     for entry in packaged {
-        let has_tar = entry
-            .files
-            .iter()
-            .any(|f| f.extension().and_then(|e| e.to_str()) == Some("gz"));
-        let has_zip = entry
-            .files
+        let missing: Vec<&str> = ctx
+            .config
+            .package
+            .compression
+            .formats
             .iter()
-            .any(|f| f.extension().and_then(|e| e.to_str()) == Some("zip"));
-        if !has_tar || !has_zip {
+            .map(|f| f.extension())
+            .filter(|ext| {
+                !entry
+                    .files
+                    .iter()
+                    .any(|f| f.to_string_lossy().ends_with(&format!(".{}", ext)))
+            })
+            .collect();
+        if !missing.is_empty() {
             bail!(
-                "crate {} missing expected archive variants (tar.gz={}, zip={})",
+                "crate {} is missing archive formats: {}",
                 entry.name,
-                has_tar,
-                has_zip
+                missing.join(", ")
             );
         }
     }
     Ok(())
 }
 
-async fn compute_sha512(path: &Path) -> Result<String> {
-    let mut file = async_fs::File::open(path).await?;
-    let mut hasher = Sha512::new();
-    let mut buf = [0u8; 8192];
-    loop {
-        let n = file.read(&mut buf).await?;
-        if n == 0 {
-            break;
-        }
-        hasher.update(&buf[..n]);
-    }
-    let digest = hasher.finalize();
-    Ok(hex::encode(digest))
-}
-
+#[tracing::instrument(skip_all)]
 pub(crate) async fn upload_assets_with_retry(
     owner: &str,
     repo: &str,
     tag: &str,
     files: &[PathBuf],
+    audit: &AuditLog,
 ) -> Result<()> {
     if files.is_empty() {
         return Ok(());
     }
+    let mut sizes = Vec::with_capacity(files.len());
+    for f in files {
+        let size = async_fs::metadata(f).await?.len();
+        ensure_asset_size(f, size)?;
+        sizes.push(size);
+    }
     tracing::info!("github: uploading {} assets", files.len());
     let gh = github::client()?;
-    let repos = gh.repos(owner.to_string(), repo.to_string());
-    let rh = repos.releases();
-    let release = rh.get_by_tag(tag).await?;
-    let token = github::token()?;
-    let client = reqwest::Client::new();
+    let release = github::release_by_tag(&gh, owner, repo, tag)
+        .await?
+        .with_context(|| format!("no GitHub release for {}", tag))?;
+    let client = net::client()?;
     let base_upload_url = release
         .upload_url
         .split('{')
         .next()
         .unwrap_or(&release.upload_url)
         .to_string();
-    for f in files {
+    for (f, size) in files.iter().zip(sizes) {
         let name = f
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("asset")
             .to_string();
+        let token = github::token().await?;
+        // Re-runs skip assets an earlier run finished and replace ones it left half uploaded.
+        if let Some(existing) = release.assets.iter().find(|a| a.name == name) {
+            if existing.state == "uploaded" && existing.size as u64 == size {
+                tracing::info!("github: {} already uploaded to {}", name, tag);
+                continue;
+            }
+            github::delete_release_asset(&client, &token, owner, repo, existing.id.into_inner())
+                .await
+                .with_context(|| format!("failed to delete incomplete asset {}", name))?;
+        }
+        cancel::checkpoint(&format!("uploading {}", name))?;
         let ct = match f.extension().and_then(|e| e.to_str()) {
             Some("gz") => "application/gzip",
             Some("zip") => "application/zip",
+            Some("zst") => "application/zstd",
             Some("sha512") => "text/plain",
+            Some("json") => "application/json",
+            Some("asc") => "application/pgp-signature",
             _ => "application/octet-stream",
         };
         let url = format!("{}?name={}", base_upload_url, url_encode(&name));
-        let bytes = async_fs::read(f).await?;
+        let attempts = net::retries() + 1;
         let mut attempt = 0;
         loop {
             attempt += 1;
+            let token = github::token().await?;
+            let file = async_fs::File::open(f).await?;
             let resp = client
                 .post(&url)
                 .bearer_auth(&token)
                 .header(header::CONTENT_TYPE, ct)
-                .body(bytes.clone())
+                .header(header::CONTENT_LENGTH, size)
+                .timeout(upload_timeout(size))
+                .body(reqwest::Body::wrap_stream(ReaderStream::new(file)))
                 .send()
                 .await;
             match resp {
                 Ok(resp) if resp.status().is_success() => {
                     tracing::debug!("uploaded asset {}", name);
+                    audit
+                        .record(AuditAction::AssetUploaded, &format!("{}/{}", tag, name))
+                        .await;
                     break;
                 }
                 Ok(resp) => {
-                    if attempt >= UPLOAD_RETRIES {
+                    if attempt >= attempts {
                         bail!("upload asset failed for {}: {}", name, resp.status());
                     }
                     tracing::warn!(
@@ -389,11 +683,11 @@ pub(crate) async fn upload_assets_with_retry(
                         name,
                         resp.status(),
                         attempt,
-                        UPLOAD_RETRIES
+                        attempts
                     );
                 }
                 Err(err) => {
-                    if attempt >= UPLOAD_RETRIES {
+                    if attempt >= attempts {
                         return Err(err.into());
                     }
                     tracing::warn!(
@@ -401,16 +695,60 @@ pub(crate) async fn upload_assets_with_retry(
                         name,
                         err,
                         attempt,
-                        UPLOAD_RETRIES
+                        attempts
                     );
                 }
             }
+            // A failed upload can leave a partial asset behind that blocks the retry by name.
+            if let Err(err) = delete_asset_by_name(&client, &token, owner, repo, tag, &name).await {
+                tracing::warn!("failed to remove partial asset {}: {}", name, err);
+            }
             sleep(Duration::from_millis(200 * attempt as u64)).await;
         }
     }
     Ok(())
 }
 
+fn ensure_asset_size(path: &Path, size: u64) -> Result<()> {
+    if size >= MAX_ASSET_BYTES {
+        bail!(
+            "asset {} is {} bytes; GitHub release assets must be smaller than 2 GiB",
+            path.display(),
+            size
+        );
+    }
+    Ok(())
+}
+
+/// Allow a minute of overhead plus a conservative 512 KiB/s transfer rate.
+fn upload_timeout(size: u64) -> Duration {
+    Duration::from_secs(60 + size / (512 * 1024))
+}
+
+async fn delete_asset_by_name(
+    client: &reqwest::Client,
+    token: &str,
+    owner: &str,
+    repo: &str,
+    tag: &str,
+    name: &str,
+) -> Result<()> {
+    let gh = github::client()?;
+    let release = gh
+        .repos(owner.to_string(), repo.to_string())
+        .releases()
+        .get_by_tag(tag)
+        .await?;
+    let Some(asset) = release.assets.iter().find(|a| a.name == name) else {
+        return Ok(());
+    };
+    github::delete_release_asset(client, token, owner, repo, asset.id.into_inner())
+        .await
+        .with_context(|| format!("failed to delete partial asset {}", name))?;
+    tracing::info!("github: removed partial asset {} before retry", name);
+    Ok(())
+}
+
 fn is_not_found(err: &octocrab::Error) -> bool {
     if let octocrab::Error::GitHub { source, .. } = err {
         return source.status_code == StatusCode::NOT_FOUND;
@@ -418,25 +756,156 @@ fn is_not_found(err: &octocrab::Error) -> bool {
     false
 }
 
-fn package_from_tree(
+/// Files written into every configured archive format of one crate.
+///
+/// Tar entries go to one uncompressed `.tar` that is compressed per format on finish.
+struct ArchiveWriter<'a> {
+    base: PathBuf,
+    compression: &'a CompressionConfig,
+    tar: Option<TarBuilder<BufWriter<fs::File>>>,
+    zip: Option<zip::ZipWriter<fs::File>>,
+    zopt: ZipOptions,
+}
+
+impl<'a> ArchiveWriter<'a> {
+    fn create(base: &Path, compression: &'a CompressionConfig) -> Result<Self> {
+        let formats = &compression.formats;
+        let tar = if formats.iter().any(|f| *f != ArchiveFormat::Zip) {
+            let file = fs::File::create(archive_path(base, "tar"))?;
+            Some(TarBuilder::new(BufWriter::new(file)))
+        } else {
+            None
+        };
+        let zip = if formats.contains(&ArchiveFormat::Zip) {
+            let file = fs::File::create(archive_path(base, ArchiveFormat::Zip.extension()))?;
+            Some(zip::ZipWriter::new(file))
+        } else {
+            None
+        };
+        Ok(Self {
+            base: base.to_path_buf(),
+            compression,
+            tar,
+            zip,
+            zopt: ZipOptions::default()
+                .compression_method(ZipCompression::Deflated)
+                .compression_level(Some(compression.gzip_level as i32))
+                .unix_permissions(0o644),
+        })
+    }
+
+    fn add(&mut self, path: &Path, data: &[u8]) -> Result<()> {
+        if let Some(tar) = &mut self.tar {
+            append_tar_entry(tar, path, data).inspect_err(|err| {
+                tracing::warn!(path=%display_path(path), error=%err, "tar append failed");
+            })?;
+        }
+        if let Some(zip) = &mut self.zip {
+            let path_str = to_unix_path(path);
+            zip.start_file(&path_str, self.zopt).inspect_err(|err| {
+                tracing::warn!(path=%path_str, error=%err, "zip start_file failed");
+            })?;
+            zip.write_all(data).inspect_err(|err| {
+                tracing::warn!(path=%path_str, error=%err, "zip write failed");
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Finish every archive, returning their paths in configured order.
+    fn finish(mut self) -> Result<Vec<PathBuf>> {
+        if let Some(mut zip) = self.zip.take() {
+            zip.finish()?;
+        }
+        if let Some(tar) = self.tar.take() {
+            tar.into_inner()?.into_inner()?.sync_all()?;
+            let plain = archive_path(&self.base, "tar");
+            let result = self.compress_tar(&plain);
+            fs::remove_file(&plain)?;
+            result?;
+        }
+        Ok(self
+            .compression
+            .formats
+            .iter()
+            .map(|f| archive_path(&self.base, f.extension()))
+            .collect())
+    }
+
+    fn compress_tar(&self, plain: &Path) -> Result<()> {
+        for format in &self.compression.formats {
+            let out = archive_path(&self.base, format.extension());
+            match format {
+                ArchiveFormat::TarGz => {
+                    let level = Compression::new(self.compression.gzip_level);
+                    let mut enc = GzEncoder::new(fs::File::create(&out)?, level);
+                    std::io::copy(&mut fs::File::open(plain)?, &mut enc)?;
+                    enc.finish()?;
+                }
+                ArchiveFormat::TarZst => zstd_compress(plain, &out, self.compression.zstd_level)?,
+                ArchiveFormat::Zip => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn archive_path(base: &Path, extension: &str) -> PathBuf {
+    let mut name = base.as_os_str().to_os_string();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+fn zstd_compress(input: &Path, output: &Path, level: i32) -> Result<()> {
+    let mut cmd = std::process::Command::new("zstd");
+    cmd.args(["-q", "-f"]);
+    if level > 19 {
+        cmd.arg("--ultra");
+    }
+    cmd.arg(format!("-{}", level));
+    let result = cmd.arg(input).arg("-o").arg(output).output();
+    match result {
+        Ok(out) if out.status.success() => Ok(()),
+        Ok(out) => bail!(
+            "zstd failed for {}: {}",
+            output.display(),
+            String::from_utf8_lossy(&out.stderr).trim()
+        ),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => bail!(
+            "tar.zst archives need the `zstd` command; install it or drop \"tar.zst\" from package.compression.formats"
+        ),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Package `crate_rel` from `tree` into `<base>.<ext>` for each configured format.
+pub(super) fn package_from_tree(
     repo: &Repository,
     tree: &git2::Tree,
     crate_rel: &Path,
-    tar_gz: &Path,
-    zip_path: &Path,
-) -> Result<()> {
-    let tar_file = fs::File::create(tar_gz)?;
-    let enc = GzEncoder::new(tar_file, Compression::default());
-    let mut tar = TarBuilder::new(enc);
-
-    let zip_file = fs::File::create(zip_path)?;
-    let mut zip = zip::ZipWriter::new(zip_file);
-    let zopt = ZipOptions::default()
-        .compression_method(ZipCompression::Deflated)
-        .unix_permissions(0o644);
-
+    exclude: &[PathBuf],
+    base: &Path,
+    compression: &CompressionConfig,
+) -> Result<Vec<PathBuf>> {
+    let mut writer = ArchiveWriter::create(base, compression)?;
     let crate_rel = normalize_relative(crate_rel);
+    write_tree(repo, tree, Path::new(""), &crate_rel, exclude, &mut writer)?;
+    writer.finish()
+}
+
+/// Write the blobs of `tree` (mounted at `prefix`) that fall under `crate_rel`,
+/// descending into submodules at their pinned commits.
+fn write_tree(
+    repo: &Repository,
+    tree: &git2::Tree,
+    prefix: &Path,
+    crate_rel: &Path,
+    exclude: &[PathBuf],
+    writer: &mut ArchiveWriter<'_>,
+) -> Result<()> {
     let mut error: Option<anyhow::Error> = None;
+    let mut submodules = Vec::new();
 
     tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
         let name = match entry.name() {
@@ -444,62 +913,155 @@ fn package_from_tree(
             None => return 0,
         };
 
-        let mut full_path = PathBuf::from(root);
+        let mut full_path = prefix.join(root);
         full_path.push(name);
 
-        if !crate_rel.as_os_str().is_empty() && !full_path.starts_with(&crate_rel) {
-            return 0;
-        }
-
-        if should_skip_artifact_path(&full_path) {
-            return 0;
-        }
-
-        if let Some(git2::ObjectType::Blob) = entry.kind()
-            && let Ok(obj) = entry.to_object(repo)
-            && let Ok(blob) = obj.into_blob()
+        if exclude.iter().any(|e| full_path.starts_with(e)) || should_skip_artifact_path(&full_path)
         {
-            let archive_path = full_path.as_path();
+            return 1;
+        }
 
-            if let Err(err) = append_tar_entry(&mut tar, archive_path, blob.content()) {
-                let msg = err.to_string();
-                tracing::warn!(path=%display_path(archive_path), error=%msg, "tar append failed");
-                if error.is_none() {
-                    error = Some(err);
+        match entry.kind() {
+            Some(git2::ObjectType::Commit) => {
+                // A submodule may hold the crate, or sit inside it.
+                if full_path.starts_with(crate_rel) || crate_rel.starts_with(&full_path) {
+                    let rel = PathBuf::from(root).join(name);
+                    submodules.push((rel, entry.id()));
                 }
-                return 1;
+                0
             }
-
-            let path_str = to_unix_path(archive_path);
-            if let Err(err) = zip.start_file(&path_str, zopt) {
-                let msg = err.to_string();
-                tracing::warn!(path=%path_str, error=%msg, "zip start_file failed");
-                if error.is_none() {
-                    error = Some(err.into());
+            Some(git2::ObjectType::Blob) => {
+                if !full_path.starts_with(crate_rel) {
+                    return 0;
                 }
-                return 1;
-            }
-            if let Err(err) = zip.write_all(blob.content()) {
-                let msg = err.to_string();
-                tracing::warn!(path=%path_str, error=%msg, "zip write failed");
-                if error.is_none() {
-                    error = Some(err.into());
+                let blob = match partial_clone::find_blob(repo, entry.id(), &full_path) {
+                    Ok(blob) => blob,
+                    Err(err) => {
+                        error = Some(err);
+                        return -1;
+                    }
+                };
+                let result = if is_lfs_pointer(blob.content()) {
+                    smudge_lfs(
+                        repo,
+                        &full_path,
+                        &PathBuf::from(root).join(name),
+                        blob.content(),
+                    )
+                    .and_then(|data| writer.add(&full_path, &data))
+                } else {
+                    writer.add(&full_path, blob.content())
+                };
+                if let Err(err) = result {
+                    error = Some(err);
+                    return -1;
                 }
-                return 1;
+                0
             }
+            _ => 0,
         }
-        0
-    })?;
+    })
+    .or_else(|err| if error.is_some() { Ok(()) } else { Err(err) })?;
 
     if let Some(err) = error {
         return Err(err);
     }
 
-    tar.into_inner()?.finish()?;
-    zip.finish()?;
+    for (rel, oid) in submodules {
+        let full_path = prefix.join(&rel);
+        let sub_repo = open_submodule(repo, &rel, &full_path)?;
+        let commit = sub_repo.find_commit(oid).with_context(|| {
+            format!(
+                "submodule {} does not contain pinned commit {}; run `git submodule update --init --recursive`",
+                display_path(&full_path),
+                oid
+            )
+        })?;
+        write_tree(
+            &sub_repo,
+            &commit.tree()?,
+            &full_path,
+            crate_rel,
+            exclude,
+            writer,
+        )?;
+    }
     Ok(())
 }
 
+const LFS_POINTER_PREFIX: &[u8] = b"version https://git-lfs.github.com/spec/v1";
+
+fn is_lfs_pointer(content: &[u8]) -> bool {
+    // Pointer files are tiny; real content never starts with the spec line by accident.
+    content.len() < 1024 && content.starts_with(LFS_POINTER_PREFIX)
+}
+
+/// Replace an LFS pointer with the object it points to via `git lfs smudge`.
+fn smudge_lfs(repo: &Repository, full_path: &Path, rel: &Path, pointer: &[u8]) -> Result<Vec<u8>> {
+    let hint = format!(
+        "{} is a Git LFS pointer and its content could not be fetched; install git-lfs and run `git lfs pull`, or list it under [package] exclude",
+        display_path(full_path)
+    );
+    let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+    let mut child = std::process::Command::new("git")
+        .current_dir(workdir)
+        .args(["lfs", "smudge", "--"])
+        .arg(rel)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context(hint.clone())?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(pointer)?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "{}: {}",
+            hint,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    tracing::debug!(path=%display_path(full_path), "packaged git lfs object");
+    Ok(output.stdout)
+}
+
+/// Patterns the root `.gitattributes` routes through the LFS filter.
+pub(crate) fn lfs_patterns(repo: &Repository) -> Result<Vec<String>> {
+    let tree = repo.head()?.peel_to_tree()?;
+    let Ok(entry) = tree.get_path(Path::new(".gitattributes")) else {
+        return Ok(Vec::new());
+    };
+    let blob = entry.to_object(repo)?.peel_to_blob()?;
+    Ok(parse_lfs_patterns(&String::from_utf8_lossy(blob.content())))
+}
+
+fn parse_lfs_patterns(attributes: &str) -> Vec<String> {
+    attributes
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next().filter(|p| !p.starts_with('#'))?;
+            parts
+                .any(|attr| attr == "filter=lfs")
+                .then(|| pattern.to_string())
+        })
+        .collect()
+}
+
+fn open_submodule(repo: &Repository, rel: &Path, full_path: &Path) -> Result<Repository> {
+    let hint = format!(
+        "submodule {} is not checked out; run `git submodule update --init --recursive` or list it under [package] exclude",
+        display_path(full_path)
+    );
+    let name = to_unix_path(rel);
+    let sub = repo.find_submodule(&name).context(hint.clone())?;
+    sub.open().context(hint)
+}
+
 fn normalize_relative(path: &Path) -> PathBuf {
     if path == Path::new(".") {
         PathBuf::new()
@@ -518,7 +1080,7 @@ fn should_skip_artifact_path(path: &Path) -> bool {
 }
 
 fn append_tar_entry(
-    tar: &mut TarBuilder<GzEncoder<fs::File>>,
+    tar: &mut TarBuilder<BufWriter<fs::File>>,
     path: &Path,
     data: &[u8],
 ) -> Result<()> {
@@ -542,3 +1104,145 @@ fn to_unix_path(path: &Path) -> String {
 fn display_path(path: &Path) -> String {
     path.to_string_lossy().into_owned()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_asset_size_rejects_two_gib() {
+        let path = Path::new("apache-foo-0.1.0-src.tar.gz");
+        assert!(ensure_asset_size(path, MAX_ASSET_BYTES - 1).is_ok());
+        let err = ensure_asset_size(path, MAX_ASSET_BYTES).unwrap_err();
+        assert!(err.to_string().contains("2 GiB"));
+    }
+
+    #[test]
+    fn select_rc_tag_honours_overrides() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let base = semver::Version::new(0, 2, 0);
+        let tags = TagFormat::default();
+
+        let (tag, n) = select_rc_tag(&repo, &tags, &base, &RcSelection::Number(3)).unwrap();
+        assert_eq!((tag.as_str(), n), ("v0.2.0-rc.3", 3));
+
+        let selection = RcSelection::Tag("v0.2.0-rc.5".into());
+        let (tag, n) = select_rc_tag(&repo, &tags, &base, &selection).unwrap();
+        assert_eq!((tag.as_str(), n), ("v0.2.0-rc.5", 5));
+
+        let mismatch = RcSelection::Tag("v0.1.9-rc.1".into());
+        assert!(select_rc_tag(&repo, &tags, &base, &mismatch).is_err());
+        assert!(select_rc_tag(&repo, &tags, &base, &RcSelection::Number(0)).is_err());
+
+        let release = TagFormat::new("release-");
+        let (tag, _) = select_rc_tag(&repo, &release, &base, &RcSelection::Next).unwrap();
+        assert_eq!(tag, "release-0.2.0-rc.1");
+        let wrong_prefix = RcSelection::Tag("v0.2.0-rc.5".into());
+        assert!(select_rc_tag(&repo, &release, &base, &wrong_prefix).is_err());
+    }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .args(["-c", "protocol.file.allow=always"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn tar_paths(path: &Path) -> Vec<String> {
+        let file = fs::File::open(path).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn package_from_tree_includes_submodules() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let lib = tmp.path().join("lib");
+        let main = tmp.path().join("main");
+        fs::create_dir_all(&lib).unwrap();
+        fs::create_dir_all(&main).unwrap();
+        git(&lib, &["init", "-q"]);
+        fs::write(lib.join("lib.txt"), "lib").unwrap();
+        git(&lib, &["add", "."]);
+        git(&lib, &["commit", "-qm", "lib"]);
+        git(&main, &["init", "-q"]);
+        fs::write(main.join("main.txt"), "main").unwrap();
+        git(
+            &main,
+            &[
+                "submodule",
+                "add",
+                "-q",
+                lib.to_str().unwrap(),
+                "vendor/lib",
+            ],
+        );
+        git(&main, &["add", "."]);
+        git(&main, &["commit", "-qm", "main"]);
+
+        let repo = Repository::open(&main).unwrap();
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        let base = tmp.path().join("a");
+        let compression = CompressionConfig::default();
+        let tar_gz = tmp.path().join("a.tar.gz");
+
+        let archives =
+            package_from_tree(&repo, &tree, Path::new("."), &[], &base, &compression).unwrap();
+        assert_eq!(archives, vec![tar_gz.clone(), tmp.path().join("a.zip")]);
+        assert!(!tmp.path().join("a.tar").exists());
+        let paths = tar_paths(&tar_gz);
+        assert!(paths.contains(&"main.txt".to_string()));
+        assert!(paths.contains(&"vendor/lib/lib.txt".to_string()));
+
+        let exclude = [PathBuf::from("vendor/lib")];
+        package_from_tree(&repo, &tree, Path::new("."), &exclude, &base, &compression).unwrap();
+        assert!(!tar_paths(&tar_gz).iter().any(|p| p.starts_with("vendor")));
+    }
+
+    #[test]
+    fn lfs_pointers_and_patterns_are_detected() {
+        let pointer = b"version https://git-lfs.github.com/spec/v1\noid sha256:abc\nsize 12\n";
+        assert!(is_lfs_pointer(pointer));
+        assert!(!is_lfs_pointer(b"fn main() {}\n"));
+
+        let attrs =
+            "# assets\n*.png filter=lfs diff=lfs merge=lfs -text\n*.rs text\ndata/** filter=lfs\n";
+        assert_eq!(parse_lfs_patterns(attrs), vec!["*.png", "data/**"]);
+    }
+
+    #[test]
+    fn compression_formats_select_archives() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(tmp.path()).unwrap();
+        std::fs::write(tmp.path().join("lib.rs"), "pub fn f() {}\n").unwrap();
+        git(tmp.path(), &["add", "."]);
+        git(tmp.path(), &["commit", "-qm", "init"]);
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+
+        let compression: CompressionConfig =
+            toml::from_str("gzip_level = 9\nformats = [\"zip\"]").unwrap();
+        compression.validate().unwrap();
+        let base = tmp.path().join("out");
+        let archives =
+            package_from_tree(&repo, &tree, Path::new("."), &[], &base, &compression).unwrap();
+        assert_eq!(archives, vec![tmp.path().join("out.zip")]);
+        assert!(!tmp.path().join("out.tar").exists());
+        assert!(!tmp.path().join("out.tar.gz").exists());
+
+        let duplicate: CompressionConfig =
+            toml::from_str("formats = [\"tar.gz\", \"tar.gz\"]").unwrap();
+        assert!(duplicate.validate().is_err());
+        let empty: CompressionConfig = toml::from_str("formats = []").unwrap();
+        assert!(empty.validate().is_err());
+        assert!(toml::from_str::<CompressionConfig>("formats = [\"tar.xz\"]").is_err());
+    }
+}
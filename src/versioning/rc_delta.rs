@@ -0,0 +1,68 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use git2::Repository;
+
+use crate::infer::InferredContext;
+
+/// Commits made after the previous rc of the planned version, so rc.2 and later can show
+/// voters what changed since the last candidate.
+#[derive(Debug, Clone)]
+pub(crate) struct RcDelta {
+    pub previous_tag: String,
+    /// Abbreviated ids, as in `ChangeEntry::sha`.
+    new: HashSet<String>,
+}
+
+impl RcDelta {
+    /// The delta for cutting rc `rc_n` of `version`: `None` for a first rc, or when no
+    /// earlier rc of the version is tagged locally.
+    pub(crate) fn resolve(
+        repo: &Repository,
+        ctx: &InferredContext,
+        version: &semver::Version,
+        rc_n: u32,
+    ) -> Result<Option<Self>> {
+        let tags = ctx.config.tags();
+        let mut previous: Option<(u32, String)> = None;
+        for reference in repo.references_glob("refs/tags/*")?.flatten() {
+            if let Some(name) = reference.shorthand()
+                && let Some((v, n)) = tags.parse_rc(name)
+                && v == *version
+                && n < rc_n
+                && previous.as_ref().is_none_or(|(best, _)| n > *best)
+            {
+                previous = Some((n, name.to_string()));
+            }
+        }
+        let Some((_, previous_tag)) = previous else {
+            return Ok(None);
+        };
+        let base = repo
+            .revparse_single(&format!("refs/tags/{}", previous_tag))
+            .and_then(|o| o.peel_to_commit())
+            .with_context(|| format!("rc tag {} does not point to a commit", previous_tag))?;
+        let tip = match &ctx.until {
+            Some(until) => repo.revparse_single(until)?.peel_to_commit()?.id(),
+            None => repo.head()?.peel_to_commit()?.id(),
+        };
+        let mut walk = repo.revwalk()?;
+        walk.push(tip)?;
+        walk.hide(base.id())?;
+        let mut new = HashSet::new();
+        for oid in walk {
+            new.insert(oid?.to_string()[..7].to_string());
+        }
+        Ok(Some(Self { previous_tag, new }))
+    }
+
+    pub(crate) fn is_new(&self, sha: &str) -> bool {
+        self.new.contains(sha)
+    }
+
+    /// Footnote label marking changelog entries new since the previous rc; the tag keeps
+    /// it unique within a crate's changelog.
+    pub(crate) fn footnote(&self) -> String {
+        format!("[^since-{}]", self.previous_tag)
+    }
+}
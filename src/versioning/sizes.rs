@@ -0,0 +1,199 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+
+use crate::rc_release::human_size;
+
+use super::rc::PackagedCrate;
+
+/// Files listed per crate, largest first.
+const LARGEST_FILES: usize = 5;
+/// Files at least this big are flagged, since source releases rarely need them.
+pub(crate) const LARGE_FILE_BYTES: u64 = 1024 * 1024;
+/// Archives whose contents can be listed without external tools.
+const LISTABLE: [&str; 2] = [".tar.gz", ".zip"];
+
+/// What one crate's source archives contain, for the prerelease report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CrateSizes {
+    pub crate_name: String,
+    pub files: usize,
+    /// Sum of the file sizes inside the archive.
+    pub uncompressed: u64,
+    pub largest: Vec<(String, u64)>,
+    /// Files of at least [`LARGE_FILE_BYTES`].
+    pub large_files: usize,
+    /// File name and size of each packaged archive.
+    pub archives: Vec<(String, u64)>,
+}
+
+impl CrateSizes {
+    /// Archive size as a share of the uncompressed contents, e.g. `23%`.
+    pub fn ratio(&self, compressed: u64) -> String {
+        if self.uncompressed == 0 {
+            return "-".to_string();
+        }
+        format!(
+            "{:.0}%",
+            compressed as f64 * 100.0 / self.uncompressed as f64
+        )
+    }
+}
+
+impl fmt::Display for CrateSizes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} files, {} uncompressed",
+            self.crate_name,
+            self.files,
+            human_size(self.uncompressed)
+        )
+    }
+}
+
+/// Measure the archives of every packaged crate. Crates packaged only as `.tar.zst`
+/// list their archive sizes but not their contents.
+pub(crate) fn measure(packaged: &[PackagedCrate]) -> Result<Vec<CrateSizes>> {
+    packaged.iter().map(measure_crate).collect()
+}
+
+fn measure_crate(entry: &PackagedCrate) -> Result<CrateSizes> {
+    let archives: Vec<&Path> = entry
+        .files
+        .iter()
+        .map(|f| f.as_path())
+        .filter(|f| f.extension().is_none_or(|e| e != "sha512"))
+        .collect();
+    let listable = LISTABLE
+        .iter()
+        .find_map(|ext| archives.iter().find(|f| f.to_string_lossy().ends_with(ext)));
+    let mut contents = match listable {
+        Some(path) if path.to_string_lossy().ends_with(".zip") => list_zip(path)?,
+        Some(path) => list_tar_gz(path)?,
+        None => Vec::new(),
+    };
+    contents.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut sizes = Vec::with_capacity(archives.len());
+    for archive in archives {
+        let size = fs::metadata(archive)
+            .with_context(|| format!("failed to stat {}", archive.display()))?
+            .len();
+        let name = archive
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        sizes.push((name, size));
+    }
+    Ok(CrateSizes {
+        crate_name: entry.name.clone(),
+        files: contents.len(),
+        uncompressed: contents.iter().map(|(_, size)| size).sum(),
+        large_files: contents
+            .iter()
+            .filter(|(_, size)| *size >= LARGE_FILE_BYTES)
+            .count(),
+        largest: contents.into_iter().take(LARGEST_FILES).collect(),
+        archives: sizes,
+    })
+}
+
+/// Path and size of each file in a `.tar.gz`. Archives hold repository-relative paths
+/// with no wrapper directory, as `rc::package_from_tree` writes them.
+fn list_tar_gz(path: &Path) -> Result<Vec<(String, u64)>> {
+    let file =
+        fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        files.push((entry.path()?.to_string_lossy().into_owned(), entry.size()));
+    }
+    Ok(files)
+}
+
+fn list_zip(path: &Path) -> Result<Vec<(String, u64)>> {
+    let file =
+        fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut files = Vec::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        files.push((entry.name().to_string(), entry.size()));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CompressionConfig;
+    use crate::versioning::rc::package_from_tree;
+    use git2::{Repository, Signature};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn archives_are_measured_largest_file_first() {
+        let dir = TempDir::new().unwrap();
+        let work = dir.path().join("foo");
+        let fixture = vec![0u8; 2 * 1024 * 1024];
+        for (name, data) in [
+            ("src/lib.rs", &b"pub fn f() {}\n"[..]),
+            ("tests/fixture.bin", &fixture[..]),
+            ("Cargo.toml", &b"[package]\n"[..]),
+        ] {
+            let path = work.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, data).unwrap();
+        }
+        let repo = Repository::init(&work).unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("a", "a@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+        let compression: CompressionConfig = toml::from_str("formats = [\"tar.gz\"]").unwrap();
+        let base = dir.path().join("apache-foo-0.1.0-src");
+        let [tarball] = package_from_tree(&repo, &tree, Path::new("."), &[], &base, &compression)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let checksum = PathBuf::from(format!("{}.sha512", tarball.display()));
+        fs::write(&checksum, "digest").unwrap();
+
+        let sizes = measure(&[PackagedCrate {
+            name: "foo".into(),
+            root: PathBuf::new(),
+            files: vec![tarball.clone(), checksum],
+        }])
+        .unwrap();
+        let [foo] = sizes.as_slice() else {
+            panic!("expected one crate");
+        };
+        assert_eq!(foo.files, 3);
+        assert_eq!(foo.uncompressed, 2 * 1024 * 1024 + 14 + 10);
+        assert_eq!(foo.largest[0], ("tests/fixture.bin".to_string(), 2 << 20));
+        assert_eq!(foo.largest[1].0, "src/lib.rs");
+        assert_eq!(foo.large_files, 1);
+        let compressed = fs::metadata(&tarball).unwrap().len();
+        assert_eq!(
+            foo.archives,
+            [("apache-foo-0.1.0-src.tar.gz".to_string(), compressed)]
+        );
+        assert_eq!(foo.ratio(compressed), "0%");
+        assert_eq!(foo.to_string(), "foo: 3 files, 2.0 MiB uncompressed");
+    }
+}
@@ -0,0 +1,334 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use tokio::process::Command;
+
+use crate::artifacts;
+use crate::infer::InferredContext;
+
+use super::rc::PackagedCrate;
+
+/// Archive preferred for verification when several formats were packaged.
+const UNPACK_ORDER: [&str; 3] = [".tar.gz", ".tar.zst", ".zip"];
+
+/// Oldest compiler that understands each edition.
+const EDITION_MINIMUMS: [(&str, &str); 3] =
+    [("2018", "1.31.0"), ("2021", "1.56.0"), ("2024", "1.85.0")];
+
+/// Unpack each crate's source tarball and build it there.
+///
+/// Depending on config, this checks the declared MSRV and runs the test suite.
+/// Runs on the archives voters will download, so files that exist in git but are
+/// missing from the tarball surface here rather than during the vote.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn verify_packaged(
+    ctx: &InferredContext,
+    packaged: &[PackagedCrate],
+    rc_tag: &str,
+) -> Result<()> {
+    let opts = &ctx.config.prerelease;
+    if !opts.msrv_check && !opts.verify_tarball {
+        return Ok(());
+    }
+    // Unpack outside the repository so cargo does not pick up its workspace.
+    let root = std::env::temp_dir()
+        .join("asfship-verify")
+        .join(&ctx.repo_name)
+        .join(rc_tag.replace('/', "_"));
+    for entry in packaged {
+        let archive = UNPACK_ORDER
+            .iter()
+            .find_map(|ext| {
+                entry
+                    .files
+                    .iter()
+                    .find(|f| f.to_string_lossy().ends_with(ext))
+            })
+            .with_context(|| format!("no source archive packaged for {}", entry.name))?
+            .clone();
+        let dest = root.join(&entry.name);
+        let unpack_dest = dest.clone();
+        tokio::task::spawn_blocking(move || unpack(&archive, &unpack_dest))
+            .await
+            .map_err(|e| anyhow::anyhow!("unpack task join error: {}", e))??;
+        let crate_dir = dest.join(&entry.root);
+        if opts.msrv_check {
+            check_msrv(ctx, &entry.name, &crate_dir, &dest).await?;
+        }
+        if opts.verify_tarball {
+            test_tarball(ctx, &entry.name, &crate_dir).await?;
+        }
+    }
+    Ok(())
+}
+
+fn unpack(archive: &Path, dest: &Path) -> Result<()> {
+    if dest.exists() {
+        fs::remove_dir_all(dest)?;
+    }
+    fs::create_dir_all(dest)?;
+    let file =
+        fs::File::open(archive).with_context(|| format!("failed to open {}", archive.display()))?;
+    let name = archive.to_string_lossy();
+    let result = if name.ends_with(".zip") {
+        zip::ZipArchive::new(file)
+            .and_then(|mut zip| zip.extract(dest))
+            .map_err(anyhow::Error::from)
+    } else if name.ends_with(".tar.zst") {
+        unpack_zstd(archive, dest)
+    } else {
+        tar::Archive::new(GzDecoder::new(file))
+            .unpack(dest)
+            .map_err(anyhow::Error::from)
+    };
+    result.with_context(|| format!("failed to unpack {}", archive.display()))
+}
+
+fn unpack_zstd(archive: &Path, dest: &Path) -> Result<()> {
+    let mut child = std::process::Command::new("zstd")
+        .args(["-d", "-q", "-c"])
+        .arg(archive)
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .context("failed to run zstd")?;
+    let stdout = child.stdout.take().context("zstd stdout unavailable")?;
+    let unpacked = tar::Archive::new(stdout).unpack(dest);
+    let status = child.wait()?;
+    unpacked?;
+    if !status.success() {
+        bail!("zstd exited with {}", status);
+    }
+    Ok(())
+}
+
+async fn check_msrv(
+    ctx: &InferredContext,
+    name: &str,
+    crate_dir: &Path,
+    unpacked: &Path,
+) -> Result<()> {
+    let manifest = fs::read_to_string(crate_dir.join("Cargo.toml"))
+        .with_context(|| format!("{} tarball has no Cargo.toml", name))?;
+    let workspace = workspace_manifest(crate_dir, unpacked)
+        .or_else(|| fs::read_to_string(ctx.repo_root.join("Cargo.toml")).ok());
+    let Some((msrv, edition)) = declared_msrv(&manifest, workspace.as_deref())? else {
+        tracing::info!(
+            "verify: {} declares no rust-version; skipping MSRV check",
+            name
+        );
+        return Ok(());
+    };
+    check_edition(name, &msrv, &edition)?;
+
+    let toolchain = msrv.to_string();
+    tracing::info!("verify: building {} with Rust {}", name, toolchain);
+    run(
+        Command::new("rustup").args(["toolchain", "install", &toolchain, "--profile", "minimal"]),
+        &format!("install Rust {} for {}", toolchain, name),
+    )
+    .await?;
+    if !crate_dir.join("Cargo.lock").exists() {
+        // Resolve with the current cargo, preferring dependency versions the MSRV supports.
+        run(
+            Command::new("cargo")
+                .current_dir(crate_dir)
+                .env("CARGO_RESOLVER_INCOMPATIBLE_RUST_VERSIONS", "fallback")
+                .arg("generate-lockfile"),
+            &format!("resolve dependencies for {}", name),
+        )
+        .await?;
+    }
+    run(
+        Command::new("rustup")
+            .current_dir(crate_dir)
+            .env("CARGO_TARGET_DIR", verify_target_dir(ctx, &toolchain))
+            .args(["run", &toolchain, "cargo", "check"]),
+        &format!(
+            "{} does not build with its declared rust-version {}",
+            name, toolchain
+        ),
+    )
+    .await
+}
+
+/// Build and test the unpacked sources with the default toolchain.
+///
+/// Catches build.rs inputs, submodules or fixtures that exist in git but did not
+/// make it into the archive.
+async fn test_tarball(ctx: &InferredContext, name: &str, crate_dir: &Path) -> Result<()> {
+    tracing::info!("verify: testing {} from its source tarball", name);
+    run(
+        Command::new("cargo")
+            .current_dir(crate_dir)
+            .env("CARGO_TARGET_DIR", verify_target_dir(ctx, "default"))
+            .args(["test", "--all-targets"]),
+        &format!("{} does not build and test from its source tarball", name),
+    )
+    .await
+}
+
+fn verify_target_dir(ctx: &InferredContext, toolchain: &str) -> PathBuf {
+    artifacts::root(ctx).join("verify-target").join(toolchain)
+}
+
+async fn run(cmd: &mut Command, what: &str) -> Result<()> {
+    let output = cmd.output().await?;
+    if !output.status.success() {
+        bail!(
+            "{}:\n{}",
+            what,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// The nearest manifest with a `[workspace]` table above `crate_dir`, without leaving
+/// the `unpacked` tarball.
+fn workspace_manifest(crate_dir: &Path, unpacked: &Path) -> Option<String> {
+    crate_dir
+        .ancestors()
+        .take_while(|dir| dir.starts_with(unpacked))
+        .filter_map(|dir| fs::read_to_string(dir.join("Cargo.toml")).ok())
+        .find(|manifest| {
+            toml::from_str::<toml::Value>(manifest).is_ok_and(|doc| doc.get("workspace").is_some())
+        })
+}
+
+/// `rust-version` and `edition` from a packaged manifest, resolving `.workspace = true`
+/// fields against the `workspace` root manifest.
+///
+/// Returns `None` when `rust-version` is absent or inherited from a workspace that does
+/// not declare it.
+fn declared_msrv(
+    manifest: &str,
+    workspace: Option<&str>,
+) -> Result<Option<(semver::Version, String)>> {
+    let doc: toml::Value =
+        toml::from_str(manifest).context("failed to parse packaged Cargo.toml")?;
+    let Some(pkg) = doc.get("package") else {
+        return Ok(None);
+    };
+    let workspace: Option<toml::Value> = workspace
+        .map(toml::from_str)
+        .transpose()
+        .context("failed to parse the workspace Cargo.toml")?;
+    let field = |key: &str| -> Option<String> {
+        let value = pkg.get(key)?;
+        if let Some(value) = value.as_str() {
+            return Some(value.to_string());
+        }
+        if value.get("workspace").and_then(|v| v.as_bool()) != Some(true) {
+            return None;
+        }
+        workspace
+            .as_ref()?
+            .get("workspace")?
+            .get("package")?
+            .get(key)?
+            .as_str()
+            .map(String::from)
+    };
+    let Some(rust_version) = field("rust-version") else {
+        return Ok(None);
+    };
+    let msrv = parse_rust_version(&rust_version)?;
+    let edition = field("edition").unwrap_or_else(|| "2015".to_string());
+    Ok(Some((msrv, edition)))
+}
+
+fn parse_rust_version(value: &str) -> Result<semver::Version> {
+    let padded = match value.matches('.').count() {
+        0 => format!("{}.0.0", value),
+        1 => format!("{}.0", value),
+        _ => value.to_string(),
+    };
+    semver::Version::parse(&padded).with_context(|| format!("invalid rust-version {:?}", value))
+}
+
+fn check_edition(name: &str, msrv: &semver::Version, edition: &str) -> Result<()> {
+    if let Some((_, min)) = EDITION_MINIMUMS.iter().find(|(e, _)| *e == edition) {
+        let min = semver::Version::parse(min).expect("valid edition minimum");
+        if *msrv < min {
+            bail!(
+                "{} declares rust-version {} but edition {} requires Rust {} or newer",
+                name,
+                msrv,
+                edition,
+                min
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpack_replaces_previous_contents() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let archive = tmp.path().join("foo.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            fs::File::create(&archive).unwrap(),
+            flate2::Compression::default(),
+        ));
+        let data = b"fn main() {}\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "crates/foo/build.rs", &data[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let dest = tmp.path().join("out");
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("stale.rs"), "").unwrap();
+        unpack(&archive, &dest).unwrap();
+        assert!(dest.join("crates/foo/build.rs").exists());
+        assert!(!dest.join("stale.rs").exists());
+    }
+
+    #[test]
+    fn declared_msrv_checks_edition_floor() {
+        let manifest = "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\nrust-version = \"1.70\"\n";
+        let (msrv, edition) = declared_msrv(manifest, None).unwrap().unwrap();
+        assert_eq!(msrv, semver::Version::new(1, 70, 0));
+        assert!(check_edition("foo", &msrv, &edition).is_ok());
+
+        let err = check_edition("foo", &semver::Version::new(1, 80, 0), "2024").unwrap_err();
+        assert!(err.to_string().contains("requires Rust 1.85.0"));
+
+        let inherited = "[package]\nname = \"foo\"\nrust-version.workspace = true\n";
+        assert!(declared_msrv(inherited, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn declared_msrv_resolves_workspace_inheritance() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let root = tmp.path().join("apache-foo-0.1.0-src");
+        let crate_dir = root.join("crates").join("foo");
+        fs::create_dir_all(&crate_dir).unwrap();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/foo\"]\n\n[workspace.package]\nrust-version = \"1.85\"\nedition = \"2024\"\n",
+        )
+        .unwrap();
+        let manifest = "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition.workspace = true\nrust-version.workspace = true\n";
+        fs::write(crate_dir.join("Cargo.toml"), manifest).unwrap();
+
+        let workspace = workspace_manifest(&crate_dir, tmp.path());
+        let (msrv, edition) = declared_msrv(manifest, workspace.as_deref())
+            .unwrap()
+            .unwrap();
+        assert_eq!(msrv, semver::Version::new(1, 85, 0));
+        assert_eq!(edition, "2024");
+
+        // The crate's own manifest is no workspace root, and nothing outside the tarball counts.
+        assert!(workspace_manifest(&crate_dir, &crate_dir).is_none());
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use git2::Oid;
+
+use crate::infer::InferredContext;
+
+/// Temporary detached worktree that `prerelease.isolated` applies the release edits in,
+/// so a failure before packaging succeeds never leaves the user's checkout half edited.
+///
+/// Removed on drop; the release commit stays reachable only once `fast_forward` moved
+/// the real branch onto it.
+pub(crate) struct Worktree {
+    repo_root: PathBuf,
+    dir: PathBuf,
+}
+
+impl Worktree {
+    /// Check out HEAD into a new worktree outside the repository, where Cargo cannot
+    /// mistake it for a member of the enclosing workspace.
+    pub(crate) fn create(repo_root: &Path) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("asfship-worktree-{}", std::process::id()));
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)
+                .with_context(|| format!("failed to remove stale worktree {}", dir.display()))?;
+        }
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo_root)
+            .args(["worktree", "add", "--detach"])
+            .arg(&dir)
+            .arg("HEAD")
+            .output()
+            .context("failed to run git worktree add")?;
+        if !output.status.success() {
+            bail!(
+                "git worktree add failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        tracing::info!("versioning: applying changes in worktree {}", dir.display());
+        Ok(Self {
+            repo_root: repo_root.to_path_buf(),
+            dir,
+        })
+    }
+
+    /// `ctx` with the repository and every crate path moved into the worktree.
+    pub(crate) fn context(&self, ctx: &InferredContext) -> InferredContext {
+        let rebase = |path: &Path| match path.strip_prefix(&ctx.repo_root) {
+            Ok(rel) => self.dir.join(rel),
+            Err(_) => path.to_path_buf(),
+        };
+        let mut moved = ctx.clone();
+        moved.repo_root = self.dir.clone();
+        for c in &mut moved.crates {
+            c.manifest_path = rebase(&c.manifest_path);
+            c.package_root = rebase(&c.package_root);
+        }
+        moved
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        let removed = Command::new("git")
+            .arg("-C")
+            .arg(&self.repo_root)
+            .args(["worktree", "remove", "--force"])
+            .arg(&self.dir)
+            .status();
+        if !removed.is_ok_and(|s| s.success()) {
+            tracing::warn!(
+                "versioning: failed to remove worktree {}; run `git worktree prune`",
+                self.dir.display()
+            );
+        }
+    }
+}
+
+/// Move the checked-out branch of `repo_root` forward to `commit`.
+pub(crate) async fn fast_forward(repo_root: &Path, commit: Oid) -> Result<()> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(repo_root)
+        .args(["merge", "--ff-only", "--quiet"])
+        .arg(commit.to_string())
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!(
+            "cannot fast-forward to the release commit {}: {}",
+            commit,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    tracing::info!("versioning: fast-forwarded checkout to {}", commit);
+    Ok(())
+}
@@ -1,86 +1,344 @@
 use std::collections::HashMap;
 
 use anyhow::{Context, Result, anyhow, bail};
-use chrono::{Duration, Utc};
-use reqwest::Client;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use chrono_tz::Tz;
+use git2::Repository;
 use serde::Serialize;
-use tera::{Context as TeraContext, Tera};
+use tera::Context as TeraContext;
+use tokio::process::Command;
 
-use crate::discussion;
+use crate::artifacts;
+use crate::asf;
+use crate::audit::{AuditAction, AuditLog};
+use crate::cancel;
+use crate::checksum::{self, ChecksumFormat};
+use crate::config::{ArtifactLinks, VoteConfig};
+use crate::discussion::{self, Draft};
+use crate::downloads;
+use crate::endpoints;
 use crate::github;
-use crate::infer::InferredContext;
-use crate::rc_release::{RcAsset, RcReleaseInfo, fetch_latest_rc_release};
+use crate::infer::{CrateInfo, InferredContext};
+use crate::net;
+use crate::notify::{self, Milestone, Notice};
+use crate::rc_release::{RcAsset, RcReleaseInfo, fetch_latest_rc_release, human_size};
+use crate::template_lint;
+use crate::templates::Templates;
+use crate::timeline::{self, Phase};
+use crate::token_scope::{self, Permission};
 
-const VOTE_TEMPLATE: &str = include_str!("../templates/vote.md");
+/// Shortest voting period ASF policy allows.
+pub(crate) const MIN_VOTE_HOURS: u32 = 72;
+
+/// Fewest binding +1 votes ASF policy allows a release to pass with.
+pub(crate) const MIN_BINDING_VOTES: u32 = 3;
 
 pub async fn run_vote(ctx: &InferredContext, dry_run: bool) -> Result<()> {
     if !github::has_token() {
         bail!("missing ASFSHIP_GITHUB_TOKEN for vote command");
     }
 
-    let release = fetch_latest_rc_release(&ctx.repo_owner, &ctx.repo_name).await?;
-    let artifacts = build_artifact_rows(&release).await?;
-    let body = render_vote_body(ctx, &release, &artifacts)?;
-    let title = format!(
-        "[VOTE] {} {}{}",
-        ctx.repo_name,
-        release.base_version_string(),
-        release.rc_suffix()
-    );
+    let Prepared {
+        release,
+        artifacts,
+        schedule,
+        draft,
+    } = prepare(ctx).await?;
+    let Draft {
+        title,
+        front,
+        body,
+        comments,
+    } = draft;
+    let links = ctx.config.vote.artifact_links;
 
     if dry_run {
-        println!("vote: dry-run (title={})", title);
+        println!("vote: dry-run (title={}{})", title, front.summary());
         println!("---\n{}", body);
+        for comment in &comments {
+            println!("--- comment\n{}", comment);
+        }
         return Ok(());
     }
 
+    token_scope::verify(ctx, &[Permission::Discussions]).await?;
+    let svn_dir = svn_url(ctx, &release);
+    let mut staged = vec![svn_dir.as_str()];
+    if links != ArtifactLinks::Github {
+        staged.extend(artifacts.iter().map(|a| a.url.as_str()));
+    }
+    template_lint::check_links(&staged)
+        .await
+        .context("svn dev artifacts are unavailable; run `asfship sync` first")?;
+
     let gh = github::client()?;
-    let category = discussion::fetch_default_category(&gh, &ctx.repo_owner, &ctx.repo_name).await?;
-    let payload = discussion::CreateDiscussionPayload {
-        title: &title,
-        body: &body,
-        category_id: category.id,
-    };
+    cancel::checkpoint("opening the vote discussion")?;
+    let (category, created) =
+        discussion::create(&gh, &ctx.repo_owner, &ctx.repo_name, &title, &body, &front).await?;
+    AuditLog::open(ctx)
+        .await
+        .record(AuditAction::DiscussionCreated, &created.html_url)
+        .await;
+    timeline::record(
+        &artifacts::root(ctx),
+        &release.version,
+        Phase::VoteOpened,
+        &release.tag,
+    )
+    .await;
 
-    let created: discussion::DiscussionResponse = gh
-        .post(
-            format!("repos/{}/{}/discussions", ctx.repo_owner, ctx.repo_name),
-            Some(&payload),
-        )
-        .await?;
+    if !comments.is_empty() {
+        let number = created
+            .number
+            .context("discussion response is missing its number; cannot post artifact table")?;
+        for comment in &comments {
+            cancel::checkpoint("posting the artifact table")?;
+            discussion::post_comment(&gh, &ctx.repo_owner, &ctx.repo_name, number, comment).await?;
+        }
+    }
 
     println!(
         "vote: discussion created (category={} url={})",
         category.name, created.html_url
     );
+    notify::send(
+        ctx,
+        Notice {
+            milestone: Milestone::VoteOpened,
+            tag: release.tag.clone(),
+            message: format!(
+                "Vote opened for {} {}{}, closing {}",
+                ctx.repo_name,
+                release.base_version_string(),
+                release.rc_suffix(),
+                schedule.close.format("%Y-%m-%d %H:%M UTC")
+            ),
+            url: Some(created.html_url.clone()),
+        },
+    )
+    .await;
     Ok(())
 }
 
+/// Render the vote discussion for the latest rc without posting it.
+pub async fn draft(ctx: &InferredContext) -> Result<Draft> {
+    Ok(prepare(ctx).await?.draft)
+}
+
+struct Prepared {
+    release: RcReleaseInfo,
+    artifacts: Vec<VoteTemplateArtifact>,
+    schedule: VoteSchedule,
+    draft: Draft,
+}
+
+#[tracing::instrument(skip_all)]
+async fn prepare(ctx: &InferredContext) -> Result<Prepared> {
+    let ctx = &*asf::with_dev_list(ctx).await?;
+    let release = fetch_latest_rc_release(ctx).await?;
+    let links = ctx.config.vote.artifact_links;
+    let artifacts = build_artifact_rows(&release, &svn_url(ctx, &release), links).await?;
+    if artifacts.is_empty() {
+        bail!(
+            "{} has no release assets; run `asfship prerelease` first",
+            release.tag
+        );
+    }
+    let git = RcTag::resolve(ctx, &release.tag).await?;
+    let templates = Templates::load(&ctx.repo_root)?;
+    let front = discussion::template_front_matter(ctx, &templates, "vote");
+    let schedule = VoteSchedule::new(Utc::now(), &ctx.config.vote)?;
+    let mut body = render_vote_body(
+        ctx, &templates, &schedule, &release, &git, &artifacts, false,
+    )?;
+    let mut comments = Vec::new();
+    if body.len() > discussion::BODY_LIMIT {
+        tracing::info!(
+            "vote: body exceeds {} characters; moving artifact table and verification commands to comments",
+            discussion::BODY_LIMIT
+        );
+        body = render_vote_body(ctx, &templates, &schedule, &release, &git, &artifacts, true)?;
+        comments = discussion::paginate(
+            "Artifacts and checksums",
+            artifacts.len(),
+            discussion::BODY_LIMIT,
+            |range| {
+                let mut tera_ctx = TeraContext::new();
+                tera_ctx.insert("artifacts", &artifacts[range]);
+                templates.render_partial("artifacts", &tera_ctx)
+            },
+        )?;
+        comments.extend(verification_steps(ctx, &artifacts).iter().map(|steps| {
+            format!(
+                "## Verification commands: {}\n\n```{}\n{}\n```\n",
+                steps.platform, steps.shell, steps.commands
+            )
+        }));
+    }
+    let draft = Draft {
+        title: title(ctx, &release),
+        front,
+        body,
+        comments,
+    };
+    Ok(Prepared {
+        release,
+        artifacts,
+        schedule,
+        draft,
+    })
+}
+
+/// Title of the vote discussion on `release`.
+pub fn title(ctx: &InferredContext, release: &RcReleaseInfo) -> String {
+    format!(
+        "[VOTE] {} {}{}",
+        ctx.repo_name,
+        release.base_version_string(),
+        release.rc_suffix()
+    )
+}
+
+/// The git objects under vote, so voters can check out and verify exactly what was tagged.
+#[derive(Debug, Serialize)]
+struct RcTag {
+    tag: String,
+    /// Id of the annotated tag object; `None` for a lightweight tag.
+    tag_object: Option<String>,
+    commit: String,
+    commit_url: String,
+    /// The tag carries an OpenPGP signature that `git tag -v` can check.
+    signed: bool,
+}
+
+impl RcTag {
+    /// Look `tag` up in the local clone, fetching it from origin when it is missing.
+    async fn resolve(ctx: &InferredContext, tag: &str) -> Result<Self> {
+        let refname = format!("refs/tags/{}", tag);
+        let repo = Repository::discover(&ctx.repo_root)?;
+        if repo.refname_to_id(&refname).is_err() {
+            let fetched = Command::new("git")
+                .arg("-C")
+                .arg(&ctx.repo_root)
+                .args(["fetch", "--no-tags", "origin"])
+                .arg(format!("+{0}:{0}", refname))
+                .output()
+                .await;
+            if !fetched.is_ok_and(|o| o.status.success()) {
+                tracing::warn!("git: could not fetch {} from origin", tag);
+            }
+        }
+        let object = repo
+            .revparse_single(&refname)
+            .with_context(|| format!("rc tag {} is neither in this clone nor on origin", tag))?;
+        let commit = object
+            .peel_to_commit()
+            .with_context(|| format!("rc tag {} does not point to a commit", tag))?
+            .id()
+            .to_string();
+        let (tag_object, signed) = match object.as_tag() {
+            Some(t) => (
+                Some(t.id().to_string()),
+                t.message()
+                    .is_some_and(|m| m.contains("-----BEGIN PGP SIGNATURE-----")),
+            ),
+            None => (None, false),
+        };
+        Ok(Self {
+            tag: tag.to_string(),
+            tag_object,
+            commit_url: format!(
+                "https://github.com/{}/{}/commit/{}",
+                ctx.repo_owner, ctx.repo_name, commit
+            ),
+            commit,
+            signed,
+        })
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct VoteTemplateArtifact {
     name: String,
+    /// Primary link: SVN dev URL, or the GitHub URL when `vote.artifact_links = "github"`.
     url: String,
+    /// GitHub URL listed next to the SVN link when `vote.artifact_links = "both"`.
+    github_url: Option<String>,
     sha512: Option<String>,
+    /// Link to the `.sha512` file published next to the artifact, when there is one.
+    checksum_url: Option<String>,
+    /// Link to the `.asc` signature published next to the artifact, when there is one.
+    signature_url: Option<String>,
+    size: u64,
+    /// `size` in binary units, e.g. `1.5 MiB`.
+    size_label: String,
 }
 
-async fn build_artifact_rows(release: &RcReleaseInfo) -> Result<Vec<VoteTemplateArtifact>> {
-    let mut sha_map = fetch_sha512_map(&release.assets).await?;
+/// Count and combined size of the artifacts listed in the vote.
+#[derive(Debug, Serialize)]
+struct ArtifactTotals {
+    count: usize,
+    size: u64,
+    size_label: String,
+}
+
+impl ArtifactTotals {
+    fn of(artifacts: &[VoteTemplateArtifact]) -> Self {
+        let size = artifacts.iter().map(|a| a.size).sum();
+        Self {
+            count: artifacts.len(),
+            size,
+            size_label: human_size(size),
+        }
+    }
+}
+
+async fn build_artifact_rows(
+    release: &RcReleaseInfo,
+    svn_dir: &str,
+    links: ArtifactLinks,
+) -> Result<Vec<VoteTemplateArtifact>> {
+    let sha_map = fetch_sha512_map(&release.assets).await?;
+    Ok(artifact_rows(release, sha_map, svn_dir, links))
+}
+
+fn artifact_rows(
+    release: &RcReleaseInfo,
+    mut sha_map: HashMap<String, String>,
+    svn_dir: &str,
+    links: ArtifactLinks,
+) -> Vec<VoteTemplateArtifact> {
+    let svn = |asset: &RcAsset| format!("{}/{}", svn_dir.trim_end_matches('/'), asset.name);
+    let primary = |asset: &RcAsset| match links {
+        ArtifactLinks::Github => asset.download_url.clone(),
+        ArtifactLinks::Svn | ArtifactLinks::Both => svn(asset),
+    };
+    let sibling = |name: String| release.assets.iter().find(|a| a.name == name).map(&primary);
     let mut rows = Vec::new();
     for asset in &release.assets {
         if asset.is_checksum() {
             continue;
         }
+        let github_url = match links {
+            ArtifactLinks::Both => Some(asset.download_url.clone()),
+            ArtifactLinks::Github | ArtifactLinks::Svn => None,
+        };
         rows.push(VoteTemplateArtifact {
             name: asset.name.clone(),
-            url: asset.download_url.clone(),
-            sha512: sha_map.remove(&asset.name),
+            url: primary(asset),
+            github_url,
+            sha512: asset.sha512.clone().or_else(|| sha_map.remove(&asset.name)),
+            checksum_url: sibling(format!("{}.sha512", asset.name)),
+            signature_url: sibling(format!("{}.asc", asset.name)),
+            size: asset.size,
+            size_label: asset.human_size(),
         });
     }
-    Ok(rows)
+    rows
 }
 
 async fn fetch_sha512_map(assets: &[RcAsset]) -> Result<HashMap<String, String>> {
-    let client = Client::new();
+    let client = net::client()?;
     let mut map = HashMap::new();
     for asset in assets {
         if !asset.is_checksum() {
@@ -92,34 +350,289 @@ async fn fetch_sha512_map(assets: &[RcAsset]) -> Result<HashMap<String, String>>
             .context("invalid sha512 asset name")?
             .to_string();
         let text = client.get(&asset.download_url).send().await?.text().await?;
-        map.insert(base, text.trim().to_string());
+        match checksum::parse_sha512(&text, &base) {
+            Some(digest) => {
+                map.insert(base, digest);
+            }
+            None => tracing::warn!("vote: unrecognized checksum format in {}", asset.name),
+        }
     }
     Ok(map)
 }
 
+/// Platforms the vote spells out verification commands for.
+#[derive(Debug, Clone, Copy)]
+enum Platform {
+    Linux,
+    MacOs,
+    Windows,
+}
+
+impl Platform {
+    const ALL: [Platform; 3] = [Platform::Linux, Platform::MacOs, Platform::Windows];
+
+    fn label(self) -> &'static str {
+        match self {
+            Platform::Linux => "Linux",
+            Platform::MacOs => "macOS",
+            Platform::Windows => "Windows (PowerShell)",
+        }
+    }
+
+    fn shell(self) -> &'static str {
+        match self {
+            Platform::Windows => "powershell",
+            Platform::Linux | Platform::MacOs => "sh",
+        }
+    }
+
+    fn download(self, url: &str, file: &str) -> String {
+        match self {
+            Platform::Windows => format!("Invoke-WebRequest {} -OutFile {}", url, file),
+            Platform::Linux | Platform::MacOs => format!("curl -fLo {} {}", file, url),
+        }
+    }
+
+    fn check_sha512(self, file: &str, format: ChecksumFormat) -> String {
+        let tool = match self {
+            Platform::Linux => "sha512sum",
+            Platform::MacOs => "shasum -a 512",
+            // Matching the digest anywhere in the file works for every checksum format.
+            Platform::Windows => {
+                return format!(
+                    "if ((Get-Content {0}.sha512) -match (Get-FileHash -Algorithm SHA512 {0}).Hash) {{ \"{0}: OK\" }} else {{ \"{0}: FAILED\" }}",
+                    file
+                );
+            }
+        };
+        match format {
+            ChecksumFormat::Bare => {
+                format!("echo \"$(cat {0}.sha512)  {0}\" | {1} -c -", file, tool)
+            }
+            ChecksumFormat::Gnu | ChecksumFormat::Bsd => format!("{} -c {}.sha512", tool, file),
+        }
+    }
+}
+
+/// Commands that download, check, and build the artifacts on one platform, ready to
+/// copy into a terminal.
+#[derive(Debug, Serialize)]
+struct VerificationSteps {
+    platform: &'static str,
+    /// Language of the Markdown code block.
+    shell: &'static str,
+    commands: String,
+}
+
+/// Verification commands for every artifact with a published checksum, followed by
+/// `cargo test` in each extracted tarball.
+fn verification_steps(
+    ctx: &InferredContext,
+    artifacts: &[VoteTemplateArtifact],
+) -> Vec<VerificationSteps> {
+    let checked: Vec<&VoteTemplateArtifact> = artifacts
+        .iter()
+        .filter(|a| a.checksum_url.is_some())
+        .collect();
+    if checked.is_empty() {
+        return Vec::new();
+    }
+    let mut tarballs: Vec<(&str, &str)> = Vec::new();
+    for a in &checked {
+        if let Some((dir, _)) = a.name.split_once(".tar.")
+            && !tarballs.iter().any(|(_, d)| *d == dir)
+        {
+            tarballs.push((&a.name, dir));
+        }
+    }
+    let keys = downloads::keys_url(&asf::project_name(ctx));
+    Platform::ALL
+        .into_iter()
+        .map(|platform| {
+            let mut groups = Vec::new();
+            if checked.iter().any(|a| a.signature_url.is_some()) {
+                groups.push(vec![
+                    platform.download(&keys, "KEYS"),
+                    "gpg --import KEYS".to_string(),
+                ]);
+            }
+            for a in &checked {
+                let mut lines = vec![platform.download(&a.url, &a.name)];
+                if let Some(url) = &a.checksum_url {
+                    lines.push(platform.download(url, &format!("{}.sha512", a.name)));
+                }
+                if let Some(url) = &a.signature_url {
+                    lines.push(platform.download(url, &format!("{}.asc", a.name)));
+                }
+                lines.push(platform.check_sha512(&a.name, ctx.config.checksum_format));
+                if a.signature_url.is_some() {
+                    lines.push(format!("gpg --verify {0}.asc {0}", a.name));
+                }
+                groups.push(lines);
+            }
+            for (archive, dir) in &tarballs {
+                groups.push(vec![
+                    format!("mkdir {}", dir),
+                    format!("tar xf {} -C {}", archive, dir),
+                    format!(
+                        "cargo test --manifest-path {}",
+                        extracted_manifest(ctx, dir)
+                    ),
+                ]);
+            }
+            VerificationSteps {
+                platform: platform.label(),
+                shell: platform.shell(),
+                commands: groups
+                    .iter()
+                    .map(|lines| lines.join("\n"))
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+            }
+        })
+        .collect()
+}
+
+/// `Cargo.toml` of the crate packaged in the archive extracted to `dir`. Archives keep
+/// the crate at its path in the repository; the crate is recognized by the archive name.
+fn extracted_manifest(ctx: &InferredContext, dir: &str) -> String {
+    let prefix = |c: &CrateInfo| {
+        if c.name == ctx.main_crate {
+            format!("apache-{}-", ctx.repo_name)
+        } else {
+            format!("apache-{}-{}-", ctx.repo_name, c.name)
+        }
+    };
+    let rel = ctx
+        .crates
+        .iter()
+        .filter(|c| {
+            dir.strip_prefix(&prefix(c))
+                .is_some_and(|rest| rest.starts_with(|ch: char| ch.is_ascii_digit()))
+        })
+        .max_by_key(|c| prefix(c).len())
+        .and_then(|c| c.package_root.strip_prefix(&ctx.repo_root).ok())
+        .map(|rel| {
+            rel.components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default();
+    if rel.is_empty() {
+        format!("{}/Cargo.toml", dir)
+    } else {
+        format!("{}/{}/Cargo.toml", dir, rel)
+    }
+}
+
+struct VoteSchedule {
+    close: DateTime<Utc>,
+    hours: u32,
+    timezone: Option<Tz>,
+}
+
+impl VoteSchedule {
+    /// Close `duration_hours` after `now`, rounded up to the next full hour.
+    fn new(now: DateTime<Utc>, cfg: &VoteConfig) -> Result<Self> {
+        let hours = cfg.duration_hours.unwrap_or(MIN_VOTE_HOURS);
+        if hours < MIN_VOTE_HOURS {
+            bail!(
+                "vote.duration_hours = {} is shorter than the {} hours required by ASF policy",
+                hours,
+                MIN_VOTE_HOURS
+            );
+        }
+        let timezone = cfg
+            .timezone
+            .as_deref()
+            .map(|name| {
+                name.parse::<Tz>().map_err(|_| {
+                    anyhow!("unknown vote.timezone {:?} (expected an IANA name)", name)
+                })
+            })
+            .transpose()?;
+        let end = now + Duration::hours(hours.into());
+        let floored = end.duration_trunc(Duration::hours(1))?;
+        let close = if floored == end {
+            end
+        } else {
+            floored + Duration::hours(1)
+        };
+        Ok(Self {
+            close,
+            hours,
+            timezone,
+        })
+    }
+
+    fn local_close(&self) -> Option<String> {
+        self.timezone.map(|tz| {
+            format!(
+                "{} ({})",
+                self.close.with_timezone(&tz).format("%Y-%m-%d %H:%M %Z"),
+                tz.name()
+            )
+        })
+    }
+
+    fn duration_label(&self) -> String {
+        if self.hours.is_multiple_of(24) {
+            format!("{} hours ({} days)", self.hours, self.hours / 24)
+        } else {
+            format!("{} hours", self.hours)
+        }
+    }
+}
+
+pub(crate) fn svn_url(ctx: &InferredContext, release: &RcReleaseInfo) -> String {
+    format!(
+        "{}/{}/{}",
+        endpoints::dist_dev(),
+        ctx.repo_name,
+        release.svn_path_component(&ctx.repo_name)
+    )
+}
+
 fn render_vote_body(
     ctx: &InferredContext,
+    templates: &Templates,
+    schedule: &VoteSchedule,
     release: &RcReleaseInfo,
+    git: &RcTag,
     artifacts: &[VoteTemplateArtifact],
+    artifacts_in_comments: bool,
 ) -> Result<String> {
     let mut tera_ctx = TeraContext::new();
-    let vote_close = (Utc::now() + Duration::days(3)).date_naive();
     tera_ctx.insert("repo", &ctx.repo_name);
     tera_ctx.insert("version", &release.base_version_string());
     tera_ctx.insert("rc_suffix", &release.rc_suffix());
+    tera_ctx.insert("svn_url", &svn_url(ctx, release));
+    tera_ctx.insert("artifacts", artifacts);
+    tera_ctx.insert("artifacts_total", &ArtifactTotals::of(artifacts));
+    tera_ctx.insert("artifacts_in_comments", &artifacts_in_comments);
+    tera_ctx.insert("verification", &verification_steps(ctx, artifacts));
+    tera_ctx.insert("vote_close_date", &schedule.close.date_naive().to_string());
     tera_ctx.insert(
-        "svn_url",
-        &format!(
-            "https://dist.apache.org/repos/dist/dev/{}/{}",
-            ctx.repo_name,
-            release.svn_path_component(&ctx.repo_name)
-        ),
+        "vote_close_time",
+        &schedule.close.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
     );
-    tera_ctx.insert("artifacts", artifacts);
-    tera_ctx.insert("vote_close_date", &vote_close.to_string());
+    tera_ctx.insert(
+        "vote_close_utc",
+        &schedule.close.format("%Y-%m-%d %H:%M UTC").to_string(),
+    );
+    tera_ctx.insert("vote_close_local", &schedule.local_close());
+    tera_ctx.insert("vote_duration", &schedule.duration_label());
+    tera_ctx.insert("dev_list", &ctx.config.asf.dev_list);
+    tera_ctx.insert("git", git);
+    let rm = asf::release_manager(&ctx.config.asf)?;
+    tera_ctx.insert(
+        "signing_key",
+        &rm.as_ref().and_then(|rm| rm.gpg_fingerprint.clone()),
+    );
+    tera_ctx.insert("release_manager", &rm);
 
-    Tera::one_off(VOTE_TEMPLATE, &tera_ctx, false)
-        .map_err(|err| anyhow!("failed to render vote template: {}", err))
+    templates.render("vote", &tera_ctx)
 }
 
 #[cfg(test)]
@@ -130,6 +643,33 @@ mod tests {
     use semver::Version;
     use std::path::PathBuf;
 
+    #[test]
+    fn vote_schedule_enforces_minimum_and_formats_timezone() {
+        let now = DateTime::parse_from_rfc3339("2024-05-01T10:15:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let short = VoteConfig {
+            duration_hours: Some(48),
+            ..Default::default()
+        };
+        assert!(VoteSchedule::new(now, &short).is_err());
+
+        let cfg = VoteConfig {
+            timezone: Some("Asia/Shanghai".into()),
+            ..Default::default()
+        };
+        let schedule = VoteSchedule::new(now, &cfg).unwrap();
+        assert_eq!(
+            schedule.close.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            "2024-05-04T11:00:00Z"
+        );
+        assert_eq!(
+            schedule.local_close().unwrap(),
+            "2024-05-04 19:00 CST (Asia/Shanghai)"
+        );
+        assert_eq!(schedule.duration_label(), "72 hours (3 days)");
+    }
+
     #[test]
     fn render_vote_body_formats_artifacts() {
         let ctx = InferredContext {
@@ -139,7 +679,12 @@ mod tests {
             crates: Vec::new(),
             main_crate: "foo".into(),
             last_stable_tag: Some("v0.1.0".into()),
+            since: None,
+            until: None,
+            config: Default::default(),
+            snapshot: None,
         };
+        let schedule = VoteSchedule::new(Utc::now(), &ctx.config.vote).unwrap();
         let release = RcReleaseInfo {
             tag: "v0.1.1-rc.1".into(),
             version: Version::parse("0.1.1").unwrap(),
@@ -148,16 +693,182 @@ mod tests {
                 name: "apache-foo-0.1.1-rc1-src.tar.gz".into(),
                 download_url: "https://example.com/tar".into(),
                 size: 10,
+                sha512: None,
             }],
+            vote: None,
         };
-        let artifacts = vec![VoteTemplateArtifact {
-            name: "apache-foo-0.1.1-rc1-src.tar.gz".into(),
-            url: "https://example.com/tar".into(),
-            sha512: Some("abcd".into()),
-        }];
-
-        let rendered = render_vote_body(&ctx, &release, &artifacts).unwrap();
-        assert!(rendered.contains("sha512=abcd"));
+        let sha_map = HashMap::from([(
+            "apache-foo-0.1.1-rc1-src.tar.gz".to_string(),
+            "abcd".to_string(),
+        )]);
+        let artifacts = artifact_rows(
+            &release,
+            sha_map,
+            &svn_url(&ctx, &release),
+            ArtifactLinks::Both,
+        );
+
+        let git = RcTag {
+            tag: "v0.1.1-rc.1".into(),
+            tag_object: Some("1111111111111111111111111111111111111111".into()),
+            commit: "2222222222222222222222222222222222222222".into(),
+            commit_url:
+                "https://github.com/apache/foo/commit/2222222222222222222222222222222222222222"
+                    .into(),
+            signed: true,
+        };
+
+        let templates = Templates::load(&ctx.repo_root).unwrap();
+        let rendered = render_vote_body(
+            &ctx, &templates, &schedule, &release, &git, &artifacts, false,
+        )
+        .unwrap();
         assert!(rendered.contains("[VOTE]"));
+        assert!(rendered.contains("Artifacts and checksums (1 files, 10 B in total):"));
+        assert!(rendered.contains(
+            "| [apache-foo-0.1.1-rc1-src.tar.gz](https://dist.apache.org/repos/dist/dev/foo/foo-0.1.1-rc1/apache-foo-0.1.1-rc1-src.tar.gz) ([GitHub](https://example.com/tar)) | 10 B | `abcd` |"
+        ));
+
+        assert!(rendered.contains(
+            "- Git tag: v0.1.1-rc.1 (tag object `1111111111111111111111111111111111111111`)\n- Commit: [2222222222222222222222222222222222222222](https://github.com/apache/foo/commit/2222222222222222222222222222222222222222)\n- The tag is signed; verify it with `git tag -v v0.1.1-rc.1`.\n"
+        ));
+
+        let summarized = render_vote_body(
+            &ctx, &templates, &schedule, &release, &git, &artifacts, true,
+        )
+        .unwrap();
+        assert!(!summarized.contains("`abcd`"));
+        assert!(summarized.contains("full checksum table is posted in the comments"));
+        let mut tera_ctx = TeraContext::new();
+        tera_ctx.insert("artifacts", &artifacts);
+        assert_eq!(
+            templates.render_partial("artifacts", &tera_ctx).unwrap(),
+            "| Artifact | Size | SHA-512 |\n| --- | --- | --- |\n| [apache-foo-0.1.1-rc1-src.tar.gz](https://dist.apache.org/repos/dist/dev/foo/foo-0.1.1-rc1/apache-foo-0.1.1-rc1-src.tar.gz) ([GitHub](https://example.com/tar)) | 10 B | `abcd` |\n"
+        );
+    }
+
+    #[test]
+    fn verification_steps_download_check_and_build_each_artifact() {
+        let krate = |name: &str, dir: &str| CrateInfo {
+            name: name.into(),
+            version: Version::parse("0.1.1").unwrap(),
+            manifest_path: PathBuf::from(format!("/repo/{}Cargo.toml", dir)),
+            package_root: PathBuf::from(format!("/repo/{}", dir)),
+            internal_dep_count: 0,
+            publish: true,
+        };
+        let ctx = InferredContext {
+            repo_root: PathBuf::from("/repo"),
+            repo_owner: "apache".into(),
+            repo_name: "foo".into(),
+            crates: vec![krate("foo", ""), krate("bar", "bar")],
+            main_crate: "foo".into(),
+            last_stable_tag: Some("v0.1.0".into()),
+            since: None,
+            until: None,
+            config: Default::default(),
+            snapshot: None,
+        };
+        let asset = |name: &str| RcAsset {
+            name: name.into(),
+            download_url: format!("https://example.com/{}", name),
+            size: 10,
+            sha512: None,
+        };
+        let release = RcReleaseInfo {
+            tag: "v0.1.1-rc.1".into(),
+            version: Version::parse("0.1.1").unwrap(),
+            rc_number: 1,
+            assets: vec![
+                asset("apache-foo-0.1.1-rc1-src.tar.gz"),
+                asset("apache-foo-0.1.1-rc1-src.tar.gz.sha512"),
+                asset("apache-foo-0.1.1-rc1-src.tar.gz.asc"),
+                asset("apache-foo-bar-0.1.1-rc1-src.zip"),
+                asset("apache-foo-bar-0.1.1-rc1-src.zip.sha512"),
+                asset("apache-foo-bar-0.1.1-rc1-src.tar.gz"),
+                asset("apache-foo-bar-0.1.1-rc1-src.tar.gz.sha512"),
+                asset("manifest.json"),
+            ],
+            vote: None,
+        };
+        let artifacts = artifact_rows(
+            &release,
+            HashMap::new(),
+            "https://dist.example/foo-0.1.1-rc1",
+            ArtifactLinks::Github,
+        );
+        assert_eq!(
+            artifacts[0].signature_url.as_deref(),
+            Some("https://example.com/apache-foo-0.1.1-rc1-src.tar.gz.asc")
+        );
+        assert_eq!(artifacts[1].checksum_url, None);
+
+        let steps = verification_steps(&ctx, &artifacts);
+        assert_eq!(
+            steps.iter().map(|s| s.platform).collect::<Vec<_>>(),
+            ["Linux", "macOS", "Windows (PowerShell)"]
+        );
+        assert_eq!(
+            steps[0].commands,
+            "\
+curl -fLo KEYS https://downloads.apache.org/foo/KEYS
+gpg --import KEYS
+
+curl -fLo apache-foo-0.1.1-rc1-src.tar.gz https://example.com/apache-foo-0.1.1-rc1-src.tar.gz
+curl -fLo apache-foo-0.1.1-rc1-src.tar.gz.sha512 https://example.com/apache-foo-0.1.1-rc1-src.tar.gz.sha512
+curl -fLo apache-foo-0.1.1-rc1-src.tar.gz.asc https://example.com/apache-foo-0.1.1-rc1-src.tar.gz.asc
+sha512sum -c apache-foo-0.1.1-rc1-src.tar.gz.sha512
+gpg --verify apache-foo-0.1.1-rc1-src.tar.gz.asc apache-foo-0.1.1-rc1-src.tar.gz
+
+curl -fLo apache-foo-bar-0.1.1-rc1-src.zip https://example.com/apache-foo-bar-0.1.1-rc1-src.zip
+curl -fLo apache-foo-bar-0.1.1-rc1-src.zip.sha512 https://example.com/apache-foo-bar-0.1.1-rc1-src.zip.sha512
+sha512sum -c apache-foo-bar-0.1.1-rc1-src.zip.sha512
+
+curl -fLo apache-foo-bar-0.1.1-rc1-src.tar.gz https://example.com/apache-foo-bar-0.1.1-rc1-src.tar.gz
+curl -fLo apache-foo-bar-0.1.1-rc1-src.tar.gz.sha512 https://example.com/apache-foo-bar-0.1.1-rc1-src.tar.gz.sha512
+sha512sum -c apache-foo-bar-0.1.1-rc1-src.tar.gz.sha512
+
+mkdir apache-foo-0.1.1-rc1-src
+tar xf apache-foo-0.1.1-rc1-src.tar.gz -C apache-foo-0.1.1-rc1-src
+cargo test --manifest-path apache-foo-0.1.1-rc1-src/Cargo.toml
+
+mkdir apache-foo-bar-0.1.1-rc1-src
+tar xf apache-foo-bar-0.1.1-rc1-src.tar.gz -C apache-foo-bar-0.1.1-rc1-src
+cargo test --manifest-path apache-foo-bar-0.1.1-rc1-src/bar/Cargo.toml"
+        );
+        assert!(
+            steps[1]
+                .commands
+                .contains("\nshasum -a 512 -c apache-foo-0.1.1-rc1-src.tar.gz.sha512\n")
+        );
+        assert!(steps[2].commands.contains(
+            "\nInvoke-WebRequest https://example.com/apache-foo-0.1.1-rc1-src.tar.gz -OutFile apache-foo-0.1.1-rc1-src.tar.gz\n"
+        ));
+
+        let schedule = VoteSchedule::new(Utc::now(), &ctx.config.vote).unwrap();
+        let git = RcTag {
+            tag: "v0.1.1-rc.1".into(),
+            tag_object: None,
+            commit: "2222222222222222222222222222222222222222".into(),
+            commit_url: "https://github.com/apache/foo/commit/2222".into(),
+            signed: false,
+        };
+        let templates = Templates::load(&ctx.repo_root).unwrap();
+        let body = render_vote_body(
+            &ctx, &templates, &schedule, &release, &git, &artifacts, false,
+        )
+        .unwrap();
+        assert!(
+            body.contains(
+                "then build):\n\n<details><summary>Linux</summary>\n\n```sh\ncurl -fLo KEYS"
+            ),
+            "{}",
+            body
+        );
+        assert!(
+            body.contains("bar/Cargo.toml\n```\n\n</details>\n\nPlease vote"),
+            "{}",
+            body
+        );
     }
 }
@@ -0,0 +1,196 @@
+use anyhow::{Context, Result, anyhow, bail};
+use git2::Repository;
+use regex::Regex;
+use tera::{Context as TeraContext, Tera};
+
+use crate::asf;
+use crate::audit::{AuditAction, AuditLog};
+use crate::branch_commit::{BranchTarget, push_branch};
+use crate::config::WebsiteFile;
+use crate::feed::FeedEntry;
+use crate::git_identity::GitIdentity;
+use crate::github;
+use crate::infer::InferredContext;
+
+/// Print which website files `release` would update and how.
+pub async fn describe(ctx: &InferredContext, entry: &FeedEntry) -> Result<()> {
+    let cfg = &ctx.config.release.website;
+    if cfg.files.is_empty() {
+        return Ok(());
+    }
+    let (target, files) = render(ctx, entry).await?;
+    let how = if cfg.pull_request {
+        format!("pull request against {}", target.branch)
+    } else {
+        format!("commit on {}", target.branch)
+    };
+    println!("  website: {} file(s) via {}", files.len(), how);
+    for (path, _) in &files {
+        println!("    - {}", path);
+    }
+    Ok(())
+}
+
+/// Update the configured website files for `entry` and publish them as a commit or pull request.
+pub async fn publish(
+    ctx: &InferredContext,
+    entry: &FeedEntry,
+    identity: &GitIdentity,
+    audit: &AuditLog,
+) -> Result<()> {
+    let cfg = &ctx.config.release.website;
+    if cfg.files.is_empty() {
+        return Ok(());
+    }
+    let (target, files) = render(ctx, entry).await?;
+    let repo = Repository::discover(&ctx.repo_root)?;
+    let sig = identity.signature()?;
+    let message = format!(
+        "docs: update website for {} {}",
+        ctx.repo_name, entry.version
+    );
+    let into = if cfg.pull_request {
+        format!("asfship/website-{}", entry.version)
+    } else {
+        target.branch.clone()
+    };
+    let Some(oid) = target.commit(&repo, &into, &files, &sig, &message)? else {
+        println!("website: already up to date for {}", entry.version);
+        return Ok(());
+    };
+    audit
+        .record(AuditAction::CommitCreated, &oid.to_string())
+        .await;
+    push_branch(&ctx.repo_root, &into).await?;
+    audit.record(AuditAction::BranchPushed, &into).await;
+    if !cfg.pull_request {
+        println!("website: updated {} file(s) on {}", files.len(), into);
+        return Ok(());
+    }
+
+    let gh = github::client()?;
+    let pr = gh
+        .pulls(ctx.repo_owner.clone(), ctx.repo_name.clone())
+        .create(&message, &into, &target.branch)
+        .body(format!(
+            "Website update for the {} release, generated by asfship.\n\nRelease: {}",
+            entry.version, entry.url
+        ))
+        .send()
+        .await
+        .with_context(|| format!("failed to open the website pull request from {}", into))?;
+    let url = pr
+        .html_url
+        .map(|u| u.to_string())
+        .unwrap_or_else(|| format!("#{}", pr.number));
+    audit.record(AuditAction::PullRequestCreated, &url).await;
+    println!("website: opened {}", url);
+    Ok(())
+}
+
+/// New contents of every configured file, read against the website branch tip.
+async fn render(
+    ctx: &InferredContext,
+    entry: &FeedEntry,
+) -> Result<(BranchTarget, Vec<(String, Vec<u8>)>)> {
+    let cfg = &ctx.config.release.website;
+    let target = BranchTarget::resolve(
+        &ctx.repo_root,
+        cfg.branch.as_deref(),
+        "release.website.branch",
+    )
+    .await?;
+    let repo = Repository::discover(&ctx.repo_root)?;
+    let mut tera_ctx = TeraContext::from_serialize(entry)?;
+    tera_ctx.insert("repo", &ctx.repo_name);
+    tera_ctx.insert("project", &asf::project_name(ctx));
+
+    let mut files = Vec::new();
+    for file in &cfg.files {
+        let template = match &file.template {
+            Some(path) => Some(
+                tokio::fs::read_to_string(ctx.repo_root.join(path))
+                    .await
+                    .with_context(|| format!("failed to read website template {}", path))?,
+            ),
+            None => None,
+        };
+        let existing = target.read(&repo, &file.path)?;
+        let content = render_file(file, template.as_deref(), existing.as_deref(), &tera_ctx)?;
+        files.push((file.path.clone(), content));
+    }
+    Ok((target, files))
+}
+
+fn render_file(
+    file: &WebsiteFile,
+    template: Option<&str>,
+    existing: Option<&[u8]>,
+    tera_ctx: &TeraContext,
+) -> Result<Vec<u8>> {
+    let render = |source: &str| {
+        Tera::one_off(source, tera_ctx, false)
+            .map_err(|err| anyhow!("failed to render website file {}: {}", file.path, err))
+    };
+    if let Some(template) = template {
+        return Ok(render(template)?.into_bytes());
+    }
+    let existing = existing.with_context(|| {
+        format!(
+            "{} does not exist on the website branch; use `template` to create it",
+            file.path
+        )
+    })?;
+    let mut content = String::from_utf8(existing.to_vec())
+        .with_context(|| format!("{} is not UTF-8", file.path))?;
+    for r in &file.replace {
+        let pattern = Regex::new(&r.pattern)?;
+        if !pattern.is_match(&content) {
+            bail!("pattern `{}` matched nothing in {}", r.pattern, file.path);
+        }
+        content = pattern
+            .replace_all(&content, render(&r.with)?.as_str())
+            .into_owned();
+    }
+    Ok(content.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WebsiteReplacement;
+
+    #[test]
+    fn replacements_render_release_values() {
+        let mut tera_ctx = TeraContext::new();
+        tera_ctx.insert("version", "0.2.0");
+        tera_ctx.insert("tag", "v0.2.0");
+        let file = WebsiteFile {
+            path: "website/config.toml".into(),
+            template: None,
+            replace: vec![WebsiteReplacement {
+                pattern: r#"(latest) = "[^"]*""#.into(),
+                with: r#"$1 = "{{ version }}""#.into(),
+            }],
+        };
+        let out = render_file(
+            &file,
+            None,
+            Some(b"latest = \"0.1.0\"\nname = \"foo\"\n"),
+            &tera_ctx,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "latest = \"0.2.0\"\nname = \"foo\"\n"
+        );
+
+        let err = render_file(&file, None, Some(b"name = \"foo\"\n"), &tera_ctx)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("matched nothing"), "{}", err);
+
+        let page = render_file(&file, Some("Download {{ tag }}"), None, &tera_ctx).unwrap();
+        assert_eq!(page, b"Download v0.2.0");
+    }
+}
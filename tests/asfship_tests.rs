@@ -6,6 +6,9 @@ use assert_cmd::Command;
 use git2::{IndexAddOption, Repository, Signature, build::CheckoutBuilder};
 use tempfile::TempDir;
 
+#[cfg(unix)]
+mod mock;
+
 fn write_file(path: &Path, content: &str) -> Result<()> {
     if let Some(dir) = path.parent() {
         fs::create_dir_all(dir)?;
@@ -16,6 +19,9 @@ fn write_file(path: &Path, content: &str) -> Result<()> {
 
 fn init_repo(root: &Path, origin: &str) -> Result<Repository> {
     let repo = Repository::init(root)?;
+    let mut config = repo.config()?;
+    config.set_str("user.name", "asfship")?;
+    config.set_str("user.email", "asfship@example.com")?;
     let mut idx = repo.index()?;
     idx.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
     idx.write()?;
@@ -43,6 +49,24 @@ fn commit_all(repo: &Repository, message: &str) -> Result<()> {
     Ok(())
 }
 
+/// Push the current branch to origin and track it there, like `git push -u origin HEAD`.
+fn push_upstream(repo: &Repository) -> Result<()> {
+    let head = repo.head()?;
+    let branch = head.shorthand().unwrap().to_string();
+    let refname = head.name().unwrap().to_string();
+    repo.find_remote("origin")?
+        .push(&[format!("{}:{}", refname, refname)], None)?;
+    repo.reference(
+        &format!("refs/remotes/origin/{}", branch),
+        head.target().unwrap(),
+        true,
+        "push",
+    )?;
+    repo.find_branch(&branch, git2::BranchType::Local)?
+        .set_upstream(Some(&format!("origin/{}", branch)))?;
+    Ok(())
+}
+
 fn read_version(manifest: &Path) -> String {
     let s = fs::read_to_string(manifest).unwrap();
     let doc: toml::Value = toml::from_str(&s).unwrap();
@@ -57,6 +81,10 @@ fn asfship_cmd(root: &Path) -> Result<Command> {
     let mut cmd = Command::cargo_bin("asfship")?;
     cmd.current_dir(root);
     cmd.env_remove("ASFSHIP_GITHUB_TOKEN");
+    // Keep a developer's gh login out of the tests.
+    cmd.env_remove("GH_TOKEN");
+    cmd.env_remove("GITHUB_TOKEN");
+    cmd.env("GH_CONFIG_DIR", root.join("target").join("gh-config"));
     Ok(cmd)
 }
 
@@ -98,6 +126,13 @@ edition = "2021"
 Workspace crates:
 - foo 0.1.0
 
+Release checklist (ASF release policy):
+- [ ] LICENSE and NOTICE at the repository root (missing LICENSE, NOTICE)
+- [ ] Source archives signed (.asc) and checksummed (.sha512)
+- [ ] Vote open for at least 72 hours
+- [ ] Vote passed with at least 3 binding +1 votes and more binding +1 than -1 votes
+- [ ] dist/release updated with the voted artifacts (set release.promote_dist, or copy the rc artifacts from dist/dev to dist/release without their -rcN marker once the vote passes)
+- [ ] Release announced
 
 Please add agenda items, blockers, and verification tasks below. Once scope is agreed, run `asfship prerelease` to prepare the first release candidate.
 "###);
@@ -133,8 +168,13 @@ edition = "2021"
 mode: dry-run
 base tag: <none>
 main crate: foo
-rc tag: <pending>
+rc tag: v0.1.1-rc.1 (planned)
 artifacts dir: <pending>
+planned artifacts:
+  - apache-foo-0.1.1-rc1-src.tar.gz
+  - apache-foo-0.1.1-rc1-src.tar.gz.sha512
+  - apache-foo-0.1.1-rc1-src.zip
+  - apache-foo-0.1.1-rc1-src.zip.sha512
 changed crates:
 * foo 0.1.0 -> 0.1.1
   Others:
@@ -175,6 +215,126 @@ edition = "2021"
     Ok(())
 }
 
+#[test]
+fn changelog_merges_unreleased_notes() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(
+        &root.join("CHANGELOG.md"),
+        "# Changelog\n\n## Unreleased\n\n### Features\n- Curated highlight\n\n## foo v0.1.0 - 2024-01-01\n\n- init\n",
+    )?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(&repo, "feat: add new module")?;
+
+    asfship_cmd(root)?.arg("prerelease").assert().success();
+    let changelog = fs::read_to_string(root.join("CHANGELOG.md"))?;
+    let release = changelog
+        .find("## foo v0.1.1")
+        .expect("new release heading");
+    assert!(
+        changelog.starts_with("# Changelog\n\n## Unreleased\n\n## foo v0.1.1"),
+        "{}",
+        changelog
+    );
+    let features = &changelog[release..changelog.find("## foo v0.1.0").unwrap()];
+    assert_eq!(features.matches("### Features").count(), 1, "{}", changelog);
+    assert!(
+        features.contains("### Features\n- Curated highlight\n- feat: add new module"),
+        "{}",
+        changelog
+    );
+    Ok(())
+}
+
+#[test]
+fn changelog_entries_follow_configured_snippets() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        r#"[changelog]
+entry = "{{ description | upper_first }}"
+
+[changelog.entries]
+feat = "{% if scope %}**{{ scope }}:** {% endif %}{{ description }} ({{ sha }})"
+"#,
+    )?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(&repo, "feat(io): add new module")?;
+    let feat = repo.head()?.peel_to_commit()?.id().to_string();
+    write_file(&root.join("src/lib.rs"), "pub fn f() -> u8 { 1 }\n")?;
+    commit_all(&repo, "fix: return a value")?;
+
+    asfship_cmd(root)?.arg("prerelease").assert().success();
+    let changelog = fs::read_to_string(root.join("CHANGELOG.md"))?;
+    assert!(
+        changelog.contains(&format!(
+            "### Features\n- **io:** add new module ({})\n",
+            &feat[..7]
+        )),
+        "{}",
+        changelog
+    );
+    assert!(
+        changelog.contains("### Fixes\n- Return a value\n"),
+        "{}",
+        changelog
+    );
+    Ok(())
+}
+
+#[test]
+fn release_highlight_trailers_lead_the_changelog() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(
+        &root.join("CHANGELOG.md"),
+        "# Changelog\n\n## Unreleased\n\n### Highlights\n- Curated highlight\n\n## foo v0.1.0 - 2024-01-01\n\n- init\n",
+    )?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(
+        &repo,
+        "feat: stream reads\n\nRelease-Highlight: Reads no longer buffer whole files\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() -> u8 { 1 }\n")?;
+    commit_all(&repo, "fix: return a value")?;
+
+    asfship_cmd(root)?.arg("prerelease").assert().success();
+    let changelog = fs::read_to_string(root.join("CHANGELOG.md"))?;
+    assert!(
+        changelog.contains(
+            "### Highlights\n- Curated highlight\n- Reads no longer buffer whole files\n\n### Features\n- feat: stream reads ("
+        ),
+        "{}",
+        changelog
+    );
+    assert_eq!(
+        changelog.matches("### Highlights").count(),
+        1,
+        "{}",
+        changelog
+    );
+    Ok(())
+}
+
 #[test]
 fn pre1_breaking_bumps_minor() -> Result<()> {
     let td = TempDir::new()?;
@@ -229,6 +389,293 @@ edition = "2021"
     Ok(())
 }
 
+#[test]
+fn plan_explains_bumps_on_dirty_tree() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(&repo, "fix: handle empty input")?;
+    write_file(&root.join("src/lib.rs"), "pub fn h() {}\n")?;
+    commit_all(&repo, "feat!: rename f to h")?;
+    write_file(&root.join("src/lib.rs"), "pub fn uncommitted() {}\n")?;
+
+    let output = asfship_cmd(root)?.arg("plan").output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains("* foo 0.1.0 -> 0.2.0 (minor: breaking change before 1.0, from "),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains(" feat!: rename f to h)\n"), "{}", stdout);
+    // Commands without a run directory keep their log in the artifacts root.
+    let log = fs::read_to_string(root.join("target/asfship/asfship.log"))?;
+    assert!(log.contains("plan: begin"), "{}", log);
+
+    let output = asfship_cmd(root)?.args(["plan", "--explain"]).output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains(
+            "  minor: 0.1.0 is before 1.0, where a breaking commit bumps the minor version\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(" breaking (`!` marker in the header): feat!: rename f to h\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains(" fix (type `fix`): fix: handle empty input\n"));
+
+    let output = asfship_cmd(root)?
+        .args(["plan", "--format", "json"])
+        .output()?;
+    assert!(output.status.success());
+    let plan: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(plan["schema_version"], 1);
+    let foo = &plan["crates"][0];
+    assert_eq!(foo["next"], "0.2.0");
+    assert_eq!(foo["bump"], "minor");
+    assert_eq!(foo["trigger"]["kind"], "breaking");
+    assert_eq!(foo["trigger"]["classified_by"], "`!` marker in the header");
+    assert_eq!(foo["commits"].as_array().map(Vec::len), Some(3));
+
+    // The version bump must not have been applied.
+    assert_eq!(read_version(&root.join("Cargo.toml")), "0.1.0");
+    Ok(())
+}
+
+#[test]
+fn since_and_until_bound_the_planned_range() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/a.rs"), "pub fn a() {}\n")?;
+    commit_all(&repo, "fix: first")?;
+    let first = repo.head()?.peel_to_commit()?.id().to_string();
+    write_file(&root.join("src/b.rs"), "pub fn b() {}\n")?;
+    commit_all(&repo, "feat: second")?;
+    write_file(&root.join("src/c.rs"), "pub fn c() {}\n")?;
+    commit_all(&repo, "feat!: third")?;
+
+    let output = asfship_cmd(root)?
+        .args([
+            "plan", "--format", "json", "--since", &first, "--until", "HEAD~1",
+        ])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let plan: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(plan["base_tag"], first.as_str());
+    let foo = &plan["crates"][0];
+    assert_eq!(foo["next"], "0.1.1");
+    let subjects: Vec<&str> = foo["commits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["subject"].as_str().unwrap())
+        .collect();
+    assert_eq!(subjects, ["feat: second"]);
+
+    let output = asfship_cmd(root)?
+        .args(["plan", "--until", "no-such-ref"])
+        .output()?;
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("--until \"no-such-ref\" is not a commit")
+    );
+
+    // Prerelease tags HEAD, so a range ending elsewhere would not match the changelog.
+    let output = asfship_cmd(root)?
+        .args(["prerelease", "--dry-run", "--until", "HEAD~1"])
+        .output()?;
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("--until \"HEAD~1\" only applies to `plan`")
+    );
+    Ok(())
+}
+
+#[test]
+fn bootstrap_limits_first_release_history() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    // `plan` runs `cargo metadata`, which leaves a lockfile behind.
+    write_file(&root.join(".gitignore"), "target/\nCargo.lock\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/old.rs"), "pub fn old() {}\n")?;
+    commit_all(&repo, "feat!: prototype api")?;
+    write_file(&root.join("src/new.rs"), "pub fn new() {}\n")?;
+    commit_all(&repo, "fix: handle empty input")?;
+    let start = repo.head()?.target().unwrap();
+    write_file(&root.join("src/more.rs"), "pub fn more() {}\n")?;
+    commit_all(&repo, "feat: add more")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        &format!(
+            "[bootstrap]\nsince = \"{}\"\ninitial_changelog = true\n",
+            start
+        ),
+    )?;
+    commit_all(&repo, "chore: configure asfship")?;
+
+    let output = asfship_cmd(root)?
+        .args(["plan", "--format", "json"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let plan: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let foo = &plan["crates"][0];
+    // The breaking prototype commit predates `since`, so it neither bumps nor shows up.
+    assert_eq!(foo["next"], "0.1.1");
+    let subjects: Vec<&str> = foo["commits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["subject"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        subjects,
+        [
+            "fix: handle empty input",
+            "feat: add more",
+            "chore: configure asfship"
+        ]
+    );
+
+    asfship_cmd(root)?.arg("prerelease").assert().success();
+    let changelog = fs::read_to_string(root.join("CHANGELOG.md"))?;
+    assert!(changelog.contains("## foo v0.1.1"), "{}", changelog);
+    assert!(changelog.contains("\nInitial release.\n"), "{}", changelog);
+    assert!(!changelog.contains("handle empty input"), "{}", changelog);
+    Ok(())
+}
+
+#[test]
+fn tag_prefix_applies_to_base_and_rc_tags() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".asfship.toml"), "tag_prefix = \"release-\"\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag_lightweight("release-0.1.0", head.as_object(), false)?;
+    // Tags in another format are not releases of this project.
+    repo.tag_lightweight("v9.0.0", head.as_object(), false)?;
+    write_file(&root.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(&repo, "fix: handle empty input")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag_lightweight("release-0.1.1-rc.1", head.as_object(), false)?;
+
+    let output = asfship_cmd(root)?
+        .args(["prerelease", "--dry-run"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("base tag: release-0.1.0\n"), "{}", stdout);
+    assert!(
+        stdout.contains("rc tag: release-0.1.1-rc.2 (planned)"),
+        "{}",
+        stdout
+    );
+    Ok(())
+}
+
+#[test]
+fn root_files_attribution_is_configurable() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    let workspace = "[workspace]\nmembers = [\"core\", \"extra\"]\nresolver = \"2\"\n";
+    write_file(&root.join("Cargo.toml"), workspace)?;
+    for name in ["core", "extra"] {
+        write_file(
+            &root.join(name).join("Cargo.toml"),
+            &format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"),
+        )?;
+        write_file(&root.join(name).join("src/lib.rs"), "pub fn f() {}\n")?;
+    }
+    write_file(&root.join(".gitignore"), "target/\nCargo.lock\n")?;
+    let repo = init_repo(root, "https://github.com/apache/core.git")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag_lightweight("v0.1.0", head.as_object(), false)?;
+    write_file(
+        &root.join("rust-toolchain.toml"),
+        "[toolchain]\nchannel = \"1.85\"\n",
+    )?;
+    commit_all(&repo, "build: pin the toolchain")?;
+
+    let planned = |config: &str| -> Result<Vec<String>> {
+        write_file(&root.join(".asfship.toml"), config)?;
+        let output = asfship_cmd(root)?
+            .args(["plan", "--format", "json"])
+            .output()?;
+        assert!(
+            output.status.success(),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let plan: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        Ok(plan["crates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap().to_string())
+            .collect())
+    };
+    assert!(planned("")?.is_empty());
+    assert_eq!(planned("root_files = \"main\"\n")?, ["core"]);
+    assert_eq!(planned("root_files = \"all\"\n")?, ["core", "extra"]);
+
+    // Left unattributed, the commit is called out rather than silently dropped.
+    write_file(&root.join(".asfship.toml"), "")?;
+    let output = asfship_cmd(root)?.arg("plan").output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains("unattributed commits (in no changelog):\n  - ")
+            && stdout.contains(" build: pin the toolchain\n"),
+        "{}",
+        stdout
+    );
+    Ok(())
+}
+
 #[test]
 fn prerelease_local_assets_creates_artifacts() -> Result<()> {
     let td = TempDir::new()?;
@@ -257,6 +704,9 @@ edition = "2021"
         output.status,
         String::from_utf8_lossy(&output.stderr)
     );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("artifact sizes:\n  foo: "), "{}", stdout);
+    assert!(stdout.contains("    largest: "), "{}", stdout);
 
     let artifact_root = root.join("target").join("asfship");
     assert!(
@@ -291,5 +741,2152 @@ edition = "2021"
         artifact_root
     );
 
+    let log = fs::read_dir(&artifact_root)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join("asfship.log"))
+        .find(|path| path.exists())
+        .expect("asfship.log in the run directory");
+    let log = fs::read_to_string(log)?;
+    assert!(log.contains("versioning: plan computed"), "{}", log);
+    assert!(
+        log.lines()
+            .any(|line| line.contains(":package_changed_crates:")
+                && line.contains("close time.busy=")),
+        "{}",
+        log
+    );
+
+    Ok(())
+}
+
+#[test]
+fn artifact_dir_prunes_old_run_directories() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\nout/\n")?;
+    write_file(&root.join(".asfship.toml"), "[artifacts]\nkeep = 2\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(&repo, "feat: add g")?;
+
+    let out = root.join("out");
+    write_file(&out.join("v0.0.8-rc.1/old.tar.gz"), "old")?;
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    write_file(&out.join("v0.0.9-rc.1/old.tar.gz"), "old")?;
+    write_file(&out.join("cache/keep.txt"), "not a run")?;
+
+    let output = asfship_cmd(root)?
+        .args(["prerelease", "--local-assets", "--artifact-dir", "out"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        out.join("v0.1.1-rc.1/apache-foo-0.1.1-rc1-src.tar.gz")
+            .is_file()
+    );
+    assert!(out.join("v0.0.9-rc.1").is_dir());
+    assert!(!out.join("v0.0.8-rc.1").exists());
+    assert!(out.join("cache/keep.txt").is_file());
+    // Shared state follows the artifact dir too.
+    assert!(out.join("audit.jsonl").is_file());
+    assert!(out.join("cache/sha512").is_dir());
+    assert!(!root.join("target/asfship").exists());
+    Ok(())
+}
+
+#[test]
+fn later_rcs_report_changes_since_the_previous_rc() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    repo.tag_lightweight(
+        "v0.1.0",
+        &repo.head()?.peel(git2::ObjectType::Commit)?,
+        false,
+    )?;
+    write_file(&root.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(&repo, "feat: add g")?;
+
+    let output = asfship_cmd(root)?
+        .args(["prerelease", "--local-assets"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!String::from_utf8(output.stdout)?.contains("changes since"));
+
+    // rc.1 failed its vote: drop its release commit and land the fix underneath.
+    let before_rc1 = repo.head()?.peel_to_commit()?.parent(0)?;
+    repo.reset(before_rc1.as_object(), git2::ResetType::Hard, None)?;
+    write_file(&root.join("src/new.rs"), "pub fn g() -> u8 { 1 }\n")?;
+    commit_all(&repo, "fix: return a value from g")?;
+    let output = asfship_cmd(root)?
+        .args(["prerelease", "--local-assets"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    let fix = repo.head()?.peel_to_commit()?.parent(0)?.id().to_string()[..7].to_string();
+    assert!(
+        stdout.contains(&format!(
+            "changes since v0.1.1-rc.1: 1 new, 1 carried over\n  - {} fix: return a value from g (foo)\n",
+            fix
+        )),
+        "{}",
+        stdout
+    );
+    let changelog = fs::read_to_string(root.join("CHANGELOG.md"))?;
+    assert!(
+        changelog.contains(&format!(
+            "- fix: return a value from g ({})[^since-v0.1.1-rc.1]\n\n[^since-v0.1.1-rc.1]: New since v0.1.1-rc.1.\n",
+            fix
+        )),
+        "{}",
+        changelog
+    );
+    assert_eq!(changelog.matches("[^since-").count(), 2, "{}", changelog);
+    let report = fs::read_to_string(root.join("target/asfship/v0.1.1-rc.2/REPORT.md"))?;
+    assert!(
+        report.contains(&format!(
+            "### Changes since `v0.1.1-rc.1`\n\n1 new commit(s); 1 carried over from `v0.1.1-rc.1`.\n\n- fix: return a value from g ({}) in `foo`\n",
+            fix
+        )),
+        "{}",
+        report
+    );
+    Ok(())
+}
+
+#[test]
+fn release_identity_comes_from_git_table_and_env() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        "[git]\nauthor_name = \"Release Bot\"\nauthor_email = \"bot@example.com\"\n",
+    )?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(&repo, "feat: add g")?;
+
+    let output = asfship_cmd(root)?
+        .env("ASFSHIP_GIT_AUTHOR_EMAIL", "release@apache.org")
+        .args(["prerelease", "--local-assets"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.author().name(), Some("Release Bot"));
+    assert_eq!(head.author().email(), Some("release@apache.org"));
+    let tag = repo
+        .find_reference("refs/tags/v0.1.1-rc.1")?
+        .peel_to_tag()?;
+    let tagger = tag.tagger().expect("annotated rc tag");
+    assert_eq!(tagger.name(), Some("Release Bot"));
+    assert_eq!(tagger.email(), Some("release@apache.org"));
+    Ok(())
+}
+
+#[test]
+fn unpublished_crates_are_skipped() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+
+    write_file(
+        &root.join("Cargo.toml"),
+        r#"[package]
+name = "foo"
+version = "0.1.0"
+edition = "2021"
+
+[workspace]
+members = ["xtask"]
+"#,
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(
+        &root.join("xtask/Cargo.toml"),
+        r#"[package]
+name = "xtask"
+version = "0.1.0"
+edition = "2021"
+publish = false
+"#,
+    )?;
+    write_file(&root.join("xtask/src/main.rs"), "fn main() {}\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+
+    write_file(&root.join("xtask/src/main.rs"), "fn main() { run() }\n")?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {} pub fn g() {}\n")?;
+    commit_all(&repo, "feat: touch both crates")?;
+
+    let mut cmd = asfship_cmd(root)?;
+    cmd.args(["prerelease", "--dry-run"]);
+    let output = cmd.output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("* foo 0.1.0 -> 0.1.1"));
+    assert!(
+        !stdout.contains("xtask"),
+        "unexpected xtask in:\n{}",
+        stdout
+    );
+    Ok(())
+}
+
+#[test]
+fn config_init_writes_answers_after_validating_them() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("incubator-foo");
+    write_file(
+        &root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"foo-core\", \"foo-cli\"]\n",
+    )?;
+    for name in ["foo-core", "foo-cli"] {
+        write_file(
+            &root.join(name).join("Cargo.toml"),
+            &format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+                name
+            ),
+        )?;
+        write_file(&root.join(name).join("src/lib.rs"), "")?;
+    }
+    init_repo(&root, "https://github.com/apache/incubator-foo.git")?;
+
+    let gnupg = td.path().join("gnupg");
+    fs::create_dir_all(&gnupg)?;
+    let mut cmd = asfship_cmd(&root)?;
+    let output = cmd
+        .env("GNUPGHOME", &gnupg)
+        .args(["config", "init"])
+        // Unknown crate, then foo-cli; default category and project; a too-short vote.
+        .write_stdin("foo-web\nfoo-cli\n\n\n48\n120\n\n")
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "stdout: {}\nstderr: {}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout.contains("foo-web is not a workspace crate"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("at least 72 hours"), "{}", stdout);
+    let config = fs::read_to_string(root.join(".asfship.toml"))?;
+    assert!(config.contains("main_crate = \"foo-cli\"\n"), "{}", config);
+    assert!(config.contains("category = \"Releases\"\n"), "{}", config);
+    assert!(config.contains("# project = \"foo\"\n"), "{}", config);
+    assert!(config.contains("duration_hours = 120\n"), "{}", config);
+
+    let mut again = asfship_cmd(&root)?;
+    let output = again.args(["config", "init"]).write_stdin("").output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+    Ok(())
+}
+
+#[test]
+fn config_typos_fail_instead_of_falling_back_to_inference() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "")?;
+    write_file(&root.join(".asfship.toml"), "main_create = \"foo\"\n")?;
+    init_repo(root, "https://github.com/apache/foo.git")?;
+
+    let output = asfship_cmd(root)?.args(["config", "validate"]).output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown field `main_create`"), "{}", stderr);
+
+    let output = asfship_cmd(root)?.args(["plan"]).output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown field `main_create`"), "{}", stderr);
+
+    write_file(
+        &root.join(".asfship.toml"),
+        "main_crate = \"foo\"\n\n[crates.fooo]\nmax_version = \"1.0.0\"\n",
+    )?;
+    let output = asfship_cmd(root)?.args(["config", "validate"]).output()?;
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "- [crates.fooo] names \"fooo\", which is not a workspace crate (crates: foo)"
+        ),
+        "{}",
+        stdout
+    );
+
+    write_file(&root.join(".asfship.toml"), "main_crate = \"foo\"\n")?;
+    let output = asfship_cmd(root)?.args(["config", "validate"]).output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is valid"));
+    Ok(())
+}
+
+#[test]
+fn only_releases_a_subset_of_the_workspace() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+
+    write_file(
+        &root.join("Cargo.toml"),
+        r#"[package]
+name = "foo"
+version = "0.1.0"
+edition = "2021"
+
+[workspace]
+members = ["bar", "qux"]
+"#,
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(
+        &root.join("bar/Cargo.toml"),
+        r#"[package]
+name = "bar"
+version = "0.3.0"
+edition = "2021"
+"#,
+    )?;
+    write_file(&root.join("bar/src/lib.rs"), "pub fn b() {}\n")?;
+    write_file(
+        &root.join("qux/Cargo.toml"),
+        "[package]\nname = \"qux\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("qux/src/lib.rs"), "pub fn q() {}\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        "[crates.bar]\ntag_prefix = \"bar-v\"\n",
+    )?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+
+    write_file(
+        &root.join("bar/src/lib.rs"),
+        "pub fn b() {} pub fn c() {}\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {} pub fn g() {}\n")?;
+    commit_all(&repo, "feat: touch both crates")?;
+
+    let mut cmd = asfship_cmd(root)?;
+    cmd.args(["prerelease", "--dry-run", "--only", "bar"]);
+    let output = cmd.output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("* bar 0.3.0 -> 0.3.1"), "{}", stdout);
+    assert!(stdout.contains("main crate: bar"), "{}", stdout);
+    // bar has its own tag series as a component.
+    assert!(stdout.contains("rc tag: bar-v0.3.1-rc.1"), "{}", stdout);
+    assert!(!stdout.contains("* foo"), "{}", stdout);
+
+    // Several crates without the main one leave no crate to name the release after.
+    let mut cmd = asfship_cmd(root)?;
+    cmd.args(["plan", "--only", "bar,qux"]);
+    let output = cmd.output()?;
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains(
+            "only lists bar, qux but not the main crate foo; list foo too, or release one component at a time"
+        ),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let mut cmd = asfship_cmd(root)?;
+    cmd.args(["plan", "--only", "baz"]);
+    let output = cmd.output()?;
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("only lists baz which is not a workspace crate")
+    );
+    Ok(())
+}
+
+#[test]
+fn prerelease_refreshes_lockfile() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+
+    write_file(
+        &root.join("Cargo.toml"),
+        r#"[package]
+name = "foo"
+version = "0.1.0"
+edition = "2021"
+"#,
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(
+        &root.join("Cargo.lock"),
+        r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 4
+
+[[package]]
+name = "foo"
+version = "0.1.0"
+"#,
+    )?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+
+    write_file(&root.join("src/lib.rs"), "pub fn f() {} pub fn g() {}\n")?;
+    commit_all(&repo, "fix: adjust f")?;
+
+    let mut cmd = asfship_cmd(root)?;
+    cmd.args(["prerelease", "--local-assets"]);
+    cmd.assert().success();
+
+    let lock = fs::read_to_string(root.join("Cargo.lock"))?;
+    assert!(lock.contains("version = \"0.1.1\""), "lockfile:\n{}", lock);
+    let head = repo.head()?.peel_to_commit()?;
+    let blob = head
+        .tree()?
+        .get_path(Path::new("Cargo.lock"))?
+        .to_object(&repo)?;
+    let committed = String::from_utf8(blob.as_blob().unwrap().content().to_vec())?;
+    assert!(committed.contains("version = \"0.1.1\""));
+    Ok(())
+}
+
+#[test]
+fn prerelease_rejects_stale_internal_requirements() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+
+    write_file(
+        &root.join("Cargo.toml"),
+        r#"[package]
+name = "foo"
+version = "0.1.0"
+edition = "2021"
+
+[workspace]
+members = ["bar"]
+"#,
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(
+        &root.join("bar/Cargo.toml"),
+        r#"[package]
+name = "bar"
+version = "0.2.0"
+edition = "2021"
+
+[target.'cfg(unix)'.dependencies]
+foo = { path = "..", version = "=0.1.0" }
+"#,
+    )?;
+    write_file(&root.join("bar/src/lib.rs"), "pub fn b() {}\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+
+    write_file(&root.join("src/lib.rs"), "pub fn f() {} pub fn g() {}\n")?;
+    commit_all(&repo, "fix: adjust f")?;
+
+    let output = asfship_cmd(root)?
+        .args(["prerelease", "--local-assets"])
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "Cargo.toml: [target.'cfg(unix)'.dependencies] foo: requirement \"=0.1.0\" does not match foo 0.1.1"
+        ),
+        "{}",
+        stderr
+    );
+    Ok(())
+}
+
+#[test]
+fn isolated_prerelease_only_fast_forwards_on_success() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+
+    write_file(
+        &root.join("Cargo.toml"),
+        r#"[package]
+name = "foo"
+version = "0.1.0"
+edition = "2021"
+
+[workspace]
+members = ["bar"]
+"#,
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    let stale = r#"[package]
+name = "bar"
+version = "0.2.0"
+edition = "2021"
+
+[target.'cfg(unix)'.dependencies]
+foo = { path = "..", version = "=0.1.0" }
+"#;
+    write_file(&root.join("bar/Cargo.toml"), stale)?;
+    write_file(&root.join("bar/src/lib.rs"), "pub fn b() {}\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {} pub fn g() {}\n")?;
+    commit_all(&repo, "fix: adjust f")?;
+    let before = repo.head()?.target();
+
+    let output = asfship_cmd(root)?
+        .args(["prerelease", "--local-assets", "--isolated"])
+        .output()?;
+    assert!(!output.status.success());
+    assert_eq!(repo.head()?.target(), before);
+    assert_eq!(read_version(&root.join("Cargo.toml")), "0.1.0");
+    // Only files Cargo and asfship create on their own, no release edits.
+    let modified: Vec<String> = repo
+        .statuses(None)?
+        .iter()
+        .filter(|s| !s.status().is_wt_new())
+        .filter_map(|s| s.path().map(str::to_string))
+        .collect();
+    assert!(modified.is_empty(), "{:?}", modified);
+
+    write_file(
+        &root.join("bar/Cargo.toml"),
+        &stale.replace("=0.1.0", "0.1"),
+    )?;
+    commit_all(&repo, "chore: relax the foo requirement")?;
+    asfship_cmd(root)?
+        .args(["prerelease", "--local-assets", "--isolated"])
+        .assert()
+        .success();
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.summary(), Some("chore(release): prepare v0.1.1"));
+    assert_eq!(read_version(&root.join("Cargo.toml")), "0.1.1");
+    assert!(repo.revparse_single("refs/tags/v0.1.1-rc.1").is_ok());
+    assert_eq!(repo.worktrees()?.len(), 0);
+    Ok(())
+}
+
+#[test]
+fn dry_run_reuses_plan_cache() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        r#"[package]
+name = "foo"
+version = "0.1.0"
+edition = "2021"
+"#,
+    )?;
+    write_file(&root.join(".gitignore"), "target/\nCargo.lock\n")?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {} pub fn g() {}\n")?;
+    commit_all(&repo, "feat: add g")?;
+
+    let run = || -> Result<String> {
+        let output = asfship_cmd(root)?
+            .args(["prerelease", "--dry-run"])
+            .output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    let first = run()?;
+    let cache_dir = root.join("target/asfship/plan-cache");
+    let entries: Vec<_> = fs::read_dir(&cache_dir)?.collect();
+    assert_eq!(
+        entries.len(),
+        1,
+        "expected one cache file in {:?}",
+        cache_dir
+    );
+    assert_eq!(first, run()?);
+
+    // A second run takes the commit analysis from the cache file instead of the repository.
+    assert!(first.contains("add g"), "stdout:\n{}", first);
+    let cache_file = entries.into_iter().next().unwrap()?.path();
+    let cached = fs::read_to_string(&cache_file)?;
+    assert!(cached.contains("\"feat: add g\""), "cache:\n{}", cached);
+    fs::write(
+        &cache_file,
+        cached.replace("\"feat: add g\"", "\"feat: add g from the cache\""),
+    )?;
+    let cached_run = run()?;
+    assert!(
+        cached_run.contains("add g from the cache"),
+        "stdout:\n{}",
+        cached_run
+    );
+
+    // Commits outside the planned range drop out of the cache.
+    assert!(
+        cached.contains("\"subject\":\"init\""),
+        "cache:\n{}",
+        cached
+    );
+    let init = repo.head()?.peel_to_commit()?.parent_id(0)?;
+    let output = asfship_cmd(root)?
+        .args(["prerelease", "--dry-run", "--since", &init.to_string()])
+        .output()?;
+    assert!(output.status.success());
+    let pruned = fs::read_to_string(&cache_file)?;
+    assert!(
+        !pruned.contains("\"subject\":\"init\""),
+        "cache:\n{}",
+        pruned
+    );
+    assert!(
+        pruned.contains("add g from the cache"),
+        "cache:\n{}",
+        pruned
+    );
+    Ok(())
+}
+
+#[test]
+fn retract_dry_run_lists_released_crates() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"foo\", \"bar\"]\nresolver = \"2\"\n",
+    )?;
+    for name in ["foo", "bar"] {
+        write_file(
+            &root.join(name).join("Cargo.toml"),
+            &format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"),
+        )?;
+        write_file(&root.join(name).join("src/lib.rs"), "pub fn f() {}\n")?;
+    }
+    write_file(&root.join(".gitignore"), "target/\nCargo.lock\n")?;
+    let repo = init_repo(root, "https://github.com/apache/foo.git")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag_lightweight("v0.1.0", head.as_object(), false)?;
+
+    write_file(
+        &root.join("foo/Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.2.0\"\nedition = \"2021\"\n",
+    )?;
+    commit_all(&repo, "chore(release): prepare v0.2.0")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag_lightweight("v0.2.0", head.as_object(), false)?;
+
+    let output = asfship_cmd(root)?
+        .args([
+            "retract",
+            "0.2.0",
+            "--reason",
+            "corrupted archive",
+            "--dry-run",
+        ])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("- yank foo 0.2.0 from crates.io"));
+    assert!(!stdout.contains("yank bar"));
+    assert!(stdout.contains("svn rm https://dist.apache.org/repos/dist/release/foo/0.2.0"));
+    assert!(stdout.contains("Reason:\ncorrupted archive"));
+    Ok(())
+}
+
+#[test]
+fn publish_dry_run_orders_by_dependency() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"core\", \"http\", \"app\"]\nresolver = \"2\"\n",
+    )?;
+    let manifests = |version: &str| {
+        [
+            ("core", String::new()),
+            (
+                "http",
+                format!(
+                    "[dependencies]\ncore = {{ path = \"../core\", version = \"{version}\" }}\n"
+                ),
+            ),
+            (
+                "app",
+                format!(
+                    "[dependencies]\ncore = {{ path = \"../core\", version = \"{version}\" }}\n\n[dev-dependencies]\nhttp = {{ path = \"../http\" }}\n"
+                ),
+            ),
+        ]
+    };
+    for (name, deps) in manifests("0.1.0") {
+        write_file(
+            &root.join(name).join("Cargo.toml"),
+            &format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n{deps}"
+            ),
+        )?;
+        write_file(&root.join(name).join("src/lib.rs"), "pub fn f() {}\n")?;
+    }
+    write_file(&root.join(".gitignore"), "target/\nCargo.lock\n")?;
+    let repo = init_repo(root, "https://github.com/apache/core.git")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag_lightweight("v0.1.0", head.as_object(), false)?;
+
+    for (name, deps) in manifests("0.2.0") {
+        write_file(
+            &root.join(name).join("Cargo.toml"),
+            &format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.2.0\"\nedition = \"2021\"\n\n{deps}"
+            ),
+        )?;
+    }
+    commit_all(&repo, "chore(release): prepare v0.2.0")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag_lightweight("v0.2.0", head.as_object(), false)?;
+
+    let output = asfship_cmd(root)?
+        .args(["publish", "0.2.0", "--jobs", "2", "--dry-run"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("- level 1: core 0.2.0\n"), "{}", stdout);
+    assert!(
+        stdout.contains("- level 2: http 0.2.0, app 0.2.0")
+            || stdout.contains("- level 2: app 0.2.0, http 0.2.0"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("- then wait up to 30 minutes for docs.rs builds\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("publish: dry-run, nothing published"));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn publish_and_check_published_against_mock_crates_io() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("foo");
+    let manifest = |version: &str| {
+        format!(
+            "[package]\nname = \"foo\"\nversion = \"{version}\"\nedition = \"2021\"\nlicense = \"Apache-2.0\"\ndescription = \"foo\"\n"
+        )
+    };
+    write_file(&root.join("Cargo.toml"), &manifest("0.1.0"))?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\nCargo.lock\n")?;
+    let repo = init_repo(&root, "https://github.com/apache/foo.git")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag_lightweight("v0.1.0", head.as_object(), false)?;
+    write_file(&root.join("Cargo.toml"), &manifest("0.2.0"))?;
+    commit_all(&repo, "chore(release): prepare v0.2.0")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag_lightweight("v0.2.0", head.as_object(), false)?;
+
+    let server = mock::MockServer::start(&td.path().join("dist"));
+    let registry = td.path().join("registry");
+    let bin = td.path().join("bin");
+    let cargo_log = td.path().join("cargo.log");
+    mock::install_fake_cargo(&bin, &registry, &cargo_log);
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let output = |args: &[&str]| -> Result<std::process::Output> {
+        let mut cmd = asfship_cmd(&root)?;
+        for var in ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"] {
+            cmd.env_remove(var);
+        }
+        Ok(cmd
+            .env("ASFSHIP_CRATES_IO_URL", server.crates_io(&registry))
+            .env("ASFSHIP_DOCS_RS_URL", server.docs_rs())
+            .env("NO_PROXY", "127.0.0.1")
+            .env("PATH", &path)
+            .args(args)
+            .output()?)
+    };
+
+    let missing = output(&["check-published", "0.2.0"])?;
+    assert!(!missing.status.success());
+    let stdout = String::from_utf8(missing.stdout)?;
+    assert!(
+        stdout.contains("- foo 0.2.0: missing on crates.io\n"),
+        "{}",
+        stdout
+    );
+
+    let published = output(&["publish", "0.2.0", "--docs-timeout", "1"])?;
+    let stdout = String::from_utf8(published.stdout)?;
+    assert!(
+        published.status.success(),
+        "stdout: {}\nstderr: {}\nrequests: {:?}",
+        stdout,
+        String::from_utf8_lossy(&published.stderr),
+        server.requests()
+    );
+    assert!(stdout.contains("- published foo 0.2.0\n"), "{}", stdout);
+    assert!(stdout.contains("- foo 0.2.0: ok (sha256="), "{}", stdout);
+    assert!(
+        stdout.contains("- foo 0.2.0: docs.rs built\n"),
+        "{}",
+        stdout
+    );
+    assert!(registry.join("foo-0.2.0.crate").is_file());
+    assert_eq!(
+        fs::read_to_string(&cargo_log)?.lines().collect::<Vec<_>>(),
+        [format!(
+            "publish -p foo --target-dir {}",
+            root.join("target/asfship/publish/foo").display()
+        )]
+    );
+    assert!(
+        server
+            .requests()
+            .contains(&"GET /crates-io/index/3/f/foo".to_string())
+    );
+
+    let checked = output(&["check-published", "0.2.0"])?;
+    let stdout = String::from_utf8(checked.stdout)?;
+    assert!(checked.status.success(), "{}", stdout);
+    assert!(stdout.contains("- foo 0.2.0: ok (sha256="), "{}", stdout);
+    assert!(stdout.contains("  docs.rs: built\n"), "{}", stdout);
+
+    let again = output(&["publish", "0.2.0"])?;
+    assert!(!again.status.success());
+    assert!(
+        String::from_utf8_lossy(&again.stderr)
+            .contains("foo 0.2.0 is already on crates.io (ok (sha256="),
+        "{}",
+        String::from_utf8_lossy(&again.stderr)
+    );
+    assert_eq!(fs::read_to_string(&cargo_log)?.lines().count(), 1);
+    Ok(())
+}
+
+#[test]
+fn git_dir_and_repo_override_work_outside_checkout() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("checkout");
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn _noop() {}\n")?;
+    let _repo = init_repo(&root, "https://gitbox.apache.org/repos/asf/foo.git")?;
+    let elsewhere = td.path().join("elsewhere");
+    fs::create_dir_all(&elsewhere)?;
+
+    let output = asfship_cmd(&elsewhere)?
+        .args(["start", "--dry-run", "--git-dir"])
+        .arg(&root)
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unsupported remote URL"));
+
+    let output = asfship_cmd(&elsewhere)?
+        .args(["start", "--dry-run", "--repo", "apache/foo", "--git-dir"])
+        .arg(&root)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8(output.stdout)?.contains("title=foo Release Kickoff"));
+    Ok(())
+}
+
+#[test]
+fn plan_leaves_shallow_clones_shallow() -> Result<()> {
+    let td = TempDir::new()?;
+    let origin = td.path().join("origin");
+    write_file(
+        &origin.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&origin.join("src/lib.rs"), "pub fn f() {}\n")?;
+    let repo = init_repo(&origin, "https://github.com/apache/foo.git")?;
+    write_file(&origin.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(&repo, "feat: add g")?;
+    let root = td.path().join("shallow");
+    let status = std::process::Command::new("git")
+        .args(["clone", "--quiet", "--depth", "1"])
+        .arg(format!("file://{}", origin.display()))
+        .arg(&root)
+        .status()?;
+    assert!(status.success());
+
+    let output = asfship_cmd(&root)?
+        .args(["plan", "--repo", "apache/foo"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("not fetching history in a read-only run"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(Repository::open(&root)?.is_shallow());
+    Ok(())
+}
+
+#[test]
+fn plan_reads_bare_mirrors() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("checkout");
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    let repo = init_repo(&root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(&repo, "feat: add g")?;
+    let mirror = td.path().join("foo.git");
+    let bare = git2::build::RepoBuilder::new()
+        .bare(true)
+        .clone(root.to_str().unwrap(), &mirror)?;
+    bare.remote_set_url("origin", "https://github.com/apache/foo.git")?;
+    let elsewhere = td.path().join("elsewhere");
+    fs::create_dir_all(&elsewhere)?;
+
+    let output = asfship_cmd(&elsewhere)?
+        .args(["plan", "--git-dir"])
+        .arg(&mirror)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("* foo 0.1.0 -> 0.1.1"), "{}", stdout);
+
+    let output = asfship_cmd(&elsewhere)?
+        .env("GIT_DIR", &mirror)
+        .arg("plan")
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8(output.stdout)?.contains("* foo 0.1.0 -> 0.1.1"));
+    assert!(
+        mirror
+            .join("target")
+            .join("asfship")
+            .join("plan-cache")
+            .is_dir()
+    );
+
+    let output = asfship_cmd(&elsewhere)?
+        .args(["plan", "--format", "changelog", "--git-dir"])
+        .arg(&mirror)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.starts_with("## foo v0.1.1 - "), "{}", stdout);
+    assert!(stdout.contains("add g"), "{}", stdout);
+
+    let output = asfship_cmd(&elsewhere)?
+        .args(["status", "--git-dir"])
+        .arg(&mirror)
+        .output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "no release steps recorded yet in {}",
+            mirror.join("target").display()
+        )),
+        "{}",
+        stderr
+    );
+
+    let output = asfship_cmd(&elsewhere)?
+        .args(["start", "--dry-run", "--git-dir"])
+        .arg(&mirror)
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is a bare repository"));
+    Ok(())
+}
+
+#[test]
+fn chdir_flag_supports_git_worktrees() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("main");
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn _noop() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\n")?;
+    let repo = init_repo(&root, "https://github.com/apache/foo.git")?;
+    let wt = td.path().join("release-0.1");
+    repo.worktree("release-0.1", &wt, None)?;
+
+    let output = asfship_cmd(td.path())?
+        .arg("-C")
+        .arg(&wt)
+        .args(["start", "--dry-run"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8(output.stdout)?.contains("title=foo Release Kickoff"));
+    Ok(())
+}
+
+#[test]
+fn preview_writes_rendered_template() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = &td.path().join("repo");
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn _noop() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\nCargo.lock\n")?;
+    let _repo = init_repo(root, "https://github.com/apache/foo.git")?;
+
+    let output = asfship_cmd(root)?.args(["preview", "start"]).output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.starts_with("preview: title=foo Release Kickoff\n---\n# foo Release Kickoff"));
+
+    let output = asfship_cmd(root)?
+        .args(["preview", "start", "-o"])
+        .arg(td.path().join("kickoff.md"))
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let written = fs::read_to_string(td.path().join("kickoff.md"))?;
+    assert!(written.starts_with("# foo Release Kickoff"));
+    assert!(written.contains("- foo 0.1.0"));
+
+    let output = asfship_cmd(root)?.args(["preview", "changelog"]).output()?;
+    assert!(!output.status.success());
+    Ok(())
+}
+
+#[test]
+fn repository_templates_override_shared_partials() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn _noop() {}\n")?;
+    write_file(
+        &root.join(".asfship/templates/partials/header.md"),
+        "Apache {{ repo }} is a data access layer.\n\n",
+    )?;
+    write_file(
+        &root.join(".asfship/templates/partials/footer.md"),
+        "\n\nVerify releases with `asfship verify`.\n",
+    )?;
+    let _repo = init_repo(root, "https://github.com/apache/foo.git")?;
+
+    let output = asfship_cmd(root)?.args(["preview", "start"]).output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout
+            .contains("# foo Release Kickoff\n\nApache foo is a data access layer.\n\n- Base tag:"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("first release candidate.\n\nVerify releases with `asfship verify`.\n"),
+        "{}",
+        stdout
+    );
+
+    write_file(
+        &root.join(".asfship/templates/start.md"),
+        "{% extends \"default/start.md\" %}{% block footer %}{% endblock %",
+    )?;
+    let output = asfship_cmd(root)?.args(["config", "validate"]).output()?;
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("invalid templates in .asfship/templates"),
+        "stdout: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    Ok(())
+}
+
+#[test]
+fn release_gate_label_requires_issue() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn _noop() {}\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        "[release.gate]\nlabel = \"release-approved\"\n",
+    )?;
+    let _repo = init_repo(root, "https://github.com/apache/foo.git")?;
+
+    let output = asfship_cmd(root)?.args(["start", "--dry-run"]).output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("requires release.gate.issue"));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn audit_log_is_commented_on_the_kickoff_discussion() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("foo");
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        "[discussion]\naudit_comment = true\n",
+    )?;
+    init_repo(&root, "https://github.com/apache/foo.git")?;
+    // An entry another run appended, timestamped after this run starts; only the run id
+    // keeps it out of this run's comment.
+    write_file(
+        &root.join("target/asfship/audit.jsonl"),
+        "{\"schema_version\":1,\"timestamp\":\"2099-01-01T00:00:00Z\",\"run\":\"other\",\"actor\":\"asfship\",\"action\":\"tag_created\",\"target\":\"v0.0.9-rc.1\"}\n",
+    )?;
+
+    let server = mock::MockServer::start(&td.path().join("dist"));
+    let mut cmd = asfship_cmd(&root)?;
+    for var in ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"] {
+        cmd.env_remove(var);
+    }
+    let output = cmd
+        .env("ASFSHIP_GITHUB_TOKEN", "mock-github-token")
+        .env("ASFSHIP_GITHUB_API_URL", server.github_api())
+        .env("NO_PROXY", "127.0.0.1")
+        .args(["start", "--repo", "apache/foo"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let discussions = server.discussions();
+    assert_eq!(discussions[0]["title"], "foo Release Kickoff");
+    let comment = discussions[0]["comments"][0]["body"].as_str().unwrap();
+    assert_eq!(
+        comment,
+        "## asfship audit log\n\n- `discussion_created https://github.com/apache/foo/discussions/1`\n"
+    );
+    let log = fs::read_to_string(root.join("target/asfship/audit.jsonl"))?;
+    assert!(
+        log.contains("\"action\":\"discussion_commented\""),
+        "{}",
+        log
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn preflight_reports_the_release_gate() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("foo");
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        "[release.gate]\nenvironment = \"release\"\nissue = 7\nlabel = \"release-approved\"\n",
+    )?;
+    let repo = init_repo(&root, "https://github.com/apache/foo.git")?;
+
+    let server = mock::MockServer::start(&td.path().join("dist"));
+    let stderr = || -> Result<String> {
+        let mut cmd = asfship_cmd(&root)?;
+        for var in ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"] {
+            cmd.env_remove(var);
+        }
+        let output = cmd
+            .env("ASFSHIP_GITHUB_TOKEN", "mock-github-token")
+            .env("ASFSHIP_GITHUB_API_URL", server.github_api())
+            .env("NO_PROXY", "127.0.0.1")
+            .args(["release", "--dry-run", "--repo", "apache/foo"])
+            .output()?;
+        Ok(String::from_utf8(output.stderr)?)
+    };
+    // The mock has no environments, so the first check reports that and stops.
+    let out = stderr()?;
+    assert!(
+        out.contains("warning: release gate: environment `release` is missing or not readable"),
+        "{}",
+        out
+    );
+
+    fs::write(
+        root.join(".asfship.toml"),
+        "[release.gate]\nissue = 7\nlabel = \"release-approved\"\n",
+    )?;
+    commit_all(&repo, "chore: gate on the tracking issue only")?;
+    let out = stderr()?;
+    assert!(
+        out.contains("warning: release gate: tracking issue https://github.com/apache/foo/issues/7 does not carry the `release-approved` label yet"),
+        "{}",
+        out
+    );
+
+    server.set_labels(7, &["release-approved"]);
+    let out = stderr()?;
+    assert!(!out.contains("release gate"), "{}", out);
+    Ok(())
+}
+
+#[test]
+fn github_app_auth_needs_a_private_key() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path();
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        "[github]\nauth = \"app\"\napp_id = 42\n",
+    )?;
+    init_repo(root, "https://github.com/apache/foo.git")?;
+
+    let output = asfship_cmd(root)?.arg("plan").output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("needs exactly one of github.private_key or github.private_key_env"),
+        "{}",
+        stderr
+    );
+
+    write_file(
+        &root.join(".asfship.toml"),
+        "[github]\nauth = \"app\"\napp_id = 42\nprivate_key = \"app.pem\"\n",
+    )?;
+    write_file(&root.join("app.pem"), "not a key\n")?;
+    let output = asfship_cmd(root)?.arg("plan").output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("GitHub App private key is not an RSA PEM key"),
+        "{}",
+        stderr
+    );
+    Ok(())
+}
+
+#[test]
+fn tag_command_creates_and_pushes_tags_idempotently() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("foo");
+    let origin = td.path().join("origin.git");
+    Repository::init_bare(&origin)?;
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.2.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\nCargo.lock\n")?;
+    let repo = init_repo(&root, origin.to_str().unwrap())?;
+    let tag = |args: &[&str]| -> Result<String> {
+        let output = asfship_cmd(&root)?
+            .args(["--repo", "apache/foo", "tag"])
+            .args(args)
+            .output()?;
+        assert!(
+            output.status.success(),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    tag(&["rc", "--no-push"])?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\npub fn g() {}\n")?;
+    commit_all(&repo, "fix: add g")?;
+    let stdout = tag(&["rc", "--no-push"])?;
+    assert!(stdout.contains("created v0.2.0-rc.2"), "{}", stdout);
+    let rc2 = repo.revparse_single("v0.2.0-rc.2")?.peel_to_commit()?.id();
+
+    let stdout = tag(&["stable"])?;
+    assert!(stdout.contains("created v0.2.0 on"), "{}", stdout);
+    let stdout = tag(&["stable", "--from-rc", "v0.2.0-rc.2"])?;
+    assert!(stdout.contains("v0.2.0 already points at"), "{}", stdout);
+    let pushed = Repository::open_bare(&origin)?
+        .revparse_single("refs/tags/v0.2.0")?
+        .peel_to_commit()?
+        .id();
+    assert_eq!(pushed, rc2);
+
+    let output = asfship_cmd(&root)?
+        .args([
+            "--repo",
+            "apache/foo",
+            "tag",
+            "stable",
+            "--from-rc",
+            "v0.2.0-rc.1",
+        ])
+        .output()?;
+    assert!(!output.status.success());
+    Ok(())
+}
+
+#[test]
+fn tag_command_tags_the_worktree_head() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("foo");
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.2.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\nCargo.lock\n")?;
+    let repo = init_repo(&root, "https://github.com/apache/foo.git")?;
+    let main_head = repo.head()?.peel_to_commit()?.id();
+
+    let wt_dir = td.path().join("wt");
+    repo.worktree("wt", &wt_dir, None)?;
+    let wt = Repository::open(&wt_dir)?;
+    write_file(&wt_dir.join("src/lib.rs"), "pub fn f() {}\npub fn g() {}\n")?;
+    commit_all(&wt, "fix: add g")?;
+    let wt_head = wt.head()?.peel_to_commit()?.id();
+    assert_ne!(wt_head, main_head);
+
+    let output = asfship_cmd(&wt_dir)?
+        .args(["--repo", "apache/foo", "tag", "rc", "--no-push"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let tagged = repo.revparse_single("v0.2.0-rc.1")?.peel_to_commit()?.id();
+    assert_eq!(tagged, wt_head);
+
+    let output = asfship_cmd(&wt_dir)?
+        .args(["--repo", "apache/foo", "tag", "stable", "--no-push"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stable = repo.revparse_single("v0.2.0")?.peel_to_commit()?.id();
+    assert_eq!(stable, wt_head);
+    Ok(())
+}
+
+#[test]
+fn tag_command_signs_rc_and_stable_tags_with_the_configured_key() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("foo");
+    let gnupg = td.path().join("gnupg");
+    fs::create_dir_all(&gnupg)?;
+    let status = std::process::Command::new("gpg")
+        .env("GNUPGHOME", &gnupg)
+        .args(["--batch", "--passphrase", "", "--quick-gen-key"])
+        .args(["Alice <alice@apache.org>", "ed25519", "sign", "never"])
+        .output()?
+        .status;
+    assert!(status.success());
+    let listing = std::process::Command::new("gpg")
+        .env("GNUPGHOME", &gnupg)
+        .args(["--list-secret-keys", "--with-colons"])
+        .output()?;
+    let fingerprint = String::from_utf8(listing.stdout)?
+        .lines()
+        .find_map(|l| l.strip_prefix("fpr:::::::::"))
+        .map(|l| l.trim_end_matches(':').to_string())
+        .unwrap();
+
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.2.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\nCargo.lock\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        &format!(
+            "[asf]\nrelease_manager = \"alice\"\ngpg_fingerprint = {:?}\n",
+            fingerprint
+        ),
+    )?;
+    let repo = init_repo(&root, "https://github.com/apache/foo.git")?;
+
+    let rc = asfship_cmd(&root)?
+        .env("GNUPGHOME", &gnupg)
+        .args(["--repo", "apache/foo", "tag", "rc", "--no-push"])
+        .output()?;
+    let stable = asfship_cmd(&root)?
+        .env("GNUPGHOME", &gnupg)
+        .args(["--repo", "apache/foo", "tag", "stable", "--no-push"])
+        .output()?;
+    let _ = std::process::Command::new("gpgconf")
+        .env("GNUPGHOME", &gnupg)
+        .args(["--kill", "gpg-agent"])
+        .status();
+    for (output, name) in [(rc, "v0.2.0-rc.1"), (stable, "v0.2.0")] {
+        assert!(
+            output.status.success(),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let tag = repo.revparse_single(name)?.peel_to_tag()?;
+        let message = tag.message().unwrap_or_default();
+        assert!(
+            message.contains("-----BEGIN PGP SIGNATURE-----"),
+            "{}",
+            message
+        );
+        assert_eq!(tag.tagger().unwrap().email(), Some("alice@apache.org"));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn pull_request_labels_override_the_plan() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("foo");
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        "[changelog.labels]\nenabled = true\nkinds = { bug = \"fix\" }\n",
+    )?;
+    let repo = init_repo(&root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/a.rs"), "pub fn a() {}\n")?;
+    commit_all(&repo, "Rework the reader (#3)")?;
+    write_file(&root.join("src/b.rs"), "pub fn b() {}\n")?;
+    commit_all(&repo, "Fix flaky CI (#4)")?;
+    write_file(&root.join("src/c.rs"), "pub fn c() {}\n")?;
+    commit_all(&repo, "feat: add c (#5)")?;
+
+    let server = mock::MockServer::start(&td.path().join("dist"));
+    server.set_labels(3, &["breaking-change"]);
+    server.set_labels(4, &["bug", "skip-changelog"]);
+    server.set_labels(5, &["highlight"]);
+    let mut cmd = asfship_cmd(&root)?;
+    for var in ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"] {
+        cmd.env_remove(var);
+    }
+    let output = cmd
+        .env("ASFSHIP_GITHUB_TOKEN", "mock-github-token")
+        .env("ASFSHIP_GITHUB_API_URL", server.github_api())
+        .env("NO_PROXY", "127.0.0.1")
+        .args(["plan", "--format", "json", "--repo", "apache/foo"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let plan: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let foo = &plan["crates"][0];
+    assert_eq!(foo["next"], "0.2.0");
+    assert_eq!(
+        foo["trigger"]["classified_by"],
+        "pull request label `breaking-change`"
+    );
+    let commits = foo["commits"].as_array().unwrap();
+    let subjects: Vec<&str> = commits
+        .iter()
+        .map(|c| c["subject"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        subjects,
+        [
+            "feat: add c (#5)",
+            "init",
+            "Rework the reader (#3)",
+            "Fix flaky CI (#4)"
+        ]
+    );
+    assert_eq!(commits[0]["highlight"], true);
+    assert_eq!(commits[3]["kind"], "fix");
+    assert_eq!(commits[3]["in_changelog"], false);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn github_app_tokens_are_renewed_before_each_request() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("foo");
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\napp.pem\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        "[github]\nauth = \"app\"\napp_id = 42\nprivate_key = \"app.pem\"\n\n[changelog.labels]\nenabled = true\n",
+    )?;
+    let status = std::process::Command::new("openssl")
+        .args(["genrsa", "-out"])
+        .arg(root.join("app.pem"))
+        .arg("2048")
+        .output()?
+        .status;
+    assert!(status.success(), "openssl genrsa failed");
+    let repo = init_repo(&root, "https://github.com/apache/foo.git")?;
+    write_file(&root.join("src/a.rs"), "pub fn a() {}\n")?;
+    commit_all(&repo, "Rework the reader (#3)")?;
+    write_file(&root.join("src/b.rs"), "pub fn b() {}\n")?;
+    commit_all(&repo, "Fix flaky CI (#4)")?;
+
+    let server = mock::MockServer::start(&td.path().join("dist"));
+    let mut cmd = asfship_cmd(&root)?;
+    for var in ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"] {
+        cmd.env_remove(var);
+    }
+    let output = cmd
+        .env("ASFSHIP_GITHUB_API_URL", server.github_api())
+        .env("NO_PROXY", "127.0.0.1")
+        .args(["plan", "--format", "json", "--repo", "apache/foo"])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let requests = server.requests();
+    let count = |needle: &str| requests.iter().filter(|r| r.as_str() == needle).count();
+    assert_eq!(count("GET /repos/apache/foo/installation"), 1);
+    let labels = requests.iter().filter(|r| r.contains("/labels")).count();
+    assert!(labels >= 2, "requests: {:?}", requests);
+    assert_eq!(
+        count("POST /app/installations/7/access_tokens"),
+        labels,
+        "requests: {:?}",
+        requests
+    );
+    Ok(())
+}
+
+#[test]
+fn prerelease_refuses_heads_origin_does_not_have() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("foo");
+    let origin = td.path().join("origin.git");
+    Repository::init_bare(&origin)?;
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\n")?;
+    let repo = init_repo(&root, origin.to_str().unwrap())?;
+    write_file(&root.join("src/new.rs"), "pub fn g() {}\n")?;
+    commit_all(&repo, "feat: add g")?;
+    let branch = repo.head()?.shorthand().unwrap().to_string();
+    // The checks run before any GitHub call, so a placeholder token is enough.
+    let prerelease = || -> Result<String> {
+        let output = asfship_cmd(&root)?
+            .env("ASFSHIP_GITHUB_TOKEN", "placeholder")
+            .args(["--repo", "apache/foo", "prerelease"])
+            .output()?;
+        assert!(!output.status.success());
+        Ok(String::from_utf8(output.stderr)?)
+    };
+
+    let stderr = prerelease()?;
+    assert!(
+        stderr.contains(&format!(
+            "branch {} does not track a remote branch; asfship pushes to origin, so run `git push -u origin {}` first",
+            branch, branch
+        )),
+        "{}",
+        stderr
+    );
+
+    push_upstream(&repo)?;
+    write_file(&root.join("src/more.rs"), "pub fn h() {}\n")?;
+    commit_all(&repo, "feat: add h")?;
+    let stderr = prerelease()?;
+    assert!(
+        stderr.contains(&format!(
+            "{} has 1 commit(s) not on origin/{}",
+            branch, branch
+        )),
+        "{}",
+        stderr
+    );
+
+    let head = repo.head()?.target().unwrap();
+    repo.set_head_detached(head)?;
+    let stderr = prerelease()?;
+    assert!(
+        stderr.contains(&format!("HEAD is detached at {}", head)),
+        "{}",
+        stderr
+    );
+    assert!(repo.tag_names(None)?.is_empty());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn prerelease_sync_vote_release_against_mock_services() -> Result<()> {
+    let td = TempDir::new()?;
+    let root = td.path().join("foo");
+    let origin = td.path().join("origin.git");
+    Repository::init_bare(&origin)?;
+    write_file(
+        &root.join("Cargo.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\n")?;
+    write_file(&root.join(".gitignore"), "target/\n")?;
+    write_file(
+        &root.join(".asfship.toml"),
+        "[package.compression]\nformats = [\"tar.gz\", \"zip\"]\n\n\
+         [release]\npromote_dist = true\nexclude_assets = ['\\.zip$']\n\n\
+         [release.pull_requests]\ncomment = true\nlabel = \"released\"\n\n\
+         [release.milestones]\nclose = true\ntitle = \"v{version}\"\n\n\
+         [[release.translations]]\nlanguage = \"x-upper\"\ncommand = \"tr a-z A-Z\"\n\n\
+         [[release.translations]]\nlanguage = \"x-tag\"\ncommand = \"echo $ASFSHIP_TAG\"\noutput = \"file\"\n",
+    )?;
+    let repo = init_repo(&root, origin.to_str().unwrap())?;
+    write_file(&root.join("src/lib.rs"), "pub fn f() {}\npub fn g() {}\n")?;
+    commit_all(&repo, "feat: add g (#7)")?;
+
+    let dist_root = td.path().join("dist");
+    let server = mock::MockServer::start(&dist_root);
+    let milestone = server.add_milestone("v0.1.1");
+    server.add_issue(12, milestone);
+    let bin = td.path().join("bin");
+    let svn_log = td.path().join("svn.log");
+    mock::install_fake_svn(&bin, &dist_root, &server.dist(), &svn_log);
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let output = |args: &[&str]| -> Result<std::process::Output> {
+        let mut cmd = asfship_cmd(&root)?;
+        for var in ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"] {
+            cmd.env_remove(var);
+        }
+        Ok(cmd
+            .env("ASFSHIP_GITHUB_TOKEN", "mock-github-token")
+            .env("ASFSHIP_GITHUB_API_URL", server.github_api())
+            .env("ASFSHIP_DIST_URL", server.dist())
+            .env("ASFSHIP_WHIMSY_URL", server.whimsy())
+            .env("NO_PROXY", "127.0.0.1")
+            .env("PATH", &path)
+            .args(args)
+            .args(["--repo", "apache/foo"])
+            .output()?)
+    };
+    let run = |args: &[&str]| -> Result<String> {
+        let output = output(args)?;
+        assert!(
+            output.status.success(),
+            "asfship {:?} failed\nstdout: {}\nstderr: {}\nrequests: {:?}",
+            args,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr),
+            server.requests()
+        );
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    push_upstream(&repo)?;
+    // A local branch named differently from the origin branch it tracks.
+    let upstream = repo.head()?.shorthand().unwrap().to_string();
+    let head = repo.head()?.peel_to_commit()?;
+    repo.branch("rc-work", &head, false)?
+        .set_upstream(Some(&format!("origin/{}", upstream)))?;
+    repo.set_head("refs/heads/rc-work")?;
+    run(&["prerelease"])?;
+    let releases = server.releases();
+    assert_eq!(releases.len(), 1);
+    let rc = &releases[0];
+    assert_eq!(rc["tag_name"], "v0.1.1-rc.1");
+    assert_eq!(rc["prerelease"], true);
+    let mut assets: Vec<&str> = rc["assets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| a["name"].as_str().unwrap())
+        .collect();
+    assets.sort();
+    assert!(
+        assets.contains(&"apache-foo-0.1.1-rc1-src.tar.gz"),
+        "{:?}",
+        assets
+    );
+    let origin_repo = Repository::open_bare(&origin)?;
+    let tagged = origin_repo
+        .revparse_single("refs/tags/v0.1.1-rc.1")?
+        .peel_to_commit()?
+        .id();
+    assert_eq!(
+        origin_repo.refname_to_id(&format!("refs/heads/{}", upstream))?,
+        tagged
+    );
+    assert!(origin_repo.find_reference("refs/heads/rc-work").is_err());
+
+    let out = run(&["sync"])?;
+    assert!(out.contains("sync: committed"), "{}", out);
+    assert!(out.contains("sync: verified "), "{}", out);
+    let staged = dist_root.join("dev/foo/foo-0.1.1-rc1");
+    assert!(staged.join("apache-foo-0.1.1-rc1-src.tar.gz").is_file());
+    assert!(
+        staged
+            .join("apache-foo-0.1.1-rc1-src.tar.gz.sha512")
+            .is_file()
+    );
+    let log = fs::read_to_string(&svn_log)?;
+    assert!(
+        log.contains("commit -m Add foo 0.1.1-rc1 artifacts"),
+        "{}",
+        log
+    );
+
+    let out = run(&["vote"])?;
+    assert!(out.contains("vote: discussion created"), "{}", out);
+    let discussions = server.discussions();
+    assert_eq!(discussions[0]["title"], "[VOTE] foo 0.1.1-rc1");
+    let vote_body = discussions[0]["body"].as_str().unwrap();
+    assert!(
+        vote_body.contains(&format!("{}/dev/foo/foo-0.1.1-rc1", server.dist())),
+        "{}",
+        vote_body
+    );
+    let rc_commit = origin_repo
+        .revparse_single("refs/tags/v0.1.1-rc.1")?
+        .peel_to_commit()?
+        .id();
+    assert!(
+        vote_body.contains(&format!("- Commit: [{}]", rc_commit)),
+        "{}",
+        vote_body
+    );
+
+    let out = run(&["open", "--print"])?;
+    assert!(
+        out.contains("* vote     https://github.com/apache/foo/discussions/1\n"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("  rc       https://github.com/apache/foo/releases/tag/v0.1.1-rc.1\n"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains(&format!(
+            "  dist     {}/dev/foo/foo-0.1.1-rc1\n",
+            server.dist()
+        )),
+        "{}",
+        out
+    );
+    assert!(out.contains("  result   (not posted yet)\n"), "{}", out);
+    assert!(!out.contains("open: "), "{}", out);
+
+    let out = run(&[
+        "result",
+        "failed",
+        "--dry-run",
+        "--vote",
+        "alice=+1",
+        "--vote",
+        "bob=+1",
+        "--vote",
+        "dave=+1",
+    ])?;
+    assert!(
+        out.contains("result: dry-run (rc_tag=v0.1.1-rc.1 outcome=failed"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("Reason: fewer than 3 binding +1 votes"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("`asfship prerelease` to cut v0.1.1-rc.2"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("`asfship abort-rc --rc-tag v0.1.1-rc.1` removes it from dist/dev"),
+        "{}",
+        out
+    );
+    assert!(!out.contains("svn rm"), "{}", out);
+    // Without a recorded failure the rc stays in dist/dev.
+    let refused = output(&["abort-rc"])?;
+    assert!(!refused.status.success());
+    assert!(
+        String::from_utf8_lossy(&refused.stderr).contains(
+            "no failed vote is recorded on v0.1.1-rc.1; run `asfship result failed` first"
+        ),
+        "{}",
+        String::from_utf8_lossy(&refused.stderr)
+    );
+    let out = run(&[
+        "result",
+        "--dry-run",
+        "--vote",
+        "alice=+1",
+        "--vote",
+        "bob=+1",
+        "--vote",
+        "carol=+1",
+        "--vote",
+        "dave=-1",
+    ])?;
+    assert!(out.contains("outcome=passed"), "{}", out);
+    // Nothing is recorded yet, so the rc is not cleared for release.
+    let refused = output(&["release"])?;
+    assert!(!refused.status.success());
+    assert!(
+        String::from_utf8_lossy(&refused.stderr)
+            .contains("no vote result is recorded on v0.1.1-rc.1; run `asfship result`"),
+        "{}",
+        String::from_utf8_lossy(&refused.stderr)
+    );
+    // A dry run still previews the release and only reports the missing result.
+    let out = run(&["release", "--dry-run"])?;
+    assert!(
+        out.contains("release: no vote result is recorded on v0.1.1-rc.1"),
+        "{}",
+        out
+    );
+    assert_eq!(server.discussions().len(), 1);
+    let out = run(&[
+        "result", "passed", "--vote", "alice=+1", "--vote", "bob=+1", "--vote", "carol=+1",
+        "--vote", "dave=+1",
+    ])?;
+    assert!(
+        out.contains("result: recorded passed on release v0.1.1-rc.1"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("next: `asfship release` promotes v0.1.1-rc.1 to v0.1.1"),
+        "{}",
+        out
+    );
+    let discussions = server.discussions();
+    assert_eq!(discussions[1]["title"], "[RESULT] [VOTE] foo 0.1.1-rc1");
+    let result_body = discussions[1]["body"].as_str().unwrap();
+    assert!(
+        result_body.contains("(v0.1.1-rc.1), held in https://github.com/apache/foo/discussions/1, has passed.\n\n- +1 binding: 3\n- +1 non-binding: 1\n- -1 binding: 0\n"),
+        "{}",
+        result_body
+    );
+    assert!(
+        server.releases()[0]["body"]
+            .as_str()
+            .unwrap()
+            .starts_with("> **Vote passed**: +1 binding: 3, +1 non-binding: 1, -1 binding: 0")
+    );
+    assert!(
+        server
+            .requests()
+            .contains(&"POST /repos/apache/foo/discussions/1/comments".to_string())
+    );
+
+    // A draft run stops for review before anything is announced.
+    let out = run(&["release", "--draft"])?;
+    assert!(
+        out.contains("release: draft GitHub release v0.1.1 is ready for review at"),
+        "{}",
+        out
+    );
+    assert!(!out.contains("release: completed"), "{}", out);
+    assert_eq!(server.releases()[0]["tag_name"], "v0.1.1");
+    assert_eq!(server.releases()[0]["draft"], true);
+    assert_eq!(server.discussions().len(), 2);
+
+    let out = run(&["release", "--finalize"])?;
+    assert!(
+        out.contains("release: draft GitHub release v0.1.1 already exists"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("release: published draft GitHub release v0.1.1"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("release: completed (stable_tag=v0.1.1"),
+        "{}",
+        out
+    );
+    let phases: Vec<&str> = out
+        .lines()
+        .skip_while(|l| *l != "timeline: 0.1.1")
+        .skip(1)
+        .take(6)
+        .map(|l| l.get(23..35).unwrap_or_default().trim())
+        .collect();
+    assert_eq!(
+        phases,
+        [
+            "rc cut",
+            "synced",
+            "vote opened",
+            "vote closed",
+            "promoted",
+            "released"
+        ],
+        "{}",
+        out
+    );
+    assert!(out.contains("  total: "), "{}", out);
+    let status = run(&["status", "v0.1.1"])?;
+    assert!(status.starts_with("timeline: 0.1.1\n"), "{}", status);
+    assert!(status.contains("(1 rc, "), "{}", status);
+    assert!(
+        status.contains(
+            "  [x] Vote passed with at least 3 binding +1 votes and more binding +1 than -1 votes\n"
+        ),
+        "{}",
+        status
+    );
+    // The mock vote closes within seconds of opening.
+    assert!(
+        status.contains("  [!] Vote open for at least 72 hours (closed after <1m)\n"),
+        "{}",
+        status
+    );
+    assert!(
+        status.contains("  [x] dist/release updated with the voted artifacts (promoted by "),
+        "{}",
+        status
+    );
+    assert!(status.contains("  [x] Release announced ("), "{}", status);
+    assert!(run(&["status", "0.1.1"])?.starts_with("timeline: 0.1.1\n"));
+    let unknown = output(&["status", "release-0.1.1"])?;
+    assert!(
+        String::from_utf8_lossy(&unknown.stderr)
+            .contains("\"release-0.1.1\" is neither a version nor a vX.Y.Z tag"),
+        "{}",
+        String::from_utf8_lossy(&unknown.stderr)
+    );
+    let releases = server.releases();
+    let stable = &releases[0];
+    assert_eq!(stable["tag_name"], "v0.1.1");
+    assert_eq!(stable["prerelease"], false);
+    assert_eq!(stable["draft"], false);
+    let body = stable["body"].as_str().unwrap();
+    assert!(
+        body.starts_with(
+            "### Links\n\n- Source release: https://downloads.apache.org/foo/0.1.1/\n"
+        ),
+        "{}",
+        body
+    );
+    assert!(
+        body.contains(
+            "- foo 0.1.1: [crates.io](https://crates.io/crates/foo/0.1.1), [docs.rs](https://docs.rs/foo/0.1.1)"
+        ),
+        "{}",
+        body
+    );
+    let mut promoted: Vec<&str> = stable["assets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| a["name"].as_str().unwrap())
+        .collect();
+    promoted.sort();
+    // The rc run manifest stays on the rc release; the rest drop their rc marker.
+    let mut assets: Vec<String> = assets
+        .iter()
+        .filter(|name| **name != "manifest.json" && !name.contains(".zip"))
+        .map(|name| name.replace("-rc1", ""))
+        .collect();
+    assets.sort();
+    assert_eq!(promoted, assets);
+    let checksum =
+        fs::read_to_string(root.join("target/asfship/v0.1.1/apache-foo-0.1.1-src.tar.gz.sha512"))?;
+    assert!(
+        checksum.ends_with("  apache-foo-0.1.1-src.tar.gz\n"),
+        "{}",
+        checksum
+    );
+    // dist/release gets the voted artifacts under their final names.
+    let released = dist_root.join("release/foo/0.1.1");
+    assert!(released.join("apache-foo-0.1.1-src.tar.gz").exists());
+    assert!(!released.join("apache-foo-0.1.1-rc1-src.tar.gz").exists());
+    // Excluded assets stay out of dist/release as they do on GitHub.
+    let staged = root.join("target/asfship/v0.1.1/dist-release");
+    assert!(staged.join("apache-foo-0.1.1-src.tar.gz").is_file());
+    assert!(!staged.join("apache-foo-0.1.1-src.zip").exists());
+    assert!(!released.join("apache-foo-0.1.1-src.zip").exists());
+    assert!(!released.join("apache-foo-0.1.1-src.zip.sha512").exists());
+    assert_eq!(
+        fs::read_to_string(released.join("apache-foo-0.1.1-src.tar.gz.sha512"))?,
+        checksum
+    );
+    assert!(origin_repo.refname_to_id("refs/tags/v0.1.1").is_ok());
+    assert_eq!(server.discussions()[2]["title"], "foo 0.1.1 released");
+    let translation = server.discussions()[2]["comments"][0]["body"].clone();
+    assert!(
+        translation
+            .as_str()
+            .unwrap()
+            .starts_with("Translation (x-upper):\n\n# FOO 0.1.1 RELEASED"),
+        "{}",
+        translation
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("target/asfship/v0.1.1/ANNOUNCEMENT.x-tag.md"))?,
+        "v0.1.1\n"
+    );
+    let comments = server.issue_comments(7);
+    assert_eq!(comments.len(), 1, "{:?}", comments);
+    assert!(
+        comments[0]["body"]
+            .as_str()
+            .unwrap()
+            .starts_with("Released in [v0.1.1](https://github.com/apache/foo/releases/tag/v0.1.1)")
+    );
+    assert_eq!(server.issue_labels(7), ["released"]);
+    let milestones = server.milestones();
+    assert_eq!(milestones[0]["state"], "closed");
+    assert_eq!(milestones[1]["title"], "v0.2.0");
+    assert_eq!(server.issue_milestone(12), milestones[1]["number"].as_u64());
+
+    // A re-run after a partial failure picks up where it stopped: here one asset
+    // never made it and everything else is reused.
+    server.remove_asset("v0.1.1", &assets[0]);
+    let out = run(&["release"])?;
+    assert!(out.contains("release: v0.1.1 already points at"), "{}", out);
+    assert!(
+        out.contains("/release/foo/0.1.1 already holds the promoted artifacts"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("release: announcement already posted at"),
+        "{}",
+        out
+    );
+    assert!(
+        out.contains("release: completed (stable_tag=v0.1.1"),
+        "{}",
+        out
+    );
+    assert_eq!(server.releases().len(), releases.len());
+    assert_eq!(server.discussions().len(), 3);
+    let mut reuploaded: Vec<String> = server.releases()[0]["assets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| a["name"].as_str().unwrap().to_string())
+        .collect();
+    reuploaded.sort();
+    assert_eq!(reuploaded, assets);
+    assert_eq!(server.issue_comments(7).len(), 1);
+    // Every GitHub call went to the mock, permission probes included.
+    assert!(
+        server
+            .requests()
+            .contains(&"POST /repos/apache/foo/git/refs".to_string())
+    );
     Ok(())
 }
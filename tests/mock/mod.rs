@@ -0,0 +1,737 @@
+// Stand-ins for GitHub, dist.apache.org, and `svn` so a whole release can run offline.
+//
+// The binary is pointed at them through its hidden endpoint variables
+// (`ASFSHIP_GITHUB_API_URL`, `ASFSHIP_DIST_URL`).
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use wiremock::matchers::any;
+use wiremock::{Mock, Respond, ResponseTemplate};
+
+const OWNER: &str = "apache";
+const REPO: &str = "foo";
+const TIMESTAMP: &str = "2024-01-01T00:00:00Z";
+
+#[derive(Default)]
+struct State {
+    releases: Vec<Value>,
+    assets: HashMap<u64, Vec<u8>>,
+    discussions: Vec<Value>,
+    /// Comments and labels on issues and pull requests, by number.
+    issue_comments: HashMap<u64, Vec<Value>>,
+    issue_labels: HashMap<u64, Vec<String>>,
+    milestones: Vec<Value>,
+    /// Milestone number of each open issue.
+    issue_milestones: HashMap<u64, u64>,
+    requests: Vec<String>,
+    next_id: u64,
+    /// Directory of `<name>-<version>.crate` files published through the fake `cargo`.
+    registry: Option<PathBuf>,
+}
+
+/// In-memory GitHub REST API for `apache/foo` that also serves the SVN dist tree.
+///
+/// One wiremock mock answers every request through [`Handler`], which keeps the state
+/// that releases, assets, and discussions need across requests.
+pub struct MockServer {
+    base: String,
+    state: Arc<Mutex<State>>,
+    _server: wiremock::MockServer,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn into_template(self) -> ResponseTemplate {
+        ResponseTemplate::new(self.status).set_body_raw(self.body, self.content_type)
+    }
+
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    fn not_found() -> Self {
+        Self::json(404, json!({ "message": "Not Found" }))
+    }
+}
+
+impl MockServer {
+    pub fn start(dist_root: &Path) -> Self {
+        // wiremock serves from a thread of its own; the runtime is only needed to start it.
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("build mock runtime");
+        let server = runtime.block_on(wiremock::MockServer::start());
+        let handler = Handler {
+            base: server.uri(),
+            dist_root: dist_root.to_path_buf(),
+            state: Arc::new(Mutex::new(State::default())),
+        };
+        let state = handler.state.clone();
+        runtime.block_on(Mock::given(any()).respond_with(handler).mount(&server));
+        Self {
+            base: server.uri(),
+            state,
+            _server: server,
+        }
+    }
+
+    pub fn github_api(&self) -> String {
+        self.base.clone()
+    }
+
+    pub fn dist(&self) -> String {
+        format!("{}/dist", self.base)
+    }
+
+    /// Whimsy root; the roster of `foo` has PMC members alice, bob, and carol, and
+    /// committer dave.
+    pub fn whimsy(&self) -> String {
+        format!("{}/whimsy", self.base)
+    }
+
+    /// crates.io root (API, sparse index, and downloads) serving the `.crate` files in
+    /// `registry`.
+    pub fn crates_io(&self, registry: &Path) -> String {
+        self.state.lock().unwrap().registry = Some(registry.to_path_buf());
+        format!("{}/crates-io", self.base)
+    }
+
+    /// docs.rs root; every crate on the mock crates.io has a successful build.
+    pub fn docs_rs(&self) -> String {
+        format!("{}/docs-rs", self.base)
+    }
+
+    /// Releases as GitHub would list them, newest first.
+    pub fn releases(&self) -> Vec<Value> {
+        self.state.lock().unwrap().releases.clone()
+    }
+
+    /// Drop an uploaded asset, as if the upload of it never finished.
+    pub fn remove_asset(&self, tag: &str, name: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(release) = state.releases.iter_mut().find(|r| r["tag_name"] == tag) {
+            release["assets"]
+                .as_array_mut()
+                .unwrap()
+                .retain(|a| a["name"] != name);
+        }
+    }
+
+    pub fn discussions(&self) -> Vec<Value> {
+        self.state.lock().unwrap().discussions.clone()
+    }
+
+    pub fn issue_comments(&self, number: u64) -> Vec<Value> {
+        let state = self.state.lock().unwrap();
+        state
+            .issue_comments
+            .get(&number)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn issue_labels(&self, number: u64) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.issue_labels.get(&number).cloned().unwrap_or_default()
+    }
+
+    /// Put `labels` on issue or pull request `number`.
+    pub fn set_labels(&self, number: u64, labels: &[&str]) {
+        let mut state = self.state.lock().unwrap();
+        let labels = labels.iter().map(|l| l.to_string()).collect();
+        state.issue_labels.insert(number, labels);
+    }
+
+    /// Add an open milestone and return its number.
+    pub fn add_milestone(&self, title: &str) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let number = state.milestones.len() as u64 + 1;
+        state
+            .milestones
+            .push(json!({ "number": number, "title": title, "state": "open" }));
+        number
+    }
+
+    pub fn milestones(&self) -> Vec<Value> {
+        self.state.lock().unwrap().milestones.clone()
+    }
+
+    /// Add an open issue in `milestone`.
+    pub fn add_issue(&self, number: u64, milestone: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.issue_milestones.insert(number, milestone);
+    }
+
+    pub fn issue_milestone(&self, number: u64) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        state.issue_milestones.get(&number).copied()
+    }
+
+    /// `METHOD /path` of every request served so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+struct Handler {
+    base: String,
+    dist_root: PathBuf,
+    state: Arc<Mutex<State>>,
+}
+
+impl Respond for Handler {
+    fn respond(&self, request: &wiremock::Request) -> ResponseTemplate {
+        let request = Request {
+            method: request.method.to_string(),
+            path: request.url.path().to_string(),
+            query: request.url.query().unwrap_or_default().to_string(),
+            body: request.body.clone(),
+        };
+        self.route(&request).into_template()
+    }
+}
+
+impl Handler {
+    fn route(&self, req: &Request) -> Response {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push(format!("{} {}", req.method, req.path));
+        let repo = format!("/repos/{}/{}", OWNER, REPO);
+        let path = req.path.as_str();
+        if let Some(rest) = path.strip_prefix("/dist/") {
+            return self.dist(rest);
+        }
+        if let Some(rest) = path.strip_prefix("/crates-io/") {
+            return crates_io(&state, rest);
+        }
+        if let Some(rest) = path.strip_prefix("/docs-rs/crate/") {
+            // `<name>/<version>/builds.json`
+            let mut parts = rest.split('/');
+            return match (parts.next(), parts.next()) {
+                (Some(name), Some(version)) if published(&state, name, version).is_some() => {
+                    Response::json(200, json!([{ "id": 1, "build_status": "success" }]))
+                }
+                _ => Response::not_found(),
+            };
+        }
+        if let Some(id) = path.strip_prefix("/download/") {
+            return match id.parse().ok().and_then(|id| state.assets.get(&id)) {
+                Some(bytes) => Response {
+                    status: 200,
+                    content_type: "application/octet-stream",
+                    body: bytes.clone(),
+                },
+                None => Response::not_found(),
+            };
+        }
+        if let Some(rest) = path.strip_prefix("/uploads/") {
+            let id = rest.split('/').next().and_then(|id| id.parse().ok());
+            return match id {
+                Some(id) => self.upload(&mut state, id, req),
+                None => Response::not_found(),
+            };
+        }
+        match path {
+            "/whimsy/public/public_ldap_projects.json" => {
+                return Response::json(
+                    200,
+                    json!({ "projects": { REPO: {
+                        "owners": ["alice", "bob", "carol"],
+                        "members": ["alice", "bob", "carol", "dave"],
+                    } } }),
+                );
+            }
+            "/whimsy/public/committee-info.json" => {
+                return Response::json(
+                    200,
+                    json!({ "committees": { REPO: { "mail_list": REPO } } }),
+                );
+            }
+            _ => {}
+        }
+        if path.starts_with("/app/installations/") && path.ends_with("/access_tokens") {
+            // Already expired, so the binary has to mint a new one for every request.
+            return Response::json(
+                201,
+                json!({ "token": "mock-installation-token", "expires_at": TIMESTAMP }),
+            );
+        }
+        let Some(rest) = path.strip_prefix(&repo) else {
+            return Response::not_found();
+        };
+        match (req.method.as_str(), rest) {
+            ("GET", "") => Response::json(
+                200,
+                json!({ "node_id": "R_foo", "full_name": "apache/foo", "permissions": { "push": true } }),
+            ),
+            ("POST", "/git/refs" | "/pulls" | "/attestations") => {
+                Response::json(422, json!({ "message": "Validation Failed" }))
+            }
+            ("GET", "/installation") => Response::json(200, json!({ "id": 7 })),
+            ("GET", "/milestones") => Response::json(200, Value::Array(state.milestones.clone())),
+            ("POST", "/milestones") => {
+                let payload: Value = serde_json::from_slice(&req.body).unwrap_or_default();
+                if !payload["title"].is_string() {
+                    return Response::json(422, json!({ "message": "Validation Failed" }));
+                }
+                let number = state.milestones.len() + 1;
+                let milestone =
+                    json!({ "number": number, "title": payload["title"], "state": "open" });
+                state.milestones.push(milestone.clone());
+                Response::json(201, milestone)
+            }
+            ("PATCH", rest) if rest.starts_with("/milestones/") => {
+                let payload: Value = serde_json::from_slice(&req.body).unwrap_or_default();
+                let number: u64 = rest["/milestones/".len()..].parse().unwrap_or_default();
+                match state.milestones.iter_mut().find(|m| m["number"] == number) {
+                    Some(milestone) => {
+                        milestone["state"] = payload["state"].clone();
+                        Response::json(200, milestone.clone())
+                    }
+                    None => Response::not_found(),
+                }
+            }
+            ("GET", "/issues") => {
+                let param = |key: &str| {
+                    req.query
+                        .split('&')
+                        .find_map(|kv| kv.strip_prefix(key)?.strip_prefix('='))
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                let page: u64 = param("page").parse().unwrap_or(1);
+                let milestone: u64 = param("milestone").parse().unwrap_or_default();
+                let issues: Vec<Value> = state
+                    .issue_milestones
+                    .iter()
+                    .filter(|(_, m)| page == 1 && **m == milestone)
+                    .map(|(number, _)| json!({ "number": number }))
+                    .collect();
+                Response::json(200, Value::Array(issues))
+            }
+            ("GET", "/releases") => Response::json(200, Value::Array(state.releases.clone())),
+            ("POST", "/releases") => {
+                let payload: Value = serde_json::from_slice(&req.body).unwrap_or_default();
+                let release = self.release(&mut state, &payload);
+                state.releases.insert(0, release.clone());
+                Response::json(201, release)
+            }
+            ("GET", rest) if rest.starts_with("/releases/tags/") => {
+                let tag = &rest["/releases/tags/".len()..];
+                // Like GitHub, drafts are only found by listing releases.
+                match state
+                    .releases
+                    .iter()
+                    .find(|r| r["tag_name"] == tag && r["draft"] != true)
+                {
+                    Some(release) => Response::json(200, release.clone()),
+                    None => Response::not_found(),
+                }
+            }
+            ("PATCH", rest) if rest.starts_with("/releases/") => {
+                let payload: Value = serde_json::from_slice(&req.body).unwrap_or_default();
+                let id: u64 = rest["/releases/".len()..].parse().unwrap_or_default();
+                match state.releases.iter_mut().find(|r| r["id"] == id) {
+                    Some(release) => {
+                        if payload["body"].is_string() {
+                            release["body"] = payload["body"].clone();
+                        }
+                        if payload["draft"].is_boolean() {
+                            release["draft"] = payload["draft"].clone();
+                        }
+                        Response::json(200, release.clone())
+                    }
+                    None => Response::not_found(),
+                }
+            }
+            ("DELETE", rest) if rest.starts_with("/releases/assets/") => {
+                let id: u64 = rest["/releases/assets/".len()..]
+                    .parse()
+                    .unwrap_or_default();
+                state.assets.remove(&id);
+                for release in &mut state.releases {
+                    release["assets"]
+                        .as_array_mut()
+                        .unwrap()
+                        .retain(|a| a["id"] != id);
+                }
+                Response {
+                    status: 204,
+                    content_type: "application/json",
+                    body: Vec::new(),
+                }
+            }
+            ("GET", "/discussions") => {
+                let newest_first = state.discussions.iter().rev().cloned().collect();
+                Response::json(200, Value::Array(newest_first))
+            }
+            ("GET", "/discussions/categories") => {
+                Response::json(200, json!([{ "id": 7, "name": "Releases" }]))
+            }
+            ("POST", "/discussions") => {
+                let mut payload: Value = serde_json::from_slice(&req.body).unwrap_or_default();
+                if !payload["title"].is_string() {
+                    return Response::json(422, json!({ "message": "Validation Failed" }));
+                }
+                let number = state.discussions.len() + 1;
+                payload["number"] = json!(number);
+                payload["node_id"] = json!(format!("D_{}", number));
+                payload["html_url"] = json!(format!(
+                    "https://github.com/{}/{}/discussions/{}",
+                    OWNER, REPO, number
+                ));
+                state.discussions.push(payload.clone());
+                Response::json(201, payload)
+            }
+            ("POST", rest) if rest.starts_with("/discussions/") => {
+                // Comments are kept on the discussion itself, under `comments`.
+                let payload: Value = serde_json::from_slice(&req.body).unwrap_or_default();
+                let number = rest
+                    .trim_start_matches("/discussions/")
+                    .trim_end_matches("/comments")
+                    .parse::<usize>()
+                    .unwrap_or_default();
+                let comment = json!({ "body": payload["body"] });
+                if let Some(discussion) = state.discussions.get_mut(number.wrapping_sub(1)) {
+                    match discussion["comments"].as_array_mut() {
+                        Some(comments) => comments.push(comment.clone()),
+                        None => discussion["comments"] = json!([comment.clone()]),
+                    }
+                }
+                Response::json(201, comment)
+            }
+            (method, rest) if rest.starts_with("/issues/") => {
+                self.issue(&mut state, method, rest, req)
+            }
+            _ => Response::not_found(),
+        }
+    }
+
+    fn release(&self, state: &mut State, payload: &Value) -> Value {
+        state.next_id += 1;
+        let id = state.next_id;
+        let tag = payload["tag_name"].as_str().unwrap_or_default();
+        let html = format!("https://github.com/{}/{}/releases/tag/{}", OWNER, REPO, tag);
+        json!({
+            "url": format!("{}/repos/{}/{}/releases/{}", self.base, OWNER, REPO, id),
+            "html_url": html,
+            "assets_url": format!("{}/repos/{}/{}/releases/{}/assets", self.base, OWNER, REPO, id),
+            "upload_url": format!("{}/uploads/{}/assets{{?name,label}}", self.base, id),
+            "id": id,
+            "node_id": format!("RE_{}", id),
+            "tag_name": tag,
+            "target_commitish": payload["target_commitish"].as_str().unwrap_or("main"),
+            "name": payload["name"],
+            "body": payload["body"],
+            "draft": payload["draft"].as_bool().unwrap_or(false),
+            "prerelease": payload["prerelease"].as_bool().unwrap_or(false),
+            "created_at": TIMESTAMP,
+            "published_at": TIMESTAMP,
+            "author": null,
+            "assets": [],
+        })
+    }
+
+    fn issue(&self, state: &mut State, method: &str, rest: &str, req: &Request) -> Response {
+        let mut parts = rest.trim_start_matches("/issues/").split('/');
+        let Some(Ok(number)) = parts.next().map(str::parse::<u64>) else {
+            return Response::not_found();
+        };
+        let payload: Value = serde_json::from_slice(&req.body).unwrap_or_default();
+        match (method, parts.next().unwrap_or_default()) {
+            ("GET", "") => {
+                let labels = state.issue_labels.get(&number).cloned().unwrap_or_default();
+                let labels: Vec<Value> = labels.iter().map(|l| json!({ "name": l })).collect();
+                Response::json(
+                    200,
+                    json!({
+                        "number": number,
+                        "html_url": format!("https://github.com/{}/{}/issues/{}", OWNER, REPO, number),
+                        "labels": labels,
+                    }),
+                )
+            }
+            ("PATCH", "") => {
+                let milestone = payload["milestone"].as_u64().unwrap_or_default();
+                state.issue_milestones.insert(number, milestone);
+                Response::json(200, json!({ "number": number, "milestone": milestone }))
+            }
+            ("GET", "comments") => Response::json(
+                200,
+                Value::Array(
+                    state
+                        .issue_comments
+                        .get(&number)
+                        .cloned()
+                        .unwrap_or_default(),
+                ),
+            ),
+            ("POST", "comments") => {
+                let comment = json!({ "body": payload["body"] });
+                state
+                    .issue_comments
+                    .entry(number)
+                    .or_default()
+                    .push(comment.clone());
+                Response::json(201, comment)
+            }
+            ("GET", "labels") => {
+                let labels = state.issue_labels.get(&number).cloned().unwrap_or_default();
+                let labels: Vec<Value> = labels.iter().map(|l| json!({ "name": l })).collect();
+                Response::json(200, Value::Array(labels))
+            }
+            ("POST", "labels") => {
+                let labels = state.issue_labels.entry(number).or_default();
+                for label in payload["labels"].as_array().into_iter().flatten() {
+                    labels.push(label.as_str().unwrap_or_default().to_string());
+                }
+                Response::json(200, json!(labels))
+            }
+            _ => Response::not_found(),
+        }
+    }
+
+    fn upload(&self, state: &mut State, release_id: u64, req: &Request) -> Response {
+        let Some(name) = req
+            .query
+            .split('&')
+            .find_map(|kv| kv.strip_prefix("name="))
+            .map(str::to_string)
+        else {
+            return Response::json(422, json!({ "message": "name is required" }));
+        };
+        let duplicate = state
+            .releases
+            .iter()
+            .filter(|r| r["id"] == release_id)
+            .flat_map(|r| r["assets"].as_array().unwrap())
+            .any(|a| a["name"] == name.as_str());
+        if duplicate {
+            return Response::json(422, json!({ "message": "already_exists" }));
+        }
+        state.next_id += 1;
+        let id = state.next_id;
+        let asset = json!({
+            "url": format!("{}/repos/{}/{}/releases/assets/{}", self.base, OWNER, REPO, id),
+            "browser_download_url": format!("{}/download/{}", self.base, id),
+            "id": id,
+            "node_id": format!("RA_{}", id),
+            "name": name,
+            "label": null,
+            "state": "uploaded",
+            "content_type": "application/octet-stream",
+            "size": req.body.len(),
+            "download_count": 0,
+            "created_at": TIMESTAMP,
+            "updated_at": TIMESTAMP,
+            "uploader": null,
+        });
+        state.assets.insert(id, req.body.clone());
+        let Some(release) = state.releases.iter_mut().find(|r| r["id"] == release_id) else {
+            return Response::not_found();
+        };
+        release["assets"]
+            .as_array_mut()
+            .unwrap()
+            .push(asset.clone());
+        Response::json(201, asset)
+    }
+
+    /// Files committed through the fake `svn`, as dist.apache.org would serve them.
+    fn dist(&self, rest: &str) -> Response {
+        let path = self.dist_root.join(rest.trim_end_matches('/'));
+        if path.is_dir() {
+            return Response::json(200, json!({}));
+        }
+        match fs::read(&path) {
+            Ok(body) => Response {
+                status: 200,
+                content_type: "application/octet-stream",
+                body,
+            },
+            Err(_) => Response::not_found(),
+        }
+    }
+}
+
+/// Bytes of `name` `version` on the mock crates.io, once the fake `cargo` published it.
+fn published(state: &State, name: &str, version: &str) -> Option<Vec<u8>> {
+    let registry = state.registry.as_ref()?;
+    fs::read(registry.join(format!("{}-{}.crate", name, version))).ok()
+}
+
+/// The crates.io API, sparse index, and download routes under `/crates-io/`.
+fn crates_io(state: &State, rest: &str) -> Response {
+    let checksum = |bytes: &[u8]| hex::encode(Sha256::digest(bytes));
+    if let Some(rest) = rest.strip_prefix("api/v1/crates/") {
+        let Some((name, version)) = rest.split_once('/') else {
+            return Response::not_found();
+        };
+        return match published(state, name, version) {
+            Some(bytes) => Response::json(
+                200,
+                json!({ "version": { "num": version, "checksum": checksum(&bytes), "yanked": false } }),
+            ),
+            None => Response::not_found(),
+        };
+    }
+    if let Some(rest) = rest.strip_prefix("crates/") {
+        let mut parts = rest.split('/');
+        let (Some(name), Some(file)) = (parts.next(), parts.next()) else {
+            return Response::not_found();
+        };
+        let version = file
+            .strip_prefix(&format!("{}-", name))
+            .and_then(|f| f.strip_suffix(".crate"))
+            .unwrap_or_default();
+        return match published(state, name, version) {
+            Some(body) => Response {
+                status: 200,
+                content_type: "application/octet-stream",
+                body,
+            },
+            None => Response::not_found(),
+        };
+    }
+    let Some(rest) = rest.strip_prefix("index/") else {
+        return Response::not_found();
+    };
+    // The last path component of a sparse index entry is the crate name.
+    let name = rest.rsplit('/').next().unwrap_or_default();
+    let prefix = format!("{}-", name);
+    let mut lines = Vec::new();
+    for entry in state
+        .registry
+        .as_ref()
+        .and_then(|r| fs::read_dir(r).ok())
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let file = entry.file_name().to_string_lossy().into_owned();
+        let Some(version) = file
+            .strip_prefix(&prefix)
+            .and_then(|f| f.strip_suffix(".crate"))
+            .filter(|v| v.starts_with(|c: char| c.is_ascii_digit()))
+        else {
+            continue;
+        };
+        let bytes = fs::read(entry.path()).unwrap_or_default();
+        lines.push(json!({ "name": name, "vers": version, "cksum": checksum(&bytes) }).to_string());
+    }
+    if lines.is_empty() {
+        return Response::not_found();
+    }
+    Response {
+        status: 200,
+        content_type: "text/plain",
+        body: (lines.join("\n") + "\n").into_bytes(),
+    }
+}
+
+/// Install a `cargo` on `bin` whose `publish` packages the crate with the real cargo and
+/// drops the `.crate` into `registry`, logging each call to `log`. Every other
+/// subcommand runs the real cargo.
+pub fn install_fake_cargo(bin: &Path, registry: &Path, log: &Path) {
+    let real = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let script = format!(
+        r#"#!/bin/sh
+if [ "$1" != publish ]; then
+  exec '{real}' "$@"
+fi
+shift
+echo "publish $*" >> '{log}'
+target=
+prev=
+for arg in "$@"; do
+  [ "$prev" = --target-dir ] && target=$arg
+  prev=$arg
+done
+'{real}' package --offline --no-verify --allow-dirty "$@" || exit 1
+mkdir -p '{registry}'
+cp "$target"/package/*.crate '{registry}/'
+"#,
+        real = real,
+        log = log.display(),
+        registry = registry.display(),
+    );
+    fs::create_dir_all(bin).unwrap();
+    let path = bin.join("cargo");
+    fs::write(&path, script).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
+
+/// Install an `svn` on `bin` that mirrors commits into `dist_root`, logging each call to `log`.
+///
+/// `url_base` is the dist URL the binary was given; the rest of a URL maps to a path
+/// below `dist_root`.
+pub fn install_fake_svn(bin: &Path, dist_root: &Path, url_base: &str, log: &Path) {
+    let script = format!(
+        r#"#!/bin/sh
+echo "$*" >> '{log}'
+root='{root}'
+base='{base}'
+cmd=$1
+shift
+for last in "$@"; do :; done
+case "$cmd" in
+  info)
+    [ -d "$root${{last#$base}}" ] && exit 0
+    echo "svn: E170000: URL '$last' doesn't exist" >&2
+    exit 1 ;;
+  mkdir)
+    mkdir -p "$root${{last#$base}}" ;;
+  list)
+    ls "$root${{last#$base}}" ;;
+  cat)
+    cat "$root${{last#$base}}" ;;
+  checkout)
+    # checkout --depth empty URL DIR
+    mkdir -p "$4/.svn"
+    echo "$3" > "$4/.svn/url" ;;
+  commit)
+    url=$(cat .svn/url)
+    dest="$root${{url#$base}}"
+    mkdir -p "$dest"
+    cp ./* "$dest/" ;;
+esac
+exit 0
+"#,
+        log = log.display(),
+        root = dist_root.display(),
+        base = url_base,
+    );
+    fs::create_dir_all(bin).unwrap();
+    let path = bin.join("svn");
+    fs::write(&path, script).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}